@@ -0,0 +1,186 @@
+//! Run a command with `command-error`, printing a detailed error message if it fails.
+//!
+//! ```shell
+//! cargo run --example run_checked -- [OPTIONS] -- PROGRAM [ARGS...]
+//! ```
+//!
+//! Options:
+//!
+//! - `--utf8`: Decode the command's output as UTF-8.
+//! - `--json`: Check that the command's output is a complete JSON document.
+//! - `--timeout SECONDS`: Stop the command if it runs for longer than `SECONDS`.
+//! - `--retry N`: Retry the command up to `N` times if it fails.
+//! - `--pretty`: Print errors with `miette` (requires the `miette` feature).
+//!
+//! On success, the command's stdout is printed. On failure, the error is printed to stderr and
+//! this program exits with the command's exit code (see `Error::exit_code`).
+
+use std::io::Write;
+use std::process::Command;
+use std::process::Output;
+use std::time::Duration;
+
+use command_error::CommandExt;
+use command_error::Error;
+use command_error::OutputContext;
+
+const USAGE: &str =
+    "Usage: run_checked [--utf8] [--json] [--timeout SECONDS] [--retry N] [--pretty] -- PROGRAM [ARGS...]";
+
+#[derive(Default)]
+struct Opts {
+    utf8: bool,
+    json: bool,
+    timeout: Option<Duration>,
+    retries: usize,
+    pretty: bool,
+    program: String,
+    args: Vec<String>,
+}
+
+impl Opts {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut opts = Self::default();
+        loop {
+            match args.next().as_deref() {
+                Some("--utf8") => opts.utf8 = true,
+                Some("--json") => opts.json = true,
+                Some("--pretty") => opts.pretty = true,
+                Some("--timeout") => {
+                    let seconds = args
+                        .next()
+                        .and_then(|seconds| seconds.parse::<f64>().ok())
+                        .ok_or("--timeout requires a number of seconds")?;
+                    opts.timeout = Some(Duration::from_secs_f64(seconds));
+                }
+                Some("--retry") => {
+                    opts.retries = args
+                        .next()
+                        .and_then(|retries| retries.parse().ok())
+                        .ok_or("--retry requires a number of retries")?;
+                }
+                Some("--") => break,
+                Some(arg) => return Err(format!("Unexpected argument: {arg}")),
+                None => return Err("Expected `--` followed by a program".to_owned()),
+            }
+        }
+        opts.program = args.next().ok_or("Expected a program after `--`")?;
+        opts.args = args.collect();
+        Ok(opts)
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        command
+    }
+
+    /// Run the command once, returning its stdout.
+    fn run(&self) -> Result<Vec<u8>, Error> {
+        let mut command = self.command();
+        if let Some(timeout) = self.timeout {
+            return command
+                .output_checked_with_timeout_and_grace(timeout, Duration::from_secs(1))
+                .map(|output| output.stdout);
+        }
+
+        if self.json {
+            command.output_checked_as(|context: OutputContext<Output>| {
+                if !context.status().success() {
+                    return Err(context.error());
+                }
+                match check_json(&context.output().stdout) {
+                    Ok(()) => Ok(context.into_output().stdout),
+                    Err(message) => Err(context.error_msg(message)),
+                }
+            })
+        } else if self.utf8 {
+            command
+                .output_checked_utf8()
+                .map(|output| output.stdout.into_bytes())
+        } else {
+            command.output_checked().map(|output| output.stdout)
+        }
+    }
+
+    /// Run the command, retrying it if it fails.
+    fn run_with_retries(&self) -> Result<Vec<u8>, Error> {
+        let attempts = self.retries + 1;
+        let mut attempt = 1;
+        loop {
+            match self.run() {
+                Err(err) if attempt < attempts => {
+                    eprintln!("Attempt {attempt} of {attempts} failed: {err}");
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Check that `output` is a complete JSON document.
+///
+/// This only checks that brackets and strings are closed, which is enough to catch truncated
+/// output; a real program would parse the output with `serde_json`.
+fn check_json(output: &[u8]) -> Result<(), &'static str> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for byte in output {
+        match (in_string, byte) {
+            (true, _) if escaped => escaped = false,
+            (true, b'\\') => escaped = true,
+            (true, b'"') => in_string = false,
+            (true, _) => {}
+            (false, b'"') => in_string = true,
+            (false, b'[' | b'{') => depth += 1,
+            (false, b']' | b'}') => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or("unexpected closing bracket in JSON")?;
+            }
+            (false, _) => {}
+        }
+    }
+    if depth > 0 || in_string {
+        Err("unexpected end of JSON input")
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "miette")]
+fn print_pretty(err: Error) {
+    eprintln!("{:?}", miette::Report::new(err));
+}
+
+#[cfg(not(feature = "miette"))]
+fn print_pretty(err: Error) {
+    eprintln!("{err}\n(`--pretty` requires the `miette` feature)");
+}
+
+fn main() {
+    let opts = match Opts::parse(std::env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    match opts.run_with_retries() {
+        Ok(stdout) => {
+            let _ = std::io::stdout().write_all(&stdout);
+        }
+        Err(err) => {
+            let code = err.exit_code();
+            if opts.pretty {
+                print_pretty(err);
+                std::process::exit(code);
+            } else {
+                err.exit_process();
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::time::SystemTime;
+
+use crate::command_display::guarded;
+use crate::duration::round_duration;
+use crate::CommandDisplay;
+#[cfg(doc)]
+use crate::SingleFlight;
+#[cfg(feature = "miette")]
+use miette::Diagnostic;
+
+/// An error from a command which was not run because the same command is already running.
+///
+/// Produced by [`SingleFlight`]. Callers can wait and retry, or use [`SingleFlight::blocking`] to
+/// wait for the running invocation to finish instead.
+///
+/// ```
+/// # use std::process::Command;
+/// # use std::time::SystemTime;
+/// # use command_error::AlreadyRunningError;
+/// # use command_error::Utf8ProgramAndArgs;
+/// let mut command = Command::new("cargo");
+/// command.arg("build");
+/// let error = AlreadyRunningError::new(
+///     Box::new(Utf8ProgramAndArgs::from(&command)),
+///     "cd /src && cargo build".to_owned(),
+///     Some(1234),
+///     SystemTime::now(),
+/// );
+/// assert!(error.to_string().starts_with(
+///     "`cargo` was not run: it's already running as `cd /src && cargo build` (pid 1234, started "
+/// ));
+/// ```
+pub struct AlreadyRunningError {
+    pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
+    /// The already-running invocation, as it was displayed when it started.
+    pub(crate) running: String,
+    pub(crate) pid: Option<u32>,
+    pub(crate) started: SystemTime,
+}
+
+impl AlreadyRunningError {
+    /// Construct a new [`AlreadyRunningError`].
+    ///
+    /// `running` is the displayed already-running invocation, `pid` is the process running it (if
+    /// it's known), and `started` is when it started.
+    pub fn new(
+        command: impl Into<Box<dyn CommandDisplay + Send + Sync>>,
+        running: String,
+        pid: Option<u32>,
+        started: SystemTime,
+    ) -> Self {
+        Self {
+            command: command.into(),
+            running,
+            pid,
+            started,
+        }
+    }
+
+    /// The already-running invocation, as it was displayed when it started.
+    pub fn running(&self) -> &str {
+        &self.running
+    }
+
+    /// The ID of the process running the already-running invocation, if it's known.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// When the already-running invocation started.
+    pub fn started(&self) -> SystemTime {
+        self.started
+    }
+}
+
+impl Debug for AlreadyRunningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AlreadyRunningError")
+            .field("program", &guarded(&*self.command).program())
+            .field("running", &self.running)
+            .field("pid", &self.pid)
+            .field("started", &self.started)
+            .finish()
+    }
+}
+
+impl Display for AlreadyRunningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // `cargo` was not run: it's already running as `cargo build` (pid 1234, started 3s ago)
+        write!(
+            f,
+            "`{}` was not run: it's already running as `{}` (",
+            guarded(&*self.command).program_quoted(),
+            self.running,
+        )?;
+        if let Some(pid) = self.pid {
+            write!(f, "pid {pid}, ")?;
+        }
+        let elapsed = SystemTime::now()
+            .duration_since(self.started)
+            .unwrap_or_default();
+        write!(f, "started {:?} ago)", round_duration(elapsed))
+    }
+}
+
+impl std::error::Error for AlreadyRunningError {}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for AlreadyRunningError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::already_running"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_assertions::assert_impl_all;
+
+    assert_impl_all!(AlreadyRunningError: Send, Sync);
+}
@@ -0,0 +1,67 @@
+use std::borrow::Cow;
+use std::process::ExitStatus;
+use std::process::Output;
+use std::sync::Arc;
+
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::OutputLike;
+
+/// A command's [`Output`], with its stdout and stderr in reference-counted buffers.
+///
+/// Cloning an [`ArcOutput`] (or its [`stdout`][ArcOutput::stdout] or
+/// [`stderr`][ArcOutput::stderr]) doesn't copy the output, so a single command's output can be
+/// shared between several consumers (for example, a logger, a hasher, and a parser) cheaply.
+/// Converting from an [`Output`] copies each buffer once.
+///
+/// Use it with methods like [`CommandExt::output_checked_as`]:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use std::sync::Arc;
+/// # use command_error::ArcOutput;
+/// # use command_error::CommandExt;
+/// # use command_error::OutputContext;
+/// let output: ArcOutput = Command::new("echo")
+///     .arg("puppy")
+///     .output_checked_as(OutputContext::require_success)
+///     .unwrap();
+///
+/// let logged = output.stdout.clone();
+/// assert!(Arc::ptr_eq(&logged, &output.stdout));
+/// assert_eq!(&*logged, b"puppy\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArcOutput {
+    /// The command's exit status.
+    pub status: ExitStatus,
+    /// The command's stdout.
+    pub stdout: Arc<[u8]>,
+    /// The command's stderr.
+    pub stderr: Arc<[u8]>,
+}
+
+impl From<Output> for ArcOutput {
+    fn from(output: Output) -> Self {
+        Self {
+            status: output.status,
+            stdout: output.stdout.into(),
+            stderr: output.stderr.into(),
+        }
+    }
+}
+
+impl OutputLike for ArcOutput {
+    fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    fn stdout(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stdout)
+    }
+
+    fn stderr(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stderr)
+    }
+}
@@ -0,0 +1,328 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::future::Future;
+use std::process::ExitStatus;
+use std::process::Output;
+
+use utf8_command::Utf8Output;
+
+#[cfg(doc)]
+use crate::ChildExt;
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Error;
+use crate::OutputContext;
+use crate::OutputLike;
+
+/// Async extension trait for commands, the counterpart of [`CommandExt`].
+///
+/// This is implemented for `tokio::process::Command` with the `tokio` feature, for
+/// `async_process::Command` with the `async-process` feature, and for
+/// `process_wrap::tokio::TokioCommandWrap` with the `process-wrap-tokio` feature.
+///
+/// Each method returns a future which runs the command and checks its exit status. Errors are
+/// the same as [`CommandExt`]'s, so their messages are identical:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use indoc::indoc;
+/// # use command_error::AsyncCommandExt;
+/// # #[cfg(feature = "tokio")]
+/// # async fn example() {
+/// # use tokio::process::Command;
+/// let err = Command::new("sh")
+///     .args(["-c", "echo puppy; false"])
+///     .output_checked_utf8()
+///     .await
+///     .unwrap_err();
+///
+/// assert_eq!(
+///     err.to_string(),
+///     indoc!(
+///         "`sh` failed: exit status: 1
+///         Command failed: `sh -c 'echo puppy; false'`
+///         Stdout:
+///           puppy"
+///     )
+/// );
+/// # }
+/// ```
+///
+/// The futures are [`Send`], so they can be spawned on a multi-threaded runtime, as long as the
+/// closures passed to them are too.
+pub trait AsyncCommandExt: Send {
+    /// The error type returned from methods on this trait.
+    type Error: From<Error> + Send + Sync;
+
+    /// The type of child process produced.
+    type Child;
+
+    /// Run a command, capturing its output. `succeeded` is called and returned to determine if the
+    /// command succeeded.
+    ///
+    /// See [`CommandExt::output_checked_as`] for more information.
+    #[track_caller]
+    fn output_checked_as<O, R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync;
+
+    /// Run a command, capturing its output. `succeeded` is called and used to determine if the
+    /// command succeeded and (optionally) to add an additional message to the error returned.
+    ///
+    /// See [`CommandExt::output_checked_with`] for more information.
+    #[track_caller]
+    fn output_checked_with<O, E>(
+        &mut self,
+        succeeded: impl Fn(&O) -> Result<(), Option<E>> + Send,
+    ) -> impl Future<Output = Result<O, Self::Error>> + Send
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: Debug + Display + Send + Sync + 'static,
+    {
+        self.output_checked_as(move |context| match succeeded(context.output()) {
+            Ok(()) => Ok(context.into_output()),
+            Err(user_error) => Err(context.maybe_error_msg(user_error).into()),
+        })
+    }
+
+    /// Run a command, capturing its output. If the command exits with a non-zero exit code, an
+    /// error is raised.
+    ///
+    /// See [`CommandExt::output_checked`] for more information.
+    #[track_caller]
+    fn output_checked(&mut self) -> impl Future<Output = Result<Output, Self::Error>> + Send {
+        self.output_checked_with(|output: &Output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        })
+    }
+
+    /// Run a command, capturing its output and decoding it as UTF-8. If the command exits with a
+    /// non-zero exit code or if its output contains invalid UTF-8, an error is raised.
+    ///
+    /// See [`CommandExt::output_checked_utf8`] for more information.
+    #[track_caller]
+    fn output_checked_utf8(
+        &mut self,
+    ) -> impl Future<Output = Result<Utf8Output, Self::Error>> + Send {
+        self.output_checked_with_utf8(|output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        })
+    }
+
+    /// Run a command, capturing its output and decoding it as UTF-8. `succeeded` is called and
+    /// used to determine if the command succeeded and (optionally) to add an additional message to
+    /// the error returned.
+    ///
+    /// See [`CommandExt::output_checked_with_utf8`] for more information.
+    #[track_caller]
+    fn output_checked_with_utf8<E>(
+        &mut self,
+        succeeded: impl Fn(&Utf8Output) -> Result<(), Option<E>> + Send,
+    ) -> impl Future<Output = Result<Utf8Output, Self::Error>> + Send
+    where
+        E: Debug + Display + Send + Sync + 'static,
+    {
+        self.output_checked_with(succeeded)
+    }
+
+    /// Run a command without capturing its output. `succeeded` is called and returned to
+    /// determine if the command succeeded.
+    ///
+    /// See [`CommandExt::status_checked_as`] for more information.
+    #[track_caller]
+    fn status_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        E: From<Self::Error>;
+
+    /// Run a command without capturing its output. `succeeded` is called and used to determine
+    /// if the command succeeded and (optionally) to add an additional message to the error
+    /// returned.
+    ///
+    /// See [`CommandExt::status_checked_with`] for more information.
+    #[track_caller]
+    fn status_checked_with<E>(
+        &mut self,
+        succeeded: impl Fn(ExitStatus) -> Result<(), Option<E>> + Send,
+    ) -> impl Future<Output = Result<ExitStatus, Self::Error>> + Send
+    where
+        E: Debug + Display + Send + Sync + 'static,
+    {
+        self.status_checked_as(move |status| match succeeded(status.status()) {
+            Ok(()) => Ok(status.status()),
+            Err(user_error) => Err(status.maybe_error_msg(user_error).into()),
+        })
+    }
+
+    /// Run a command without capturing its output. If the command exits with a non-zero status
+    /// code, an error is raised containing information about the command that was run.
+    ///
+    /// See [`CommandExt::status_checked`] for more information.
+    #[track_caller]
+    fn status_checked(&mut self) -> impl Future<Output = Result<ExitStatus, Self::Error>> + Send {
+        self.status_checked_with(|status| {
+            if status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        })
+    }
+
+    /// Spawn a command.
+    ///
+    /// The returned child contains context information about the command that produced it, which
+    /// can be used to produce detailed error messages if the child process fails; see
+    /// [`AsyncChildExt`].
+    ///
+    /// ```
+    /// # use command_error::AsyncChildExt;
+    /// # use command_error::AsyncCommandExt;
+    /// # #[cfg(feature = "tokio")]
+    /// # async fn example() -> Result<(), command_error::Error> {
+    /// # use tokio::process::Command;
+    /// Command::new("sleep")
+    ///     .arg("0")
+    ///     .spawn_checked()?
+    ///     .wait_checked()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[track_caller]
+    fn spawn_checked(&mut self) -> Result<Self::Child, Self::Error>;
+
+    /// Log the command that will be run.
+    ///
+    /// With the `tracing` feature enabled, this will emit a debug-level log with message
+    /// `Executing command` and a `command` field containing the displayed command (by default,
+    /// shell-quoted).
+    fn log(&self) -> Result<(), Self::Error>;
+}
+
+/// Async extension trait for child processes, the counterpart of [`ChildExt`].
+///
+/// Children with context are produced by [`AsyncCommandExt::spawn_checked`].
+pub trait AsyncChildExt: Send + Sized {
+    /// The error type returned from methods on this trait.
+    type Error: From<Error> + Send;
+
+    /// Wait for the process to complete, capturing its output. `succeeded` is called and returned
+    /// to determine if the command succeeded.
+    ///
+    /// See [`ChildExt::output_checked_as`] for more information.
+    fn output_checked_as<O, R, E>(
+        self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error>;
+
+    /// Wait for the process to complete, capturing its output. `succeeded` is called and used to
+    /// determine if the command succeeded and (optionally) to add an additional message to the
+    /// error returned.
+    ///
+    /// See [`ChildExt::output_checked_with`] for more information.
+    fn output_checked_with<O, E>(
+        self,
+        succeeded: impl Fn(&O) -> Result<(), Option<E>> + Send,
+    ) -> impl Future<Output = Result<O, Self::Error>> + Send
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: Debug + Display + Send + Sync + 'static,
+    {
+        self.output_checked_as(move |context| match succeeded(context.output()) {
+            Ok(()) => Ok(context.into_output()),
+            Err(user_error) => Err(context.maybe_error_msg(user_error).into()),
+        })
+    }
+
+    /// Wait for the process to complete, capturing its output. If the command exits with a
+    /// non-zero exit code, an error is raised.
+    ///
+    /// See [`ChildExt::output_checked`] for more information.
+    fn output_checked(self) -> impl Future<Output = Result<Output, Self::Error>> + Send {
+        self.output_checked_with(|output: &Output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        })
+    }
+
+    /// Wait for the process to complete, capturing its output and decoding it as UTF-8. If the
+    /// command exits with a non-zero exit code, an error is raised.
+    ///
+    /// See [`ChildExt::output_checked_utf8`] for more information.
+    fn output_checked_utf8(self) -> impl Future<Output = Result<Utf8Output, Self::Error>> + Send {
+        self.output_checked_with(|output: &Utf8Output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        })
+    }
+
+    /// Wait for the process to exit. `succeeded` is called and returned to determine if the
+    /// command succeeded.
+    ///
+    /// See [`ChildExt::wait_checked_as`] for more information.
+    fn wait_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        E: From<Self::Error>;
+
+    /// Wait for the process to exit. `succeeded` is called and used to determine if the command
+    /// succeeded and (optionally) to add an additional message to the error returned.
+    ///
+    /// See [`ChildExt::wait_checked_with`] for more information.
+    fn wait_checked_with<E>(
+        &mut self,
+        succeeded: impl Fn(ExitStatus) -> Result<(), Option<E>> + Send,
+    ) -> impl Future<Output = Result<ExitStatus, Self::Error>> + Send
+    where
+        E: Debug + Display + Send + Sync + 'static,
+    {
+        self.wait_checked_as(move |context| match succeeded(context.status()) {
+            Ok(()) => Ok(context.status()),
+            Err(user_error) => Err(context.maybe_error_msg(user_error).into()),
+        })
+    }
+
+    /// Wait for the process to exit. If the command exits with a non-zero status code, an error
+    /// is raised containing information about the command that was run.
+    ///
+    /// See [`ChildExt::wait_checked`] for more information.
+    fn wait_checked(&mut self) -> impl Future<Output = Result<ExitStatus, Self::Error>> + Send {
+        self.wait_checked_with(|status| {
+            if status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        })
+    }
+}
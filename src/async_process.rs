@@ -0,0 +1,198 @@
+use std::borrow::Borrow;
+use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::future::Future;
+use std::panic::Location;
+use std::path::Path;
+use std::process::CommandArgs;
+use std::process::CommandEnvs;
+use std::process::ExitStatus;
+use std::process::Output;
+
+use ::async_process::Child;
+use ::async_process::Command;
+
+use crate::run;
+use crate::run::Ran;
+use crate::utf8_program_and_args::CommandParts;
+use crate::AsyncChildExt;
+use crate::AsyncCommandExt;
+use crate::ChildContext;
+use crate::Error;
+use crate::ExecError;
+use crate::OutputContext;
+use crate::OutputLike;
+use crate::Started;
+use crate::Utf8ProgramAndArgs;
+use crate::WaitError;
+
+impl CommandParts for Command {
+    fn get_program(&self) -> &OsStr {
+        Command::get_program(self)
+    }
+
+    fn get_args(&self) -> CommandArgs<'_> {
+        Command::get_args(self)
+    }
+
+    fn get_envs(&self) -> CommandEnvs<'_> {
+        Command::get_envs(self)
+    }
+
+    fn get_current_dir(&self) -> Option<&Path> {
+        Command::get_current_dir(self)
+    }
+}
+
+/// Display an `async_process::Command` the same way as a [`std::process::Command`].
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use command_error::Utf8ProgramAndArgs;
+/// let mut command = async_process::Command::new("echo");
+/// command.arg("puppy doggy").current_dir("/puppy");
+/// let displayed: Utf8ProgramAndArgs = (&command).into();
+/// assert_eq!(displayed.to_string(), "cd /puppy && echo 'puppy doggy'");
+/// ```
+impl<'a> From<&'a Command> for Utf8ProgramAndArgs {
+    fn from(command: &'a Command) -> Self {
+        Self::from_command_parts(command)
+    }
+}
+
+/// The async extension trait is implemented for `async_process::Command`, with the same error
+/// messages as [`crate::CommandExt`]:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use indoc::indoc;
+/// # use async_process::Command;
+/// # use command_error::AsyncCommandExt;
+/// # async fn example() {
+/// let err = Command::new("sh")
+///     .args(["-c", "false"])
+///     .output_checked()
+///     .await
+///     .unwrap_err();
+///
+/// assert_eq!(
+///     err.to_string(),
+///     indoc!(
+///         "`sh` failed: exit status: 1
+///         Command failed: `sh -c false`"
+///     )
+/// );
+/// # }
+/// ```
+impl AsyncCommandExt for Command {
+    type Error = Error;
+    type Child = ChildContext<Child>;
+
+    fn output_checked_as<O, R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        let location = Location::caller();
+        async move {
+            self.log()?;
+            let started = Started::now();
+            let result = self.output().await;
+            let displayed = display(self, location);
+            match result {
+                Ok(output) => {
+                    started.log_completion(&displayed, output.status);
+                    Ran::new(output, displayed).check_output(succeeded)
+                }
+                Err(inner) => Err(run::exec_error(displayed, self.get_current_dir(), inner).into()),
+            }
+        }
+    }
+
+    fn status_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        E: From<Self::Error>,
+    {
+        let location = Location::caller();
+        async move {
+            self.log()?;
+            let started = Started::now();
+            let result = self.status().await;
+            let displayed = display(self, location);
+            match result {
+                Ok(status) => {
+                    started.log_completion(&displayed, status);
+                    Ran::new(status, displayed).check_status(succeeded)
+                }
+                Err(inner) => Err(run::exec_error(displayed, self.get_current_dir(), inner).into()),
+            }
+        }
+    }
+
+    fn spawn_checked(&mut self) -> Result<Self::Child, Self::Error> {
+        self.log()?;
+        let displayed = display(self, Location::caller());
+        match self.spawn() {
+            Ok(child) => Ok(ChildContext::new(child, displayed)),
+            Err(inner) => Err(Error::from(
+                ExecError::new(displayed, inner).with_current_dir(self.get_current_dir()),
+            )),
+        }
+    }
+
+    fn log(&self) -> Result<(), Self::Error> {
+        #[cfg(feature = "tracing")]
+        {
+            let command = Utf8ProgramAndArgs::from(self);
+            tracing::debug!(%command, "Executing command");
+        }
+        Ok(())
+    }
+}
+
+/// Display `command` for an error or context object, recording that it was run from `location`.
+fn display(command: &Command, location: &'static Location<'static>) -> Box<Utf8ProgramAndArgs> {
+    Box::new(Utf8ProgramAndArgs::from(command).with_location(location))
+}
+
+impl AsyncChildExt for ChildContext<Child> {
+    type Error = Error;
+
+    async fn output_checked_as<O, R, E>(
+        self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E> + Send,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error>,
+    {
+        let command = dyn_clone::clone_box(self.command.borrow());
+        match self.child.output().await {
+            Ok(output) => Ran::new(output, command).check_output(succeeded),
+            Err(inner) => Err(Error::from(WaitError { command, inner }).into()),
+        }
+    }
+
+    async fn wait_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E> + Send,
+    ) -> Result<R, E>
+    where
+        E: From<Self::Error>,
+    {
+        let command = dyn_clone::clone_box(self.command.borrow());
+        match self.child.status().await {
+            Ok(status) => Ran::new(status, command).check_status(succeeded),
+            Err(inner) => Err(Error::from(WaitError { command, inner }).into()),
+        }
+    }
+}
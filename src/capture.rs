@@ -0,0 +1,747 @@
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::process::Child;
+use std::process::ChildStderr;
+use std::process::ChildStdin;
+use std::process::ChildStdout;
+use std::process::ExitStatus;
+use std::process::Output;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::run;
+use crate::CaptureError;
+use crate::Clock;
+use crate::CommandDisplay;
+use crate::Error;
+use crate::OutputStream;
+use crate::StdinError;
+use crate::StreamTimings;
+use crate::Termination;
+
+/// The size of the buffer used for each read from a child's output pipes.
+pub(crate) const CHUNK_SIZE: usize = 8 * 1024;
+
+/// How much of each stream [`capture`] keeps when it's asked not to keep everything.
+///
+/// This is enough for the end of the output to be shown in an error message.
+pub(crate) const RETAINED_TAIL: usize = 64 * 1024;
+
+/// How often to check if the child has exited while its output is being read.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long to keep reading output after a child that timed out has been stopped.
+///
+/// If the child started a background process which inherited its output pipes, the pipes may
+/// stay open indefinitely; after this long, the output read so far is returned.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// A child process which can be waited for.
+///
+/// This lets [`capture`] work with both [`Child`] and `process_wrap` children.
+pub(crate) trait WaitChild {
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>>;
+
+    fn wait(&mut self) -> std::io::Result<ExitStatus>;
+
+    /// Ask the child to exit (with `SIGTERM` on Unix), returning `false` if that isn't supported
+    /// on this platform.
+    fn terminate(&mut self) -> std::io::Result<bool>;
+
+    /// Forcibly kill the child.
+    fn kill(&mut self) -> std::io::Result<()>;
+}
+
+impl WaitChild for Child {
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        Child::try_wait(self)
+    }
+
+    fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        Child::wait(self)
+    }
+
+    #[cfg(unix)]
+    fn terminate(&mut self) -> std::io::Result<bool> {
+        // SAFETY: `kill` has no memory-safety preconditions. The child hasn't been reaped (we'd
+        // have its exit status), so its PID can't have been reused.
+        if unsafe { libc::kill(self.id() as libc::pid_t, libc::SIGTERM) } == 0 {
+            Ok(true)
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn terminate(&mut self) -> std::io::Result<bool> {
+        Ok(false)
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        Child::kill(self)
+    }
+}
+
+/// Notices gaps in a child's output, for [`capture_on_stall`].
+struct StallWatch<'a> {
+    interval: Duration,
+    /// When output was last read, or when reading started.
+    last_output: Instant,
+    /// Whether `on_stall` has been called for the current gap.
+    warned: bool,
+    on_stall: &'a mut dyn FnMut(Duration),
+}
+
+impl<'a> StallWatch<'a> {
+    fn new(interval: Duration, on_stall: &'a mut dyn FnMut(Duration)) -> Self {
+        Self {
+            interval,
+            last_output: Instant::now(),
+            warned: false,
+            on_stall,
+        }
+    }
+
+    /// Note that output was just read.
+    fn output(&mut self) {
+        self.last_output = Instant::now();
+        self.warned = false;
+    }
+
+    /// Call `on_stall` if no output has been read for longer than the interval.
+    fn poll(&mut self) {
+        let gap = self.last_output.elapsed();
+        if !self.warned && gap > self.interval {
+            self.warned = true;
+            (self.on_stall)(gap);
+        }
+    }
+}
+
+/// Stops a child which runs for too long, for [`capture_with_timeout`].
+///
+/// Deadlines are measured with `clock`. Everything else (polling, draining output) uses the real
+/// time, so a fake clock only changes when the child is stopped.
+struct Stopper<'a> {
+    state: StopState,
+    grace: Duration,
+    clock: &'a dyn Clock,
+}
+
+enum StopState {
+    /// Waiting for the child to exit before the deadline.
+    Running { deadline: Instant },
+    /// The child was asked to exit, and will be killed if it doesn't by `kill_at`.
+    Terminating { kill_at: Instant },
+    /// The child was killed.
+    Killed(Termination),
+}
+
+impl<'a> Stopper<'a> {
+    fn new(timeout: Duration, grace: Duration, clock: &'a dyn Clock) -> Self {
+        Self {
+            state: StopState::Running {
+                deadline: clock.now() + timeout,
+            },
+            grace,
+            clock,
+        }
+    }
+
+    /// Terminate or kill the child if it's past its deadline.
+    fn poll(&mut self, child: &mut impl WaitChild) -> std::io::Result<()> {
+        let now = self.clock.now();
+        match self.state {
+            StopState::Running { deadline } if now > deadline => {
+                if !self.grace.is_zero() && child.terminate()? {
+                    self.state = StopState::Terminating {
+                        kill_at: now + self.grace,
+                    };
+                } else {
+                    child.kill()?;
+                    self.state = StopState::Killed(Termination::Killed);
+                }
+            }
+            StopState::Terminating { kill_at } if now > kill_at => {
+                child.kill()?;
+                self.state = StopState::Killed(Termination::KilledAfterGrace(self.grace));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// How the child was stopped, if it timed out.
+    fn termination(&self) -> Option<Termination> {
+        match self.state {
+            StopState::Running { .. } => None,
+            StopState::Terminating { .. } => Some(Termination::Graceful),
+            StopState::Killed(termination) => Some(termination),
+        }
+    }
+}
+
+/// An error while capturing a child's output and waiting for it to exit.
+pub(crate) enum CaptureFailure {
+    /// Reading the child's output failed.
+    Read(std::io::Error),
+    /// Waiting for or stopping the child failed.
+    Wait(std::io::Error),
+    /// Writing the child's input failed.
+    Stdin(std::io::Error),
+}
+
+impl CaptureFailure {
+    /// Convert this failure into an [`Error`] for `command`.
+    pub(crate) fn into_error(self, command: Box<dyn CommandDisplay + Send + Sync>) -> Error {
+        match self {
+            CaptureFailure::Read(inner) => Error::from(CaptureError::new(command, inner)),
+            CaptureFailure::Wait(inner) => run::wait_error(command, inner),
+            CaptureFailure::Stdin(inner) => Error::from(StdinError::new(command, inner)),
+        }
+    }
+}
+
+/// A child's complete output, read by [`capture`].
+pub(crate) struct Captured {
+    pub(crate) output: Output,
+    /// When the child's output streams were read and closed, relative to its exit.
+    pub(crate) timings: StreamTimings,
+}
+
+/// Read `stdout` and `stderr` to completion, passing each chunk to `on_chunk` as it arrives, and
+/// wait for `child` to exit.
+///
+/// Each stream is read on its own thread, and chunks are sent to the calling thread through a
+/// channel holding at most `bound` chunks. If `on_chunk` can't keep up, the reader threads block,
+/// the pipes fill, and the child blocks on its writes, rather than unread chunks accumulating in
+/// the channel.
+///
+/// At most the last `retain` bytes of each stream are kept in the returned output; pass
+/// `usize::MAX` to keep everything, or [`RETAINED_TAIL`] to keep memory use bounded.
+///
+/// While reading, the child is polled so that we know when it exited relative to when its streams
+/// were closed.
+pub(crate) fn capture(
+    child: &mut impl WaitChild,
+    stdout: Option<impl Read + Send + 'static>,
+    stderr: Option<impl Read + Send + 'static>,
+    bound: usize,
+    retain: usize,
+    on_chunk: impl FnMut(OutputStream, &[u8]),
+) -> Result<Captured, CaptureFailure> {
+    capture_inner(child, stdout, stderr, bound, retain, on_chunk, None, None)
+        .map(|(captured, _)| captured)
+}
+
+/// Read `stdout` and `stderr` to completion and wait for `child` to exit, calling `on_stall` with
+/// the time since output was last read whenever no output is read for longer than `interval`.
+///
+/// `on_stall` is called once for each gap in the output, and the child is left running.
+pub(crate) fn capture_on_stall(
+    child: &mut impl WaitChild,
+    stdout: Option<impl Read + Send + 'static>,
+    stderr: Option<impl Read + Send + 'static>,
+    interval: Duration,
+    on_stall: &mut dyn FnMut(Duration),
+) -> Result<Captured, CaptureFailure> {
+    capture_inner(
+        child,
+        stdout,
+        stderr,
+        STALL_BOUND,
+        usize::MAX,
+        |_, _| {},
+        None,
+        Some(StallWatch::new(interval, on_stall)),
+    )
+    .map(|(captured, _)| captured)
+}
+
+/// The number of chunks buffered by [`capture_on_stall`].
+const STALL_BOUND: usize = 16;
+
+/// Write `input` to `stdin`, while reading `stdout` and `stderr` to completion and waiting for
+/// `child` to exit.
+///
+/// If `close_stdin` is true, `stdin` is closed after `input` is written; otherwise, it's kept
+/// open until `child` exits.
+///
+/// `input` is written on a scoped thread, so the child can't deadlock by filling its output pipes
+/// before it has read all of its input. If the child exits without reading all of its input, the
+/// broken pipe is ignored; whether the child succeeded is up to its exit status.
+pub(crate) fn capture_with_input<W: Write + Send>(
+    child: &mut impl WaitChild,
+    stdin: W,
+    stdout: Option<impl Read + Send + 'static>,
+    stderr: Option<impl Read + Send + 'static>,
+    input: &[u8],
+    close_stdin: bool,
+) -> Result<Captured, CaptureFailure> {
+    thread::scope(|scope| {
+        let writer = scope.spawn(move || {
+            let mut stdin = stdin;
+            let written = match stdin.write_all(input) {
+                Err(error) if error.kind() == ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            };
+            // Returning `stdin` keeps it open until the thread is joined, after `child` exits.
+            (written, (!close_stdin).then_some(stdin))
+        });
+        let captured = capture(child, stdout, stderr, INPUT_BOUND, usize::MAX, |_, _| {});
+        if captured.is_err() {
+            // Don't leave the writer blocked on a child which will never read its input.
+            let _ = child.kill();
+        }
+        // The writer thread doesn't panic.
+        let (written, stdin) = writer.join().unwrap_or((Ok(()), None::<W>));
+        drop(stdin);
+        let captured = captured?;
+        written.map_err(CaptureFailure::Stdin)?;
+        Ok(captured)
+    })
+}
+
+/// The number of chunks buffered by [`capture_with_input`].
+const INPUT_BOUND: usize = 16;
+
+/// Read `stdout` and `stderr` to completion and wait for `child` to exit, stopping it if it runs
+/// for longer than `timeout`.
+///
+/// When the timeout expires, the child is asked to exit (with `SIGTERM` on Unix), and then killed
+/// if it's still running after `grace`. If `grace` is zero or graceful termination isn't
+/// supported, the child is killed immediately. A child which exits exactly at the deadline isn't
+/// considered to have timed out.
+///
+/// The timeout and grace period are measured with `clock`.
+///
+/// Returns how the child was stopped, if it timed out, along with the output it wrote before it
+/// was stopped.
+pub(crate) fn capture_with_timeout(
+    child: &mut impl WaitChild,
+    stdout: Option<impl Read + Send + 'static>,
+    stderr: Option<impl Read + Send + 'static>,
+    timeout: Duration,
+    grace: Duration,
+    clock: &dyn Clock,
+) -> Result<(Captured, Option<Termination>), CaptureFailure> {
+    capture_inner(
+        child,
+        stdout,
+        stderr,
+        TIMEOUT_BOUND,
+        usize::MAX,
+        |_, _| {},
+        Some(Stopper::new(timeout, grace, clock)),
+        None,
+    )
+}
+
+/// Wait for `child` to exit without reading its output, stopping it if it runs for longer than
+/// `timeout`.
+///
+/// See [`capture_with_timeout`].
+pub(crate) fn wait_with_timeout(
+    child: &mut impl WaitChild,
+    timeout: Duration,
+    grace: Duration,
+    clock: &dyn Clock,
+) -> Result<(ExitStatus, Option<Termination>), CaptureFailure> {
+    capture_with_timeout(
+        child,
+        None::<std::io::Empty>,
+        None::<std::io::Empty>,
+        timeout,
+        grace,
+        clock,
+    )
+    .map(|(captured, termination)| (captured.output.status, termination))
+}
+
+/// The number of chunks buffered by [`capture_with_timeout`].
+const TIMEOUT_BOUND: usize = 16;
+
+#[allow(clippy::too_many_arguments)]
+fn capture_inner(
+    child: &mut impl WaitChild,
+    stdout: Option<impl Read + Send + 'static>,
+    stderr: Option<impl Read + Send + 'static>,
+    bound: usize,
+    retain: usize,
+    mut on_chunk: impl FnMut(OutputStream, &[u8]),
+    mut stopper: Option<Stopper<'_>>,
+    mut stall: Option<StallWatch<'_>>,
+) -> Result<(Captured, Option<Termination>), CaptureFailure> {
+    let (sender, receiver) = mpsc::sync_channel(bound);
+    let mut readers = Vec::with_capacity(2);
+    if let Some(stdout) = stdout {
+        readers.push(spawn_reader(OutputStream::Stdout, stdout, sender.clone()));
+    }
+    if let Some(stderr) = stderr {
+        readers.push(spawn_reader(OutputStream::Stderr, stderr, sender.clone()));
+    }
+    drop(sender);
+
+    let started = Instant::now();
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut stdout_first_byte = None;
+    let mut stderr_first_byte = None;
+    let mut stdout_closed = None;
+    let mut stderr_closed = None;
+    let mut exit: Option<(ExitStatus, Instant)> = None;
+    let mut result = Ok(());
+    let mut open = readers.len();
+
+    while open > 0 {
+        let timed_out = stopper.as_ref().and_then(Stopper::termination).is_some();
+        let message = match exit {
+            None => match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(message) => Some(message),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            },
+            Some((_, exited)) if timed_out => {
+                let remaining = (exited + DRAIN_TIMEOUT).saturating_duration_since(Instant::now());
+                match receiver.recv_timeout(remaining) {
+                    Ok(message) => Some(message),
+                    // Give up on the streams; the reader threads are left to finish on their own.
+                    Err(_) => break,
+                }
+            }
+            Some(_) => match receiver.recv() {
+                Ok(message) => Some(message),
+                Err(mpsc::RecvError) => break,
+            },
+        };
+
+        if exit.is_none() {
+            let polled = child.try_wait().and_then(|status| {
+                if status.is_none() {
+                    if let Some(stopper) = &mut stopper {
+                        stopper.poll(child)?;
+                    }
+                }
+                Ok(status)
+            });
+            match polled {
+                Ok(Some(status)) => exit = Some((status, Instant::now())),
+                Ok(None) => {
+                    if let Some(stall) = &mut stall {
+                        stall.poll();
+                    }
+                }
+                Err(error) => {
+                    abandon(child, receiver, readers);
+                    return Err(CaptureFailure::Wait(error));
+                }
+            }
+        }
+
+        match message {
+            Some(Message::Chunk(stream, chunk)) => {
+                on_chunk(stream, &chunk);
+                let (output, first_byte) = match stream {
+                    OutputStream::Stdout => (&mut stdout, &mut stdout_first_byte),
+                    OutputStream::Stderr => (&mut stderr, &mut stderr_first_byte),
+                };
+                if !chunk.is_empty() {
+                    if first_byte.is_none() {
+                        *first_byte = Some(Instant::now());
+                    }
+                    if let Some(stall) = &mut stall {
+                        stall.output();
+                    }
+                }
+                append_tail(output, &chunk, retain);
+            }
+            Some(Message::Closed(stream, closed_result)) => {
+                open -= 1;
+                let closed = Some(Instant::now());
+                match stream {
+                    OutputStream::Stdout => stdout_closed = closed,
+                    OutputStream::Stderr => stderr_closed = closed,
+                }
+                if result.is_ok() {
+                    result = closed_result;
+                }
+            }
+            None => {}
+        }
+    }
+
+    if open == 0 {
+        for reader in readers {
+            // The reader threads don't panic.
+            let _ = reader.join();
+        }
+    }
+    if let Err(error) = result {
+        let _ = child.kill();
+        return Err(CaptureFailure::Read(error));
+    }
+
+    let (status, exited) = match (exit, &mut stopper) {
+        (Some(exit), _) => exit,
+        (None, None) => (child.wait().map_err(CaptureFailure::Wait)?, Instant::now()),
+        // The child closed its streams but is still running, so keep enforcing the timeout.
+        (None, Some(stopper)) => loop {
+            let polled = child.try_wait().and_then(|status| {
+                if status.is_none() {
+                    stopper.poll(child)?;
+                }
+                Ok(status)
+            });
+            match polled {
+                Ok(Some(status)) => break (status, Instant::now()),
+                Ok(None) => thread::sleep(POLL_INTERVAL),
+                Err(error) => {
+                    let _ = child.kill();
+                    return Err(CaptureFailure::Wait(error));
+                }
+            }
+        },
+    };
+
+    Ok((
+        Captured {
+            output: Output {
+                status,
+                stdout,
+                stderr,
+            },
+            timings: StreamTimings {
+                started,
+                exited,
+                stdout_first_byte,
+                stderr_first_byte,
+                stdout_closed,
+                stderr_closed,
+            },
+        },
+        stopper.as_ref().and_then(Stopper::termination),
+    ))
+}
+
+/// Append `chunk` to `output`, dropping bytes from the front so that at most `retain` are kept.
+fn append_tail(output: &mut Vec<u8>, chunk: &[u8], retain: usize) {
+    if chunk.len() >= retain {
+        output.clear();
+        output.extend_from_slice(&chunk[chunk.len() - retain..]);
+        return;
+    }
+    let excess = (output.len() + chunk.len()).saturating_sub(retain);
+    output.drain(..excess);
+    output.extend_from_slice(chunk);
+}
+
+/// Clean up after waiting for or stopping `child` failed partway through [`capture_inner`].
+///
+/// The child is killed, so that its pipes close and the reader threads reach the end of their
+/// streams; dropping `receiver` stops them if they're blocked on a full channel instead. The
+/// reader threads are joined once they finish, unless the pipes are held open (by a background
+/// process which inherited them) for longer than [`DRAIN_TIMEOUT`], in which case they're left to
+/// finish on their own.
+fn abandon(
+    child: &mut impl WaitChild,
+    receiver: mpsc::Receiver<Message>,
+    readers: Vec<thread::JoinHandle<()>>,
+) {
+    let _ = child.kill();
+    drop(receiver);
+    let deadline = Instant::now() + DRAIN_TIMEOUT;
+    while readers.iter().any(|reader| !reader.is_finished()) && Instant::now() < deadline {
+        thread::sleep(POLL_INTERVAL);
+    }
+    for reader in readers {
+        if reader.is_finished() {
+            // The reader threads don't panic.
+            let _ = reader.join();
+        }
+    }
+}
+
+/// A closure which drives a conversation with a child over its stdin and stdout.
+pub(crate) type Interact<'a> =
+    Box<dyn FnOnce(&mut ChildStdin, &mut ChildStdout) -> std::io::Result<()> + 'a>;
+
+/// Hand a child's stdin and stdout to `interact`, then read the rest of its output and wait for it
+/// to exit.
+///
+/// Stderr is read on a background thread while `interact` runs, so the child can't block on it.
+/// Stdin is closed once `interact` returns. If `interact` fails, the child is killed, and the error
+/// is returned along with the child's output.
+pub(crate) fn interact(
+    child: &mut impl WaitChild,
+    mut stdin: ChildStdin,
+    mut stdout: ChildStdout,
+    stderr: Option<ChildStderr>,
+    interact: Interact<'_>,
+) -> Result<(Output, Option<std::io::Error>), CaptureFailure> {
+    let stderr = stderr.map(|mut stderr| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            stderr.read_to_end(&mut buffer).map(|_| buffer)
+        })
+    });
+
+    let interact_error = interact(&mut stdin, &mut stdout).err();
+    drop(stdin);
+    if interact_error.is_some() {
+        child.kill().map_err(CaptureFailure::Wait)?;
+    }
+
+    let mut rest = Vec::new();
+    stdout
+        .read_to_end(&mut rest)
+        .map_err(CaptureFailure::Read)?;
+    let status = child.wait().map_err(CaptureFailure::Wait)?;
+    let stderr = match stderr {
+        // The reader thread doesn't panic.
+        Some(reader) => reader
+            .join()
+            .unwrap_or_else(|_| Ok(Vec::new()))
+            .map_err(CaptureFailure::Read)?,
+        None => Vec::new(),
+    };
+
+    Ok((
+        Output {
+            status,
+            stdout: rest,
+            stderr,
+        },
+        interact_error,
+    ))
+}
+
+/// Read `stdout` and `stderr` to completion into `stdout_buffer` and `stderr_buffer`, and wait for
+/// `child` to exit.
+///
+/// The buffers are cleared first, so their allocations are reused. `stderr` is read on a scoped
+/// thread while `stdout` is read on the calling thread.
+pub(crate) fn read_into(
+    child: &mut impl WaitChild,
+    stdout: Option<impl Read + Send>,
+    stderr: Option<impl Read + Send>,
+    stdout_buffer: &mut Vec<u8>,
+    stderr_buffer: &mut Vec<u8>,
+) -> Result<ExitStatus, CaptureFailure> {
+    stdout_buffer.clear();
+    stderr_buffer.clear();
+    thread::scope(|scope| {
+        let stderr =
+            stderr.map(|mut stderr| scope.spawn(move || stderr.read_to_end(stderr_buffer)));
+        let stdout = match stdout {
+            Some(mut stdout) => stdout.read_to_end(stdout_buffer),
+            None => Ok(0),
+        };
+        let stderr = match stderr {
+            // The reader thread doesn't panic.
+            Some(reader) => reader.join().unwrap_or(Ok(0)),
+            None => Ok(0),
+        };
+        stdout.and(stderr).map_err(CaptureFailure::Read)
+    })?;
+    child.wait().map_err(CaptureFailure::Wait)
+}
+
+enum Message {
+    Chunk(OutputStream, Vec<u8>),
+    Closed(OutputStream, std::io::Result<()>),
+}
+
+fn spawn_reader(
+    stream: OutputStream,
+    mut reader: impl Read + Send + 'static,
+    sender: mpsc::SyncSender<Message>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buffer = vec![0; CHUNK_SIZE];
+        let result = loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break Ok(()),
+                Ok(n) => {
+                    if sender
+                        .send(Message::Chunk(stream, buffer[..n].to_vec()))
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(error) => break Err(error),
+            }
+        };
+        let _ = sender.send(Message::Closed(stream, result));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_append_tail() {
+        let mut output = Vec::new();
+        append_tail(&mut output, b"puppy", 8);
+        assert_eq!(output, b"puppy");
+        append_tail(&mut output, b"doggy", 8);
+        assert_eq!(output, b"ppydoggy");
+        append_tail(&mut output, b"kitty cat", 8);
+        assert_eq!(output, b"itty cat");
+        append_tail(&mut output, b"puppy", usize::MAX);
+        assert_eq!(output, b"itty catpuppy");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_failure_kills_child() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::Command;
+        use std::process::Stdio;
+
+        /// A child which can't be polled.
+        struct Unpollable(Child);
+
+        impl WaitChild for Unpollable {
+            fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+                Err(std::io::Error::other("Polling failed"))
+            }
+
+            fn wait(&mut self) -> std::io::Result<ExitStatus> {
+                self.0.wait()
+            }
+
+            fn terminate(&mut self) -> std::io::Result<bool> {
+                Ok(false)
+            }
+
+            fn kill(&mut self) -> std::io::Result<()> {
+                self.0.kill()
+            }
+        }
+
+        let mut child = Unpollable(
+            Command::new("sleep")
+                .arg("60")
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .unwrap(),
+        );
+        let stdout = child.0.stdout.take();
+        let stderr = child.0.stderr.take();
+        let started = Instant::now();
+        match capture(&mut child, stdout, stderr, 1, usize::MAX, |_, _| {}) {
+            Err(CaptureFailure::Wait(error)) => assert_eq!(error.to_string(), "Polling failed"),
+            _ => panic!("Capturing from an unpollable child didn't fail to wait"),
+        }
+        assert!(started.elapsed() < Duration::from_secs(30));
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGKILL));
+    }
+}
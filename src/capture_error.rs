@@ -0,0 +1,157 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+
+use crate::command_display::guarded;
+use crate::messages::messages;
+use crate::CommandDisplay;
+#[cfg(doc)]
+use crate::CommandExt;
+#[cfg(doc)]
+use crate::OutputError;
+#[cfg(feature = "miette")]
+use miette::Diagnostic;
+
+/// An error from failing to capture a command's output, like running out of disk space or memory
+/// while reading it.
+///
+/// This is a failure to handle the command's output, rather than a failure of the command itself
+/// (an [`OutputError`]). Produced by methods which read the command's output themselves, like
+/// [`CommandExt::output_checked_with_backpressure`]; [`CommandExt::output_checked`] relies on
+/// [`std::process::Command::output`], which doesn't distinguish capture failures from other I/O
+/// errors.
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::Utf8ProgramAndArgs;
+/// # use command_error::CaptureError;
+/// let command = Command::new("foo");
+/// let error = CaptureError::new(
+///     Box::new(Utf8ProgramAndArgs::from(&command)),
+///     std::io::Error::new(std::io::ErrorKind::StorageFull, "No space left on device"),
+/// );
+/// assert_eq!(
+///     error.to_string(),
+///     "Failed to capture output of `foo`: No space left on device"
+/// );
+/// assert!(error.is_storage_full());
+/// ```
+pub struct CaptureError {
+    pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
+    pub(crate) inner: std::io::Error,
+}
+
+impl CaptureError {
+    /// Construct a new [`CaptureError`].
+    pub fn new(
+        command: impl Into<Box<dyn CommandDisplay + Send + Sync>>,
+        inner: std::io::Error,
+    ) -> Self {
+        Self {
+            command: command.into(),
+            inner,
+        }
+    }
+
+    /// Get the [`std::io::ErrorKind`] of the underlying error.
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.inner.kind()
+    }
+
+    /// Get a reference to the underlying error.
+    pub fn inner(&self) -> &std::io::Error {
+        &self.inner
+    }
+
+    /// True if the output couldn't be captured because a disk or quota is full (`ENOSPC` or
+    /// `EDQUOT`).
+    pub fn is_storage_full(&self) -> bool {
+        matches!(
+            self.inner.kind(),
+            std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded
+        )
+    }
+}
+
+impl Debug for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CaptureError")
+            .field("program", &guarded(&*self.command).program())
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} `{}`: {}",
+            messages().failed_to_capture_output_of,
+            guarded(&*self.command).program_quoted(),
+            self.inner
+        )
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for CaptureError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::capture"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_assertions::assert_impl_all;
+
+    assert_impl_all!(CaptureError: Send, Sync);
+
+    #[test]
+    fn test_capture_read_failure() {
+        use std::process::Command;
+
+        use crate::capture;
+        use crate::Error;
+        use crate::Utf8ProgramAndArgs;
+
+        struct FullDisk;
+
+        impl std::io::Read for FullDisk {
+            fn read(&mut self, _buffer: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::StorageFull,
+                    "No space left on device",
+                ))
+            }
+        }
+
+        let mut command = Command::new("true");
+        let mut child = command.spawn().unwrap();
+        let failure = match capture::capture(
+            &mut child,
+            Some(FullDisk),
+            None::<FullDisk>,
+            1,
+            usize::MAX,
+            |_, _| {},
+        ) {
+            Ok(_) => panic!("Capturing from a failing reader succeeded"),
+            Err(failure) => failure,
+        };
+        let _ = child.wait();
+        match failure.into_error(Box::new(Utf8ProgramAndArgs::from(&command))) {
+            Error::Capture(error) => {
+                assert!(error.is_storage_full());
+                assert_eq!(
+                    error.to_string(),
+                    "Failed to capture output of `true`: No space left on device"
+                );
+            }
+            error => panic!("Unexpected error: {error}"),
+        }
+    }
+}
@@ -1,16 +1,23 @@
 use std::borrow::Borrow;
 use std::fmt::Debug;
-
-#[cfg(doc)]
 use std::process::Child;
+
 #[cfg(doc)]
 use std::process::Command;
 
+use crate::command_display::guarded;
+use crate::CaptureError;
 #[cfg(doc)]
 use crate::ChildExt;
 use crate::CommandDisplay;
+use crate::Error;
 #[cfg(doc)]
 use crate::OutputContext;
+use crate::Progress;
+use crate::ProgressReaders;
+use crate::StdioConfig;
+use crate::Utf8ProgramAndArgs;
+use crate::WaitError;
 
 /// A [`Child`] process combined with context about the [`Command`] that produced it.
 ///
@@ -20,9 +27,76 @@ use crate::OutputContext;
 pub struct ChildContext<C> {
     pub(crate) child: C,
     pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
+    /// Readers for the child's output, started by [`ChildContext::poll_progress`].
+    pub(crate) progress: Option<ProgressReaders>,
+    /// How the child's standard streams were connected when it was spawned, if known.
+    pub(crate) stdio: Option<StdioConfig>,
 }
 
 impl<C> ChildContext<C> {
+    /// Construct a new [`ChildContext`] from a child process and the command which produced it.
+    ///
+    /// This is the way to adopt a child process spawned elsewhere (for example, by a third-party
+    /// sandbox launcher) so that it can be checked with [`ChildExt`], with the command shown in
+    /// every error message:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::ChildContext;
+    /// # use command_error::ChildExt;
+    /// # use command_error::Utf8ProgramAndArgs;
+    /// let mut command = Command::new("sh");
+    /// command.args(["-c", "exit 1"]);
+    /// let child = command.spawn().unwrap();
+    ///
+    /// let mut child = ChildContext::new(child, Box::new(Utf8ProgramAndArgs::from(&command)));
+    /// assert_eq!(
+    ///     child.wait_checked().unwrap_err().to_string(),
+    ///     "`sh` failed: exit status: 1\nCommand failed: `sh -c 'exit 1'`"
+    /// );
+    /// ```
+    pub fn new(child: C, command: impl Into<Box<dyn CommandDisplay + Send + Sync>>) -> Self {
+        Self {
+            child,
+            command: command.into(),
+            progress: None,
+            stdio: None,
+        }
+    }
+
+    /// Note how the child's standard streams were connected when it was spawned.
+    pub(crate) fn with_stdio(mut self, stdio: StdioConfig) -> Self {
+        self.stdio = Some(stdio);
+        self
+    }
+
+    /// Construct a new [`ChildContext`] from a child process and the program and arguments which
+    /// produced it.
+    ///
+    /// This is a convenience for [`ChildContext::new`] when the [`Command`] itself isn't
+    /// available:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::ChildContext;
+    /// # use command_error::ChildExt;
+    /// let child = Command::new("sh").args(["-c", "exit 1"]).spawn().unwrap();
+    ///
+    /// let mut child = ChildContext::adopt(child, "sh", &["-c", "exit 1"]);
+    /// assert_eq!(
+    ///     child.wait_checked().unwrap_err().to_string(),
+    ///     "`sh` failed: exit status: 1\nCommand failed: `sh -c 'exit 1'`"
+    /// );
+    /// ```
+    pub fn adopt(child: C, program: &str, args: &[&str]) -> Self {
+        Self::new(
+            child,
+            Box::new(Utf8ProgramAndArgs::from_parts(program, args)),
+        )
+    }
+
     /// Get the child process.
     pub fn into_child(self) -> C {
         self.child
@@ -44,6 +118,92 @@ impl<C> ChildContext<C> {
     }
 }
 
+impl ChildContext<Child> {
+    /// Check if the child process has exited and get any output it's written since the last call,
+    /// without blocking.
+    ///
+    /// The first call makes reads from the child's piped stdout and stderr non-blocking (with
+    /// `O_NONBLOCK` on Unix, and by peeking at the pipes on Windows); each call then reads
+    /// whatever output is available, after checking whether the child has exited. Output is kept
+    /// as it's read, so [`ChildExt`] methods like [`output_checked`][ChildExt::output_checked]
+    /// still produce the complete output (and the usual error messages) after the child has been
+    /// polled. Streams that aren't piped produce no output.
+    ///
+    /// If the child has exited, all the output it wrote is reported, unless a background process
+    /// it started keeps writing to its pipes.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use std::process::Stdio;
+    /// # use command_error::ChildExt;
+    /// # use command_error::CommandExt;
+    /// let mut child = Command::new("sh")
+    ///     .args(["-c", "echo puppy; exit 1"])
+    ///     .stdout(Stdio::piped())
+    ///     .stderr(Stdio::piped())
+    ///     .spawn_checked()
+    ///     .unwrap();
+    ///
+    /// while child.poll_progress().unwrap().running {
+    ///     std::thread::sleep(std::time::Duration::from_millis(10));
+    /// }
+    ///
+    /// let err = child.output_checked().unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exit status: 1
+    ///         Command failed: `sh -c 'echo puppy; exit 1'`
+    ///         Stdout:
+    ///           puppy"
+    ///     )
+    /// );
+    /// ```
+    pub fn poll_progress(&mut self) -> Result<Progress, Error> {
+        // The readers take the child's streams, so they can't be examined afterwards.
+        self.stdio
+            .get_or_insert_with(|| StdioConfig::of(&self.child));
+        let progress = match &mut self.progress {
+            Some(progress) => progress,
+            None => match ProgressReaders::new(&mut self.child) {
+                Ok(progress) => self.progress.insert(progress),
+                Err(inner) => {
+                    return Err(Error::from(CaptureError::new(
+                        dyn_clone::clone_box(self.command.borrow()),
+                        inner,
+                    )));
+                }
+            },
+        };
+        let status = match self.child.try_wait() {
+            Ok(status) => status,
+            Err(inner) => {
+                return Err(Error::from(WaitError {
+                    inner,
+                    command: dyn_clone::clone_box(self.command.borrow()),
+                }));
+            }
+        };
+        let (new_stdout, new_stderr) = match progress.take_new() {
+            Ok(new) => new,
+            Err(inner) => {
+                return Err(Error::from(CaptureError::new(
+                    dyn_clone::clone_box(self.command.borrow()),
+                    inner,
+                )));
+            }
+        };
+        Ok(Progress {
+            running: status.is_none(),
+            new_stdout,
+            new_stderr,
+            status,
+        })
+    }
+}
+
 impl<C> Debug for ChildContext<C>
 where
     C: Debug,
@@ -51,7 +211,7 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ChildContext")
             .field("child", &self.child)
-            .field("command", &self.command.to_string())
+            .field("command", &guarded(&*self.command).to_string())
             .finish()
     }
 }
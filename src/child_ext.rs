@@ -7,16 +7,18 @@ use std::process::Output;
 
 use utf8_command::Utf8Output;
 
+use crate::run::Ran;
 use crate::ChildContext;
 #[cfg(doc)]
 use crate::CommandExt;
 
 use crate::Error;
-use crate::ExecError;
 use crate::OutputContext;
-use crate::OutputConversionError;
 use crate::OutputLike;
-use crate::TryWaitContext;
+use crate::StdioConfig;
+use crate::Supervisor;
+use crate::SupervisorAction;
+use crate::TryWaitExt;
 use crate::WaitError;
 
 /// Checked methods for [`Child`] processes.
@@ -28,10 +30,10 @@ use crate::WaitError;
 /// Additionally, methods that return an [`ExitStatus`] are named
 /// [`wait_checked`][`ChildExt::wait_checked`] instead of
 /// [`status_checked`][`CommandExt::status_checked`], to match the method names on [`Child`].
-pub trait ChildExt: Sized {
-    /// The error type returned from methods on this trait.
-    type Error: From<Error>;
-
+///
+/// Non-blocking polling (like [`try_wait_checked`][`TryWaitExt::try_wait_checked`]) is provided
+/// by the [`TryWaitExt`] supertrait.
+pub trait ChildExt: TryWaitExt + Sized {
     /// Wait for the process to complete, capturing its output. `succeeded` is called and returned
     /// to determine if the command succeeded.
     ///
@@ -122,42 +124,6 @@ pub trait ChildExt: Sized {
         self.output_checked_with(succeeded)
     }
 
-    /// Check if the process has exited.
-    ///
-    /// The `succeeded` closure is called and returned to determine the result.
-    ///
-    /// Errors while attempting to retrieve the process's exit status are returned as
-    /// [`WaitError`]s.
-    ///
-    /// See [`Child::try_wait`] for more information.
-    #[track_caller]
-    fn try_wait_checked_as<R, E>(
-        &mut self,
-        succeeded: impl Fn(TryWaitContext) -> Result<R, E>,
-    ) -> Result<R, E>
-    where
-        E: From<Self::Error>;
-
-    /// Check if the process has exited and, if it failed, return an error.
-    ///
-    /// Errors while attempting to retrieve the process's exit status are transformed into
-    /// [`WaitError`]s.
-    ///
-    /// See [`Child::try_wait`] for more information.
-    #[track_caller]
-    fn try_wait_checked(&mut self) -> Result<Option<ExitStatus>, Self::Error> {
-        self.try_wait_checked_as(|context| match context.into_output_context() {
-            Some(context) => {
-                if context.status().success() {
-                    Ok(Some(context.status()))
-                } else {
-                    Err(context.error().into())
-                }
-            }
-            None => Ok(None),
-        })
-    }
-
     /// Wait for the process to exit. `succeeded` is called and returned to determine
     /// if the command succeeded.
     ///
@@ -206,6 +172,156 @@ pub trait ChildExt: Sized {
         })
     }
 
+    /// Wait for the process to exit and get its exit status, without reaping it.
+    ///
+    /// This uses [`waitid(2)`][waitid] with `WNOWAIT`, which leaves the process as a zombie so
+    /// that its exit status can still be collected by another component, like an external reaper,
+    /// or by a later call to [`wait_checked`][ChildExt::wait_checked] or [`Child::wait`]. The
+    /// returned context can be checked like the ones given to
+    /// [`wait_checked_as`][ChildExt::wait_checked_as]:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::ChildExt;
+    /// # use command_error::CommandExt;
+    /// let mut child = Command::new("sh")
+    ///     .args(["-c", "exit 3"])
+    ///     .spawn_checked()
+    ///     .unwrap();
+    ///
+    /// let context = child.wait_without_reaping().unwrap();
+    /// assert_eq!(context.code(), Some(3));
+    ///
+    /// // The process hasn't been reaped, so it can still be waited for.
+    /// assert_eq!(
+    ///     child.wait_checked().unwrap_err().to_string(),
+    ///     "`sh` failed: exit status: 3\nCommand failed: `sh -c 'exit 3'`"
+    /// );
+    /// ```
+    ///
+    /// The zombie process keeps its process ID and a process table entry until it's reaped, so
+    /// something must reap it eventually; otherwise, zombies leak for as long as this process
+    /// runs. If the process has already been reaped elsewhere, this fails with `ECHILD`.
+    ///
+    /// Only available on Unix.
+    ///
+    /// [waitid]: https://man7.org/linux/man-pages/man2/waitid.2.html
+    #[cfg(unix)]
+    #[track_caller]
+    fn wait_without_reaping(&mut self) -> Result<OutputContext<ExitStatus>, Self::Error>;
+
+    /// Supervise a process which is expected to run forever, restarting it when it exits.
+    ///
+    /// This waits for the process to exit; any exit (even with exit code 0) is unexpected, and
+    /// `on_exit` decides what to do about it:
+    ///
+    /// - [`SupervisorAction::Restart`] spawns a replacement process with `supervisor` (after
+    ///   waiting for the supervisor's backoff) and keeps supervising it. `self` is replaced with
+    ///   the new process. After [`Supervisor::max_restarts`] restarts, an error is returned instead.
+    /// - [`SupervisorAction::Escalate`] returns an error; [`SupervisorAction::escalate`] produces
+    ///   the standard error for an unexpected exit.
+    /// - [`SupervisorAction::Ignore`] returns the process's exit status.
+    ///
+    /// With the `tracing` feature enabled, a warning is logged for each restart.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::cell::Cell;
+    /// # use std::process::Command;
+    /// # use std::time::Duration;
+    /// # use command_error::ChildExt;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Supervisor;
+    /// # use command_error::SupervisorAction;
+    /// let mut supervisor = Supervisor::new(|| {
+    ///     Command::new("sh").args(["-c", "exit 0"]).spawn_checked()
+    /// })
+    /// .max_restarts(1)
+    /// .backoff(Duration::ZERO);
+    ///
+    /// let exits = Cell::new(0);
+    /// let err = supervisor
+    ///     .spawn()
+    ///     .unwrap()
+    ///     .supervise_checked(&mut supervisor, |_context| {
+    ///         exits.set(exits.get() + 1);
+    ///         SupervisorAction::Restart
+    ///     })
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(exits.get(), 2);
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exited unexpectedly (gave up after 1 restart)
+    ///         exit status: 0
+    ///         Command failed: `sh -c 'exit 0'`"
+    ///     )
+    /// );
+    ///
+    /// let err = supervisor
+    ///     .spawn()
+    ///     .unwrap()
+    ///     .supervise_checked(&mut supervisor, SupervisorAction::escalate)
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exited unexpectedly
+    ///         exit status: 0
+    ///         Command failed: `sh -c 'exit 0'`"
+    ///     )
+    /// );
+    /// ```
+    #[track_caller]
+    fn supervise_checked(
+        &mut self,
+        supervisor: &mut Supervisor<Self>,
+        on_exit: impl Fn(OutputContext<ExitStatus>) -> SupervisorAction,
+    ) -> Result<ExitStatus, Self::Error> {
+        let mut restarts = 0;
+        let mut backoff = supervisor.backoff.min(supervisor.max_backoff);
+        loop {
+            let context = self.wait_checked_as(Ok::<_, Self::Error>)?;
+            let status = context.status();
+            let command = dyn_clone::clone_box(&*context.command);
+            let stdio = context.stdio;
+            match on_exit(context) {
+                SupervisorAction::Restart => {
+                    if restarts >= supervisor.max_restarts {
+                        let mut context = OutputContext::new(status, command);
+                        context.stdio = stdio;
+                        return Err(context
+                            .error_msg(format!(
+                                "exited unexpectedly (gave up after {restarts} restart{})",
+                                if restarts == 1 { "" } else { "s" }
+                            ))
+                            .into());
+                    }
+                    restarts += 1;
+                    #[cfg(feature = "tracing")]
+                    {
+                        tracing::warn!(
+                            %command,
+                            %status,
+                            restart = restarts,
+                            ?backoff,
+                            "Restarting command after unexpected exit"
+                        );
+                    }
+                    supervisor.clock.sleep(backoff);
+                    backoff = backoff.saturating_mul(2).min(supervisor.max_backoff);
+                    *self = supervisor.spawn()?;
+                }
+                SupervisorAction::Escalate(error) => return Err(error.into()),
+                SupervisorAction::Ignore => return Ok(status),
+            }
+        }
+    }
+
     /// Log the command that will be run.
     ///
     /// With the `tracing` feature enabled, this will emit a debug-level log with message
@@ -215,8 +331,6 @@ pub trait ChildExt: Sized {
 }
 
 impl ChildExt for ChildContext<Child> {
-    type Error = Error;
-
     fn output_checked_as<O, R, E>(
         self,
         succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
@@ -231,48 +345,43 @@ impl ChildExt for ChildContext<Child> {
     {
         self.log()?;
         let command = dyn_clone::clone_box(self.command.borrow());
-        match self.child.wait_with_output() {
-            Ok(output) => match output.try_into() {
-                Ok(output) => succeeded(OutputContext { output, command }),
-                Err(error) => Err(Error::from(OutputConversionError {
-                    command,
-                    inner: Box::new(error),
-                })
-                .into()),
-            },
-            Err(inner) => Err(Error::from(ExecError { command, inner }).into()),
+        let stdio = self.stdio.unwrap_or_else(|| StdioConfig::of(&self.child));
+        let output = match self.progress {
+            Some(progress) => progress.wait_with_output(self.child),
+            None => self.child.wait_with_output(),
+        };
+        match output {
+            Ok(output) => Ran::new(output, command)
+                .with_stdio(Some(stdio))
+                .check_output(succeeded),
+            Err(inner) => Err(Error::from(WaitError { command, inner }).into()),
         }
     }
 
-    fn try_wait_checked_as<R, E>(
+    fn wait_checked_as<R, E>(
         &mut self,
-        succeeded: impl Fn(TryWaitContext) -> Result<R, E>,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E>,
     ) -> Result<R, E>
     where
         E: From<Self::Error>,
     {
+        self.log()?;
         let command = dyn_clone::clone_box(self.command.borrow());
-        match self.child.try_wait() {
-            Ok(status) => succeeded(TryWaitContext { status, command }),
-            Err(inner) => Err(Error::from(WaitError { inner, command }).into()),
+        match self.child.wait() {
+            Ok(status) => Ran::new(status, command)
+                .with_stdio(self.stdio)
+                .check_status(succeeded),
+            Err(inner) => Err(Error::from(WaitError { command, inner }).into()),
         }
     }
 
-    fn wait_checked_as<R, E>(
-        &mut self,
-        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E>,
-    ) -> Result<R, E>
-    where
-        E: From<Self::Error>,
-    {
+    #[cfg(unix)]
+    fn wait_without_reaping(&mut self) -> Result<OutputContext<ExitStatus>, Self::Error> {
         self.log()?;
         let command = dyn_clone::clone_box(self.command.borrow());
-        match self.child.wait() {
-            Ok(status) => succeeded(OutputContext {
-                output: status,
-                command,
-            }),
-            Err(inner) => Err(Error::from(ExecError { command, inner }).into()),
+        match wait_without_reaping(self.child.id()) {
+            Ok(status) => Ok(OutputContext::new(status, command)),
+            Err(inner) => Err(Error::from(WaitError { command, inner })),
         }
     }
 
@@ -284,3 +393,45 @@ impl ChildExt for ChildContext<Child> {
         Ok(())
     }
 }
+
+/// Wait for the process `pid` to exit and get its exit status, leaving it unreaped.
+#[cfg(unix)]
+fn wait_without_reaping(pid: u32) -> std::io::Result<ExitStatus> {
+    use std::os::unix::process::ExitStatusExt;
+
+    // SAFETY: `siginfo_t` is plain data, so all zeroes is a valid value.
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: `info` is a valid `siginfo_t` for `waitid` to write to.
+        let result = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if result == 0 {
+            break;
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+
+    // SAFETY: `waitid` filled in `info` for a child state change, so `si_status` is set.
+    let status = unsafe { info.si_status() };
+    // Reassemble the raw wait status, as returned by `waitpid`.
+    let raw = match info.si_code {
+        libc::CLD_EXITED => (status & 0xff) << 8,
+        libc::CLD_KILLED => status,
+        libc::CLD_DUMPED => status | 0x80,
+        code => {
+            return Err(std::io::Error::other(format!(
+                "waitid returned unexpected si_code {code}"
+            )))
+        }
+    };
+    Ok(ExitStatus::from_raw(raw))
+}
@@ -0,0 +1,126 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt as _;
+use std::path::Path;
+use std::process::Command;
+
+use crate::pre_exec_guard;
+use crate::pre_exec_guard::PreExecGuard;
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Error;
+
+/// Whether the root directory was changed in this (child) process; see [`PreExecGuard`].
+static ROOT_CHANGED: PreExecGuard = PreExecGuard::new();
+
+/// Change `command`'s root directory to `root` (and its working directory to the new root) before
+/// it executes.
+///
+/// The root directory remains set on `command` for subsequent runs. If a different root directory
+/// was already set on `command`, it fails to start; see [`explain`]. Fails if `root` contains a
+/// NUL byte.
+pub(crate) fn apply(command: &mut Command, root: &Path) -> std::io::Result<()> {
+    let root = CString::new(root.as_os_str().as_bytes())?;
+    let fingerprint = pre_exec_guard::fingerprint(&root);
+    // SAFETY: The closure only claims the guard and calls `chroot` and `chdir`, which are
+    // async-signal-safe, and doesn't allocate; the path is converted beforehand.
+    unsafe {
+        command.pre_exec(move || {
+            if !ROOT_CHANGED.claim(fingerprint)? {
+                return Ok(());
+            }
+            if libc::chroot(root.as_ptr()) != 0 || libc::chdir(c"/".as_ptr()) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+/// Explain a failure to start a command with [`CommandExt::output_checked_with_chroot`], if it
+/// was likely due to missing privileges or a different root directory set by an earlier call.
+pub(crate) fn explain(error: Error) -> Error {
+    let error = pre_exec_guard::explain(
+        error,
+        "chroot",
+        "a different root directory was already set on this command by an earlier call; use a \
+        new `Command` to change it",
+    );
+    match error {
+        Error::Exec(mut error) if error.inner.raw_os_error() == Some(libc::EPERM) => {
+            error.inner = std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!(
+                    "{}; changing the root directory requires root privileges or the \
+                    CAP_SYS_CHROOT capability",
+                    error.inner
+                ),
+            );
+            error.step = Some("chroot");
+            Error::Exec(error)
+        }
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    use crate::CommandExt;
+    use crate::ExecError;
+    use crate::Utf8ProgramAndArgs;
+
+    #[test]
+    fn test_chroot() {
+        let root =
+            std::env::temp_dir().join(format!("command-error-chroot-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let err = Command::new("/bin/true")
+            .output_checked_with_chroot(&root)
+            .unwrap_err();
+        std::fs::remove_dir(&root).unwrap();
+
+        let Error::Exec(err) = err else {
+            panic!("Expected an exec error, got {err:?}");
+        };
+        // SAFETY: `geteuid` is always safe to call.
+        if unsafe { libc::geteuid() } == 0 {
+            // The empty root directory doesn't contain `/bin/true`.
+            assert_eq!(err.inner.kind(), std::io::ErrorKind::NotFound);
+        } else {
+            assert_eq!(err.spawn_step(), Some("chroot"));
+            assert!(err.to_string().contains("CAP_SYS_CHROOT"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_explain_repeated() {
+        let command = Utf8ProgramAndArgs::from_parts("ls", &[]);
+        let err = explain(Error::from(ExecError::new(
+            Box::new(command),
+            std::io::Error::from_raw_os_error(libc::EALREADY),
+        )));
+        assert_eq!(
+            err.to_string(),
+            "Failed to execute `ls` (chroot): a different root directory was already set on this \
+            command by an earlier call; use a new `Command` to change it"
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let command = Utf8ProgramAndArgs::from_parts("ls", &[]);
+        let err = explain(Error::from(ExecError::new(
+            Box::new(command),
+            std::io::Error::from_raw_os_error(libc::EPERM),
+        )));
+        assert_eq!(
+            err.to_string(),
+            "Failed to execute `ls` (chroot): Operation not permitted (os error 1); changing the \
+            root directory requires root privileges or the CAP_SYS_CHROOT capability"
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::dedup::command_fingerprint;
+use crate::CircuitOpenError;
+use crate::Clock;
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::OutputError;
+use crate::Utf8ProgramAndArgs;
+
+/// A policy for failing fast when a command fails repeatedly.
+///
+/// When the same command (the same program and arguments) has failed `threshold` times within
+/// `window`, the circuit "opens": for the next `cool_down`, running the command fails immediately
+/// with a [`CircuitOpenError`] instead of hammering the external tool. Once the cool-down has
+/// elapsed, the command is allowed to run again; if it fails, the circuit opens again
+/// immediately, and if it succeeds, its failure history is cleared.
+///
+/// Only failures of the command itself (an [`OutputError`]) are counted; commands which fail to
+/// start are not.
+///
+/// The breaker's state is keyed by the command's fingerprint, so a single breaker (for example, in
+/// a `static`) can be shared by every command in a program. Use it with
+/// [`CommandExt::output_checked_with_circuit_breaker`]:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use std::time::Duration;
+/// # use command_error::CommandExt;
+/// # use command_error::CircuitBreaker;
+/// # use command_error::Error;
+/// let breaker = CircuitBreaker::new(2, Duration::from_secs(60), Duration::from_secs(30));
+/// let mut command = Command::new("sh");
+/// command.args(["-c", "exit 1"]);
+///
+/// for _ in 0..2 {
+///     let err = command.output_checked_with_circuit_breaker(&breaker).unwrap_err();
+///     assert!(matches!(err, Error::Output(_)));
+/// }
+///
+/// let err = command.output_checked_with_circuit_breaker(&breaker).unwrap_err();
+/// assert!(matches!(err, Error::CircuitOpen(_)));
+/// assert!(err.to_string().starts_with(
+///     "`sh` was not run: it failed 2 times in the last 60s, so it's short-circuited for 30s"
+/// ));
+/// ```
+pub struct CircuitBreaker {
+    threshold: usize,
+    window: Duration,
+    cool_down: Duration,
+    clock: Box<dyn Clock>,
+    states: Mutex<HashMap<u64, CircuitState>>,
+}
+
+#[derive(Default)]
+struct CircuitState {
+    /// When the command failed within the last `window`.
+    failures: VecDeque<Instant>,
+    /// The most recent failure.
+    last_error: Option<Arc<OutputError>>,
+    /// If the circuit is open, when it closes again.
+    open_until: Option<Instant>,
+    /// Whether the cool-down has elapsed and the command is being retried.
+    half_open: bool,
+}
+
+impl CircuitBreaker {
+    /// Construct a new [`CircuitBreaker`] which opens the circuit for `cool_down` after a command
+    /// fails `threshold` times within `window`.
+    pub fn new(threshold: usize, window: Duration, cool_down: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            cool_down,
+            clock: Box::new(Instant::now),
+            states: Default::default(),
+        }
+    }
+
+    /// Use `clock` to get the current time instead of [`Instant::now`].
+    ///
+    /// This is useful for testing.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Forget all recorded failures, closing every circuit.
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    /// Check if `command` may run, returning its fingerprint if it may.
+    pub(crate) fn check(&self, command: &Utf8ProgramAndArgs) -> Result<u64, CircuitOpenError> {
+        let key = command_fingerprint(command);
+        let now = self.clock.now();
+        let mut states = self.lock();
+        if let Some(state) = states.get_mut(&key) {
+            if let Some(open_until) = state.open_until {
+                if now < open_until {
+                    return Err(CircuitOpenError::new(
+                        Box::new(command.clone()),
+                        state
+                            .last_error
+                            .clone()
+                            .expect("An open circuit has a most recent failure"),
+                        state.failures.len(),
+                        self.window,
+                        open_until - now,
+                    ));
+                }
+                state.open_until = None;
+                state.half_open = true;
+            }
+        }
+        Ok(key)
+    }
+
+    /// Record that the command with the given fingerprint succeeded.
+    pub(crate) fn record_success(&self, key: u64) {
+        self.lock().remove(&key);
+    }
+
+    /// Record that the command with the given fingerprint failed.
+    pub(crate) fn record_failure(&self, key: u64, error: OutputError) {
+        let now = self.clock.now();
+        let mut states = self.lock();
+        let state = states.entry(key).or_default();
+        state.failures.push_back(now);
+        while let Some(failure) = state.failures.front() {
+            if now.saturating_duration_since(*failure) > self.window {
+                state.failures.pop_front();
+            } else {
+                break;
+            }
+        }
+        state.last_error = Some(Arc::new(error));
+        if state.half_open || state.failures.len() >= self.threshold {
+            state.open_until = Some(now + self.cool_down);
+            state.half_open = false;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, CircuitState>> {
+        // The state is always consistent, even if a panic occurred while it was locked.
+        self.states.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("threshold", &self.threshold)
+            .field("window", &self.window)
+            .field("cool_down", &self.cool_down)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use static_assertions::assert_impl_all;
+    use std::process::Command;
+
+    use crate::clock::ManualClock;
+    use crate::CommandExt;
+    use crate::Error;
+
+    assert_impl_all!(CircuitBreaker: Send, Sync);
+
+    fn breaker(clock: &ManualClock, threshold: usize) -> CircuitBreaker {
+        CircuitBreaker::new(threshold, Duration::from_secs(60), Duration::from_secs(30))
+            .with_clock(clock.clone())
+    }
+
+    fn run(command: &mut Command, breaker: &CircuitBreaker) -> &'static str {
+        match command.output_checked_with_circuit_breaker(breaker) {
+            Ok(_) => "ok",
+            Err(Error::Output(_)) => "failed",
+            Err(Error::CircuitOpen(_)) => "open",
+            Err(err) => panic!("Unexpected error: {err}"),
+        }
+    }
+
+    fn failing() -> Command {
+        let mut command = Command::new("sh");
+        command.args(["-c", "exit 1"]);
+        command
+    }
+
+    #[test]
+    fn test_opens_after_threshold() {
+        let clock = ManualClock::new();
+        let breaker = breaker(&clock, 3);
+        let mut command = failing();
+        assert_eq!(run(&mut command, &breaker), "failed");
+        assert_eq!(run(&mut command, &breaker), "failed");
+        assert_eq!(run(&mut command, &breaker), "failed");
+        assert_eq!(run(&mut command, &breaker), "open");
+
+        clock.advance(Duration::from_secs(29));
+        assert_eq!(run(&mut command, &breaker), "open");
+
+        // After the cool-down, one retry is allowed before the circuit opens again.
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(run(&mut command, &breaker), "failed");
+        assert_eq!(run(&mut command, &breaker), "open");
+    }
+
+    #[test]
+    fn test_failures_outside_window() {
+        let clock = ManualClock::new();
+        let breaker = breaker(&clock, 2);
+        let mut command = failing();
+        assert_eq!(run(&mut command, &breaker), "failed");
+        clock.advance(Duration::from_secs(61));
+        assert_eq!(run(&mut command, &breaker), "failed");
+        assert_eq!(run(&mut command, &breaker), "failed");
+        assert_eq!(run(&mut command, &breaker), "open");
+    }
+
+    #[test]
+    fn test_keyed_by_command() {
+        let clock = ManualClock::new();
+        let breaker = breaker(&clock, 1);
+        assert_eq!(run(&mut failing(), &breaker), "failed");
+        assert_eq!(run(&mut failing(), &breaker), "open");
+        assert_eq!(run(&mut Command::new("true"), &breaker), "ok");
+    }
+
+    #[test]
+    fn test_success_closes_circuit() {
+        let clock = ManualClock::new();
+        let breaker = breaker(&clock, 2);
+        let mut command = Command::new("sh");
+        command.args(["-c", "test -e \"$FILE\""]);
+        command.env("FILE", "/this/does/not/exist");
+        assert_eq!(run(&mut command, &breaker), "failed");
+        command.env("FILE", "/");
+        assert_eq!(run(&mut command, &breaker), "ok");
+        command.env("FILE", "/this/does/not/exist");
+        assert_eq!(run(&mut command, &breaker), "failed");
+        assert_eq!(run(&mut command, &breaker), "failed");
+        assert_eq!(run(&mut command, &breaker), "open");
+    }
+
+    #[test]
+    fn test_error_message() {
+        let clock = ManualClock::new();
+        let breaker = breaker(&clock, 1);
+        let mut command = failing();
+        run(&mut command, &breaker);
+        clock.advance(Duration::from_secs(10));
+        let err = command
+            .output_checked_with_circuit_breaker(&breaker)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            indoc::indoc!(
+                "`sh` was not run: it failed 1 time in the last 60s, so it's short-circuited for 20s
+                Most recent failure: `sh` failed: exit status: 1
+                Command failed: `sh -c 'exit 1'`"
+            )
+        );
+        match err {
+            Error::CircuitOpen(err) => {
+                assert_eq!(err.retry_after(), Duration::from_secs(20));
+                assert!(std::error::Error::source(&err).is_some());
+            }
+            err => panic!("Unexpected error: {err}"),
+        }
+    }
+}
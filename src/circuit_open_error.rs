@@ -0,0 +1,118 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::command_display::guarded;
+use crate::duration::round_duration;
+#[cfg(doc)]
+use crate::CircuitBreaker;
+use crate::CommandDisplay;
+use crate::OutputError;
+#[cfg(feature = "miette")]
+use miette::Diagnostic;
+
+/// An error from a command which was not run because it has failed too many times recently.
+///
+/// Produced by [`CircuitBreaker`]. The most recent real failure of the command is available from
+/// [`CircuitOpenError::last_error`] and as the error's [`source`][std::error::Error::source].
+pub struct CircuitOpenError {
+    pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
+    pub(crate) last_error: Arc<OutputError>,
+    pub(crate) failures: usize,
+    pub(crate) window: Duration,
+    pub(crate) retry_after: Duration,
+}
+
+impl CircuitOpenError {
+    /// Construct a new [`CircuitOpenError`].
+    ///
+    /// `failures` is the number of times the command failed within `window`, and `retry_after` is
+    /// how long until the command will be run again.
+    pub fn new(
+        command: impl Into<Box<dyn CommandDisplay + Send + Sync>>,
+        last_error: Arc<OutputError>,
+        failures: usize,
+        window: Duration,
+        retry_after: Duration,
+    ) -> Self {
+        Self {
+            command: command.into(),
+            last_error,
+            failures,
+            window,
+            retry_after,
+        }
+    }
+
+    /// The most recent failure of the command.
+    pub fn last_error(&self) -> &OutputError {
+        &self.last_error
+    }
+
+    /// The number of times the command failed recently.
+    pub fn failures(&self) -> usize {
+        self.failures
+    }
+
+    /// How long until the command will be run again.
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+}
+
+impl Debug for CircuitOpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CircuitOpenError")
+            .field("program", &guarded(&*self.command).program())
+            .field("last_error", &self.last_error)
+            .field("failures", &self.failures)
+            .field("window", &self.window)
+            .field("retry_after", &self.retry_after)
+            .finish()
+    }
+}
+
+impl Display for CircuitOpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // `nix` was not run: it failed 3 times in the last 60s, so it's short-circuited for 30s
+        // Most recent failure: `nix` failed: exit status: 1
+        // ...
+        write!(
+            f,
+            "`{}` was not run: it failed {} time{} in the last {:?}, so it's short-circuited for {:?}\
+            \nMost recent failure: {}",
+            guarded(&*self.command).program_quoted(),
+            self.failures,
+            if self.failures == 1 { "" } else { "s" },
+            round_duration(self.window),
+            round_duration(self.retry_after),
+            self.last_error,
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.last_error)
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for CircuitOpenError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::circuit_open"))
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        Some(&*self.last_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_assertions::assert_impl_all;
+
+    assert_impl_all!(CircuitOpenError: Send, Sync);
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+#[cfg(any(test, feature = "test-helpers"))]
+use std::sync::Arc;
+#[cfg(any(test, feature = "test-helpers"))]
+use std::sync::Mutex;
+#[cfg(any(test, feature = "test-helpers"))]
+use std::sync::PoisonError;
+
+#[cfg(doc)]
+use crate::CircuitBreaker;
+#[cfg(doc)]
+use crate::CommandExt;
+#[cfg(doc)]
+use crate::Deadline;
+#[cfg(doc)]
+use crate::RateLimiter;
+#[cfg(doc)]
+use crate::Supervisor;
+
+/// A source of the current time, for deadlines, time windows, and backoff.
+///
+/// These features read the time from a [`Clock`] and wait with [`Clock::sleep`] rather than
+/// calling [`Instant::now`] and [`std::thread::sleep`] directly, so that tests can inject a fake
+/// clock and check their behavior without sleeping:
+///
+/// - [`CircuitBreaker`], [`RateLimiter`], and [`Supervisor`], with their `with_clock` methods.
+/// - Timeouts and deadlines, with [`CommandExt::output_checked_with_timeout_and_clock`],
+///   [`CommandExt::status_checked_with_timeout_and_clock`],
+///   [`CommandExt::output_checked_with_deadline_shared_and_clock`], and
+///   [`Deadline::after_with_clock`].
+///
+/// Each of them uses the real time by default. Everything else always uses the real time and
+/// can't be given a [`Clock`]: stall detection ([`CommandExt::output_checked_on_stall`]), stream
+/// timings, timestamps in invocation records, single-flight locks, and completion logging. So
+/// does the timeout of the diagnostic re-run in [`CommandExt::output_checked_diagnosing`], and
+/// the polling of a command's exit while its output is read.
+///
+/// [`Clock`] is implemented for functions returning an [`Instant`], so [`Instant::now`] itself is
+/// the default clock:
+///
+/// ```
+/// # use std::time::Instant;
+/// # use command_error::Clock;
+/// let clock: &dyn Clock = &Instant::now;
+/// let before = Instant::now();
+/// assert!(clock.now() >= before);
+/// ```
+///
+/// With the `test-helpers` feature, `ManualClock` is a clock which only advances when told to.
+pub trait Clock: Send + Sync {
+    /// Get the current time.
+    fn now(&self) -> Instant;
+
+    /// Get the current wall-clock time, for timestamps.
+    ///
+    /// Defaults to [`SystemTime::now`].
+    fn now_system(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    /// Block for `duration`.
+    ///
+    /// Defaults to [`std::thread::sleep`].
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> Instant + Send + Sync,
+{
+    fn now(&self) -> Instant {
+        self()
+    }
+}
+
+/// A [`Clock`] which only advances when [`ManualClock::advance`] is called, for testing
+/// time-based features deterministically and without sleeping.
+///
+/// Clones of a [`ManualClock`] share the same time, so one clone can be given to the feature
+/// under test (like with `RateLimiter::with_clock`) while the test keeps another to advance it.
+/// [`Clock::sleep`] advances the clock instead of blocking.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use command_error::Clock;
+/// # use command_error::ManualClock;
+/// let clock = ManualClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(5));
+/// clock.clone().sleep(Duration::from_secs(10));
+/// assert_eq!(clock.now() - start, Duration::from_secs(15));
+/// ```
+#[cfg(any(test, feature = "test-helpers"))]
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<(Instant, SystemTime)>>,
+}
+
+#[cfg(any(test, feature = "test-helpers"))]
+impl ManualClock {
+    /// Construct a new [`ManualClock`], starting at the current time.
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new((Instant::now(), SystemTime::now()))),
+        }
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        now.0 += duration;
+        now.1 += duration;
+    }
+}
+
+#[cfg(any(test, feature = "test-helpers"))]
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, feature = "test-helpers"))]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.lock().unwrap_or_else(PoisonError::into_inner).0
+    }
+
+    fn now_system(&self) -> SystemTime {
+        self.now.lock().unwrap_or_else(PoisonError::into_inner).1
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
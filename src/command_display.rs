@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use std::fmt::Display;
-
-#[cfg(doc)]
+use std::panic::AssertUnwindSafe;
+use std::panic::Location;
 use std::process::Command;
 
 use dyn_clone::DynClone;
@@ -64,4 +64,304 @@ pub trait CommandDisplay: Display + DynClone {
     /// );
     /// ```
     fn args(&self) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_>;
+
+    /// The `argv[0]` the command's program is invoked with, if it was overridden and differs from
+    /// the program name.
+    ///
+    /// On Unix, a program can be invoked with an `argv[0]` different from its path (see
+    /// [`std::os::unix::process::CommandExt::arg0`]), which matters for multiplexed programs like
+    /// `busybox`. When this is set, error messages note the name the program was invoked as.
+    ///
+    /// The default implementation returns [`None`].
+    fn arg0(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+
+    /// Whether the command can't be reconstructed exactly from this display, because part of it
+    /// wasn't valid UTF-8 and was decoded lossily, or because arguments were omitted (see
+    /// [`CommandDisplay::omitted_args`]).
+    ///
+    /// When this is `true` and no arguments were omitted, error messages note that the command is
+    /// shown approximately. (Omitted arguments are already noted in the displayed command.)
+    ///
+    /// The default implementation returns `false`.
+    fn is_lossy(&self) -> bool {
+        false
+    }
+
+    /// The number of the command's arguments which aren't included in
+    /// [`CommandDisplay::args`], because the command had too many to store.
+    ///
+    /// The default implementation returns 0.
+    fn omitted_args(&self) -> usize {
+        0
+    }
+
+    /// Where in the source code the command was run from, if known.
+    ///
+    /// [`CommandExt`](crate::CommandExt) and [`ChildExt`](crate::ChildExt) methods are
+    /// `#[track_caller]`, and record their caller's location in the [`Utf8ProgramAndArgs`] they
+    /// produce, so that an error can be traced back to the exact call which produced it. For
+    /// [`ChildExt`](crate::ChildExt) methods, this is where the child was spawned.
+    ///
+    /// The location is included in errors' [`Debug`](std::fmt::Debug) output, and in
+    /// [verbose](crate::OutputError::verbose) error messages.
+    ///
+    /// The default implementation returns [`None`].
+    fn location(&self) -> Option<&'static Location<'static>> {
+        None
+    }
+
+    /// Reconstruct a [`Command`] which runs this program with these arguments.
+    ///
+    /// This is lossy: the program and arguments have been decoded as UTF-8, so any that weren't
+    /// valid UTF-8 contain � U+FFFD REPLACEMENT CHARACTER instead of the original bytes. Settings
+    /// which aren't displayed, like stdio redirections, aren't reconstructed either.
+    ///
+    /// # Panics
+    ///
+    /// If any arguments were omitted (see [`CommandDisplay::omitted_args`]), because running the
+    /// command without them could do something quite different. Check
+    /// [`CommandDisplay::is_lossy`] first if that's possible.
+    ///
+    /// ```should_panic
+    /// # use std::process::Command;
+    /// # use command_error::Utf8ProgramAndArgs;
+    /// # use command_error::CommandDisplay;
+    /// let mut command = Command::new("rm");
+    /// command.args((0..2000).map(|i| format!("{i}.tmp")));
+    /// let displayed = Utf8ProgramAndArgs::from(&command);
+    /// assert!(displayed.is_lossy());
+    /// // Only the first 1024 arguments were stored, so this panics.
+    /// displayed.to_command();
+    /// ```
+    ///
+    /// The default implementation only sets the program and arguments; [`Utf8ProgramAndArgs`]
+    /// also sets the working directory, environment variables, and `argv[0]`.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::Utf8ProgramAndArgs;
+    /// # use command_error::CommandDisplay;
+    /// let mut command = Command::new("echo");
+    /// command.arg("puppy doggy").current_dir("/").env("COLOR", "GOLDEN");
+    /// let displayed = Utf8ProgramAndArgs::from(&command);
+    /// let rebuilt = displayed.to_command();
+    /// assert_eq!(
+    ///     Utf8ProgramAndArgs::from(&rebuilt).to_string(),
+    ///     displayed.to_string(),
+    /// );
+    /// ```
+    fn to_command(&self) -> Command {
+        assert_not_truncated(&self.program(), self.omitted_args());
+        let mut command = Command::new(&*self.program());
+        command.args(self.args().map(|arg| arg.into_owned()));
+        command
+    }
+}
+
+/// Error constructors accept any `impl Into<Box<dyn CommandDisplay + Send + Sync>>`, so a boxed
+/// display can be passed directly, as can a [`Utf8ProgramAndArgs`] by value or by reference:
+///
+/// ```
+/// # use std::process::Command;
+/// # use command_error::Utf8ProgramAndArgs;
+/// # use command_error::WaitError;
+/// let displayed = Utf8ProgramAndArgs::from(&Command::new("sh"));
+/// let inner = || std::io::Error::new(std::io::ErrorKind::Other, "oops");
+/// WaitError::new(Box::new(displayed.clone()), inner());
+/// WaitError::new(&displayed, inner());
+/// WaitError::new(displayed, inner());
+/// ```
+impl<T> From<Box<T>> for Box<dyn CommandDisplay + Send + Sync>
+where
+    T: CommandDisplay + Send + Sync + 'static,
+{
+    fn from(command: Box<T>) -> Self {
+        command
+    }
+}
+
+/// A [`CommandDisplay`] wrapper which catches panics from the wrapped implementation.
+///
+/// Errors display their commands through this, so that a buggy custom [`CommandDisplay`] degrades
+/// the error message (to `<display error>`) rather than turning every attempt to display the
+/// error into a panic which masks the real failure. The panic hook still runs, so the panic is
+/// reported.
+#[derive(Clone, Copy)]
+pub(crate) struct Guarded<'a>(&'a (dyn CommandDisplay + Send + Sync));
+
+/// Panic if `omitted_args` of `program`'s arguments were omitted, so that it can't be
+/// reconstructed; see [`CommandDisplay::to_command`].
+#[track_caller]
+pub(crate) fn assert_not_truncated(program: &str, omitted_args: usize) {
+    assert!(
+        omitted_args == 0,
+        "Can't reconstruct `{program}`: {omitted_args} of its arguments were omitted"
+    );
+}
+
+/// Shown in place of any part of a command which couldn't be displayed.
+pub(crate) const DISPLAY_ERROR: &str = "<display error>";
+
+/// Wrap `command` in a [`Guarded`].
+pub(crate) fn guarded(command: &(dyn CommandDisplay + Send + Sync)) -> Guarded<'_> {
+    Guarded(command)
+}
+
+/// Call `f`, returning [`None`] if it panics.
+fn catch<T>(f: impl FnOnce() -> T) -> Option<T> {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).ok()
+}
+
+impl<'a> Guarded<'a> {
+    /// See [`CommandDisplay::program`].
+    pub(crate) fn program(self) -> Cow<'a, str> {
+        catch(|| self.0.program()).unwrap_or(Cow::Borrowed(DISPLAY_ERROR))
+    }
+
+    /// See [`CommandDisplay::program_quoted`].
+    ///
+    /// If this panics, the unquoted [`CommandDisplay::program`] is used instead.
+    pub(crate) fn program_quoted(self) -> Cow<'a, str> {
+        catch(|| self.0.program_quoted()).unwrap_or_else(|| self.program())
+    }
+
+    /// See [`CommandDisplay::arg0`].
+    pub(crate) fn arg0(self) -> Option<Cow<'a, str>> {
+        catch(|| self.0.arg0()).flatten()
+    }
+
+    /// See [`CommandDisplay::is_lossy`].
+    pub(crate) fn is_lossy(self) -> bool {
+        catch(|| self.0.is_lossy()).unwrap_or(false)
+    }
+
+    /// Whether error messages should note that the command is shown approximately.
+    ///
+    /// Commands with omitted arguments already say so when displayed, so this is only `true` for
+    /// commands which were decoded lossily and had no arguments omitted.
+    pub(crate) fn needs_lossy_note(self) -> bool {
+        self.is_lossy() && catch(|| self.0.omitted_args()).unwrap_or(0) == 0
+    }
+
+    /// See [`CommandDisplay::location`].
+    pub(crate) fn location(self) -> Option<&'static Location<'static>> {
+        catch(|| self.0.location()).flatten()
+    }
+}
+
+impl Display for Guarded<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match catch(|| Display::fmt(self.0, f)) {
+            Some(result) => result,
+            // Anything written before the panic is kept; there's no way to take it back.
+            None => match catch(|| self.0.program()) {
+                Some(program) => write!(f, "{program} {DISPLAY_ERROR}"),
+                None => f.write_str(DISPLAY_ERROR),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use std::process::ExitStatus;
+
+    use crate::Error;
+    use crate::ExecError;
+    use crate::OutputError;
+
+    /// A [`CommandDisplay`] which panics when it's displayed.
+    #[derive(Clone)]
+    struct Panicking {
+        program_panics: bool,
+    }
+
+    impl Display for Panicking {
+        fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            panic!("index out of bounds");
+        }
+    }
+
+    impl CommandDisplay for Panicking {
+        fn program(&self) -> Cow<'_, str> {
+            if self.program_panics {
+                panic!("index out of bounds");
+            }
+            Cow::Borrowed("sandboxed")
+        }
+
+        fn args(&self) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+            panic!("index out of bounds");
+        }
+    }
+
+    #[test]
+    fn test_panicking_display() {
+        let error = OutputError::new(
+            Box::new(Panicking {
+                program_panics: false,
+            }),
+            Box::new(ExitStatus::default()),
+        );
+        assert_eq!(
+            error.to_string(),
+            indoc!(
+                "`sandboxed` failed: exit status: 0
+                Command failed: `sandboxed <display error>`"
+            )
+        );
+
+        let error = OutputError::new(
+            Box::new(Panicking {
+                program_panics: true,
+            }),
+            Box::new(ExitStatus::default()),
+        );
+        assert_eq!(
+            error.to_string(),
+            indoc!(
+                "`<display error>` failed: exit status: 0
+                Command failed: `<display error>`"
+            )
+        );
+        assert!(format!("{error:?}").contains(r#"program: "<display error>""#));
+
+        let error = ExecError::new(
+            Box::new(Panicking {
+                program_panics: true,
+            }),
+            std::io::Error::other("denied"),
+        );
+        assert_eq!(
+            error.to_string(),
+            "Failed to execute `<display error>`: denied"
+        );
+    }
+
+    #[test]
+    fn test_panicking_display_rendered() {
+        let error = Error::from(OutputError::new(
+            Box::new(Panicking {
+                program_panics: true,
+            }),
+            Box::new(ExitStatus::default()),
+        ));
+        assert_eq!(error.program(), DISPLAY_ERROR);
+
+        let mut compact = String::new();
+        error.write_compact(&mut compact).unwrap();
+        assert_eq!(compact, "`<display error>` failed: exit status: 0");
+
+        let mut json = Vec::new();
+        error.write_json(&mut json).unwrap();
+        assert!(String::from_utf8(json)
+            .unwrap()
+            .starts_with(r#"{"kind":"output","program":"<display error>","exit_code":0,"#));
+    }
 }
@@ -1,18 +1,76 @@
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::io::IsTerminal;
+use std::ops::RangeInclusive;
+#[cfg(unix)]
+use std::os::fd::OwnedFd;
+use std::panic::Location;
+#[cfg(unix)]
+use std::path::Path;
 use std::process::Child;
+use std::process::ChildStdin;
+use std::process::ChildStdout;
 use std::process::ExitStatus;
+use std::process::Stdio;
 use std::process::{Command, Output};
+use std::time::Duration;
+use std::time::Instant;
 
 use utf8_command::Utf8Output;
 
+use crate::capture::RETAINED_TAIL;
+#[cfg(unix)]
+use crate::chroot;
+#[cfg(feature = "tracing")]
+use crate::duration::round_duration;
+use crate::fallback;
+use crate::line_prefixer::LinePrefixer;
+use crate::run;
+use crate::spawn_hook::run_spawn_hook;
+use crate::CaptureError;
 use crate::ChildContext;
+#[cfg(doc)]
+use crate::ChildExt;
+use crate::CircuitBreaker;
+use crate::Clock;
+#[cfg(doc)]
+use crate::CommandDisplay;
+use crate::Deadline;
+use crate::DiagnosticRerun;
 use crate::Error;
 use crate::ExecError;
+use crate::InvocationRecord;
+#[cfg(doc)]
+use crate::LazyMessage;
+#[cfg(feature = "tracing")]
+use crate::LazyProgramAndArgs;
+use crate::LossyUtf8Output;
+use crate::NormalizedUtf8Output;
 use crate::OutputContext;
-use crate::OutputConversionError;
+use crate::OutputError;
 use crate::OutputLike;
+use crate::OutputStream;
+use crate::RateLimiter;
+use crate::RecordReplay;
+use crate::RecordReplayMode;
+#[cfg(unix)]
+use crate::ResourceLimits;
+#[cfg(all(
+    feature = "seccomp",
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+use crate::SeccompFilter;
+use crate::SingleFlight;
+use crate::Spawner;
+use crate::StdioConfig;
+use crate::Termination;
+use crate::TimeoutError;
+use crate::TtyOutput;
+#[cfg(feature = "test-helpers")]
+use crate::UnexpectedSuccess;
 use crate::Utf8ProgramAndArgs;
+use crate::Validator;
 
 /// Extension trait for [`Command`].
 ///
@@ -40,7 +98,9 @@ use crate::Utf8ProgramAndArgs;
 /// );
 /// ```
 ///
-/// With the `tracing` feature enabled, commands will be logged before they run.
+/// With the `tracing` feature enabled, commands will be logged before they run, and again when
+/// they finish with their exit status, elapsed time, and start and end times (`start_time` and
+/// `end_time`, in milliseconds since the Unix epoch).
 ///
 /// # Method overview
 ///
@@ -50,10 +110,53 @@ use crate::Utf8ProgramAndArgs;
 /// | [`output_checked_with`][CommandExt::output_checked_with`] | Arbitrary | Custom |
 /// | [`output_checked_as`][CommandExt::output_checked_as`] | Arbitrary | Custom, with arbitrary error type |
 /// | [`output_checked_utf8`][CommandExt::output_checked_utf8`] | UTF-8 | If non-zero exit code |
+/// | [`output_checked_recorded`][CommandExt::output_checked_recorded`] | Bytes, with an [`InvocationRecord`] | If non-zero exit code |
+/// | [`output_checked_quiet`][CommandExt::output_checked_quiet`] | None, output only shown on failure | If non-zero exit code |
 /// | [`output_checked_with_utf8`][CommandExt::output_checked_with_utf8`] | UTF-8 | Custom |
+/// | [`output_checked_nul_separated`][CommandExt::output_checked_nul_separated`] | Bytes, split on NUL | If non-zero exit code |
+/// | [`output_checked_nul_separated_utf8`][CommandExt::output_checked_nul_separated_utf8`] | UTF-8, split on NUL | If non-zero exit code |
+/// | [`output_checked_with_backpressure`][CommandExt::output_checked_with_backpressure`] | Bytes, streamed | If non-zero exit code |
+/// | [`status_checked_with_backpressure`][CommandExt::status_checked_with_backpressure`] | None, streamed | If non-zero exit code |
+/// | [`output_checked_prefixed`][CommandExt::output_checked_prefixed`] | Bytes, streamed with a prefix | If non-zero exit code |
+/// | [`output_checked_counting_lines`][CommandExt::output_checked_counting_lines`] | Line count | If non-zero exit code |
+/// | [`output_checked_streaming_as`][CommandExt::output_checked_streaming_as`] | Arbitrary, streamed | Custom, with arbitrary error type |
+/// | [`output_checked_with_circuit_breaker`][CommandExt::output_checked_with_circuit_breaker`] | Bytes | If non-zero exit code, or failing fast after repeated failures |
+/// | [`output_checked_rate_limited`][CommandExt::output_checked_rate_limited`] | Bytes | If non-zero exit code |
+/// | [`output_checked_validated`][CommandExt::output_checked_validated`] | Bytes | Custom, with composable [`Validator`]s |
+/// | [`output_checked_diagnosing`][CommandExt::output_checked_diagnosing`] | Bytes | If non-zero exit code, re-running silent failures for more information |
+/// | [`output_checked_with_single_flight`][CommandExt::output_checked_with_single_flight`] | Bytes | If non-zero exit code, or if the command is already running |
+/// | [`output_checked_with_limits`][CommandExt::output_checked_with_limits`] | Bytes | If non-zero exit code, noting exceeded resource limits |
+/// | [`output_checked_with_stdin_fd`][CommandExt::output_checked_with_stdin_fd`] | Bytes, with a file descriptor as stdin | If non-zero exit code |
+/// | [`output_checked_trying`][CommandExt::output_checked_trying`] | Bytes | If non-zero exit code, or if none of the programs are found |
+/// | [`output_checked_with_clean_env`][CommandExt::output_checked_with_clean_env`] | Bytes | If non-zero exit code |
+/// | [`output_checked_with_stdin_writer`][CommandExt::output_checked_with_stdin_writer`] | Bytes, after an interactive conversation | If non-zero exit code, or if the conversation fails |
+/// | [`output_checked_into_buf`][CommandExt::output_checked_into_buf] | Bytes, in caller-provided buffers | If non-zero exit code |
+/// | [`output_checked_timeout`][CommandExt::output_checked_timeout] | Bytes | If non-zero exit code, or if the command times out |
+/// | [`output_checked_timeout_with`][CommandExt::output_checked_timeout_with] | Bytes | Custom, or if the command times out |
+/// | [`output_checked_with_timeout_and_grace`][CommandExt::output_checked_with_timeout_and_grace`] | Bytes | If non-zero exit code, or if the command times out |
+/// | [`output_checked_with_deadline_shared`][CommandExt::output_checked_with_deadline_shared] | Bytes | If non-zero exit code, or if a shared [`Deadline`] passes |
+/// | [`output_checked_with_deadline_shared_and_clock`][CommandExt::output_checked_with_deadline_shared_and_clock] | Bytes | If non-zero exit code, or if a shared [`Deadline`] passes according to a [`Clock`] |
+/// | [`output_checked_with_timeout_and_clock`][CommandExt::output_checked_with_timeout_and_clock`] | Bytes | If non-zero exit code, or if the command times out according to a [`Clock`] |
+/// | [`output_checked_with_spawner`][CommandExt::output_checked_with_spawner`] | Bytes | If non-zero exit code, reporting the [`Spawner`] backend used |
 /// | [`status_checked`][CommandExt::status_checked`] | None | If non-zero exit code |
 /// | [`status_checked_with`][CommandExt::status_checked_with`] | None | Custom |
+/// | [`status_checked_timeout`][CommandExt::status_checked_timeout] | None | If non-zero exit code, or if the command times out |
 /// | [`status_checked_as`][CommandExt::status_checked_as`] | None | Custom, with arbitrary error type |
+/// | [`status_checked_allowing_range`][CommandExt::status_checked_allowing_range`] | None | If exit code is outside of a range |
+/// | [`status_checked_with_spawner`][CommandExt::status_checked_with_spawner`] | None | If non-zero exit code, reporting the [`Spawner`] backend used |
+/// | [`output_checked_with_tty_detection`][CommandExt::output_checked_with_tty_detection`] | Bytes, if stdout is not a terminal | If non-zero exit code |
+///
+/// # Standard streams
+///
+/// Methods which read a command's output as it runs (like
+/// [`output_checked_with_backpressure`][CommandExt::output_checked_with_backpressure] and
+/// [`output_checked_timeout`][CommandExt::output_checked_timeout]) or write to its input (like
+/// [`output_checked_with_input`][CommandExt::output_checked_with_input]) pipe those streams
+/// before spawning it, replacing any stdio configured on the command. [`Command`] has no way to
+/// read or unset its stdio configuration, so it can't be restored afterwards: the pipes remain
+/// configured for later runs of the command, unless they're replaced with [`Command::stdin`],
+/// [`Command::stdout`], and [`Command::stderr`]. Each of these methods notes which streams it
+/// leaves piped.
 pub trait CommandExt: Sized {
     /// The error type returned from methods on this trait.
     type Error: From<Error> + Send + Sync;
@@ -191,6 +294,31 @@ pub trait CommandExt: Sized {
     ///     )
     /// );
     /// ```
+    ///
+    /// If the message is expensive to build, return a [`LazyMessage`] instead; it's only built if
+    /// the error is displayed:
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use std::process::Output;
+    /// # use command_error::CommandExt;
+    /// # use command_error::LazyMessage;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo '{}'"])
+    ///     .output_checked_with(|output: &Output| {
+    ///         if output.stdout.starts_with(b"[") {
+    ///             Ok(())
+    ///         } else {
+    ///             let stdout = output.stdout.clone();
+    ///             Err(Some(LazyMessage::new(move || {
+    ///                 format!("expected a list, got {} bytes", stdout.len())
+    ///             })))
+    ///         }
+    ///     })
+    ///     .unwrap_err();
+    ///
+    /// assert!(err.to_string().starts_with("`sh` failed: expected a list, got 3 bytes"));
+    /// ```
     #[track_caller]
     fn output_checked_with<O, E>(
         &mut self,
@@ -259,6 +387,97 @@ pub trait CommandExt: Sized {
         })
     }
 
+    /// Run a command, capturing its output but only returning its exit status. If the command
+    /// exits with a non-zero exit code, the error includes the captured output.
+    ///
+    /// This is for the common case where output only matters when something breaks: on success,
+    /// the output is discarded and nothing is shown, and on failure, the error has the complete
+    /// stdout and stderr. Compare [`CommandExt::status_checked`], which doesn't capture output at
+    /// all, and [`CommandExt::output_checked`], which always returns it.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let status = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .output_checked_quiet()
+    ///     .unwrap();
+    /// assert!(status.success());
+    ///
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo puppy; echo doggy >&2; exit 1"])
+    ///     .output_checked_quiet()
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exit status: 1
+    ///         Command failed: `sh -c 'echo puppy; echo doggy >&2; exit 1'`
+    ///         Stdout:
+    ///           puppy
+    ///         Stderr:
+    ///           doggy"
+    ///     )
+    /// );
+    /// ```
+    #[track_caller]
+    fn output_checked_quiet(&mut self) -> Result<ExitStatus, Self::Error> {
+        self.output_checked().map(|output| output.status)
+    }
+
+    /// Run a command, capturing its output, and return an [`InvocationRecord`] describing the
+    /// invocation along with the result. If the command exits with a non-zero exit code, an error
+    /// is raised.
+    ///
+    /// `label` identifies who (or what) asked for the command to be run, like a user or service
+    /// name, and is included in the record. A record is returned whether the command succeeds or
+    /// fails, so that every invocation can be logged with the same schema:
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let (result, record) = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .output_checked_recorded("alice");
+    /// let output = result.unwrap();
+    /// assert_eq!(output.stdout, b"puppy\n");
+    /// assert_eq!(record.args(), ["puppy"]);
+    /// assert_eq!(record.stdout_bytes(), 6);
+    /// assert_eq!(record.error(), None);
+    ///
+    /// let (result, record) = Command::new("ooga booga").output_checked_recorded("alice");
+    /// assert_eq!(
+    ///     record.error(),
+    ///     Some(result.unwrap_err().to_string().as_str()),
+    /// );
+    /// ```
+    #[track_caller]
+    fn output_checked_recorded(
+        &mut self,
+        label: &str,
+    ) -> (Result<Output, Self::Error>, InvocationRecord)
+    where
+        Self::Error: Display,
+    {
+        let started = Instant::now();
+        let mut record = InvocationRecord::start(label, self.command_display());
+        let result = match self.output_checked_as(Ok::<OutputContext<Output>, Self::Error>) {
+            Ok(context) => {
+                record = record.with_output(context.output());
+                if context.success() {
+                    Ok(context.into_output())
+                } else {
+                    Err(Self::Error::from(context.error()))
+                }
+            }
+            Err(error) => Err(error),
+        };
+        let error = result.as_ref().err().map(ToString::to_string);
+        (result, record.finish(started.elapsed(), error))
+    }
+
     /// Run a command, capturing its output and decoding it as UTF-8. If the command exits with a
     /// non-zero exit code or if its output contains invalid UTF-8, an error is raised.
     ///
@@ -296,6 +515,63 @@ pub trait CommandExt: Sized {
         })
     }
 
+    /// Run a command, capturing its output, decoding it as UTF-8, and converting `\r\n` line
+    /// endings to `\n`. If the command exits with a non-zero exit code or if its output contains
+    /// invalid UTF-8, an error is raised.
+    ///
+    /// This is useful for comparing the output of Windows tools with expected output. The
+    /// normalized output is also shown in errors. See [`NormalizedUtf8Output`].
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let output = Command::new("printf")
+    ///     .arg(r"puppy\r\ndoggy\n")
+    ///     .output_checked_utf8_normalized()
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, "puppy\ndoggy\n");
+    /// ```
+    #[track_caller]
+    fn output_checked_utf8_normalized(&mut self) -> Result<Utf8Output, Self::Error> {
+        self.output_checked_with(|output: &NormalizedUtf8Output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        })
+        .map(Utf8Output::from)
+    }
+
+    /// Run a command, capturing its output, to be decoded as UTF-8 with invalid sequences replaced
+    /// by � U+FFFD REPLACEMENT CHARACTER. If the command exits with a non-zero exit code, an
+    /// error is raised.
+    ///
+    /// Unlike [`CommandExt::output_checked_utf8`], invalid UTF-8 isn't an error, and valid output
+    /// isn't copied when it's decoded. See [`LossyUtf8Output`].
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let output = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .output_checked_utf8_lossy()
+    ///     .unwrap();
+    /// assert_eq!(output.stdout_str(), "puppy\n");
+    /// ```
+    #[track_caller]
+    fn output_checked_utf8_lossy(&mut self) -> Result<LossyUtf8Output, Self::Error> {
+        self.output_checked_with(|output: &LossyUtf8Output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        })
+    }
+
     /// Run a command, capturing its output and decoding it as UTF-8. `succeeded` is called and
     /// used to determine if the command succeeded and (optionally) to add an additional message to
     /// the error returned.
@@ -334,239 +610,2245 @@ pub trait CommandExt: Sized {
         self.output_checked_with(succeeded)
     }
 
-    /// Run a command without capturing its output. `succeeded` is called and returned to determine
-    /// if the command succeeded.
+    /// Run a command, capturing its output and splitting stdout into NUL-separated records. If the
+    /// command exits with a non-zero exit code, an error is raised.
     ///
-    /// This gives the caller full control over success logic and the output and errors produced.
+    /// This is useful for tools like `find -print0` and `git ls-files -z`, which use NUL bytes to
+    /// separate records that may contain newlines. A trailing empty record (after the final NUL) is
+    /// dropped.
     ///
     /// ```
     /// # use pretty_assertions::assert_eq;
-    /// # use indoc::indoc;
     /// # use std::process::Command;
-    /// # use std::process::ExitStatus;
     /// # use command_error::CommandExt;
-    /// # use command_error::OutputContext;
-    /// let succeeded = |context: OutputContext<ExitStatus>| {
-    ///     match context.status().code() {
-    ///         Some(code) => Ok(code),
-    ///         None => Err(context.error_msg("no exit code")),
-    ///     }
-    /// };
-    ///
-    /// let code = Command::new("true")
-    ///     .status_checked_as(succeeded)
+    /// let records = Command::new("printf")
+    ///     .arg(r"puppy\0dog\ngy\0")
+    ///     .output_checked_nul_separated()
     ///     .unwrap();
-    /// assert_eq!(code, 0);
     ///
-    /// let err = Command::new("sh")
-    ///     .args(["-c", "kill \"$$\""])
-    ///     .status_checked_as(succeeded)
-    ///     .unwrap_err();
-    /// assert_eq!(
-    ///     err.to_string(),
-    ///     indoc!(
-    ///         r#"`sh` failed: no exit code
-    ///         signal: 15 (SIGTERM)
-    ///         Command failed: `sh -c 'kill "$$"'`"#
-    ///     )
-    /// );
+    /// assert_eq!(records, vec![b"puppy".to_vec(), b"dog\ngy".to_vec()]);
     /// ```
     ///
-    /// To error on non-zero exit codes, use [`CommandExt::status_checked`].
-    ///
-    /// See [`Command::status`] for more information.
+    /// See [`CommandExt::output_checked_nul_separated_utf8`] to decode the records as UTF-8.
     #[track_caller]
-    fn status_checked_as<R, E>(
-        &mut self,
-        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E>,
-    ) -> Result<R, E>
-    where
-        E: From<Self::Error>;
+    fn output_checked_nul_separated(&mut self) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.output_checked()
+            .map(|output| split_nul(&output.stdout).map(<[u8]>::to_vec).collect())
+    }
 
-    /// Run a command without capturing its output. `succeeded` is called and used to determine
-    /// if the command succeeded and (optionally) to add an additional message to the error
-    /// returned.
+    /// Run a command, capturing its output, decoding it as UTF-8, and splitting stdout into
+    /// NUL-separated records. If the command exits with a non-zero exit code or if its output
+    /// contains invalid UTF-8, an error is raised.
+    ///
+    /// See [`CommandExt::output_checked_nul_separated`] for more information.
     ///
     /// ```
     /// # use pretty_assertions::assert_eq;
-    /// # use indoc::indoc;
     /// # use std::process::Command;
-    /// # use std::process::ExitStatus;
     /// # use command_error::CommandExt;
-    /// # use command_error::OutputContext;
-    /// let status = Command::new("false")
-    ///     .status_checked_with(|status| {
-    ///         match status.code() {
-    ///             // Exit codes 0 and 1 are OK.
-    ///             Some(0) | Some(1) => Ok(()),
-    ///             // Other exit codes are errors.
-    ///             _ => Err(None::<String>)
-    ///         }
-    ///     })
+    /// let records = Command::new("printf")
+    ///     .arg(r"puppy\0doggy\0")
+    ///     .output_checked_nul_separated_utf8()
     ///     .unwrap();
-    /// assert_eq!(status.code(), Some(1));
+    ///
+    /// assert_eq!(records, vec!["puppy", "doggy"]);
     /// ```
+    #[track_caller]
+    fn output_checked_nul_separated_utf8(&mut self) -> Result<Vec<String>, Self::Error> {
+        self.output_checked_utf8().map(|output| {
+            split_nul(output.stdout.as_bytes())
+                // Splitting valid UTF-8 on an ASCII byte produces valid UTF-8.
+                .map(|record| String::from_utf8_lossy(record).into_owned())
+                .collect()
+        })
+    }
+
+    /// Run a command, capturing its output and feeding its stdout into `hasher` as it's read. If
+    /// the command exits with a non-zero exit code, an error is raised.
     ///
-    /// See [`Command::status`] for more information.
+    /// Returns the command's output and the finalized hash of its stdout. Hashing happens during
+    /// capture, so large outputs don't need a second pass just to be hashed. `hasher` is reset
+    /// after finalizing, so it can be reused.
+    ///
+    /// If the command fails, `hasher` isn't finalized or reset: it's left holding whatever stdout
+    /// was read before the failure. Reset it (with [`digest::Digest::reset`]) before reusing it.
+    ///
+    /// Requires the `digest` feature.
+    ///
+    /// ```ignore
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// use sha2::Digest;
+    /// use sha2::Sha256;
+    ///
+    /// let (output, hash) = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .output_checked_hashing(&mut Sha256::new())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(output.stdout, b"puppy\n");
+    /// assert_eq!(hash, Sha256::digest(b"puppy\n"));
+    /// ```
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[cfg(feature = "digest")]
     #[track_caller]
-    fn status_checked_with<E>(
+    fn output_checked_hashing<D>(
         &mut self,
-        succeeded: impl Fn(ExitStatus) -> Result<(), Option<E>>,
-    ) -> Result<ExitStatus, Self::Error>
+        hasher: &mut D,
+    ) -> Result<(Output, digest::Output<D>), Self::Error>
     where
-        E: Debug + Display + Send + Sync + 'static,
+        D: digest::Digest + digest::FixedOutputReset,
     {
-        self.status_checked_as(|status| match succeeded(status.status()) {
-            Ok(()) => Ok(status.status()),
-            Err(user_error) => Err(status.maybe_error_msg(user_error).into()),
-        })
+        let output = self.output_checked_with_backpressure(HASHING_BOUND, |stream, chunk| {
+            if stream == OutputStream::Stdout {
+                digest::Digest::update(hasher, chunk);
+            }
+        })?;
+        Ok((output, digest::Digest::finalize_reset(hasher)))
     }
 
-    /// Run a command without capturing its output. If the command exits with a non-zero status
-    /// code, an error is raised containing information about the command that was run:
+    /// Run a command and count the lines in its stdout, like `wc -l`. If the command exits with a
+    /// non-zero exit code, an error is raised.
+    ///
+    /// Lines are counted as the output is read, without splitting it into separate strings. A
+    /// final line without a trailing newline is counted (unlike `wc -l`), so this agrees with
+    /// [`str::lines`].
     ///
     /// ```
     /// # use pretty_assertions::assert_eq;
-    /// # use indoc::indoc;
     /// # use std::process::Command;
-    /// # use std::process::ExitStatus;
     /// # use command_error::CommandExt;
-    /// let err = Command::new("sh")
-    ///     .args(["-c", "exit 1"])
-    ///     .status_checked()
-    ///     .unwrap_err();
+    /// let lines = Command::new("printf")
+    ///     .arg("puppy\ndoggy\nkitty")
+    ///     .output_checked_counting_lines()
+    ///     .unwrap();
+    /// assert_eq!(lines, 3);
     ///
-    /// assert_eq!(
-    ///     err.to_string(),
-    ///     indoc!(
-    ///         "`sh` failed: exit status: 1
-    ///         Command failed: `sh -c 'exit 1'`"
-    ///     )
-    /// );
+    /// let lines = Command::new("true").output_checked_counting_lines().unwrap();
+    /// assert_eq!(lines, 0);
     /// ```
     ///
-    /// See [`Command::status`] for more information.
+    /// The output isn't kept, except for the last 64 KiB of each stream, which is included in the
+    /// error message if the command fails.
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
     #[track_caller]
-    fn status_checked(&mut self) -> Result<ExitStatus, Self::Error> {
-        self.status_checked_with(|status| {
-            if status.success() {
-                Ok(())
-            } else {
-                Err(None::<String>)
+    fn output_checked_counting_lines(&mut self) -> Result<usize, Self::Error> {
+        let mut newlines = 0;
+        let mut unterminated = false;
+        self.status_checked_with_backpressure(LINE_COUNTING_BOUND, |stream, chunk| {
+            if stream == OutputStream::Stdout && !chunk.is_empty() {
+                newlines += chunk.iter().filter(|byte| **byte == b'\n').count();
+                unterminated = chunk.last() != Some(&b'\n');
             }
-        })
+        })?;
+        Ok(newlines + usize::from(unterminated))
     }
 
-    /// Spawn a command.
-    ///
-    /// The returned child contains context information about the command that produced it, which
-    /// can be used to produce detailed error messages if the child process fails.
+    /// Run a command which is expected to fail, returning the error describing its failure.
     ///
-    /// See [`Command::spawn`] for more information.
+    /// This inverts the usual polarity of [`CommandExt::output_checked`], for tests which assert
+    /// that a command fails in a particular way. If the command unexpectedly succeeds, an
+    /// [`UnexpectedSuccess`] error including its full output is returned instead.
     ///
     /// ```
     /// # use pretty_assertions::assert_eq;
     /// # use indoc::indoc;
     /// # use std::process::Command;
-    /// # use std::process::ExitStatus;
     /// # use command_error::CommandExt;
-    /// let err = Command::new("ooga booga")
-    ///     .spawn_checked()
-    ///     .unwrap_err();
+    /// let error = Command::new("sh")
+    ///     .args(["-c", "echo 'usage: frobnicate FILE' >&2; exit 2"])
+    ///     .expect_failure()
+    ///     .unwrap();
+    /// assert_eq!(error.output().status().code(), Some(2));
+    /// assert!(error.output().stderr().contains("usage"));
     ///
+    /// let error = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .expect_failure()
+    ///     .unwrap_err();
     /// assert_eq!(
-    ///     err.to_string(),
-    ///     "Failed to execute `'ooga booga'`: No such file or directory (os error 2)"
+    ///     error.to_string(),
+    ///     indoc!(
+    ///         "`echo` succeeded unexpectedly: exit status: 0
+    ///         Command: `echo puppy`
+    ///         Stdout:
+    ///           puppy"
+    ///     )
     /// );
     /// ```
-    #[track_caller]
-    fn spawn_checked(&mut self) -> Result<Self::Child, Self::Error>;
-
-    /// Log the command that will be run.
     ///
-    /// With the `tracing` feature enabled, this will emit a debug-level log with message
-    /// `Executing command` and a `command` field containing the command and arguments shell-quoted.
-    fn log(&self) -> Result<(), Self::Error>;
-}
-
-impl CommandExt for Command {
-    type Error = Error;
-    type Child = ChildContext<Child>;
-
-    fn log(&self) -> Result<(), Self::Error> {
-        #[cfg(feature = "tracing")]
-        {
-            let command: Utf8ProgramAndArgs = self.into();
-            tracing::debug!(%command, "Executing command");
-        }
-        Ok(())
-    }
-
-    fn output_checked_as<O, R, E>(
-        &mut self,
-        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
-    ) -> Result<R, E>
+    /// # Panics
+    ///
+    /// If the command fails some other way, like if it can't be started. These failures usually
+    /// indicate a broken test environment rather than a passing test.
+    #[cfg(feature = "test-helpers")]
+    #[track_caller]
+    fn expect_failure(&mut self) -> Result<OutputError, UnexpectedSuccess>
     where
-        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
-        <O as TryFrom<Output>>::Error: Display + Send + Sync,
-        E: From<Self::Error> + Send + Sync,
+        Self::Error: Display,
     {
-        self.log()?;
-        let displayed: Utf8ProgramAndArgs = (&*self).into();
-        match self.output() {
-            Ok(output) => match output.try_into() {
-                Ok(output) => succeeded(OutputContext {
-                    output,
-                    command: Box::new(displayed),
-                }),
-                Err(error) => Err(Error::from(OutputConversionError {
-                    command: Box::new(displayed),
-                    inner: Box::new(error),
-                })
-                .into()),
-            },
-            Err(inner) => Err(Error::from(ExecError {
-                command: Box::new(displayed),
-                inner,
-            })
-            .into()),
+        match self.output_checked_as(Ok::<OutputContext<Output>, Self::Error>) {
+            Ok(context) if context.success() => {
+                Err(UnexpectedSuccess::new(context.command, context.output))
+            }
+            Ok(context) => Ok(context.into_output_error()),
+            Err(error) => panic!("Expected command to fail, but it couldn't be run: {error}"),
         }
     }
 
-    fn status_checked_as<R, E>(
-        &mut self,
-        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E>,
+    /// Run a command which is expected to succeed, panicking with the error's [`Display`] message
+    /// if it fails.
+    ///
+    /// This is like `output_checked().unwrap()`, but the panic message is the full error message
+    /// rather than its [`Debug`] representation, so test setup commands read cleanly:
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let output = Command::new("echo").arg("puppy").output_checked_asserting();
+    /// assert_eq!(output.stdout, b"puppy\n");
+    /// ```
+    ///
+    /// ```should_panic
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// Command::new("sh")
+    ///     .args(["-c", "echo puppy; false"])
+    ///     .output_checked_asserting();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the command fails, in any way that [`CommandExt::output_checked`] would return an error.
+    #[cfg(feature = "test-helpers")]
+    #[track_caller]
+    fn output_checked_asserting(&mut self) -> Output
+    where
+        Self::Error: Display,
+    {
+        match self.output_checked() {
+            Ok(output) => output,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// Run a command, streaming its output to `on_chunk` as it's produced. Once the command exits,
+    /// `succeeded` is called with the complete output and returned to determine if the command
+    /// succeeded.
+    ///
+    /// Output is read on background threads and passed to `on_chunk` on the calling thread through
+    /// a channel holding at most `bound` chunks. If `on_chunk` is slow, reading from the child
+    /// stops until it catches up, so a chatty command blocks on its writes rather than racing
+    /// ahead of the consumer.
+    ///
+    /// The complete output is also retained for `succeeded` and for error messages, so memory use
+    /// still grows with the size of the command's output.
+    ///
+    /// The command's stdout and stderr are always piped.
+    ///
+    /// See [`CommandExt::output_checked_as`] and [`CommandExt::output_checked_with_backpressure`]
+    /// for more information.
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_streaming_as<O, R, E>(
+        &mut self,
+        bound: usize,
+        on_chunk: impl FnMut(OutputStream, &[u8]),
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync;
+
+    /// Run a command, streaming its output to `on_chunk` as it's produced. If the command exits
+    /// with a non-zero exit code, an error is raised.
+    ///
+    /// At most `bound` chunks of output are buffered between the threads reading from the child and
+    /// `on_chunk`; when the buffer is full, the child is left to block on its writes until
+    /// `on_chunk` catches up. This applies backpressure to a command which is faster than its
+    /// consumer, but doesn't bound memory use: the complete output is retained as well.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::OutputStream;
+    /// let mut stdout_chunks = Vec::new();
+    /// let output = Command::new("sh")
+    ///     .args(["-c", "echo puppy; echo doggy >&2"])
+    ///     .output_checked_with_backpressure(1, |stream, chunk| {
+    ///         if stream == OutputStream::Stdout {
+    ///             stdout_chunks.push(chunk.to_vec());
+    ///         }
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(stdout_chunks.concat(), b"puppy\n");
+    /// assert_eq!(output.stdout, b"puppy\n");
+    /// assert_eq!(output.stderr, b"doggy\n");
+    /// ```
+    ///
+    /// The complete output is still returned (and included in error messages), so this is best
+    /// suited for displaying progress. Use [`CommandExt::status_checked_with_backpressure`] to
+    /// keep memory use bounded, or see [`CommandExt::output_checked_streaming_as`] for full
+    /// control over the result.
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_backpressure(
+        &mut self,
+        bound: usize,
+        on_chunk: impl FnMut(OutputStream, &[u8]),
+    ) -> Result<Output, Self::Error> {
+        self.output_checked_streaming_as(bound, on_chunk, |context: OutputContext<Output>| {
+            if context.success() {
+                Ok(context.into_output())
+            } else {
+                Err(context.error().into())
+            }
+        })
+    }
+
+    /// Run a command, streaming its output to `on_chunk` as it's produced without keeping it. If
+    /// the command exits with a non-zero exit code, an error is raised.
+    ///
+    /// Like [`CommandExt::output_checked_with_backpressure`], at most `bound` chunks are buffered
+    /// between the threads reading from the child and `on_chunk`. Unlike it, only the last 64 KiB
+    /// of each stream is kept, to be shown in the error message if the command fails, so memory
+    /// use stays bounded however much the command writes.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::OutputStream;
+    /// let mut stdout_bytes = 0;
+    /// let status = Command::new("sh")
+    ///     .args(["-c", "head -c 1000000 /dev/zero"])
+    ///     .status_checked_with_backpressure(1, |stream, chunk| {
+    ///         if stream == OutputStream::Stdout {
+    ///             stdout_bytes += chunk.len();
+    ///         }
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert!(status.success());
+    /// assert_eq!(stdout_bytes, 1_000_000);
+    /// ```
+    ///
+    /// The command's stdout and stderr are always piped. Types other than [`Command`] and
+    /// `process_wrap::std::StdCommandWrap` which implement this trait keep the complete output by
+    /// default.
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn status_checked_with_backpressure(
+        &mut self,
+        bound: usize,
+        on_chunk: impl FnMut(OutputStream, &[u8]),
+    ) -> Result<ExitStatus, Self::Error> {
+        self.output_checked_streaming_as(bound, on_chunk, |context: OutputContext<Output>| {
+            if context.success() {
+                Ok(context.status())
+            } else {
+                Err(context.error().into())
+            }
+        })
+    }
+
+    /// Run a command, capturing its output and writing each line to the current process's stdout
+    /// or stderr as it's read, prefixed with `label`. If the command exits with a non-zero exit
+    /// code, an error is raised.
+    ///
+    /// This is useful for running several commands at once without their output interleaving
+    /// confusingly, like `docker-compose` does:
+    ///
+    /// ```text
+    /// web | Listening on port 8080
+    /// db  | Ready to accept connections
+    /// ```
+    ///
+    /// Lines are written as soon as they're complete, and a trailing incomplete line is written
+    /// once the command exits. The returned output (and the output included in errors) isn't
+    /// prefixed:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let output = Command::new("sh")
+    ///     .args(["-c", "echo ready; echo warming up >&2"])
+    ///     .output_checked_prefixed("web")
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"ready\n");
+    /// assert_eq!(output.stderr, b"warming up\n");
+    /// ```
+    ///
+    /// Pad labels to the same width to line up the output of several commands.
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_prefixed(&mut self, label: &str) -> Result<Output, Self::Error> {
+        let mut prefixer = LinePrefixer::new(label);
+        let result = self.output_checked_with_backpressure(PREFIXED_BOUND, |stream, chunk| {
+            prefixer.write_chunk(stream, chunk)
+        });
+        prefixer.finish();
+        result
+    }
+
+    /// Run a command, capturing its output. `validator` determines if the command succeeded; if it
+    /// fails, every condition which failed is listed in the error, and available from
+    /// [`OutputError::violations`].
+    ///
+    /// See [`Validator`] for examples, and [`CommandExt::output_checked_with`] for more
+    /// information.
+    #[track_caller]
+    fn output_checked_validated(&mut self, validator: &Validator) -> Result<Output, Self::Error> {
+        self.output_checked_as(|context: OutputContext<Output>| {
+            let violations = validator.violations(context.output());
+            if violations.is_empty() {
+                Ok(context.into_output())
+            } else {
+                Err(Error::from(context.into_output_error().with_violations(violations)).into())
+            }
+        })
+    }
+
+    /// Run a command, capturing its output. If the command exits with a non-zero exit code, an
+    /// error is raised; if it also produced no output at all, it's re-run once with `extra_args`
+    /// (like `-v`) to gather more information, and the re-run's output is attached to the error.
+    ///
+    /// The re-run is stopped if it runs for longer than `timeout`. It's never triggered when the
+    /// command fails to start, or when the command produced any output. The re-run's output is
+    /// available from [`OutputError::diagnostic_rerun`].
+    ///
+    /// The re-run command is reconstructed with [`CommandDisplay::to_command`], which is lossy;
+    /// see its documentation for details. Commands with so many arguments that some were
+    /// [omitted](CommandDisplay::omitted_args) can't be reconstructed, so they aren't re-run.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::time::Duration;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "[ \"$1\" = -v ] && echo 'config file missing' >&2; exit 1", "sh"])
+    ///     .output_checked_diagnosing(&["-v"], Duration::from_secs(10))
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         r#"`sh` failed: exit status: 1
+    ///         Command failed: `sh -c '[ "$1" = -v ] && echo '\''config file missing'\'' >&2; exit 1' sh`
+    ///         Diagnostic re-run output (`sh -c '[ "$1" = -v ] && echo '\''config file missing'\'' >&2; exit 1' sh -v`, exit status: 1):
+    ///           Stderr:
+    ///             config file missing"#
+    ///     )
+    /// );
+    ///
+    /// // Commands which explain their failures aren't re-run.
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo 'disk full' >&2; exit 1"])
+    ///     .output_checked_diagnosing(&["-v"], Duration::from_secs(10))
+    ///     .unwrap_err();
+    /// assert!(!err.to_string().contains("Diagnostic re-run"));
+    /// ```
+    #[track_caller]
+    fn output_checked_diagnosing(
+        &mut self,
+        extra_args: &[&str],
+        timeout: Duration,
+    ) -> Result<Output, Self::Error> {
+        self.output_checked_as(|context: OutputContext<Output>| {
+            let output = context.output();
+            if output.status.success() {
+                Ok(context.into_output())
+            } else if !output.stdout.is_empty()
+                || !output.stderr.is_empty()
+                || context.command.omitted_args() > 0
+            {
+                Err(context.error().into())
+            } else {
+                let rerun = DiagnosticRerun::run(context.rerun_with_args(extra_args), timeout);
+                let error = context.into_output_error();
+                Err(Error::from(match rerun {
+                    Some(rerun) => error.with_diagnostic_rerun(rerun),
+                    None => error,
+                })
+                .into())
+            }
+        })
+    }
+
+    /// Run a command unless it's already running, capturing its output. If the command exits
+    /// with a non-zero exit code, an error is raised.
+    ///
+    /// If the same command is already running, an [`Error::AlreadyRunning`] is returned instead,
+    /// or (if `single_flight` is [blocking][`SingleFlight::blocking`]) the running command is
+    /// waited for first. See [`SingleFlight`] for more information.
+    #[track_caller]
+    fn output_checked_with_single_flight(
+        &mut self,
+        single_flight: &SingleFlight,
+    ) -> Result<Output, Self::Error> {
+        let _guard = single_flight.acquire(&self.command_display())?;
+        self.output_checked()
+    }
+
+    /// Run a command and record its output to a fixture file, or replay previously-recorded output
+    /// without running the command, depending on the [`RecordReplay`]'s mode.
+    ///
+    /// Both successful and failed runs are recorded, and replayed output is checked the same way
+    /// as [`CommandExt::output_checked`] checks real output, so replayed errors are identical to
+    /// recorded ones. Commands that fail to start are not recorded.
+    ///
+    /// When replaying, the fixture must have been recorded for the same command (including its
+    /// working directory and environment variables), or an error is returned. Failures to read or
+    /// write the fixture are returned as a [`CaptureError`] naming the fixture's path.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::RecordReplay;
+    /// let path = std::env::temp_dir().join("command-error-doctest-recording.fixture");
+    /// let mut command = Command::new("sh");
+    /// command.args(["-c", "echo puppy; exit 1"]);
+    ///
+    /// let recorded = command
+    ///     .output_checked_recording(&RecordReplay::record(&path))
+    ///     .unwrap_err();
+    /// let replayed = command
+    ///     .output_checked_recording(&RecordReplay::replay(&path))
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(recorded.to_string(), replayed.to_string());
+    /// assert_eq!(
+    ///     replayed.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exit status: 1
+    ///         Command failed: `sh -c 'echo puppy; exit 1'`
+    ///         Stdout:
+    ///           puppy"
+    ///     )
+    /// );
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    #[track_caller]
+    fn output_checked_recording(&mut self, fixture: &RecordReplay) -> Result<Output, Self::Error> {
+        let displayed = self.command_display();
+        let check = |context: OutputContext<Output>| {
+            if context.success() {
+                Ok(context.into_output())
+            } else {
+                Err(Self::Error::from(context.error()))
+            }
+        };
+
+        match fixture.mode() {
+            RecordReplayMode::Record => self.output_checked_as(|context: OutputContext<Output>| {
+                match fixture.write(&displayed, context.output()) {
+                    Ok(()) => check(context),
+                    Err(inner) => Err(Error::from(CaptureError::new(
+                        Box::new(displayed.clone()),
+                        fixture.context(inner),
+                    ))
+                    .into()),
+                }
+            }),
+            RecordReplayMode::Replay => {
+                let (recorded, output) = match fixture.read() {
+                    Ok(fixture) => fixture,
+                    Err(inner) => {
+                        return Err(Error::from(CaptureError::new(
+                            Box::new(displayed),
+                            fixture.context(inner),
+                        ))
+                        .into());
+                    }
+                };
+                if recorded.to_string() != displayed.to_string() {
+                    let inner = std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "Fixture `{}` was recorded for a different command: `{recorded}`",
+                            fixture.path().display()
+                        ),
+                    );
+                    return Err(Error::from(CaptureError::new(Box::new(displayed), inner)).into());
+                }
+                check(OutputContext::new(output, Box::new(displayed)))
+            }
+        }
+    }
+
+    /// Run a command without capturing its output. `succeeded` is called and returned to determine
+    /// if the command succeeded.
+    ///
+    /// This gives the caller full control over success logic and the output and errors produced.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use std::process::ExitStatus;
+    /// # use command_error::CommandExt;
+    /// # use command_error::OutputContext;
+    /// let succeeded = |context: OutputContext<ExitStatus>| {
+    ///     match context.code() {
+    ///         Some(code) => Ok(code),
+    ///         None => Err(context.error_msg("no exit code")),
+    ///     }
+    /// };
+    ///
+    /// let code = Command::new("true")
+    ///     .status_checked_as(succeeded)
+    ///     .unwrap();
+    /// assert_eq!(code, 0);
+    ///
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "kill \"$$\""])
+    ///     .status_checked_as(succeeded)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         r#"`sh` failed: no exit code
+    ///         signal: 15 (SIGTERM)
+    ///         Command failed: `sh -c 'kill "$$"'`"#
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// To error on non-zero exit codes, use [`CommandExt::status_checked`].
+    ///
+    /// See [`Command::status`] for more information.
+    #[track_caller]
+    fn status_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        E: From<Self::Error>;
+
+    /// Run a command without capturing its output. `succeeded` is called and used to determine
+    /// if the command succeeded and (optionally) to add an additional message to the error
+    /// returned.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use std::process::ExitStatus;
+    /// # use command_error::CommandExt;
+    /// # use command_error::OutputContext;
+    /// let status = Command::new("false")
+    ///     .status_checked_with(|status| {
+    ///         match status.code() {
+    ///             // Exit codes 0 and 1 are OK.
+    ///             Some(0) | Some(1) => Ok(()),
+    ///             // Other exit codes are errors.
+    ///             _ => Err(None::<String>)
+    ///         }
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(status.code(), Some(1));
+    /// ```
+    ///
+    /// See [`Command::status`] for more information.
+    #[track_caller]
+    fn status_checked_with<E>(
+        &mut self,
+        succeeded: impl Fn(ExitStatus) -> Result<(), Option<E>>,
+    ) -> Result<ExitStatus, Self::Error>
+    where
+        E: Debug + Display + Send + Sync + 'static,
+    {
+        self.status_checked_as(|status| match succeeded(status.status()) {
+            Ok(()) => Ok(status.status()),
+            Err(user_error) => Err(status.maybe_error_msg(user_error).into()),
+        })
+    }
+
+    /// Run a command without capturing its output. If the command exits with a non-zero status
+    /// code, an error is raised containing information about the command that was run:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use std::process::ExitStatus;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "exit 1"])
+    ///     .status_checked()
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exit status: 1
+    ///         Command failed: `sh -c 'exit 1'`"
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// See [`Command::status`] for more information.
+    #[track_caller]
+    fn status_checked(&mut self) -> Result<ExitStatus, Self::Error> {
+        self.status_checked_with(|status| {
+            if status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        })
+    }
+
+    /// Run a command without capturing its output. If the command exits with an exit code outside
+    /// of `allowed`, an error is raised.
+    ///
+    /// This is useful for tools with exit code conventions, like [`sysexits.h`][sysexits] which
+    /// reserves codes `64` and above for errors. Commands killed by a signal (which have no exit
+    /// code) always fail.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let status = Command::new("sh")
+    ///     .args(["-c", "exit 1"])
+    ///     .status_checked_allowing_range(0..=63)
+    ///     .unwrap();
+    /// assert_eq!(status.code(), Some(1));
+    ///
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "exit 64"])
+    ///     .status_checked_allowing_range(0..=63)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exit status: 64
+    ///         Command failed: `sh -c 'exit 64'`"
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// [sysexits]: https://man.freebsd.org/cgi/man.cgi?query=sysexits
+    #[track_caller]
+    fn status_checked_allowing_range(
+        &mut self,
+        allowed: RangeInclusive<i32>,
+    ) -> Result<ExitStatus, Self::Error> {
+        self.status_checked_with(|status| match status.code() {
+            Some(code) if allowed.contains(&code) => Ok(()),
+            _ => Err(None::<String>),
+        })
+    }
+
+    /// Run a command without capturing its output, and return whether it succeeded.
+    ///
+    /// This is for probing commands where a non-zero exit code is expected data rather than an
+    /// error, like `git rev-parse --is-inside-work-tree`. The command's stdout and stderr are
+    /// captured and discarded so probes are quiet. An exit code of 0 returns `true` and any other
+    /// exit returns `false`, including a command killed by a signal (which has no exit code).
+    /// Failing to start or wait for the command is still an error:
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// assert!(Command::new("true").succeeds().unwrap());
+    /// assert!(!Command::new("sh").args(["-c", "echo puppy; exit 1"]).succeeds().unwrap());
+    /// assert!(!Command::new("sh").args(["-c", "kill -9 $$"]).succeeds().unwrap());
+    ///
+    /// // The command's stdio is left as it was, so it can be run again to capture its output.
+    /// let mut command = Command::new("echo");
+    /// command.arg("puppy");
+    /// assert!(command.succeeds().unwrap());
+    /// assert_eq!(command.output_checked().unwrap().stdout, b"puppy\n");
+    ///
+    /// let err = Command::new("ooga booga").succeeds().unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Failed to execute `'ooga booga'`: No such file or directory (os error 2)"
+    /// );
+    /// ```
+    ///
+    /// See [`CommandExt::succeeds_with_output`] to keep the command's output.
+    ///
+    /// The command's stdio configuration isn't changed, so a command which was configured to
+    /// inherit stdout still writes to it, and later runs of the command aren't affected. The
+    /// exception is `process_wrap::std::StdCommandWrap`, which doesn't capture output by default:
+    /// its stdout and stderr are set to null instead, and remain so for later runs.
+    #[track_caller]
+    fn succeeds(&mut self) -> Result<bool, Self::Error> {
+        self.succeeds_with_output()
+            .map(|(success, _output)| success)
+    }
+
+    /// Run a command, capturing its output, and return whether it succeeded along with its output.
+    ///
+    /// This is [`CommandExt::succeeds`], but the output is kept (for example, to log why a probe
+    /// returned `false`) rather than discarded:
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let (succeeded, output) = Command::new("sh")
+    ///     .args(["-c", "echo not a repository >&2; exit 128"])
+    ///     .succeeds_with_output()
+    ///     .unwrap();
+    /// assert!(!succeeded);
+    /// assert_eq!(output.stderr, b"not a repository\n");
+    /// ```
+    #[track_caller]
+    fn succeeds_with_output(&mut self) -> Result<(bool, Output), Self::Error> {
+        self.output_checked_as(|context: OutputContext<Output>| {
+            Ok((context.success(), context.into_output()))
+        })
+    }
+
+    /// Run a command, capturing its output, unless it has failed too many times recently. If the
+    /// command exits with a non-zero exit code, an error is raised.
+    ///
+    /// Failures are recorded in `breaker`; when the command has failed too often, it's not run
+    /// and an [`Error::CircuitOpen`] is returned instead. See [`CircuitBreaker`] for more
+    /// information.
+    #[track_caller]
+    fn output_checked_with_circuit_breaker(
+        &mut self,
+        breaker: &CircuitBreaker,
+    ) -> Result<Output, Self::Error> {
+        let key = breaker
+            .check(&self.command_display())
+            .map_err(Error::from)?;
+        self.output_checked_as(|context: OutputContext<Output>| {
+            if context.success() {
+                breaker.record_success(key);
+                Ok(context.into_output())
+            } else {
+                breaker.record_failure(
+                    key,
+                    OutputError::new(
+                        dyn_clone::clone_box(&*context.command),
+                        Box::new(context.output().clone()),
+                    ),
+                );
+                Err(context.error().into())
+            }
+        })
+    }
+
+    /// Run a command, capturing its output, after waiting for a permit from `limiter`. If the
+    /// command exits with a non-zero exit code, an error is raised.
+    ///
+    /// This blocks until the [`RateLimiter`] allows another command to start, which keeps
+    /// throttling in one place when many commands talk to the same rate-limited service.
+    #[track_caller]
+    fn output_checked_rate_limited(
+        &mut self,
+        limiter: &RateLimiter,
+    ) -> Result<Output, Self::Error> {
+        limiter.acquire();
+        self.output_checked()
+    }
+
+    /// Run a command with [`ResourceLimits`] applied, capturing its output. If the command exits
+    /// with a non-zero exit code, an error is raised.
+    ///
+    /// The limits are set with `setrlimit` in the child after it's forked and before it executes
+    /// the program, and remain set on the command for subsequent runs. If the command is killed
+    /// after exceeding a limit, the error message notes the likely cause:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use std::time::Duration;
+    /// # use command_error::CommandExt;
+    /// # use command_error::ResourceLimits;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "while :; do :; done"])
+    ///     .output_checked_with_limits(ResourceLimits::new().cpu_time(Duration::from_secs(1)))
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: CPU time limit of 1s exceeded
+    ///         signal: 24 (SIGXCPU)
+    ///         Command failed: `sh -c 'while :; do :; done'`"
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// The limits can't be changed once they're set on a command: [`Command`] has no way to
+    /// remove them. Calling this method again with the same limits runs the command as usual, but
+    /// with different limits, the command fails to start with an error saying so; use a new
+    /// [`Command`] to change the limits.
+    ///
+    /// Only available on Unix.
+    #[cfg(unix)]
+    #[track_caller]
+    fn output_checked_with_limits(&mut self, limits: ResourceLimits)
+        -> Result<Output, Self::Error>;
+
+    /// Run a command with its root directory changed to `root` (see [`chroot(2)`][chroot]),
+    /// capturing its output. If the command exits with a non-zero exit code, an error is raised.
+    ///
+    /// The root directory is changed in the child after it's forked and before it executes the
+    /// program, so the program (and any libraries it loads) must exist inside `root`. The child's
+    /// working directory is the new root, like with `chroot(1)`, and the root directory remains
+    /// set on the command for subsequent runs. Error messages display the command with a `chroot`
+    /// prefix:
+    ///
+    /// ```no_run
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("/bin/sh")
+    ///     .args(["-c", "exit 1"])
+    ///     .output_checked_with_chroot("/srv/jail")
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "`/bin/sh` failed: exit status: 1\nCommand failed: `chroot /srv/jail /bin/sh -c 'exit 1'`"
+    /// );
+    /// ```
+    ///
+    /// Changing the root directory requires root privileges (or the `CAP_SYS_CHROOT` capability
+    /// on Linux); without them, the command fails to start with an error saying so. A chroot
+    /// isn't a security boundary against a privileged child.
+    ///
+    /// The root directory can't be changed once it's set on a command: [`Command`] has no way to
+    /// unset it. Calling this method again with the same `root` runs the command as usual, but
+    /// with a different `root`, the command fails to start with an error saying so; use a new
+    /// [`Command`] for each root directory.
+    ///
+    /// Only available on Unix.
+    ///
+    /// [chroot]: https://man7.org/linux/man-pages/man2/chroot.2.html
+    #[cfg(unix)]
+    #[track_caller]
+    fn output_checked_with_chroot(&mut self, root: impl AsRef<Path>)
+        -> Result<Output, Self::Error>;
+
+    /// Run a command with `stdin` as its standard input, capturing its output. If the command
+    /// exits with a non-zero exit code, an error is raised.
+    ///
+    /// This is useful for handing an already-open socket or pipe to a child, like for systemd
+    /// socket activation or `git upload-pack`. The file descriptor is moved into the command and
+    /// closed in this process once the command has been spawned.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::io::Write;
+    /// # use std::os::fd::OwnedFd;
+    /// # use std::process::Command;
+    /// # use std::process::Stdio;
+    /// # use command_error::CommandExt;
+    /// let mut writer = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .stdout(Stdio::piped())
+    ///     .spawn()
+    ///     .unwrap();
+    /// let pipe = OwnedFd::from(writer.stdout.take().unwrap());
+    ///
+    /// let output = Command::new("cat").output_checked_with_stdin_fd(pipe).unwrap();
+    /// assert_eq!(output.stdout, b"puppy\n");
+    /// # writer.wait().unwrap();
+    /// ```
+    ///
+    /// Only available on Unix.
+    #[cfg(unix)]
+    #[track_caller]
+    fn output_checked_with_stdin_fd(&mut self, stdin: OwnedFd) -> Result<Output, Self::Error>;
+
+    /// Run a command with a [`SeccompFilter`] installed, capturing its output. If the command exits
+    /// with a non-zero exit code, an error is raised.
+    ///
+    /// The filter is installed in the child after it's forked and before it executes the program
+    /// (which also sets `PR_SET_NO_NEW_PRIVS`), and remains installed on the command for
+    /// subsequent runs. If the command is killed by `SIGSYS`, the error message notes that it
+    /// likely made a system call the filter disallows:
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::SeccompAction;
+    /// # use command_error::SeccompFilter;
+    /// let filter = SeccompFilter::new(SeccompAction::Allow)
+    ///     .rule(libc::SYS_uname, SeccompAction::KillProcess);
+    /// let err = Command::new("uname")
+    ///     .output_checked_with_seccomp(&filter)
+    ///     .unwrap_err();
+    /// assert!(err.to_string().starts_with(
+    ///     "`uname` failed: Killed by the seccomp filter, likely for making a disallowed system call\n\
+    ///     signal: 31 (SIGSYS)"
+    /// ));
+    /// ```
+    ///
+    /// The filter can't be changed once it's installed on a command: [`Command`] has no way to
+    /// remove it. Calling this method again with the same filter runs the command as usual, but
+    /// with a different filter, the command fails to start with an error saying so; use a new
+    /// [`Command`] to run with a different filter. A filter longer than the kernel's limit of
+    /// 4096 BPF instructions is rejected before the command is started.
+    ///
+    /// Only available on Linux on x86-64 and AArch64, with the `seccomp` feature. Types other than
+    /// [`Command`] and `process_wrap::std::StdCommandWrap` which implement this trait can't install
+    /// a filter by default, so the command isn't run and an [`ExecError`] is returned instead.
+    #[cfg(all(
+        feature = "seccomp",
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[track_caller]
+    fn output_checked_with_seccomp(
+        &mut self,
+        filter: &SeccompFilter,
+    ) -> Result<Output, Self::Error> {
+        let _ = filter;
+        Err(Error::from(ExecError::new(
+            Box::new(self.command_display()),
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "seccomp filters can't be installed on this type of command",
+            ),
+        ))
+        .into())
+    }
+
+    /// Run a command with the first of `programs` that can be found, capturing its output. If the
+    /// command exits with a non-zero exit code, an error is raised.
+    ///
+    /// Each program is run with this command's arguments, environment, and working directory, in
+    /// order, until one of them starts. Only programs that aren't found are skipped: if a program
+    /// starts and then fails, its error is returned without trying the rest. If none of the
+    /// programs are found, the error lists all of them:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let output = Command::new("python")
+    ///     .args(["-c", "echo puppy"])
+    ///     .output_checked_trying(&["puppython3", "sh"])
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"puppy\n");
+    ///
+    /// let err = Command::new("python")
+    ///     .output_checked_trying(&["puppython3", "puppython"])
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Failed to execute `puppython3`: None of the programs were found: \
+    ///     `puppython3`, `puppython`"
+    /// );
+    /// ```
+    ///
+    /// [`Command`] doesn't expose its stdio configuration or platform-specific settings (like
+    /// `pre_exec` hooks), so these aren't applied to the fallback programs; output is always
+    /// captured. If `programs` is empty, an error is returned without running anything.
+    #[track_caller]
+    fn output_checked_trying(&mut self, programs: &[&str]) -> Result<Output, Self::Error>;
+
+    /// Run a command with a clean environment containing only `vars`, capturing its output. If
+    /// the command exits with a non-zero exit code, an error is raised.
+    ///
+    /// This clears the command's environment with [`Command::env_clear`] and then sets `vars`, so
+    /// the command doesn't inherit any environment variables from the current process. This is
+    /// useful for hermetic builds. Error messages display the command with `env -i` (see
+    /// [`Utf8ProgramAndArgs::with_env_clear`]), so it can be reproduced exactly:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// std::env::set_var("PUPPY", "doggy");
+    /// let output = Command::new("/bin/sh")
+    ///     .args(["-c", "echo \"${PUPPY:-none} $COLOR\""])
+    ///     .output_checked_with_clean_env(&[("COLOR", "GOLDEN")])
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"none GOLDEN\n");
+    ///
+    /// let err = Command::new("/bin/sh")
+    ///     .args(["-c", "exit 1"])
+    ///     .output_checked_with_clean_env(&[("COLOR", "GOLDEN")])
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`/bin/sh` failed: exit status: 1
+    ///         Command failed: `env -i COLOR=GOLDEN /bin/sh -c 'exit 1'`"
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// Note that without `PATH`, programs may not be found by name; pass an absolute path or
+    /// include `PATH` in `vars`. The environment remains cleared for subsequent runs of this
+    /// command, but later error messages won't include `env -i`.
+    #[track_caller]
+    fn output_checked_with_clean_env(
+        &mut self,
+        vars: &[(&str, &str)],
+    ) -> Result<Output, Self::Error>;
+
+    /// Run a command with piped stdio, letting `interact` converse with it over its stdin and
+    /// stdout, then wait for it to exit. If the command exits with a non-zero exit code, an error
+    /// is raised.
+    ///
+    /// This is useful for scripting interactive programs, like REPLs, which need their input
+    /// written in response to their output. The command's stderr is captured in the background
+    /// while `interact` runs. Once `interact` returns, the command's stdin is closed, and any
+    /// stdout that `interact` didn't read is captured in the returned [`Output`].
+    ///
+    /// If `interact` fails, the command is killed and an error is raised. Avoiding deadlocks (for
+    /// example, by not writing more to stdin than the command will read before it responds) is up
+    /// to `interact`.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::io::BufRead;
+    /// # use std::io::BufReader;
+    /// # use std::io::Write;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let mut command = Command::new("sh");
+    /// command.args(["-c", "read name; echo \"Hello, $name\"; read name; echo \"Bye, $name\""]);
+    ///
+    /// let output = command
+    ///     .output_checked_with_stdin_writer(|stdin, stdout| {
+    ///         writeln!(stdin, "puppy")?;
+    ///         let mut line = String::new();
+    ///         BufReader::new(stdout).read_line(&mut line)?;
+    ///         assert_eq!(line, "Hello, puppy\n");
+    ///         writeln!(stdin, "doggy")
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"Bye, doggy\n");
+    ///
+    /// let err = command
+    ///     .output_checked_with_stdin_writer(|_stdin, _stdout| {
+    ///         Err(std::io::Error::other("unexpected prompt"))
+    ///     })
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: interaction failed: unexpected prompt
+    ///         signal: 9 (SIGKILL)
+    ///         Command failed: `sh -c 'read name; echo \"Hello, $name\"; read name; echo \"Bye, $name\"'`"
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// This leaves the command's stdin, stdout, and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_stdin_writer(
+        &mut self,
+        interact: impl FnOnce(&mut ChildStdin, &mut ChildStdout) -> std::io::Result<()>,
+    ) -> Result<Output, Self::Error>;
+
+    /// Run a command, writing `input` to its stdin and capturing its output. If the command exits
+    /// with a non-zero exit code, an error is raised.
+    ///
+    /// The command's stdin is piped and closed after `input` is written, so commands like `jq` or
+    /// `gpg` which read until the end of their input will finish. Input is written while output is
+    /// read, so large inputs and outputs can't deadlock. If the command exits without reading all
+    /// of its input, that's not an error by itself. Failing to write the input produces an
+    /// [`Error::Stdin`].
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let output = Command::new("tr")
+    ///     .args(["a-z", "A-Z"])
+    ///     .output_checked_with_input("puppy\n")
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"PUPPY\n");
+    ///
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "cat >&2; exit 1"])
+    ///     .output_checked_with_input(b"doggy\n")
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exit status: 1
+    ///         Command failed: `sh -c 'cat >&2; exit 1'`
+    ///         Stderr:
+    ///           doggy"
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// This leaves the command's stdin, stdout, and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_input(
+        &mut self,
+        input: impl AsRef<[u8]>,
+    ) -> Result<Output, Self::Error> {
+        self.output_checked_with_input_as(input.as_ref(), |context: OutputContext<Output>| {
+            context.require_success().map_err(Into::into)
+        })
+    }
+
+    /// Run a command, writing `input` to its stdin and capturing its output, decoding it as
+    /// UTF-8. If the command exits with a non-zero exit code or its output isn't UTF-8, an error
+    /// is raised.
+    ///
+    /// See [`CommandExt::output_checked_with_input`] and [`CommandExt::output_checked_utf8`].
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let output = Command::new("sort")
+    ///     .output_checked_with_input_utf8("puppy\ndoggy\n")
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, "doggy\npuppy\n");
+    /// ```
+    ///
+    /// This leaves the command's stdin, stdout, and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_input_utf8(
+        &mut self,
+        input: impl AsRef<[u8]>,
+    ) -> Result<Utf8Output, Self::Error> {
+        self.output_checked_with_input_as(input.as_ref(), |context: OutputContext<Utf8Output>| {
+            context.require_success().map_err(Into::into)
+        })
+    }
+
+    /// Run a command, writing `input` to its stdin and capturing its output. `succeeded` is called
+    /// and returned to determine if the command succeeded.
+    ///
+    /// This is the most general input method; see [`CommandExt::output_checked_with_input`] and
+    /// [`CommandExt::output_checked_as`].
+    ///
+    /// This leaves the command's stdin, stdout, and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_input_as<O, R, E>(
+        &mut self,
+        input: &[u8],
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync;
+
+    /// Run a command, writing `input` to its stdin and capturing its output, and only closing
+    /// stdin after `input` is written if `close` is `true`. If the command exits with a non-zero
+    /// exit code, an error is raised.
+    ///
+    /// With `close` set to `true`, this is the same as [`CommandExt::output_checked_with_input`].
+    /// With `close` set to `false`, stdin is kept open until the command exits, for commands which
+    /// read their input incrementally until a terminator (like a blank line or a `quit` command)
+    /// and misbehave if they reach the end of their input first.
+    ///
+    /// **Keeping stdin open can deadlock.** A command which reads until the end of its input
+    /// (like `cat`, `sort`, or `jq`) never sees the end, so it never exits, and this method never
+    /// returns. Only keep stdin open for commands which exit by themselves after reading `input`.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let output = Command::new("sh")
+    ///     .args(["-c", "read -r line; echo \"got $line\""])
+    ///     .output_checked_with_stdin_keeping_open("puppy\n", false)
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"got puppy\n");
+    ///
+    /// // `cat` reads until the end of its input, so stdin must be closed.
+    /// let output = Command::new("cat")
+    ///     .output_checked_with_stdin_keeping_open("doggy\n", true)
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"doggy\n");
+    /// ```
+    ///
+    /// This leaves the command's stdin, stdout, and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_stdin_keeping_open(
+        &mut self,
+        input: impl AsRef<[u8]>,
+        close: bool,
+    ) -> Result<Output, Self::Error>;
+
+    /// Run a command, reading its stdout and stderr into `stdout` and `stderr`. If the command
+    /// exits with a non-zero exit code, an error is raised.
+    ///
+    /// The buffers are cleared before the command runs, and their allocations are reused, so
+    /// running many commands in a loop with the same buffers doesn't allocate new ones for each
+    /// command. If the command fails, its output is copied into the error.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let mut stdout = Vec::new();
+    /// let mut stderr = Vec::new();
+    /// for name in ["puppy", "doggy"] {
+    ///     Command::new("echo")
+    ///         .arg(name)
+    ///         .output_checked_into_buf(&mut stdout, &mut stderr)
+    ///         .unwrap();
+    ///     assert_eq!(stdout, format!("{name}\n").as_bytes());
+    /// }
+    ///
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo kitty >&2; exit 1"])
+    ///     .output_checked_into_buf(&mut stdout, &mut stderr)
+    ///     .unwrap_err();
+    /// assert_eq!(stdout, b"");
+    /// assert_eq!(stderr, b"kitty\n");
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exit status: 1
+    ///         Command failed: `sh -c 'echo kitty >&2; exit 1'`
+    ///         Stderr:
+    ///           kitty"
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_into_buf(
+        &mut self,
+        stdout: &mut Vec<u8>,
+        stderr: &mut Vec<u8>,
+    ) -> Result<ExitStatus, Self::Error>;
+
+    /// Run a command with `spawner`, capturing its output. If the command exits with a non-zero
+    /// exit code, an error is raised.
+    ///
+    /// If the command fails to start, the error reports which backend was used and, if the
+    /// backend reports it, which step of starting the command failed. See [`Spawner`] for more
+    /// information.
+    ///
+    /// For wrapped commands, the wrappers aren't applied; the inner [`Command`] is run directly.
+    fn output_checked_with_spawner(&mut self, spawner: &dyn Spawner)
+        -> Result<Output, Self::Error>;
+
+    /// Run a command with `spawner` without capturing its output. If the command exits with a
+    /// non-zero exit code, an error is raised.
+    ///
+    /// See [`CommandExt::output_checked_with_spawner`].
+    fn status_checked_with_spawner(
+        &mut self,
+        spawner: &dyn Spawner,
+    ) -> Result<ExitStatus, Self::Error>;
+
+    /// Run a command, capturing its output, and stop it if it runs for longer than `timeout`. If
+    /// the command exits with a non-zero exit code or times out, an error is raised.
+    ///
+    /// When the timeout expires, the command is asked to exit with `SIGTERM`, giving it a chance
+    /// to clean up, like `timeout(1)` and init systems do. If it's still running after `grace`,
+    /// it's killed with `SIGKILL`. If `grace` is zero, the command is killed immediately. On
+    /// Windows, there's no graceful termination, so the command is always killed immediately
+    /// (with `TerminateProcess`).
+    ///
+    /// A timed-out command produces an [`Error::Timeout`], which notes how the command was
+    /// stopped and includes any output it wrote before then:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::time::Duration;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// # use command_error::Termination;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo Connecting...; exec sleep 10"])
+    ///     .output_checked_with_timeout_and_grace(
+    ///         Duration::from_millis(100),
+    ///         Duration::from_secs(5),
+    ///     )
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` timed out after 100ms and was terminated gracefully
+    ///         Command failed: `sh -c 'echo Connecting...; exec sleep 10'`
+    ///         Stdout:
+    ///           Connecting..."
+    ///     )
+    /// );
+    ///
+    /// // This command ignores `SIGTERM`, so it's killed after the grace period.
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "trap '' TERM; sleep 10"])
+    ///     .output_checked_with_timeout_and_grace(
+    ///         Duration::from_millis(100),
+    ///         Duration::from_millis(100),
+    ///     )
+    ///     .unwrap_err();
+    /// let Error::Timeout(err) = err else { unreachable!() };
+    /// assert_eq!(
+    ///     err.termination(),
+    ///     Termination::KilledAfterGrace(Duration::from_millis(100))
+    /// );
+    ///
+    /// let output = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .output_checked_with_timeout_and_grace(Duration::from_secs(10), Duration::from_secs(1))
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"puppy\n");
+    /// ```
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_timeout_and_grace(
+        &mut self,
+        timeout: Duration,
+        grace: Duration,
+    ) -> Result<Output, Self::Error> {
+        self.output_checked_with_timeout_and_clock(timeout, grace, &Instant::now)
+    }
+
+    /// Run a command, capturing its output, and kill it if it runs for longer than `timeout`. If
+    /// the command exits with a non-zero exit code or times out, an error is raised.
+    ///
+    /// This is [`CommandExt::output_checked_with_timeout_and_grace`] with no grace period. A
+    /// command which exits exactly at the deadline is considered to have finished in time.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::time::Duration;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// # use command_error::Termination;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo Connecting...; exec sleep 10"])
+    ///     .output_checked_timeout(Duration::from_millis(100))
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` timed out after 100ms and was killed
+    ///         Command failed: `sh -c 'echo Connecting...; exec sleep 10'`
+    ///         Stdout:
+    ///           Connecting..."
+    ///     )
+    /// );
+    ///
+    /// // The output written before the command was killed is kept.
+    /// let Error::Timeout(timeout) = err else {
+    ///     panic!("Expected a timeout: {err}");
+    /// };
+    /// assert_eq!(timeout.timeout(), Duration::from_millis(100));
+    /// assert_eq!(timeout.termination(), Termination::Killed);
+    /// assert_eq!(timeout.output().stdout, b"Connecting...\n");
+    ///
+    /// let output = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .output_checked_timeout(Duration::from_secs(10))
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"puppy\n");
+    /// ```
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[doc(alias = "output_checked_with_timeout")]
+    #[track_caller]
+    fn output_checked_timeout(&mut self, timeout: Duration) -> Result<Output, Self::Error> {
+        self.output_checked_with_timeout_and_grace(timeout, Duration::ZERO)
+    }
+
+    /// Run a command, capturing its output, and kill it if it runs for longer than `timeout`.
+    /// `succeeded` is called and used to determine if the command succeeded and (optionally) to
+    /// add an additional message to the error returned.
+    ///
+    /// See [`CommandExt::output_checked_timeout`] and [`CommandExt::output_checked_with`]. A
+    /// timed-out command produces an [`Error::Timeout`] without calling `succeeded`.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::time::Duration;
+    /// # use std::process::Command;
+    /// # use std::process::Output;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// let output = Command::new("sh")
+    ///     .args(["-c", "echo puppy; exit 1"])
+    ///     .output_checked_timeout_with(Duration::from_secs(10), |output: &Output| {
+    ///         if output.stdout.starts_with(b"puppy") {
+    ///             Ok(())
+    ///         } else {
+    ///             Err(None::<String>)
+    ///         }
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"puppy\n");
+    ///
+    /// let err = Command::new("sleep")
+    ///     .arg("10")
+    ///     .output_checked_timeout_with(Duration::from_millis(100), |_: &Output| {
+    ///         Ok::<_, Option<String>>(())
+    ///     })
+    ///     .unwrap_err();
+    /// assert!(matches!(err, Error::Timeout(_)));
+    /// ```
+    #[track_caller]
+    fn output_checked_timeout_with<O, E>(
+        &mut self,
+        timeout: Duration,
+        succeeded: impl Fn(&O) -> Result<(), Option<E>>,
+    ) -> Result<O, Self::Error>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: Debug + Display + Send + Sync + 'static,
+    {
+        self.output_checked_timeout_as(timeout, |context| match succeeded(context.output()) {
+            Ok(()) => Ok(context.into_output()),
+            Err(user_error) => Err(context.maybe_error_msg(user_error).into()),
+        })
+    }
+
+    /// Run a command, capturing its output, and kill it if it runs for longer than `timeout`.
+    /// `succeeded` is called and returned to determine if the command succeeded.
+    ///
+    /// This is the most general timeout method; see [`CommandExt::output_checked_timeout_with`]
+    /// and [`CommandExt::output_checked_as`].
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_timeout_as<O, R, E>(
+        &mut self,
+        timeout: Duration,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync;
+
+    /// Run a command, capturing its output, and stop it if it's still running at a [`Deadline`]
+    /// shared with other commands. If the command exits with a non-zero exit code, an error is
+    /// raised.
+    ///
+    /// Each command is given the time remaining until the deadline (and stopped without a grace
+    /// period if it runs out), so a workflow of several commands runs within one overall budget.
+    /// If the deadline has already passed, an error is returned immediately without starting the
+    /// command:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::time::Duration;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Deadline;
+    /// # use command_error::Error;
+    /// let deadline = Deadline::after(Duration::from_millis(200));
+    ///
+    /// Command::new("true")
+    ///     .output_checked_with_deadline_shared(&deadline)
+    ///     .unwrap();
+    ///
+    /// // This uses up the rest of the budget...
+    /// let err = Command::new("sleep")
+    ///     .arg("10")
+    ///     .output_checked_with_deadline_shared(&deadline)
+    ///     .unwrap_err();
+    /// assert!(matches!(err, Error::Timeout(_)));
+    ///
+    /// // ...so this isn't started at all.
+    /// let err = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .output_checked_with_deadline_shared(&deadline)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "`echo` timed out after 200ms and wasn't started because its deadline had already \
+    ///     passed\nCommand failed: `echo puppy`"
+    /// );
+    /// ```
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_deadline_shared(
+        &mut self,
+        deadline: &Deadline,
+    ) -> Result<Output, Self::Error> {
+        self.output_checked_with_deadline_shared_and_clock(deadline, &Instant::now)
+    }
+
+    /// Run a command, capturing its output, and stop it if it's still running when `deadline`
+    /// passes, as measured by `clock`. If the deadline has already passed, the command isn't
+    /// started.
+    ///
+    /// This is [`CommandExt::output_checked_with_deadline_shared`] with an injectable [`Clock`];
+    /// see [`CommandExt::output_checked_with_timeout_and_clock`].
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_deadline_shared_and_clock(
+        &mut self,
+        deadline: &Deadline,
+        clock: &dyn Clock,
+    ) -> Result<Output, Self::Error> {
+        match deadline.remaining_with_clock(clock) {
+            Some(remaining) => {
+                self.output_checked_with_timeout_and_clock(remaining, Duration::ZERO, clock)
+            }
+            None => Err(Error::from(TimeoutError::new(
+                Box::new(self.command_display()),
+                Output {
+                    status: ExitStatus::default(),
+                    stdout: Vec::new(),
+                    stderr: Vec::new(),
+                },
+                deadline.budget(),
+                Termination::NotStarted,
+            ))
+            .into()),
+        }
+    }
+
+    /// Run a command, capturing its output, and stop it if it runs for longer than `timeout`, as
+    /// measured by `clock`. If the command exits with a non-zero exit code, an error is raised.
+    ///
+    /// This is [`CommandExt::output_checked_with_timeout_and_grace`] with an injectable [`Clock`],
+    /// so that tests can check timeout behavior deterministically and without sleeping. Only the
+    /// deadlines are measured with `clock`; the command is still polled in real time.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::sync::atomic::AtomicU32;
+    /// # use std::sync::atomic::Ordering;
+    /// # use std::time::Duration;
+    /// # use std::time::Instant;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// // A clock which advances by an hour every time it's read.
+    /// let start = Instant::now();
+    /// let reads = AtomicU32::new(0);
+    /// let clock = move || start + Duration::from_secs(60 * 60) * reads.fetch_add(1, Ordering::SeqCst);
+    ///
+    /// let err = Command::new("sleep")
+    ///     .arg("10")
+    ///     .output_checked_with_timeout_and_clock(Duration::from_secs(60), Duration::ZERO, &clock)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "`sleep` timed out after 60s and was killed\nCommand failed: `sleep 10`"
+    /// );
+    /// ```
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_with_timeout_and_clock(
+        &mut self,
+        timeout: Duration,
+        grace: Duration,
+        clock: &dyn Clock,
+    ) -> Result<Output, Self::Error>;
+
+    /// Run a command without capturing its output, and stop it if it runs for longer than
+    /// `timeout`. If the command exits with a non-zero exit code or times out, an error is raised.
+    ///
+    /// This is the [`CommandExt::status_checked`] counterpart to
+    /// [`CommandExt::output_checked_with_timeout_and_grace`]: when the timeout expires, the
+    /// command is sent `SIGTERM`, and killed with `SIGKILL` if it's still running after `grace`.
+    /// On Windows, the command is killed immediately with `TerminateProcess`.
+    ///
+    /// A timed-out command produces an [`Error::Timeout`], which is distinct from the
+    /// [`Error::Output`] produced by a command which exits unsuccessfully on its own. If the
+    /// command finishes in time, its [`ExitStatus`] is returned unchanged.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::time::Duration;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// # use command_error::Termination;
+    /// let err = Command::new("sleep")
+    ///     .arg("10")
+    ///     .status_checked_timeout(Duration::from_millis(100), Duration::from_secs(5))
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "`sleep` timed out after 100ms and was terminated gracefully\n\
+    ///     Command failed: `sleep 10`"
+    /// );
+    /// let Error::Timeout(err) = err else { unreachable!() };
+    /// assert_eq!(err.termination(), Termination::Graceful);
+    ///
+    /// // This command ignores `SIGTERM`, so it's killed after the grace period.
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "trap '' TERM; while :; do :; done"])
+    ///     .status_checked_timeout(Duration::from_millis(100), Duration::from_millis(100))
+    ///     .unwrap_err();
+    /// let Error::Timeout(err) = err else { unreachable!() };
+    /// assert_eq!(
+    ///     err.termination(),
+    ///     Termination::KilledAfterGrace(Duration::from_millis(100))
+    /// );
+    ///
+    /// let status = Command::new("true")
+    ///     .status_checked_timeout(Duration::from_secs(10), Duration::from_secs(1))
+    ///     .unwrap();
+    /// assert!(status.success());
+    ///
+    /// let err = Command::new("false")
+    ///     .status_checked_timeout(Duration::from_secs(10), Duration::from_secs(1))
+    ///     .unwrap_err();
+    /// assert!(matches!(err, Error::Output(_)));
+    /// ```
+    #[track_caller]
+    fn status_checked_timeout(
+        &mut self,
+        timeout: Duration,
+        grace: Duration,
+    ) -> Result<ExitStatus, Self::Error> {
+        self.status_checked_with_timeout_and_clock(timeout, grace, &Instant::now)
+    }
+
+    /// Run a command without capturing its output, and stop it if it runs for longer than
+    /// `timeout`, as measured by `clock`. If the command exits with a non-zero exit code or times
+    /// out, an error is raised.
+    ///
+    /// This is [`CommandExt::status_checked_timeout`] with an injectable [`Clock`]; see
+    /// [`CommandExt::output_checked_with_timeout_and_clock`].
+    #[track_caller]
+    fn status_checked_with_timeout_and_clock(
+        &mut self,
+        timeout: Duration,
+        grace: Duration,
+        clock: &dyn Clock,
+    ) -> Result<ExitStatus, Self::Error>;
+
+    /// Run a command, capturing its output, and call `on_stall` whenever it goes without producing
+    /// output for longer than `interval`. If the command exits with a non-zero exit code, an error
+    /// is raised.
+    ///
+    /// Unlike the timeout methods, this never stops the command: it measures the gaps between
+    /// chunks of output (on either stream), not the command's total running time. `on_stall` is
+    /// called with the time since output was last read (or since the command started), once for
+    /// each gap, while the command is still running.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let mut stalls = Vec::new();
+    /// let output = Command::new("sh")
+    ///     .args(["-c", "echo fetching; sleep 0.5; echo done"])
+    ///     .output_checked_on_stall(Duration::from_millis(100), |stalled_for| {
+    ///         stalls.push(stalled_for);
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"fetching\ndone\n");
+    /// assert_eq!(stalls.len(), 1);
+    /// assert!(stalls[0] > Duration::from_millis(100));
+    /// ```
+    ///
+    /// See [`CommandExt::output_checked_warn_on_stall`] to log stalls instead.
+    ///
+    /// This leaves the command's stdout and stderr piped; see
+    /// [standard streams](CommandExt#standard-streams).
+    #[track_caller]
+    fn output_checked_on_stall(
+        &mut self,
+        interval: Duration,
+        on_stall: impl FnMut(Duration),
+    ) -> Result<Output, Self::Error>;
+
+    /// Run a command, capturing its output, and log a warning whenever it goes without producing
+    /// output for longer than `interval`. If the command exits with a non-zero exit code, an error
+    /// is raised.
+    ///
+    /// This surfaces commands which get stuck partway through in logs, while letting them continue
+    /// to run. See [`CommandExt::output_checked_on_stall`] for details.
+    ///
+    /// With the `tracing` feature enabled, each stall emits a warn-level log with message
+    /// `"Command has stopped producing output"`. Without it, this behaves like
+    /// [`CommandExt::output_checked`].
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let output = Command::new("sh")
+    ///     .args(["-c", "sleep 0.2; echo puppy"])
+    ///     .output_checked_warn_on_stall(Duration::from_millis(100))
+    ///     .unwrap();
+    /// assert_eq!(output.stdout, b"puppy\n");
+    /// ```
+    #[track_caller]
+    fn output_checked_warn_on_stall(&mut self, interval: Duration) -> Result<Output, Self::Error> {
+        #[cfg(feature = "tracing")]
+        let command = self.command_display();
+        self.output_checked_on_stall(interval, |_stalled_for| {
+            #[cfg(feature = "tracing")]
+            {
+                tracing::warn!(
+                    %command,
+                    stalled_for = ?round_duration(_stalled_for),
+                    "Command has stopped producing output"
+                );
+            }
+        })
+    }
+
+    /// Run a command, capturing its output only if stdout is not a terminal. If the command exits
+    /// with a non-zero exit code, an error is raised.
+    ///
+    /// If the current process's stdout is a terminal, the command inherits its stdio (so the user
+    /// sees colored or interactive output) and this behaves like [`CommandExt::status_checked`].
+    /// Otherwise, this behaves like [`CommandExt::output_checked`]. This mirrors how many CLIs
+    /// adapt to interactive and piped contexts.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::io::IsTerminal;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::TtyOutput;
+    /// let output = Command::new("echo")
+    ///     .arg("puppy")
+    ///     .output_checked_with_tty_detection()
+    ///     .unwrap();
+    ///
+    /// assert!(output.status().success());
+    /// match output {
+    ///     TtyOutput::Inherited(_) => assert!(std::io::stdout().is_terminal()),
+    ///     TtyOutput::Captured(output) => assert_eq!(output.stdout, b"puppy\n"),
+    /// }
+    /// ```
+    #[track_caller]
+    fn output_checked_with_tty_detection(&mut self) -> Result<TtyOutput, Self::Error> {
+        if std::io::stdout().is_terminal() {
+            self.status_checked().map(TtyOutput::Inherited)
+        } else {
+            self.output_checked().map(TtyOutput::Captured)
+        }
+    }
+
+    /// Spawn a command.
+    ///
+    /// The returned child contains context information about the command that produced it, which
+    /// can be used to produce detailed error messages if the child process fails.
+    ///
+    /// See [`Command::spawn`] for more information.
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::ChildExt;
+    /// Command::new("sleep")
+    ///     .arg("0")
+    ///     .spawn_checked()?
+    ///     .wait_checked()?;
+    /// # Ok::<(), command_error::Error>(())
+    /// ```
+    ///
+    /// If the command can't be started, an [`ExecError`] is returned:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use std::process::ExitStatus;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("ooga booga")
+    ///     .spawn_checked()
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Failed to execute `'ooga booga'`: No such file or directory (os error 2)"
+    /// );
+    /// ```
+    #[track_caller]
+    fn spawn_checked(&mut self) -> Result<Self::Child, Self::Error>;
+
+    /// Spawn a command with its stdout and stderr piped and its stdin null, so that its output
+    /// can be captured with [`ChildExt::output_checked`] and friends.
+    ///
+    /// [`spawn_checked`][CommandExt::spawn_checked] (like [`Command::spawn`]) inherits stdout and
+    /// stderr by default, so their output goes to the terminal and isn't captured. Error messages
+    /// from [`ChildExt`] note streams which weren't captured, rather than suggesting that the
+    /// command printed nothing:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::ChildExt;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo puppy; exit 1"])
+    ///     .spawn_checked_piped()
+    ///     .unwrap()
+    ///     .output_checked_utf8()
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`sh` failed: exit status: 1
+    ///         Command failed: `sh -c 'echo puppy; exit 1'`
+    ///         Stdout:
+    ///           puppy"
+    ///     )
+    /// );
+    ///
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "exit 1"])
+    ///     .spawn_checked()
+    ///     .unwrap()
+    ///     .output_checked_utf8()
+    ///     .unwrap_err();
+    /// // How the streams were connected can only be determined on Linux, while the child runs.
+    /// assert!(err.to_string().starts_with(indoc!(
+    ///     "`sh` failed: exit status: 1
+    ///     Command failed: `sh -c 'exit 1'`
+    ///     Stdout: (output not captured: stream was "
+    /// )));
+    /// ```
+    ///
+    /// This replaces any stdio configured on the command. [`Command`] has no way to read or unset
+    /// its stdio configuration, so it can't be restored after the child is spawned: the null stdin
+    /// and piped stdout and stderr remain configured for later runs of the command, unless they're
+    /// replaced with [`Command::stdin`], [`Command::stdout`], and [`Command::stderr`].
+    #[track_caller]
+    fn spawn_checked_piped(&mut self) -> Result<Self::Child, Self::Error>;
+
+    /// Log the command that will be run.
+    ///
+    /// With the `tracing` feature enabled, this will emit a debug-level log with message
+    /// `Executing command` and a `command` field containing the command and arguments shell-quoted.
+    fn log(&self) -> Result<(), Self::Error>;
+
+    /// Get the command's program, arguments, working directory, and environment, for use in error
+    /// messages or diagnostics.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let mut command = Command::new("echo");
+    /// command.arg("puppy doggy");
+    /// assert_eq!(
+    ///     command.command_display().to_string(),
+    ///     "echo 'puppy doggy'"
+    /// );
+    /// ```
+    fn command_display(&self) -> Utf8ProgramAndArgs;
+}
+
+impl CommandExt for Command {
+    type Error = Error;
+    type Child = ChildContext<Child>;
+
+    fn log(&self) -> Result<(), Self::Error> {
+        #[cfg(feature = "tracing")]
+        {
+            let command = LazyProgramAndArgs::new(self);
+            tracing::debug!(%command, "Executing command");
+        }
+        Ok(())
+    }
+
+    fn command_display(&self) -> Utf8ProgramAndArgs {
+        self.into()
+    }
+
+    fn output_checked_trying(&mut self, programs: &[&str]) -> Result<Output, Self::Error> {
+        for program in programs {
+            match fallback::with_program(self, program).output_checked() {
+                Err(error) if fallback::is_not_found(&error) => {}
+                result => return result,
+            }
+        }
+        Err(fallback::not_found(self, programs))
+    }
+
+    #[cfg(unix)]
+    fn output_checked_with_stdin_fd(&mut self, stdin: OwnedFd) -> Result<Output, Self::Error> {
+        self.stdin(Stdio::from(stdin));
+        self.output_checked()
+    }
+
+    #[cfg(unix)]
+    fn output_checked_with_chroot(
+        &mut self,
+        root: impl AsRef<Path>,
+    ) -> Result<Output, Self::Error> {
+        let root = root.as_ref();
+        if let Err(inner) = chroot::apply(self, root) {
+            return Err(run::exec_error(
+                Box::new(self.command_display()),
+                None,
+                inner,
+            ));
+        }
+        run::output_displayed(self, |command| {
+            Utf8ProgramAndArgs::from(command).with_chroot(root.to_string_lossy())
+        })
+        .map_err(chroot::explain)?
+        .check_output(OutputContext::require_success)
+    }
+
+    #[cfg(unix)]
+    fn output_checked_with_limits(
+        &mut self,
+        limits: ResourceLimits,
+    ) -> Result<Output, Self::Error> {
+        limits.apply(self);
+        self.output_checked_as(|context| limits.check(context))
+            .map_err(ResourceLimits::explain_repeated)
+    }
+
+    #[cfg(all(
+        feature = "seccomp",
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    fn output_checked_with_seccomp(
+        &mut self,
+        filter: &SeccompFilter,
+    ) -> Result<Output, Self::Error> {
+        if let Err(inner) = filter.apply(self) {
+            return Err(run::exec_error(
+                Box::new(self.command_display()),
+                None,
+                inner,
+            ));
+        }
+        self.output_checked_as(|context| filter.check(context))
+            .map_err(SeccompFilter::explain_repeated)
+    }
+
+    fn output_checked_with_timeout_and_clock(
+        &mut self,
+        timeout: Duration,
+        grace: Duration,
+        clock: &dyn Clock,
+    ) -> Result<Output, Self::Error> {
+        run::output_with_timeout(self, timeout, grace, clock)?
+            .check_output(OutputContext::require_success)
+    }
+
+    fn status_checked_with_timeout_and_clock(
+        &mut self,
+        timeout: Duration,
+        grace: Duration,
+        clock: &dyn Clock,
+    ) -> Result<ExitStatus, Self::Error> {
+        run::status_with_timeout(self, timeout, grace, clock)?
+            .check_status(OutputContext::require_success)
+    }
+
+    fn output_checked_on_stall(
+        &mut self,
+        interval: Duration,
+        mut on_stall: impl FnMut(Duration),
+    ) -> Result<Output, Self::Error> {
+        run::output_on_stall(self, interval, &mut on_stall)?
+            .check_output(OutputContext::require_success)
+    }
+
+    fn output_checked_timeout_as<O, R, E>(
+        &mut self,
+        timeout: Duration,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        run::output_with_timeout(self, timeout, Duration::ZERO, &Instant::now)?
+            .check_output(succeeded)
+    }
+
+    fn output_checked_with_clean_env(
+        &mut self,
+        vars: &[(&str, &str)],
+    ) -> Result<Output, Self::Error> {
+        self.env_clear().envs(vars.iter().copied());
+        run::output_displayed(self, |command| {
+            Utf8ProgramAndArgs::from(command).with_env_clear()
+        })?
+        .check_output(OutputContext::require_success)
+    }
+
+    fn output_checked_with_stdin_writer(
+        &mut self,
+        interact: impl FnOnce(&mut ChildStdin, &mut ChildStdout) -> std::io::Result<()>,
+    ) -> Result<Output, Self::Error> {
+        run::output_interactive(self, Box::new(interact))?
+            .check_output(OutputContext::require_success)
+    }
+
+    fn output_checked_with_input_as<O, R, E>(
+        &mut self,
+        input: &[u8],
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        run::output_with_input(self, input, true)?.check_output(succeeded)
+    }
+
+    fn output_checked_with_stdin_keeping_open(
+        &mut self,
+        input: impl AsRef<[u8]>,
+        close: bool,
+    ) -> Result<Output, Self::Error> {
+        run::output_with_input(self, input.as_ref(), close)?
+            .check_output(OutputContext::require_success)
+    }
+
+    fn output_checked_into_buf(
+        &mut self,
+        stdout: &mut Vec<u8>,
+        stderr: &mut Vec<u8>,
+    ) -> Result<ExitStatus, Self::Error> {
+        run::output_into(self, stdout, stderr)?.check_into(stdout, stderr)
+    }
+
+    fn output_checked_with_spawner(
+        &mut self,
+        spawner: &dyn Spawner,
+    ) -> Result<Output, Self::Error> {
+        run::output_spawned(self, spawner)?.check_output(OutputContext::require_success)
+    }
+
+    fn status_checked_with_spawner(
+        &mut self,
+        spawner: &dyn Spawner,
+    ) -> Result<ExitStatus, Self::Error> {
+        run::status_spawned(self, spawner)?.check_status(OutputContext::require_success)
+    }
+
+    fn output_checked_as<O, R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        run::output(self)?.check_output(succeeded)
+    }
+
+    fn output_checked_streaming_as<O, R, E>(
+        &mut self,
+        bound: usize,
+        mut on_chunk: impl FnMut(OutputStream, &[u8]),
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        run::output_streaming(self, bound, usize::MAX, &mut on_chunk)?.check_output(succeeded)
+    }
+
+    fn status_checked_with_backpressure(
+        &mut self,
+        bound: usize,
+        mut on_chunk: impl FnMut(OutputStream, &[u8]),
+    ) -> Result<ExitStatus, Self::Error> {
+        run::output_streaming(self, bound, RETAINED_TAIL, &mut on_chunk)?
+            .check_output(OutputContext::<Output>::require_success)
+            .map(|output| output.status)
+    }
+
+    fn status_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E>,
     ) -> Result<R, E>
     where
         E: From<Self::Error>,
     {
-        self.log()?;
-        let displayed: Utf8ProgramAndArgs = (&*self).into();
-        let displayed = Box::new(displayed);
-        match self.status() {
-            Ok(status) => succeeded(OutputContext {
-                output: status,
-                command: displayed,
-            }),
-            Err(inner) => Err(Error::from(ExecError {
-                command: displayed,
-                inner,
-            })
-            .into()),
-        }
+        run::status(self)?.check_status(succeeded)
     }
 
     fn spawn_checked(&mut self) -> Result<Self::Child, Self::Error> {
-        let displayed: Utf8ProgramAndArgs = (&*self).into();
+        run_spawn_hook(self);
+        self.log()?;
+        let displayed = Utf8ProgramAndArgs::from(&*self).with_location(Location::caller());
         match self.spawn() {
-            Ok(child) => Ok(ChildContext {
-                child,
-                command: Box::new(displayed),
-            }),
-            Err(inner) => Err(Error::from(ExecError {
-                command: Box::new(displayed),
-                inner,
-            })),
+            Ok(child) => {
+                let stdio = StdioConfig::of(&child);
+                Ok(ChildContext::new(child, Box::new(displayed)).with_stdio(stdio))
+            }
+            Err(inner) => Err(Error::from(
+                ExecError::new(Box::new(displayed), inner).with_current_dir(self.get_current_dir()),
+            )),
         }
     }
+
+    fn spawn_checked_piped(&mut self) -> Result<Self::Child, Self::Error> {
+        self.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn_checked()
+    }
+}
+
+/// The number of chunks buffered while counting the lines in a command's output.
+const LINE_COUNTING_BOUND: usize = 16;
+
+/// The number of chunks buffered while writing a command's output with a prefix.
+const PREFIXED_BOUND: usize = 16;
+
+/// The number of chunks buffered while hashing a command's output.
+#[cfg(feature = "digest")]
+const HASHING_BOUND: usize = 16;
+
+/// Split `bytes` on NUL bytes, dropping a trailing empty record.
+fn split_nul(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let bytes = bytes.strip_suffix(b"\0").unwrap_or(bytes);
+    bytes
+        .split(|byte| *byte == b'\0')
+        .take(if bytes.is_empty() { 0 } else { usize::MAX })
 }
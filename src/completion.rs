@@ -0,0 +1,56 @@
+use std::fmt::Display;
+use std::process::ExitStatus;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+#[cfg(feature = "tracing")]
+use std::time::SystemTime;
+
+/// When a command started, for logging its completion.
+///
+/// With the `tracing` feature enabled, [`Started::log_completion`] emits a debug-level event with
+/// message `Command finished`, containing the command, its exit status, how long it ran
+/// (`elapsed`), and when it started and finished as milliseconds since the Unix epoch
+/// (`start_time` and `end_time`). Without the `tracing` feature, this does nothing.
+pub(crate) struct Started {
+    #[cfg(feature = "tracing")]
+    wall: SystemTime,
+    #[cfg(feature = "tracing")]
+    monotonic: Instant,
+}
+
+impl Started {
+    pub(crate) fn now() -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            wall: SystemTime::now(),
+            #[cfg(feature = "tracing")]
+            monotonic: Instant::now(),
+        }
+    }
+
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn log_completion(&self, command: &dyn Display, status: ExitStatus) {
+        #[cfg(feature = "tracing")]
+        {
+            let elapsed = self.monotonic.elapsed();
+            let start_time = unix_millis(self.wall);
+            let end_time = unix_millis(self.wall + elapsed);
+            tracing::debug!(
+                %command,
+                %status,
+                ?elapsed,
+                start_time,
+                end_time,
+                "Command finished"
+            );
+        }
+    }
+}
+
+/// Milliseconds since the Unix epoch, or 0 if `time` is before the epoch.
+#[cfg(feature = "tracing")]
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Clock;
+#[cfg(doc)]
+use crate::CommandExt;
+
+/// A deadline shared between several commands, so that a whole workflow runs within one overall
+/// time budget.
+///
+/// Used by [`CommandExt::output_checked_with_deadline_shared`]. Each command is stopped if it's
+/// still running at the deadline, so time used by earlier commands leaves less for later ones.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use command_error::Deadline;
+/// let deadline = Deadline::after(Duration::from_secs(60));
+/// assert!(deadline.remaining().is_some());
+/// assert_eq!(deadline.budget(), Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    /// When the deadline was created.
+    start: Instant,
+    /// When the deadline passes.
+    at: Instant,
+}
+
+impl Deadline {
+    /// Construct a new [`Deadline`] which passes at `at`.
+    pub fn new(at: Instant) -> Self {
+        Self {
+            start: Instant::now(),
+            at,
+        }
+    }
+
+    /// Construct a new [`Deadline`] which passes after `budget` from now.
+    pub fn after(budget: Duration) -> Self {
+        Self::after_with_clock(budget, &Instant::now)
+    }
+
+    /// Construct a new [`Deadline`] which passes after `budget` from now, as measured by `clock`.
+    pub fn after_with_clock(budget: Duration, clock: &dyn Clock) -> Self {
+        let start = clock.now();
+        Self {
+            start,
+            at: start + budget,
+        }
+    }
+
+    /// When the deadline passes.
+    pub fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// The total time budget, from when the deadline was created until it passes.
+    pub fn budget(&self) -> Duration {
+        self.at.saturating_duration_since(self.start)
+    }
+
+    /// The time left until the deadline passes, or [`None`] if it's already passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.remaining_with_clock(&Instant::now)
+    }
+
+    /// The time left until the deadline passes as measured by `clock`, or [`None`] if it's
+    /// already passed.
+    pub fn remaining_with_clock(&self, clock: &dyn Clock) -> Option<Duration> {
+        self.at
+            .checked_duration_since(clock.now())
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::process::Command;
+
+    use crate::clock::ManualClock;
+    use crate::CommandExt;
+    use crate::Error;
+    use crate::Termination;
+
+    #[test]
+    fn test_deadline_with_clock() {
+        let clock = ManualClock::new();
+        let deadline = Deadline::after_with_clock(Duration::from_secs(60), &clock);
+        assert_eq!(
+            deadline.remaining_with_clock(&clock),
+            Some(Duration::from_secs(60))
+        );
+
+        Command::new("true")
+            .output_checked_with_deadline_shared_and_clock(&deadline, &clock)
+            .unwrap();
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(deadline.remaining_with_clock(&clock), None);
+        let err = Command::new("true")
+            .output_checked_with_deadline_shared_and_clock(&deadline, &clock)
+            .unwrap_err();
+        let Error::Timeout(err) = err else {
+            panic!("Expected a timeout, got {err:?}");
+        };
+        assert_eq!(err.termination(), Termination::NotStarted);
+    }
+}
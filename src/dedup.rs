@@ -0,0 +1,238 @@
+use std::process::ExitStatus;
+
+use crate::CommandDisplay;
+use crate::Error;
+
+/// The maximum number of characters of normalized stderr included in [`Error::dedup_key`].
+const STDERR_KEY_CHARS: usize = 1024;
+
+impl Error {
+    /// A hash of this error, for detecting repeated failures.
+    ///
+    /// Two errors with the same key are (very likely) the same failure, so callers can cheaply
+    /// compare against the last error they logged and write "previous error repeated 57 times"
+    /// instead of logging the same multi-line message over and over.
+    ///
+    /// The key covers:
+    ///
+    /// - The kind of error (failure to execute, to wait, a failing exit status, or a conversion
+    ///   error).
+    /// - The program and its arguments (but not its environment or working directory).
+    /// - The exit code, or the signal the program was killed by on Unix.
+    /// - For failing exit statuses, the program's stderr, normalized by collapsing each run of
+    ///   whitespace to a single space, trimming leading and trailing whitespace, and truncating
+    ///   to the first 1024 characters.
+    /// - For execution and wait failures, the [`std::io::ErrorKind`] of the underlying error.
+    ///
+    /// The key is stable across runs and platforms with the same inputs, so it can be persisted.
+    /// Output containing timestamps, process IDs, or other volatile data will produce different
+    /// keys for otherwise-identical failures; use [`Error::dedup_key_lenient`] to ignore the
+    /// output entirely.
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let run = || {
+    ///     Command::new("sh")
+    ///         .args(["-c", "echo 'disk   full' >&2; exit 1"])
+    ///         .output_checked()
+    ///         .unwrap_err()
+    /// };
+    ///
+    /// assert_eq!(run().dedup_key(), run().dedup_key());
+    /// ```
+    pub fn dedup_key(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.hash_command(&mut hasher);
+        match self {
+            Error::Exec(error) => hasher.write_str(&format!("{:?}", error.inner.kind())),
+            Error::Wait(error) => hasher.write_str(&format!("{:?}", error.inner.kind())),
+            Error::Capture(error) => hasher.write_str(&format!("{:?}", error.inner.kind())),
+            Error::Stdin(error) => hasher.write_str(&format!("{:?}", error.inner.kind())),
+            Error::Output(error) => {
+                hasher.write_str(&normalize_stderr(&error.output.stderr()));
+            }
+            Error::Conversion(_)
+            | Error::CircuitOpen(_)
+            | Error::Timeout(_)
+            | Error::AlreadyRunning(_) => {}
+        }
+        hasher.finish()
+    }
+
+    /// A hash of this error which ignores the program's output.
+    ///
+    /// This is like [`Error::dedup_key`], but only covers the kind of error, the program and its
+    /// arguments, and the exit code or signal. Use this when the program's output contains
+    /// timestamps or other data that changes between otherwise-identical failures.
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let run = |time: &str| {
+    ///     Command::new("sh")
+    ///         .args(["-c", "echo \"[$TIME] disk full\" >&2; exit 1"])
+    ///         .env("TIME", time)
+    ///         .output_checked()
+    ///         .unwrap_err()
+    /// };
+    ///
+    /// assert_ne!(run("12:00").dedup_key(), run("12:30").dedup_key());
+    /// assert_eq!(run("12:00").dedup_key_lenient(), run("12:30").dedup_key_lenient());
+    /// ```
+    pub fn dedup_key_lenient(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.hash_command(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Hash the kind of error, the command, and its exit status.
+    fn hash_command(&self, hasher: &mut StableHasher) {
+        hasher.write_str(match self {
+            Error::Exec(_) => "exec",
+            Error::Wait(_) => "wait",
+            Error::Output(_) => "output",
+            Error::Conversion(_) => "conversion",
+            Error::CircuitOpen(_) => "circuit-open",
+            Error::Timeout(_) => "timeout",
+            Error::AlreadyRunning(_) => "already-running",
+            Error::Capture(_) => "capture",
+            Error::Stdin(_) => "stdin",
+        });
+        let command = self.command();
+        hasher.write_str(&command.program());
+        for arg in command.args() {
+            hasher.write_str(&arg);
+        }
+        if let Error::Output(error) = self {
+            hash_status(hasher, error.output.status());
+        }
+    }
+}
+
+fn hash_status(hasher: &mut StableHasher, status: ExitStatus) {
+    match status.code() {
+        Some(code) => {
+            hasher.write_str("code");
+            hasher.write(&code.to_le_bytes());
+        }
+        None => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::ExitStatusExt;
+                if let Some(signal) = status.signal() {
+                    hasher.write_str("signal");
+                    hasher.write(&signal.to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+/// Collapse runs of whitespace to a single space, trim, and truncate to [`STDERR_KEY_CHARS`].
+fn normalize_stderr(stderr: &str) -> String {
+    let mut normalized = String::new();
+    for (i, word) in stderr.split_whitespace().enumerate() {
+        if i > 0 {
+            normalized.push(' ');
+        }
+        normalized.push_str(word);
+        if normalized.len() >= STDERR_KEY_CHARS {
+            break;
+        }
+    }
+    normalized.chars().take(STDERR_KEY_CHARS).collect()
+}
+
+/// A stable fingerprint of a command's program and arguments.
+pub(crate) fn command_fingerprint(command: &dyn CommandDisplay) -> u64 {
+    let mut hasher = StableHasher::new();
+    hasher.write_str(&command.program());
+    for arg in command.args() {
+        hasher.write_str(&arg);
+    }
+    hasher.finish()
+}
+
+/// A 64-bit FNV-1a hasher.
+///
+/// Unlike [`std::collections::hash_map::DefaultHasher`], the output of this hasher is stable
+/// across Rust versions and platforms.
+struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Write a length-prefixed string, so that adjacent strings can't run together.
+    fn write_str(&mut self, string: &str) {
+        self.write(&(string.len() as u64).to_le_bytes());
+        self.write(string.as_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::process::Command;
+
+    use crate::CommandExt;
+
+    fn failure(stderr: &str, code: i32) -> Error {
+        Command::new("sh")
+            .args(["-c", "printf '%s' \"$STDERR\" >&2; exit \"$CODE\""])
+            .env("STDERR", stderr)
+            .env("CODE", code.to_string())
+            .output_checked()
+            .unwrap_err()
+    }
+
+    #[test]
+    fn test_normalize_stderr() {
+        assert_eq!(
+            normalize_stderr("  disk\n\n full\t(errno 28)  \n"),
+            "disk full (errno 28)"
+        );
+        assert_eq!(normalize_stderr(&"x".repeat(2000)).len(), STDERR_KEY_CHARS);
+    }
+
+    #[test]
+    fn test_dedup_key_whitespace() {
+        assert_eq!(
+            failure("disk full\n", 1).dedup_key(),
+            failure("disk   full", 1).dedup_key()
+        );
+    }
+
+    #[test]
+    fn test_dedup_key_timestamps() {
+        let first = failure("2024-01-01T12:00:00Z disk full", 1);
+        let second = failure("2024-01-01T12:00:30Z disk full", 1);
+        assert_ne!(first.dedup_key(), second.dedup_key());
+        assert_eq!(first.dedup_key_lenient(), second.dedup_key_lenient());
+    }
+
+    #[test]
+    fn test_dedup_key_exit_code() {
+        assert_ne!(
+            failure("", 1).dedup_key_lenient(),
+            failure("", 2).dedup_key_lenient()
+        );
+    }
+}
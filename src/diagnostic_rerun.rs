@@ -0,0 +1,118 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::process::Command;
+use std::process::Output;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::command_display::guarded;
+use crate::duration::round_duration;
+use crate::messages::messages;
+use crate::output_error::write_indented;
+use crate::run;
+use crate::CommandDisplay;
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Error;
+use crate::OutputContext;
+#[cfg(doc)]
+use crate::OutputError;
+use crate::OutputLike;
+
+/// How long a command re-run for diagnostics is given to exit after it's asked to.
+const GRACE: Duration = Duration::from_secs(1);
+
+/// The output of a failed command's diagnostic re-run, attached to its [`OutputError`].
+///
+/// Produced by [`CommandExt::output_checked_diagnosing`].
+pub struct DiagnosticRerun {
+    pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
+    pub(crate) output: Output,
+    /// If the re-run timed out, the timeout it exceeded.
+    pub(crate) timed_out: Option<Duration>,
+}
+
+impl DiagnosticRerun {
+    /// Re-run `command` once, stopping it after `timeout`.
+    ///
+    /// Returns `None` if the command couldn't be run at all.
+    pub(crate) fn run(mut command: Command, timeout: Duration) -> Option<Self> {
+        let (command, output, timed_out) =
+            match run::output_with_timeout(&mut command, timeout, GRACE, &Instant::now) {
+                Ok(ran) => ran
+                    .check_output(|context: OutputContext<Output>| {
+                        Ok::<_, Error>((context.command, context.output, None))
+                    })
+                    .ok()?,
+                Err(Error::Timeout(error)) => (error.command, error.output, Some(timeout)),
+                Err(_) => return None,
+            };
+        Some(Self {
+            command,
+            output,
+            timed_out,
+        })
+    }
+
+    /// Get a reference to the command that was re-run, including its extra arguments.
+    pub fn command(&self) -> &(dyn CommandDisplay + Send + Sync) {
+        &*self.command
+    }
+
+    /// The re-run's output.
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// If the re-run timed out, the timeout it exceeded.
+    pub fn timed_out(&self) -> Option<Duration> {
+        self.timed_out
+    }
+}
+
+impl Debug for DiagnosticRerun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiagnosticRerun")
+            .field("command", &guarded(&*self.command).to_string())
+            .field("status", &self.output.status)
+            .field("stdout_utf8", &OutputLike::stdout(&self.output))
+            .field("stderr_utf8", &OutputLike::stderr(&self.output))
+            .field("timed_out", &self.timed_out)
+            .finish()
+    }
+}
+
+impl Display for DiagnosticRerun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Diagnostic re-run output (`tool -v`, exit status: 1):
+        //   Stderr:
+        //     ...
+        write!(
+            f,
+            "Diagnostic re-run output (`{}`, ",
+            guarded(&*self.command)
+        )?;
+        match self.timed_out {
+            Some(timeout) => write!(f, "timed out after {:?}):", round_duration(timeout))?,
+            None => write!(f, "{}):", self.output.status)?,
+        }
+
+        const INDENT: &str = "  ";
+        let mut empty = true;
+        for (name, text) in [
+            (messages().stdout, OutputLike::stdout(&self.output)),
+            (messages().stderr, OutputLike::stderr(&self.output)),
+        ] {
+            let text = text.trim();
+            if !text.is_empty() {
+                empty = false;
+                writeln!(f, "\n{INDENT}{name}:")?;
+                write_indented(f, text, &INDENT.repeat(2))?;
+            }
+        }
+        if empty {
+            write!(f, "\n{INDENT}(no output)")?;
+        }
+        Ok(())
+    }
+}
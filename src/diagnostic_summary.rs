@@ -0,0 +1,216 @@
+use std::fmt::Display;
+
+#[cfg(doc)]
+use crate::OutputError;
+
+/// A summary of the compiler diagnostics in a failed command's output.
+///
+/// Produced by [`OutputError::diagnostic_summary`], which recognizes the `error:` and `warning:`
+/// lines written by `rustc`, `cargo`, `gcc`, and `clang`:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// # use command_error::Error;
+/// let err = Command::new("sh")
+///     .args(["-c", "
+///         echo 'warning: unused variable: `x`' >&2
+///         echo 'error[E0308]: mismatched types' >&2
+///         echo 'error: could not compile `puppy` (lib) due to 1 previous error' >&2
+///         exit 101
+///     "])
+///     .output_checked()
+///     .unwrap_err();
+///
+/// let Error::Output(err) = err else { unreachable!() };
+/// let summary = err.diagnostic_summary().unwrap();
+/// assert_eq!(summary.errors(), 1);
+/// assert_eq!(summary.warnings(), 1);
+/// assert_eq!(
+///     summary.to_string(),
+///     "1 error, 1 warning (first: error[E0308]: mismatched types)"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticSummary {
+    errors: usize,
+    warnings: usize,
+    first_error: Option<String>,
+}
+
+impl DiagnosticSummary {
+    /// Parse diagnostics from a command's output streams, returning [`None`] if there aren't any.
+    pub(crate) fn parse(outputs: &[&str]) -> Option<Self> {
+        let mut summary = Self {
+            errors: 0,
+            warnings: 0,
+            first_error: None,
+        };
+        for line in outputs.iter().flat_map(|output| output.lines()) {
+            let line = line.trim();
+            match Severity::of(line) {
+                Some(Severity::Error) => {
+                    summary.errors += 1;
+                    if summary.first_error.is_none() {
+                        summary.first_error = Some(line.to_owned());
+                    }
+                }
+                Some(Severity::Warning) => summary.warnings += 1,
+                None => {}
+            }
+        }
+        if summary.errors == 0 && summary.warnings == 0 {
+            None
+        } else {
+            Some(summary)
+        }
+    }
+
+    /// The number of errors.
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    /// The number of warnings.
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+
+    /// The first error line, like `error[E0308]: mismatched types`.
+    pub fn first_error(&self) -> Option<&str> {
+        self.first_error.as_deref()
+    }
+}
+
+impl Display for DiagnosticSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // 3 errors, 12 warnings (first: error[E0308]: mismatched types)
+        write!(
+            f,
+            "{} error{}, {} warning{}",
+            self.errors,
+            if self.errors == 1 { "" } else { "s" },
+            self.warnings,
+            if self.warnings == 1 { "" } else { "s" },
+        )?;
+        if let Some(first_error) = &self.first_error {
+            write!(f, " (first: {first_error})")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    /// The severity of a diagnostic line, or [`None`] if it isn't a diagnostic.
+    fn of(line: &str) -> Option<Self> {
+        // `cargo` summarizes the diagnostics it's already printed; don't count them twice.
+        if line.starts_with("error: could not compile")
+            || line.starts_with("error: aborting due to")
+            || (line.starts_with("warning: ") && line.contains(" generated "))
+            || line.starts_with("warning: build failed")
+        {
+            return None;
+        }
+
+        // `rustc` and `cargo`: `error: ...`, `error[E0308]: ...`, `warning: ...`
+        if let Some(severity) = Self::prefix(line) {
+            return Some(severity);
+        }
+
+        // `gcc` and `clang`: `main.c:3:1: error: ...`, `main.c:3:1: fatal error: ...`
+        if line.contains(": error: ") || line.contains(": fatal error: ") {
+            Some(Self::Error)
+        } else if line.contains(": warning: ") {
+            Some(Self::Warning)
+        } else {
+            None
+        }
+    }
+
+    fn prefix(line: &str) -> Option<Self> {
+        let (severity, rest) = if let Some(rest) = line.strip_prefix("error") {
+            (Self::Error, rest)
+        } else if let Some(rest) = line.strip_prefix("warning") {
+            (Self::Warning, rest)
+        } else {
+            return None;
+        };
+
+        // Skip an error code like `[E0308]`.
+        let rest = match rest.strip_prefix('[') {
+            Some(code) => &code[code.find(']')? + 1..],
+            None => rest,
+        };
+
+        rest.starts_with(':').then_some(severity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_rustc() {
+        let summary = DiagnosticSummary::parse(&[indoc!(
+            "
+               Compiling puppy v0.1.0 (/puppy)
+            warning: unused variable: `x`
+             --> src/lib.rs:2:9
+            error[E0308]: mismatched types
+             --> src/lib.rs:3:5
+            error: cannot find value `y` in this scope
+            warning: `puppy` (lib) generated 1 warning
+            error: could not compile `puppy` (lib) due to 2 previous errors; 1 warning emitted
+            "
+        )])
+        .unwrap();
+        assert_eq!(
+            summary,
+            DiagnosticSummary {
+                errors: 2,
+                warnings: 1,
+                first_error: Some("error[E0308]: mismatched types".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_gcc() {
+        let summary = DiagnosticSummary::parse(&[indoc!(
+            "
+            main.c: In function 'main':
+            main.c:3:5: warning: implicit declaration of function 'puts'
+            main.c:4:1: error: expected ';' before '}' token
+            "
+        )])
+        .unwrap();
+        assert_eq!(
+            summary.to_string(),
+            "1 error, 1 warning (first: main.c:4:1: error: expected ';' before '}' token)"
+        );
+    }
+
+    #[test]
+    fn test_parse_unrelated() {
+        assert_eq!(
+            DiagnosticSummary::parse(&[indoc!(
+                "
+                errors are fine
+                warnings: none
+                error[unterminated: oops
+                "
+            )]),
+            None
+        );
+    }
+}
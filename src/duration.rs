@@ -0,0 +1,14 @@
+use std::time::Duration;
+
+/// Round a [`Duration`] for display in error messages.
+///
+/// Durations of at least a second are rounded to the nearest second, and shorter durations are
+/// rounded to the nearest millisecond, so that their [`Debug`] representations are short (`42s`
+/// or `250ms` rather than `42.003912741s`).
+pub(crate) fn round_duration(duration: Duration) -> Duration {
+    if duration >= Duration::from_secs(1) {
+        Duration::from_secs(duration.as_secs_f64().round() as u64)
+    } else {
+        Duration::from_millis((duration.as_secs_f64() * 1000.0).round() as u64)
+    }
+}
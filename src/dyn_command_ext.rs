@@ -0,0 +1,91 @@
+use std::process::ExitStatus;
+use std::process::Output;
+
+use utf8_command::Utf8Output;
+
+use crate::CommandExt;
+use crate::Error;
+
+/// A dyn-compatible subset of [`CommandExt`].
+///
+/// [`CommandExt`] has generic methods (like [`CommandExt::output_checked_as`]), so it can't be
+/// used as a trait object. [`DynCommandExt`] exposes the monomorphic methods instead, so that
+/// different kinds of commands can be stored and run uniformly:
+///
+/// ```
+/// # use std::process::Command;
+/// use command_error::DynCommandExt;
+///
+/// let mut commands: Vec<Box<dyn DynCommandExt>> = vec![
+///     Box::new(Command::new("true")),
+///     Box::new({
+///         let mut command = Command::new("echo");
+///         command.arg("puppy");
+///         command
+///     }),
+/// ];
+///
+/// for command in &mut commands {
+///     command.dyn_status_checked().unwrap();
+/// }
+///
+/// let output = commands[1].dyn_output_checked_utf8().unwrap();
+/// assert_eq!(output.stdout, "puppy\n");
+/// ```
+///
+/// [`DynCommandExt`] is implemented for every [`CommandExt`] type which uses this crate's
+/// [`Error`]. Its methods are prefixed with `dyn_` so that calls on a concrete type with both
+/// traits in scope (like after `use command_error::*`) aren't ambiguous.
+pub trait DynCommandExt: Send {
+    /// Run a command, capturing its output. If the command exits with a non-zero exit code, an
+    /// error is raised.
+    ///
+    /// See [`CommandExt::output_checked`].
+    fn dyn_output_checked(&mut self) -> Result<Output, Error>;
+
+    /// Run a command, capturing its output and decoding it as UTF-8. If the command exits with a
+    /// non-zero exit code or if its output contains invalid UTF-8, an error is raised.
+    ///
+    /// See [`CommandExt::output_checked_utf8`].
+    fn dyn_output_checked_utf8(&mut self) -> Result<Utf8Output, Error>;
+
+    /// Run a command without capturing its output. If the command exits with a non-zero status
+    /// code, an error is raised.
+    ///
+    /// See [`CommandExt::status_checked`].
+    fn dyn_status_checked(&mut self) -> Result<ExitStatus, Error>;
+}
+
+impl<C> DynCommandExt for C
+where
+    C: CommandExt<Error = Error> + Send,
+{
+    fn dyn_output_checked(&mut self) -> Result<Output, Error> {
+        CommandExt::output_checked(self)
+    }
+
+    fn dyn_output_checked_utf8(&mut self) -> Result<Utf8Output, Error> {
+        CommandExt::output_checked_utf8(self)
+    }
+
+    fn dyn_status_checked(&mut self) -> Result<ExitStatus, Error> {
+        CommandExt::status_checked(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_assertions::assert_obj_safe;
+
+    assert_obj_safe!(DynCommandExt);
+
+    #[test]
+    fn test_no_ambiguity_with_command_ext() {
+        use crate::*;
+
+        let mut command = std::process::Command::new("true");
+        command.status_checked().unwrap();
+        command.dyn_status_checked().unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::RwLock;
+
+#[cfg(doc)]
+use crate::Utf8ProgramAndArgs;
+
+/// Shown in place of the value of a redacted environment variable.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// Whether an environment variable's value should be redacted, given its name; see
+/// [`set_env_redaction`].
+type Redaction = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// The redaction rule currently used when displaying commands; see [`set_env_redaction`].
+static ENV_REDACTION: RwLock<Option<Redaction>> = RwLock::new(None);
+
+/// Redact the values of environment variables for which `redact` returns `true` (given the
+/// variable's name) in all commands displayed after this call, for the rest of the process.
+///
+/// Redacted variables are displayed as `KEY=<redacted>`, so that secrets passed to commands with
+/// [`Command::env`][std::process::Command::env] don't leak into error messages and logs:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// command_error::set_env_redaction(|key| key.contains("SECRET") || key.ends_with("_TOKEN"));
+///
+/// let err = Command::new("sh")
+///     .args(["-c", "exit 1"])
+///     .env("AWS_SECRET_ACCESS_KEY", "hunter2")
+///     .env("REGION", "us-east-1")
+///     .output_checked()
+///     .unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "`sh` failed: exit status: 1\n\
+///     Command failed: `AWS_SECRET_ACCESS_KEY=<redacted> REGION=us-east-1 sh -c 'exit 1'`"
+/// );
+/// ```
+///
+/// Values are redacted when an error is produced, so they aren't kept in memory. To redact
+/// variables for a single displayed command, see [`Utf8ProgramAndArgs::redact_env`].
+pub fn set_env_redaction(redact: impl Fn(&str) -> bool + Send + Sync + 'static) {
+    *ENV_REDACTION
+        .write()
+        .unwrap_or_else(|error| error.into_inner()) = Some(Box::new(redact));
+}
+
+/// Whether the value of the environment variable `key` should be redacted.
+pub(crate) fn is_redacted(key: &str) -> bool {
+    ENV_REDACTION
+        .read()
+        .unwrap_or_else(|error| error.into_inner())
+        .as_ref()
+        .is_some_and(|redact| redact(key))
+}
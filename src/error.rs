@@ -1,32 +1,69 @@
+use std::borrow::Cow;
 use std::fmt::Display;
+use std::panic::Location;
 
 #[cfg(doc)]
 use std::process::Child;
-#[cfg(doc)]
 use std::process::Command;
 #[cfg(doc)]
 use std::process::Output;
 
+use crate::command_display::guarded;
 use crate::output_conversion_error::OutputConversionError;
+use crate::AlreadyRunningError;
+use crate::CaptureError;
+use crate::CircuitOpenError;
+use crate::CommandDisplay;
 use crate::ExecError;
 use crate::OutputError;
+use crate::StdinError;
+use crate::TimeoutError;
 use crate::WaitError;
 
+#[cfg(doc)]
+use crate::CircuitBreaker;
 #[cfg(doc)]
 use crate::CommandExt;
+#[cfg(doc)]
+use crate::SingleFlight;
 #[cfg(feature = "miette")]
 use miette::Diagnostic;
 
 /// An error produced by a [`Command`] failure.
+///
+/// With the `miette` feature, each kind of error has a stable diagnostic code, which can be used
+/// to filter errors or link to documentation:
+///
+/// | Variant | Code |
+/// | ------- | ---- |
+/// | [`Error::Exec`] | `command_error::exec` |
+/// | [`Error::Wait`] | `command_error::wait` |
+/// | [`Error::Capture`] | `command_error::capture` |
+/// | [`Error::Stdin`] | `command_error::stdin` |
+/// | [`Error::Output`] | `command_error::output` |
+/// | [`Error::Conversion`] | `command_error::conversion` |
+/// | [`Error::CircuitOpen`] | `command_error::circuit_open` |
+/// | [`Error::Timeout`] | `command_error::timeout` |
+/// | [`Error::AlreadyRunning`] | `command_error::already_running` |
+///
+/// `UnexpectedSuccess` errors (with the `test-helpers` feature) have
+/// the code `command_error::unexpected_success`.
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum Error {
-    /// An execution failure, when a [`Command`] fails to start or [`Child::wait`].
+    /// An execution failure, when a [`Command`] fails to start.
     Exec(ExecError),
     /// A failure to wait for a [`Command`].
     ///
     /// See: [`Child::wait`].
     Wait(WaitError),
+    /// A failure to capture a [`Command`]'s output, like running out of disk space while reading
+    /// it.
+    Capture(CaptureError),
+    /// A failure to write a [`Command`]'s input to its stdin.
+    ///
+    /// See: [`CommandExt::output_checked_with_input`].
+    Stdin(StdinError),
     /// An output failure, when a [`Command`] fails by returning a non-zero exit code (or in other
     /// cases, when custom validation logic is supplied in methods like
     /// [`CommandExt::output_checked_with`]).
@@ -37,9 +74,116 @@ pub enum Error {
     /// An output conversion error, when [`Output`] fails to convert to a custom format as
     /// requested by methods like [`CommandExt::output_checked_utf8`].
     Conversion(OutputConversionError),
+    /// A command which was not run because it has failed too many times recently.
+    ///
+    /// See: [`CircuitBreaker`].
+    CircuitOpen(CircuitOpenError),
+    /// A command which didn't exit before its timeout and was stopped.
+    ///
+    /// See: [`CommandExt::output_checked_with_timeout_and_grace`].
+    Timeout(TimeoutError),
+    /// A command which was not run because the same command is already running.
+    ///
+    /// See: [`SingleFlight`].
+    AlreadyRunning(AlreadyRunningError),
 }
 
 impl Error {
+    /// The name of the program which failed, for any kind of error.
+    ///
+    /// This is useful for grouping errors by program.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "exit 1"])
+    ///     .status_checked()
+    ///     .unwrap_err();
+    /// assert_eq!(err.program(), "sh");
+    ///
+    /// let err = Command::new("ooga booga").status_checked().unwrap_err();
+    /// assert_eq!(err.program(), "ooga booga");
+    /// ```
+    pub fn program(&self) -> Cow<'_, str> {
+        guarded(self.command()).program()
+    }
+
+    /// Where in the source code the failing command was run from, for any kind of error, if
+    /// known.
+    ///
+    /// [`CommandExt`] methods are `#[track_caller]`, so this is the location of the call which
+    /// produced the error; see [`CommandDisplay::location`].
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("ooga booga").status_checked().unwrap_err();
+    /// let location = err.location().unwrap();
+    /// assert_eq!(location.file(), file!());
+    /// assert_eq!(location.line(), line!() - 3);
+    /// ```
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        guarded(self.command()).location()
+    }
+
+    /// Reconstruct the failing command with `extra` arguments appended, to re-run it (for
+    /// example, with `--verbose`) to gather more information about the failure.
+    ///
+    /// The command is rebuilt with [`CommandDisplay::to_command`], which is lossy: arguments
+    /// which weren't valid UTF-8 aren't preserved, and neither are settings which aren't
+    /// displayed, like stdio redirections.
+    ///
+    /// # Panics
+    ///
+    /// If the command had so many arguments that some were omitted; see
+    /// [`CommandDisplay::omitted_args`].
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "[ \"$1\" = -v ] && echo \"extra $1\" >&2; exit 1", "sh"])
+    ///     .output_checked()
+    ///     .unwrap_err();
+    /// assert!(!err.to_string().contains("extra -v"));
+    ///
+    /// let err = err.rerun_with_args(&["-v"]).output_checked().unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         r#"`sh` failed: exit status: 1
+    ///         Command failed: `sh -c '[ "$1" = -v ] && echo "extra $1" >&2; exit 1' sh -v`
+    ///         Stderr:
+    ///           extra -v"#
+    ///     )
+    /// );
+    /// ```
+    pub fn rerun_with_args(&self, extra: &[&str]) -> Command {
+        let mut command = self.command().to_command();
+        command.args(extra);
+        command
+    }
+
+    /// The command which failed.
+    pub(crate) fn command(&self) -> &(dyn CommandDisplay + Send + Sync) {
+        match self {
+            Error::Exec(inner) => &*inner.command,
+            Error::Wait(inner) => &*inner.command,
+            Error::Output(inner) => &*inner.command,
+            Error::Conversion(inner) => &*inner.command,
+            Error::CircuitOpen(inner) => &*inner.command,
+            Error::Timeout(inner) => &*inner.command,
+            Error::AlreadyRunning(inner) => &*inner.command,
+            Error::Capture(inner) => &*inner.command,
+            Error::Stdin(inner) => &*inner.command,
+        }
+    }
+
     #[cfg(feature = "miette")]
     fn as_inner_diagnostic(&self) -> &(dyn Diagnostic + Send + Sync + 'static) {
         match self {
@@ -47,6 +191,11 @@ impl Error {
             Error::Wait(inner) => inner,
             Error::Output(inner) => inner,
             Error::Conversion(inner) => inner,
+            Error::CircuitOpen(inner) => inner,
+            Error::Timeout(inner) => inner,
+            Error::AlreadyRunning(inner) => inner,
+            Error::Capture(inner) => inner,
+            Error::Stdin(inner) => inner,
         }
     }
 }
@@ -58,6 +207,11 @@ impl Display for Error {
             Error::Wait(error) => write!(f, "{}", error),
             Error::Output(error) => write!(f, "{}", error),
             Error::Conversion(error) => write!(f, "{}", error),
+            Error::CircuitOpen(error) => write!(f, "{}", error),
+            Error::Timeout(error) => write!(f, "{}", error),
+            Error::AlreadyRunning(error) => write!(f, "{}", error),
+            Error::Capture(error) => write!(f, "{}", error),
+            Error::Stdin(error) => write!(f, "{}", error),
         }
     }
 }
@@ -86,6 +240,36 @@ impl From<OutputConversionError> for Error {
     }
 }
 
+impl From<CircuitOpenError> for Error {
+    fn from(error: CircuitOpenError) -> Self {
+        Self::CircuitOpen(error)
+    }
+}
+
+impl From<TimeoutError> for Error {
+    fn from(error: TimeoutError) -> Self {
+        Self::Timeout(error)
+    }
+}
+
+impl From<AlreadyRunningError> for Error {
+    fn from(error: AlreadyRunningError) -> Self {
+        Self::AlreadyRunning(error)
+    }
+}
+
+impl From<CaptureError> for Error {
+    fn from(error: CaptureError) -> Self {
+        Self::Capture(error)
+    }
+}
+
+impl From<StdinError> for Error {
+    fn from(error: StdinError) -> Self {
+        Self::Stdin(error)
+    }
+}
+
 impl std::error::Error for Error {}
 
 #[cfg(feature = "miette")]
@@ -1,11 +1,22 @@
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::panic::Location;
+use std::path::Path;
 
+use crate::command_display::guarded;
+use crate::messages::messages;
+use crate::spawn_error_class::classify_spawn_error;
 use crate::CommandDisplay;
 #[cfg(doc)]
 use crate::CommandExt;
+use crate::ExecFailure;
 #[cfg(doc)]
 use crate::OutputError;
+use crate::SpawnErrorClass;
+#[cfg(doc)]
+use crate::Spawner;
+#[cfg(doc)]
+use crate::StdSpawner;
 #[cfg(feature = "miette")]
 use miette::Diagnostic;
 
@@ -35,23 +46,130 @@ use miette::Diagnostic;
 ///     "Failed to execute `echo`: File not found (os error 2)"
 /// );
 /// ```
+///
+/// If the command's working directory is missing or isn't a directory, the error says so:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// # use command_error::Error;
+/// # use command_error::ExecFailure;
+/// let Error::Exec(err) = Command::new("ls")
+///     .current_dir("/ooga/booga")
+///     .output_checked()
+///     .unwrap_err()
+/// else {
+///     unreachable!()
+/// };
+/// assert_eq!(
+///     err.failure(),
+///     &ExecFailure::WorkingDirMissing("/ooga/booga".into())
+/// );
+/// assert_eq!(
+///     err.to_string(),
+///     "Failed to execute `ls`: working directory `/ooga/booga` does not exist: \
+///     No such file or directory (os error 2)"
+/// );
+/// ```
 pub struct ExecError {
     pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
     pub(crate) inner: std::io::Error,
+    /// The name of the [`Spawner`] used to start the command, if one was used.
+    pub(crate) backend: Option<&'static str>,
+    /// The step of starting the command which failed, if known.
+    pub(crate) step: Option<&'static str>,
+    /// Why the command failed to start.
+    pub(crate) failure: ExecFailure,
 }
 
 impl ExecError {
     /// Construct a new [`ExecError`].
-    pub fn new(command: Box<dyn CommandDisplay + Send + Sync>, inner: std::io::Error) -> Self {
-        Self { command, inner }
+    pub fn new(
+        command: impl Into<Box<dyn CommandDisplay + Send + Sync>>,
+        inner: std::io::Error,
+    ) -> Self {
+        Self {
+            command: command.into(),
+            inner,
+            backend: None,
+            step: None,
+            failure: ExecFailure::Program,
+        }
+    }
+
+    /// Check whether the command's working directory, `current_dir`, is why it failed to start.
+    ///
+    /// See [`ExecFailure`].
+    pub(crate) fn with_current_dir(mut self, current_dir: Option<&Path>) -> Self {
+        self.failure = ExecFailure::detect(current_dir);
+        self
+    }
+
+    /// Record which [`Spawner`] backend was used to start the command, and which step of starting
+    /// it failed.
+    pub(crate) fn with_backend(
+        mut self,
+        backend: &'static str,
+        step: Option<&'static str>,
+    ) -> Self {
+        self.backend = Some(backend);
+        self.step = step;
+        self
+    }
+
+    /// The name of the [`Spawner`] backend used to start the command, if it was started with
+    /// [`CommandExt::output_checked_with_spawner`] or similar (including [`StdSpawner`]).
+    pub fn backend(&self) -> Option<&'static str> {
+        self.backend
+    }
+
+    /// The step of starting the command which failed, like `posix_spawn_file_actions_adddup2`, if
+    /// the backend reports it.
+    pub fn spawn_step(&self) -> Option<&'static str> {
+        self.step
+    }
+
+    /// Why the command failed to start, as far as can be determined.
+    pub fn failure(&self) -> &ExecFailure {
+        &self.failure
+    }
+
+    /// Where in the source code the command was run from, if known.
+    ///
+    /// See [`CommandDisplay::location`].
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        guarded(&*self.command).location()
+    }
+
+    /// Whether starting the command failed for a likely temporary reason, like the system being
+    /// at its process limit, so that starting it again later may succeed.
+    ///
+    /// See [`classify_spawn_error`] for which errors are transient; a missing program never is.
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// let Error::Exec(err) = Command::new("ooga booga").output_checked().unwrap_err() else {
+    ///     unreachable!()
+    /// };
+    /// assert!(!err.is_transient());
+    /// ```
+    pub fn is_transient(&self) -> bool {
+        classify_spawn_error(&self.inner) == SpawnErrorClass::Transient
     }
 }
 
 impl Debug for ExecError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ExecError")
-            .field("program", &self.command.program())
+            .field("program", &guarded(&*self.command).program())
             .field("inner", &self.inner)
+            .field("backend", &self.backend)
+            .field("step", &self.step)
+            .field("failure", &self.failure)
+            .field("location", &self.location())
             .finish()
     }
 }
@@ -60,10 +178,30 @@ impl Display for ExecError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Failed to execute `{}`: {}",
-            self.command.program_quoted(),
-            self.inner
-        )
+            "{} `{}`",
+            messages().failed_to_execute,
+            guarded(&*self.command).program_quoted()
+        )?;
+        if let Some(backend) = self.backend {
+            write!(f, " via {backend}")?;
+        }
+        if let Some(step) = self.step {
+            write!(f, " ({step})")?;
+        }
+        match &self.failure {
+            ExecFailure::WorkingDirMissing(dir) => {
+                write!(f, ": working directory `{}` does not exist", dir.display())?;
+            }
+            ExecFailure::WorkingDirNotADirectory(dir) => {
+                write!(
+                    f,
+                    ": working directory `{}` is not a directory",
+                    dir.display()
+                )?;
+            }
+            ExecFailure::Program => {}
+        }
+        write!(f, ": {}", self.inner)
     }
 }
 
@@ -71,10 +209,17 @@ impl std::error::Error for ExecError {}
 
 #[cfg(feature = "miette")]
 impl Diagnostic for ExecError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::exec"))
+    }
+
     fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        if self.failure.working_dir().is_some() {
+            return None;
+        }
         Some(Box::new(format!(
             "Is {} installed and present on your $PATH?",
-            self.command.program_quoted()
+            guarded(&*self.command).program_quoted()
         )))
     }
 }
@@ -0,0 +1,71 @@
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+
+#[cfg(doc)]
+use crate::ExecError;
+
+/// Why a command failed to start, as far as can be determined; see [`ExecError::failure`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExecFailure {
+    /// The command's working directory (set with [`std::process::Command::current_dir`]) doesn't
+    /// exist.
+    WorkingDirMissing(PathBuf),
+    /// The command's working directory exists, but isn't a directory.
+    WorkingDirNotADirectory(PathBuf),
+    /// The command failed to start for another reason, most often because its program couldn't
+    /// be found.
+    Program,
+}
+
+impl ExecFailure {
+    /// Determine why a command with working directory `current_dir` failed to start.
+    ///
+    /// This checks the working directory after the fact, so it can be fooled by a directory
+    /// which is created or deleted in between.
+    pub(crate) fn detect(current_dir: Option<&Path>) -> Self {
+        let Some(current_dir) = current_dir else {
+            return Self::Program;
+        };
+        match std::fs::metadata(current_dir) {
+            Ok(metadata) if !metadata.is_dir() => {
+                Self::WorkingDirNotADirectory(current_dir.to_owned())
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                Self::WorkingDirMissing(current_dir.to_owned())
+            }
+            _ => Self::Program,
+        }
+    }
+
+    /// The working directory responsible for the failure, if any.
+    pub fn working_dir(&self) -> Option<&Path> {
+        match self {
+            Self::WorkingDirMissing(dir) | Self::WorkingDirNotADirectory(dir) => Some(dir),
+            Self::Program => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(ExecFailure::detect(None), ExecFailure::Program);
+        assert_eq!(
+            ExecFailure::detect(Some(Path::new("/"))),
+            ExecFailure::Program
+        );
+        assert_eq!(
+            ExecFailure::detect(Some(Path::new("/ooga/booga"))),
+            ExecFailure::WorkingDirMissing("/ooga/booga".into())
+        );
+        let file = std::env::current_exe().unwrap();
+        assert_eq!(
+            ExecFailure::detect(Some(&file)),
+            ExecFailure::WorkingDirNotADirectory(file.clone())
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::io::ErrorKind;
+use std::process::ExitCode;
+use std::process::ExitStatus;
+
+use crate::exit_signal;
+use crate::Error;
+use crate::OutputError;
+
+/// The exit code used when a command couldn't be executed because it wasn't found.
+const NOT_FOUND: i32 = 127;
+/// The exit code used when a command couldn't be executed for another reason, like permissions.
+const NOT_EXECUTABLE: i32 = 126;
+/// The exit code used when a command timed out, matching `timeout(1)`.
+const TIMED_OUT: i32 = 124;
+/// The exit code used when a command wasn't run because it's already running, matching
+/// `EX_TEMPFAIL` from `sysexits.h`.
+const TEMPORARY_FAILURE: i32 = 75;
+/// The exit code used when a command's output couldn't be captured, matching `EX_IOERR` from
+/// `sysexits.h`.
+const IO_ERROR: i32 = 74;
+/// The exit code used when there's no better exit code to report.
+const FAILURE: i32 = 1;
+
+impl Error {
+    /// An exit code for a wrapper process to exit with in order to report this error
+    /// transparently.
+    ///
+    /// The mapping follows the conventions of POSIX shells:
+    ///
+    /// - If the command exited with a non-zero code, that code is used. On Windows, exit codes
+    ///   are 32-bit and are used as-is, so `0xC0000005` (an access violation) is preserved.
+    /// - On Unix, if the command was killed by a signal, `128 + signal` is used (so `SIGKILL` is
+    ///   137).
+    /// - If the command couldn't be executed because it wasn't found, 127 is used.
+    /// - If the command couldn't be executed for any other reason (like missing permissions), 126
+    ///   is used.
+    /// - If the command timed out, 124 is used (like `timeout(1)`).
+    /// - If the command wasn't run because it's already running, 75 (`EX_TEMPFAIL`) is used.
+    /// - If the command's output couldn't be captured, 74 (`EX_IOERR`) is used.
+    /// - Otherwise, 1 is used. This includes commands which exited successfully but were rejected
+    ///   by custom validation logic, failures to wait for a command, and output conversion
+    ///   failures.
+    ///
+    /// If the command was short-circuited by a [`crate::CircuitBreaker`], the most recent
+    /// failure's exit code is used.
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "exit 3"])
+    ///     .status_checked()
+    ///     .unwrap_err();
+    /// assert_eq!(err.exit_code(), 3);
+    ///
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "kill -9 $$"])
+    ///     .status_checked()
+    ///     .unwrap_err();
+    /// assert_eq!(err.exit_code(), 137);
+    ///
+    /// let err = Command::new("ooga booga").status_checked().unwrap_err();
+    /// assert_eq!(err.exit_code(), 127);
+    /// ```
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Exec(inner) => match inner.inner.kind() {
+                ErrorKind::NotFound => NOT_FOUND,
+                _ => NOT_EXECUTABLE,
+            },
+            Error::Output(inner) => output_exit_code(inner),
+            Error::CircuitOpen(inner) => output_exit_code(inner.last_error()),
+            Error::Timeout(_) => TIMED_OUT,
+            Error::AlreadyRunning(_) => TEMPORARY_FAILURE,
+            Error::Capture(_) | Error::Stdin(_) => IO_ERROR,
+            Error::Wait(_) | Error::Conversion(_) => FAILURE,
+        }
+    }
+
+    /// Print this error to stderr and exit the current process with [`Error::exit_code`].
+    ///
+    /// This is intended for wrapper tools which want to be transparent about their
+    /// subprocesses' exit statuses:
+    ///
+    /// ```no_run
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// if let Err(err) = Command::new("cargo").arg("build").status_checked() {
+    ///     err.exit_process();
+    /// }
+    /// ```
+    ///
+    /// Note that, like [`std::process::exit`], destructors on the current stack (and other
+    /// threads' stacks) won't be run.
+    pub fn exit_process(&self) -> ! {
+        eprintln!("{self}");
+        std::process::exit(self.exit_code())
+    }
+
+    /// An [`ExitCode`] for the current process to exit with in order to propagate this error,
+    /// following the same conventions as [`Error::exit_code`].
+    ///
+    /// Unlike [`Error::exit_process`], this can be returned from library code and eventually from
+    /// `main`, so destructors still run:
+    ///
+    /// ```no_run
+    /// # use std::process::Command;
+    /// # use std::process::ExitCode;
+    /// # use command_error::CommandExt;
+    /// fn main() -> ExitCode {
+    ///     match Command::new("cargo").arg("build").status_checked() {
+    ///         Ok(_) => ExitCode::SUCCESS,
+    ///         Err(err) => {
+    ///             eprintln!("{err}");
+    ///             err.propagated_exit_code()
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`ExitCode`] can only be constructed from a `u8` on stable Rust, so on Windows, exit codes
+    /// above 255 (like NTSTATUS codes such as `0xC0000005`) are reported as 1.
+    pub fn propagated_exit_code(&self) -> ExitCode {
+        to_exit_code(self.exit_code())
+    }
+}
+
+impl OutputError {
+    /// An [`ExitCode`] for the current process to exit with in order to propagate this command's
+    /// failure as its own.
+    ///
+    /// - If the command exited with a non-zero code, that code is used.
+    /// - On Unix, if the command was killed by a signal, `128 + signal` is used (so `SIGTERM` is
+    ///   143).
+    /// - Otherwise, 1 is used. This includes commands which exited successfully but were rejected
+    ///   by custom validation logic.
+    ///
+    /// [`ExitCode`] can only be constructed from a `u8` on stable Rust, so on Windows, exit codes
+    /// above 255 (like NTSTATUS codes such as `0xC0000005`) are reported as 1.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use std::process::ExitCode;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "exit 3"])
+    ///     .output_checked()
+    ///     .unwrap_err();
+    /// let Error::Output(err) = err else { unreachable!() };
+    /// assert_eq!(err.propagated_exit_code(), ExitCode::from(3));
+    /// ```
+    pub fn propagated_exit_code(&self) -> ExitCode {
+        to_exit_code(output_exit_code(self))
+    }
+}
+
+/// Convert an exit code from [`Error::exit_code`] into an [`ExitCode`], falling back to
+/// [`FAILURE`] if it doesn't fit in a `u8`.
+fn to_exit_code(code: i32) -> ExitCode {
+    ExitCode::from(u8::try_from(code).unwrap_or(FAILURE as u8))
+}
+
+fn output_exit_code(error: &OutputError) -> i32 {
+    status_exit_code(error.output.status())
+}
+
+fn status_exit_code(status: ExitStatus) -> i32 {
+    match status.code() {
+        Some(0) => FAILURE,
+        Some(code) => code,
+        None => match exit_signal(status) {
+            Some(signal) => 128 + signal,
+            None => FAILURE,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::process::Output;
+
+    use crate::Utf8ProgramAndArgs;
+
+    fn output_error(status: ExitStatus) -> OutputError {
+        OutputError::new(
+            Box::new(Utf8ProgramAndArgs::from(&std::process::Command::new(
+                "tool",
+            ))),
+            Box::new(Output {
+                status,
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            }),
+        )
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_propagated_exit_code_unix() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // `exit 3`; the wait status holds the exit code in the second byte.
+        let error = output_error(ExitStatus::from_raw(3 << 8));
+        assert_eq!(error.propagated_exit_code(), ExitCode::from(3));
+
+        // Killed by `SIGTERM`.
+        let error = output_error(ExitStatus::from_raw(libc::SIGTERM));
+        assert_eq!(error.propagated_exit_code(), ExitCode::from(143));
+
+        // Exited successfully, but rejected by validation.
+        let error = output_error(ExitStatus::from_raw(0));
+        assert_eq!(error.propagated_exit_code(), ExitCode::FAILURE);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_propagated_exit_code_windows() {
+        use std::os::windows::process::ExitStatusExt;
+
+        let error = output_error(ExitStatus::from_raw(3));
+        assert_eq!(error.propagated_exit_code(), ExitCode::from(3));
+
+        // `STATUS_ACCESS_VIOLATION` doesn't fit in an `ExitCode`.
+        let error = output_error(ExitStatus::from_raw(0xC0000005));
+        assert_eq!(output_exit_code(&error), 0xC0000005_u32 as i32);
+        assert_eq!(error.propagated_exit_code(), ExitCode::FAILURE);
+    }
+}
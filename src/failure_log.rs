@@ -0,0 +1,178 @@
+#[cfg(feature = "tracing")]
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "tracing")]
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "tracing")]
+use crate::command_display::guarded;
+#[cfg(doc)]
+use crate::OutputContext;
+use crate::OutputError;
+
+/// Whether failed commands are logged; see [`log_failures`].
+#[cfg(feature = "tracing")]
+static LOG_FAILURES: AtomicBool = AtomicBool::new(false);
+
+/// The number of bytes of each output stream included in a logged failure.
+#[cfg(feature = "tracing")]
+const LOGGED_OUTPUT_BYTES: usize = 4096;
+
+/// Enable or disable logging an error-level `tracing` event whenever a command fails.
+///
+/// This is off by default, because callers usually log or report the errors they handle, and
+/// logging them here too would log every failure twice. Turn it on to make sure failures end up
+/// in the logs even if the caller swallows the error.
+///
+/// When enabled, each failure produces an event with message `Command failed`, containing the
+/// `command`, its exit `status`, the first 4 KiB of its `stdout` and `stderr`, and the `reason`
+/// the command was considered to have failed, if one was given. Failures are logged when the error
+/// is constructed, like by [`OutputContext::error`] and the [`CommandExt`][crate::CommandExt]
+/// methods which check the exit status.
+///
+/// ```
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// command_error::log_failures(true);
+/// // Logs a `Command failed` event, even though the error is ignored.
+/// let _ = Command::new("false").output_checked();
+/// # command_error::log_failures(false);
+/// ```
+///
+/// Only available with the `tracing` feature.
+#[cfg(feature = "tracing")]
+pub fn log_failures(enabled: bool) {
+    LOG_FAILURES.store(enabled, Ordering::Relaxed);
+}
+
+/// Log `error`, if failures are being logged (see `log_failures`).
+///
+/// Without the `tracing` feature, this does nothing.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn log_failure(error: &OutputError) {
+    #[cfg(feature = "tracing")]
+    if LOG_FAILURES.load(Ordering::Relaxed) {
+        let stdout = error.output.stdout();
+        let stderr = error.output.stderr();
+        tracing::error!(
+            command = %guarded(&*error.command),
+            status = %error.output.status(),
+            stdout = truncated(&stdout),
+            stderr = truncated(&stderr),
+            reason = error.user_error.as_ref().map(tracing::field::display),
+            "Command failed"
+        );
+    }
+}
+
+/// Truncate `output` to at most [`LOGGED_OUTPUT_BYTES`], on a character boundary.
+#[cfg(feature = "tracing")]
+fn truncated(output: &str) -> &str {
+    let mut end = output.len().min(LOGGED_OUTPUT_BYTES);
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    &output[..end]
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fmt::Debug;
+    use std::process::Command;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use tracing::field::Field;
+    use tracing::field::Visit;
+    use tracing::span;
+    use tracing::Event;
+    use tracing::Metadata;
+    use tracing::Subscriber;
+
+    use crate::CommandExt;
+
+    /// A [`Subscriber`] which records the fields of `Command failed` events.
+    #[derive(Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<Fields>>>);
+
+    /// The fields of an event, as strings.
+    #[derive(Debug, Default, PartialEq)]
+    struct Fields(Vec<(String, String)>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.push((field.name().to_owned(), format!("{value:?}")));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.push((field.name().to_owned(), value.to_owned()));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            if fields
+                .0
+                .contains(&("message".to_owned(), "Command failed".to_owned()))
+            {
+                self.0.lock().unwrap().push(fields);
+            }
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn test_log_failures() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let _ = Command::new("sh")
+                .args(["-c", "echo puppy; exit 1"])
+                .output_checked();
+
+            log_failures(true);
+            let _ = Command::new("sh")
+                .args(["-c", "echo doggy; echo woof >&2; exit 2"])
+                .output_checked_with(|_: &std::process::Output| Err(Some("bad dog")));
+            log_failures(false);
+        });
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![Fields(vec![
+                ("message".to_owned(), "Command failed".to_owned()),
+                (
+                    "command".to_owned(),
+                    "sh -c 'echo doggy; echo woof >&2; exit 2'".to_owned()
+                ),
+                ("status".to_owned(), "exit status: 2".to_owned()),
+                ("stdout".to_owned(), "doggy\n".to_owned()),
+                ("stderr".to_owned(), "woof\n".to_owned()),
+                ("reason".to_owned(), "bad dog".to_owned()),
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        let output = format!("{}é", "x".repeat(LOGGED_OUTPUT_BYTES - 1));
+        assert_eq!(truncated(&output).len(), LOGGED_OUTPUT_BYTES - 1);
+        assert_eq!(truncated("puppy"), "puppy");
+    }
+}
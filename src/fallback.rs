@@ -0,0 +1,50 @@
+use std::process::Command;
+
+use crate::Error;
+use crate::ExecError;
+use crate::Utf8ProgramAndArgs;
+
+/// Copy `command`'s arguments, environment, and working directory to a new [`Command`] running
+/// `program`.
+///
+/// [`Command`] doesn't expose its stdio configuration or platform-specific settings, so those
+/// aren't copied.
+pub(crate) fn with_program(command: &Command, program: &str) -> Command {
+    let mut new = Command::new(program);
+    new.args(command.get_args());
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => new.env(key, value),
+            None => new.env_remove(key),
+        };
+    }
+    if let Some(current_dir) = command.get_current_dir() {
+        new.current_dir(current_dir);
+    }
+    new
+}
+
+/// Whether `error` indicates that a program wasn't found, so the next fallback should be tried.
+pub(crate) fn is_not_found(error: &Error) -> bool {
+    matches!(error, Error::Exec(error) if error.inner.kind() == std::io::ErrorKind::NotFound)
+}
+
+/// An error indicating that none of `programs` could be found.
+pub(crate) fn not_found(command: &Command, programs: &[&str]) -> Error {
+    let displayed: Utf8ProgramAndArgs = match programs.first() {
+        Some(program) => (&with_program(command, program)).into(),
+        None => command.into(),
+    };
+    let names = programs
+        .iter()
+        .map(|program| format!("`{}`", shell_words::quote(program)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Error::from(ExecError::new(
+        Box::new(displayed),
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("None of the programs were found: {names}"),
+        ),
+    ))
+}
@@ -0,0 +1,403 @@
+//! The fixed phrases which make up rendered error messages, for tools which parse them.
+//!
+//! The [`Display`](std::fmt::Display) implementations of the error types in this crate are built
+//! from these constants, so downstream log parsers can match on the same phrases at compile time
+//! rather than copying them. [`VERSION`] is bumped whenever the structure of rendered messages
+//! changes (like a section being added, renamed, or reordered), so parsers can detect that they
+//! need updating.
+//!
+//! These are the default English phrases, which make up [`Messages::ENGLISH`]; if they're replaced
+//! with [`crate::set_messages`] or [`Messages::display`], rendered messages won't match them. See
+//! [`parse_rendered`] to extract the parts of a rendered message with the current [`Messages`].
+
+use crate::messages::messages;
+#[cfg(doc)]
+use crate::Error;
+use crate::Messages;
+
+/// The version of the structure of rendered error messages.
+///
+/// This is bumped whenever the layout of rendered messages changes in a way that may affect
+/// parsers, even if the constants in this module don't change.
+pub const VERSION: u32 = 1;
+
+/// The label at the start of the line showing the command which failed, followed by `: ` and
+/// the backtick-quoted command, like ``Command failed: `cargo build` ``.
+pub const COMMAND_FAILED_PREFIX: &str = "Command failed";
+
+/// The label at the start of the line showing a command which unexpectedly succeeded, followed
+/// by `: ` and the backtick-quoted command.
+pub const COMMAND_PREFIX: &str = "Command";
+
+/// The header of the section containing a command's stdout, followed by `:`.
+pub const STDOUT_HEADER: &str = "Stdout";
+
+/// The header of the section containing a command's stderr, followed by `:`.
+pub const STDERR_HEADER: &str = "Stderr";
+
+/// The start of the message for a command which couldn't be started, followed by the
+/// backtick-quoted program name.
+pub const FAILED_TO_EXECUTE_PREFIX: &str = "Failed to execute";
+
+/// The label at the start of the line showing a command's `argv[0]`, if it was overridden,
+/// followed by `: ` and the backtick-quoted name.
+pub const INVOKED_AS_PREFIX: &str = "Invoked as";
+
+/// The indentation of each line of a command's output within the [`STDOUT_HEADER`] and
+/// [`STDERR_HEADER`] sections.
+pub const OUTPUT_INDENT: &str = "  ";
+
+/// The parts of a rendered error message; see [`parse_rendered`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedErrorMessage {
+    program: String,
+    command: Option<String>,
+    exit_code: Option<i32>,
+    stdout: Option<String>,
+    stderr: Option<String>,
+}
+
+impl ParsedErrorMessage {
+    /// The program name from the first line of the message, as quoted for the shell.
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// The full command line, if the message includes it.
+    pub fn command(&self) -> Option<&str> {
+        self.command.as_deref()
+    }
+
+    /// The command's exit code, if the message includes one.
+    ///
+    /// This is [`None`] for commands killed by a signal.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// The command's stdout, without indentation, if the message includes it.
+    pub fn stdout(&self) -> Option<&str> {
+        self.stdout.as_deref()
+    }
+
+    /// The command's stderr, without indentation, if the message includes it.
+    pub fn stderr(&self) -> Option<&str> {
+        self.stderr.as_deref()
+    }
+}
+
+/// Extract the program, command line, exit code, and output sections from a rendered error
+/// message, like the [`Display`](std::fmt::Display)ed form of an [`Error`].
+///
+/// This is best-effort: it returns [`None`] if the message doesn't look like one of this crate's
+/// errors, and parts which can't be found are left out. Labels are matched against the current
+/// [`Messages`], so messages rendered with different [`Messages`] should be parsed within
+/// [`Messages::scope`] with the same ones. Messages rendered by a different [`VERSION`] of the
+/// format may not be parsed correctly.
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// let err = Command::new("sh")
+///     .args(["-c", "echo puppy; echo doggy >&2; exit 2"])
+///     .output_checked()
+///     .unwrap_err();
+/// let parsed = command_error::parse_rendered(&err.to_string()).unwrap();
+/// assert_eq!(parsed.program(), "sh");
+/// assert_eq!(parsed.command(), Some("sh -c 'echo puppy; echo doggy >&2; exit 2'"));
+/// assert_eq!(parsed.exit_code(), Some(2));
+/// assert_eq!(parsed.stdout(), Some("puppy"));
+/// assert_eq!(parsed.stderr(), Some("doggy"));
+/// ```
+pub fn parse_rendered(message: &str) -> Option<ParsedErrorMessage> {
+    let messages = messages();
+    let mut lines = message.lines().peekable();
+    let first = lines.next()?;
+    let program = quoted(first)?.to_owned();
+    let mut parsed = ParsedErrorMessage {
+        program,
+        command: None,
+        exit_code: exit_code(first),
+        stdout: None,
+        stderr: None,
+    };
+
+    while let Some(line) = lines.next() {
+        if let Some(command) = line
+            .strip_prefix(messages.command_failed)
+            .or_else(|| line.strip_prefix(messages.command))
+            .and_then(|rest| rest.strip_prefix(": `"))
+        {
+            if parsed.command.is_none() {
+                let mut command = command.to_owned();
+                while !command.ends_with('`') {
+                    let Some(line) = lines.next() else {
+                        break;
+                    };
+                    match command.strip_suffix(WRAP_CONTINUATION) {
+                        // Long commands may be soft-wrapped onto multiple lines; unwrap them.
+                        Some(unwrapped) => {
+                            command.truncate(unwrapped.len());
+                            command.push(' ');
+                            command.push_str(line.strip_prefix(WRAP_INDENT).unwrap_or(line));
+                        }
+                        // Otherwise, the command contains a newline.
+                        None => {
+                            command.push('\n');
+                            command.push_str(line);
+                        }
+                    }
+                }
+                command.pop();
+                parsed.command = Some(command);
+            }
+        } else if let Some(section) = section_header(line, &messages) {
+            let mut text = Vec::new();
+            while let Some(line) = lines.next_if(|line| line.starts_with(OUTPUT_INDENT)) {
+                text.push(&line[OUTPUT_INDENT.len()..]);
+            }
+            let text = (!text.is_empty()).then(|| text.join("\n"));
+            match section {
+                Section::Stdout => parsed.stdout = text,
+                Section::Stderr => parsed.stderr = text,
+            }
+        } else if parsed.exit_code.is_none() {
+            parsed.exit_code = exit_code(line);
+        }
+    }
+
+    Some(parsed)
+}
+
+/// The end of a line of a soft-wrapped command, before the rest of the command on the next line.
+const WRAP_CONTINUATION: &str = " \\";
+
+/// The indentation of the continuation lines of a soft-wrapped command.
+const WRAP_INDENT: &str = "  ";
+
+enum Section {
+    Stdout,
+    Stderr,
+}
+
+/// Parse a line like `Stdout:` into the section it starts.
+fn section_header(line: &str, messages: &Messages) -> Option<Section> {
+    let header = line.strip_suffix(':')?;
+    if header == messages.stdout {
+        Some(Section::Stdout)
+    } else if header == messages.stderr {
+        Some(Section::Stderr)
+    } else {
+        None
+    }
+}
+
+/// The text between the first pair of backticks in `line`.
+fn quoted(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once('`')?;
+    let (quoted, _) = rest.split_once('`')?;
+    Some(quoted)
+}
+
+/// Parse an exit code from a line containing a displayed [`std::process::ExitStatus`], like
+/// `exit status: 1` (on Unix) or `exit code: 1` (on Windows).
+fn exit_code(line: &str) -> Option<i32> {
+    let (_, rest) = line
+        .split_once("exit status: ")
+        .or_else(|| line.split_once("exit code: "))?;
+    let digits = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '-'))
+        .map_or(rest, |end| &rest[..end]);
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::AlreadyRunningError;
+    use crate::CaptureError;
+    use crate::CircuitOpenError;
+    use crate::CommandExt;
+    use crate::Error;
+    use crate::OutputConversionError;
+    use crate::StdinError;
+    use crate::Utf8ProgramAndArgs;
+    use crate::WaitError;
+
+    fn parse(error: impl std::fmt::Display) -> ParsedErrorMessage {
+        let rendered = error.to_string();
+        match parse_rendered(&rendered) {
+            Some(parsed) => parsed,
+            None => panic!("Failed to parse rendered error:\n{rendered}"),
+        }
+    }
+
+    fn displayed() -> Utf8ProgramAndArgs {
+        Utf8ProgramAndArgs::from(&*Command::new("cargo").arg("build"))
+    }
+
+    fn io_error() -> std::io::Error {
+        std::io::Error::other("Input/output error")
+    }
+
+    fn output_error() -> Error {
+        Command::new("sh")
+            .args(["-c", "echo puppy; echo doggy; echo kitty >&2; exit 3"])
+            .output_checked()
+            .unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_output_error() {
+        let parsed = parse(output_error());
+        assert_eq!(parsed.program(), "sh");
+        assert_eq!(
+            parsed.command(),
+            Some("sh -c 'echo puppy; echo doggy; echo kitty >&2; exit 3'")
+        );
+        assert_eq!(parsed.exit_code(), Some(3));
+        assert_eq!(parsed.stdout(), Some("puppy\ndoggy"));
+        assert_eq!(parsed.stderr(), Some("kitty"));
+    }
+
+    #[test]
+    fn test_parse_wrapped_output_error() {
+        let Error::Output(error) = output_error() else {
+            unreachable!()
+        };
+        let rendered = error.with_wrap_width(Some(20)).to_string();
+        assert!(rendered.contains("`sh \\\n  -c \\\n"), "{rendered}");
+        let parsed = parse(rendered);
+        assert_eq!(parsed.exit_code(), Some(3));
+        assert_eq!(
+            parsed.command(),
+            Some("sh -c 'echo puppy; echo doggy; echo kitty >&2; exit 3'")
+        );
+        assert_eq!(parsed.stderr(), Some("kitty"));
+    }
+
+    #[test]
+    fn test_parse_exec_error() {
+        let parsed = parse(Command::new("ooga booga").output_checked().unwrap_err());
+        assert_eq!(parsed.program(), "'ooga booga'");
+        assert_eq!(parsed.command(), None);
+        assert_eq!(parsed.exit_code(), None);
+    }
+
+    #[test]
+    fn test_parse_io_errors() {
+        for error in [
+            Error::from(WaitError::new(displayed(), io_error())),
+            Error::from(CaptureError::new(displayed(), io_error())),
+            Error::from(StdinError::new(displayed(), io_error())),
+        ] {
+            let parsed = parse(error);
+            assert_eq!(parsed.program(), "cargo");
+            assert_eq!(parsed.exit_code(), None);
+            assert_eq!(parsed.stdout(), None);
+        }
+    }
+
+    #[test]
+    fn test_parse_conversion_error() {
+        let parsed = parse(OutputConversionError::new(
+            displayed(),
+            Box::new("invalid utf-8"),
+        ));
+        assert_eq!(parsed.program(), "cargo");
+    }
+
+    #[test]
+    fn test_parse_timeout_error() {
+        let parsed = parse(
+            Command::new("sh")
+                .args(["-c", "echo Connecting...; exec sleep 10"])
+                .output_checked_timeout(Duration::from_millis(100))
+                .unwrap_err(),
+        );
+        assert_eq!(parsed.program(), "sh");
+        assert_eq!(
+            parsed.command(),
+            Some("sh -c 'echo Connecting...; exec sleep 10'")
+        );
+        assert_eq!(parsed.exit_code(), None);
+        assert_eq!(parsed.stdout(), Some("Connecting..."));
+    }
+
+    #[test]
+    fn test_parse_circuit_open_error() {
+        let Error::Output(last_error) = output_error() else {
+            unreachable!()
+        };
+        let parsed = parse(CircuitOpenError::new(
+            displayed(),
+            Arc::new(last_error),
+            3,
+            Duration::from_secs(60),
+            Duration::from_secs(30),
+        ));
+        assert_eq!(parsed.program(), "cargo");
+        assert_eq!(parsed.exit_code(), Some(3));
+        assert_eq!(parsed.stderr(), Some("kitty"));
+    }
+
+    #[test]
+    fn test_parse_already_running_error() {
+        let parsed = parse(AlreadyRunningError::new(
+            displayed(),
+            "cargo build".to_owned(),
+            Some(1234),
+            SystemTime::now(),
+        ));
+        assert_eq!(parsed.program(), "cargo");
+        assert_eq!(parsed.exit_code(), None);
+    }
+
+    #[cfg(feature = "test-helpers")]
+    #[test]
+    fn test_parse_unexpected_success() {
+        let parsed = parse(
+            Command::new("sh")
+                .args(["-c", "echo puppy"])
+                .expect_failure()
+                .unwrap_err(),
+        );
+        assert_eq!(parsed.program(), "sh");
+        assert_eq!(parsed.command(), Some("sh -c 'echo puppy'"));
+        assert_eq!(parsed.exit_code(), Some(0));
+        assert_eq!(parsed.stdout(), Some("puppy"));
+    }
+
+    #[test]
+    fn test_parse_with_messages() {
+        let messages = Messages::default()
+            .with_command_failed("Befehl fehlgeschlagen")
+            .with_stderr("Standardfehlerausgabe");
+        let rendered = messages.display(&output_error()).to_string();
+        assert!(
+            rendered.contains("\nStandardfehlerausgabe:\n"),
+            "{rendered}"
+        );
+
+        let parsed = messages.scope(|| parse(&rendered));
+        assert_eq!(
+            parsed.command(),
+            Some("sh -c 'echo puppy; echo doggy; echo kitty >&2; exit 3'")
+        );
+        assert_eq!(parsed.stderr(), Some("kitty"));
+    }
+
+    #[test]
+    fn test_parse_not_an_error() {
+        assert_eq!(parse_rendered("puppy doggy"), None);
+        assert_eq!(parse_rendered(""), None);
+    }
+}
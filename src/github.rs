@@ -0,0 +1,265 @@
+use std::fmt::Write;
+
+use crate::command_display::guarded;
+use crate::duration::round_duration;
+use crate::messages::messages;
+use crate::Error;
+
+/// The maximum number of lines of stderr included in a GitHub Actions annotation.
+const STDERR_TAIL_LINES: usize = 10;
+
+/// The maximum number of bytes of stderr included in a GitHub Actions annotation.
+///
+/// GitHub truncates long annotations, so a few very long lines shouldn't crowd out the command.
+const STDERR_TAIL_BYTES: usize = 4 * 1024;
+
+impl Error {
+    /// Render this error as a GitHub Actions [`::error` workflow command][error-command].
+    ///
+    /// The annotation's title contains the program name and exit status, and its message contains
+    /// the command that failed and the last few lines of its stderr. The result is a single line,
+    /// with special characters escaped according to GitHub's rules.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo 100% broken >&2; exit 1"])
+    ///     .output_checked()
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_github_annotation(),
+    ///     "::error title=`sh` failed%3A exit status%3A 1::\
+    ///     Command failed: `sh -c 'echo 100%25 broken >&2; exit 1'`%0A\
+    ///     Stderr:%0A\
+    ///     100%25 broken"
+    /// );
+    /// ```
+    ///
+    /// [error-command]: https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions#setting-an-error-message
+    pub fn to_github_annotation(&self) -> String {
+        format!(
+            "::error title={}::{}",
+            escape_property(&self.github_title()),
+            escape_data(&self.github_message()),
+        )
+    }
+
+    /// Render this error's complete message in a collapsible GitHub Actions log
+    /// [group][group-command].
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo puppy; exit 1"])
+    ///     .output_checked()
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_github_group(),
+    ///     indoc!(
+    ///         "::group::`sh` failed: exit status: 1
+    ///         `sh` failed: exit status: 1
+    ///         Command failed: `sh -c 'echo puppy; exit 1'`
+    ///         Stdout:
+    ///           puppy
+    ///         ::endgroup::"
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// [group-command]: https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions#grouping-log-lines
+    pub fn to_github_group(&self) -> String {
+        format!(
+            "::group::{}\n{self}\n::endgroup::",
+            escape_data(&self.github_title())
+        )
+    }
+
+    fn github_title(&self) -> String {
+        let messages = messages();
+        match self {
+            Error::Exec(error) => format!(
+                "{} `{}`",
+                messages.failed_to_execute,
+                guarded(&*error.command).program_quoted()
+            ),
+            Error::Wait(error) => format!(
+                "{} `{}`",
+                messages.failed_to_wait_for,
+                guarded(&*error.command).program_quoted()
+            ),
+            Error::Output(error) => format!(
+                "`{}` {}: {}",
+                guarded(&*error.command).program_quoted(),
+                messages.failed,
+                error.output.status()
+            ),
+            Error::Conversion(error) => format!(
+                "{} `{}` {}",
+                messages.failed_to_convert,
+                guarded(&*error.command).program_quoted(),
+                messages.output
+            ),
+            Error::CircuitOpen(error) => format!(
+                "`{}` {}",
+                guarded(&*error.command).program_quoted(),
+                messages.was_short_circuited
+            ),
+            Error::Timeout(error) => format!(
+                "`{}` {} {:?}",
+                guarded(&*error.command).program_quoted(),
+                messages.timed_out_after,
+                round_duration(error.timeout)
+            ),
+            Error::AlreadyRunning(error) => format!(
+                "`{}` {}",
+                guarded(&*error.command).program_quoted(),
+                messages.is_already_running
+            ),
+            Error::Capture(error) => format!(
+                "{} `{}`",
+                messages.failed_to_capture_output_of,
+                guarded(&*error.command).program_quoted()
+            ),
+            Error::Stdin(error) => format!(
+                "{} `{}`",
+                messages.failed_to_write_to_stdin_of,
+                guarded(&*error.command).program_quoted()
+            ),
+        }
+    }
+
+    fn github_message(&self) -> String {
+        match self {
+            Error::Output(error) => {
+                let mut message = format!(
+                    "{}: `{}`",
+                    messages().command_failed,
+                    guarded(&*error.command)
+                );
+                if let Some(user_error) = &error.user_error {
+                    message = format!("{user_error}\n{message}");
+                }
+                let stderr = error.output.stderr();
+                let stderr = stderr.trim();
+                if !stderr.is_empty() {
+                    let (omitted, tail) = stderr_tail(stderr);
+                    let _ = write!(message, "\n{}:", messages().stderr);
+                    if omitted.ends_with('\n') {
+                        let _ = write!(message, "\n[{} lines omitted]", omitted.lines().count());
+                    } else if !omitted.is_empty() {
+                        let _ = write!(message, "\n[{} bytes omitted]", omitted.len());
+                    }
+                    for line in tail.lines() {
+                        message.push('\n');
+                        message.push_str(line);
+                    }
+                }
+                message
+            }
+            error => error.to_string(),
+        }
+    }
+}
+
+/// Split `stderr` into the part omitted from an annotation and the tail that's included: at most
+/// [`STDERR_TAIL_LINES`] lines and [`STDERR_TAIL_BYTES`] bytes.
+///
+/// The tail starts at the beginning of a line, unless the last line alone is too long.
+fn stderr_tail(stderr: &str) -> (&str, &str) {
+    let mut start = stderr
+        .rmatch_indices('\n')
+        .nth(STDERR_TAIL_LINES - 1)
+        .map_or(0, |(newline, _)| newline + 1);
+    if stderr.len() - start > STDERR_TAIL_BYTES {
+        start = stderr.len() - STDERR_TAIL_BYTES;
+        while !stderr.is_char_boundary(start) {
+            start += 1;
+        }
+        if let Some(newline) = stderr[start..].find('\n') {
+            start += newline + 1;
+        }
+    }
+    stderr.split_at(start)
+}
+
+/// Escape data (the message) for a GitHub Actions workflow command.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value (like the title) for a GitHub Actions workflow command.
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CommandExt;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_escape_data() {
+        assert_eq!(
+            escape_data("100%\r\nno: problem, really"),
+            "100%25%0D%0Ano: problem, really"
+        );
+    }
+
+    #[test]
+    fn test_stderr_tail() {
+        assert_eq!(stderr_tail("puppy\ndoggy"), ("", "puppy\ndoggy"));
+
+        let lines = (1..=12).map(|i| format!("{i}\n")).collect::<String>();
+        let (omitted, tail) = stderr_tail(lines.trim());
+        assert_eq!(omitted, "1\n2\n");
+        assert_eq!(tail.lines().count(), STDERR_TAIL_LINES);
+
+        // Long lines are dropped until the tail fits.
+        let long = "x".repeat(STDERR_TAIL_BYTES / 2);
+        let stderr = format!("{long}\n{long}\n{long}\nkitty");
+        let (omitted, tail) = stderr_tail(&stderr);
+        assert_eq!(omitted, format!("{long}\n{long}\n"));
+        assert_eq!(tail, format!("{long}\nkitty"));
+
+        // A single line that's too long is cut at a character boundary.
+        let stderr = "é".repeat(STDERR_TAIL_BYTES);
+        let (omitted, tail) = stderr_tail(&stderr);
+        assert_eq!(omitted.len() + tail.len(), stderr.len());
+        assert!(tail.len() <= STDERR_TAIL_BYTES);
+        assert!(!omitted.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_github_message_byte_cap() {
+        let err = std::process::Command::new("sh")
+            .args(["-c", "head -c 100000 /dev/zero | tr '\\0' x >&2; exit 1"])
+            .output_checked()
+            .unwrap_err();
+        let message = err.github_message();
+        assert!(
+            message.contains("\nStderr:\n[95904 bytes omitted]\n"),
+            "{message}"
+        );
+        assert!(message.len() < 2 * STDERR_TAIL_BYTES);
+    }
+
+    #[test]
+    fn test_escape_property() {
+        assert_eq!(
+            escape_property("100%\r\nno: problem, really"),
+            "100%25%0D%0Ano%3A problem%2C really"
+        );
+    }
+}
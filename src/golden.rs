@@ -0,0 +1,301 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use similar::TextDiff;
+use utf8_command::Utf8Output;
+
+use crate::failure_log::log_failure;
+use crate::Error;
+use crate::OutputContext;
+use crate::OutputError;
+
+/// The number of unchanged lines shown around each change in a diff.
+const CONTEXT_LINES: usize = 3;
+
+/// The maximum number of hunks shown in a diff.
+const MAX_HUNKS: usize = 8;
+
+/// A function applied to each line by a [`Normalizer`].
+type LineMap = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Normalizes command output before it's compared with expected output, so that details which
+/// change from run to run (like timestamps or temporary paths) don't cause spurious mismatches.
+///
+/// Used by [`OutputContext::expect_stdout_with`]. The same normalization is applied to both the
+/// expected and actual output.
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use command_error::Normalizer;
+/// let normalizer = Normalizer::new()
+///     .trim_trailing_whitespace()
+///     .map_lines(|line| match line.split_once(" took ") {
+///         Some((before, _duration)) => format!("{before} took [DURATION]"),
+///         None => line.to_owned(),
+///     });
+///
+/// assert_eq!(
+///     normalizer.normalize("build took 3.2s   \ntest took 1.1s\n"),
+///     "build took [DURATION]\ntest took [DURATION]\n"
+/// );
+/// ```
+///
+/// [`Normalizer::map_lines`] takes any function, so masks can be implemented with whichever
+/// pattern-matching library a test suite already uses (like `regex`).
+#[derive(Default)]
+pub struct Normalizer {
+    trim_trailing_whitespace: bool,
+    line_maps: Vec<LineMap>,
+}
+
+impl Normalizer {
+    /// Construct a new [`Normalizer`] which doesn't change anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove whitespace from the end of each line.
+    pub fn trim_trailing_whitespace(mut self) -> Self {
+        self.trim_trailing_whitespace = true;
+        self
+    }
+
+    /// Replace each line with the result of `map`, after any previously added normalization.
+    pub fn map_lines(mut self, map: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.line_maps.push(Box::new(map));
+        self
+    }
+
+    /// Normalize `text`.
+    ///
+    /// Lines are normalized without their line endings, which are preserved (as `\n`).
+    pub fn normalize(&self, text: &str) -> String {
+        let mut normalized = String::with_capacity(text.len());
+        for line in text.split_inclusive('\n') {
+            let (line, newline) = match line.strip_suffix('\n') {
+                Some(line) => (line.strip_suffix('\r').unwrap_or(line), "\n"),
+                None => (line, ""),
+            };
+            let mut line = if self.trim_trailing_whitespace {
+                line.trim_end().to_owned()
+            } else {
+                line.to_owned()
+            };
+            for map in &self.line_maps {
+                line = map(&line);
+            }
+            normalized.push_str(&line);
+            normalized.push_str(newline);
+        }
+        normalized
+    }
+}
+
+impl std::fmt::Debug for Normalizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Normalizer")
+            .field("trim_trailing_whitespace", &self.trim_trailing_whitespace)
+            .field("line_maps", &self.line_maps.len())
+            .finish()
+    }
+}
+
+impl OutputContext<Utf8Output> {
+    /// Check that the command's stdout is exactly `expected`.
+    ///
+    /// If it isn't, an [`OutputError`] is returned with a unified diff from the expected output
+    /// to the actual output as its message, along with the usual command context:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::OutputContext;
+    /// let err = Command::new("printf")
+    ///     .arg("puppy\\ndoggy\\n")
+    ///     .output_checked_as(|context: OutputContext<_>| context.expect_stdout("puppy\nkitty\n"))
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     indoc!(
+    ///         "`printf` failed: stdout didn't match the expected output:
+    ///         --- expected
+    ///         +++ actual
+    ///         @@ -1,2 +1,2 @@
+    ///          puppy
+    ///         -kitty
+    ///         +doggy
+    ///         exit status: 0
+    ///         Command failed: `printf 'puppy\\ndoggy\\n'`
+    ///         Stdout:
+    ///           puppy
+    ///           doggy"
+    ///     )
+    /// );
+    /// ```
+    ///
+    /// At most 8 hunks of the diff are shown.
+    ///
+    /// Only available with the `diff` feature.
+    pub fn expect_stdout(&self, expected: &str) -> Result<(), Error> {
+        self.expect_stdout_with(expected, &Normalizer::new())
+    }
+
+    /// Check that the command's stdout is `expected`, after both are normalized with
+    /// `normalizer`.
+    ///
+    /// See [`OutputContext::expect_stdout`].
+    pub fn expect_stdout_with(&self, expected: &str, normalizer: &Normalizer) -> Result<(), Error> {
+        let expected = normalizer.normalize(expected);
+        let actual = normalizer.normalize(&self.output.stdout);
+        if expected == actual {
+            return Ok(());
+        }
+        let message = format!(
+            "stdout didn't match the expected output:\n{}",
+            unified_diff(&expected, &actual)
+        );
+        let error = OutputError::new(
+            dyn_clone::clone_box(&*self.command),
+            Box::new(self.output.clone()),
+        )
+        .with_message(Box::new(message));
+        log_failure(&error);
+        Err(Error::from(error))
+    }
+
+    /// Assert that the command's stdout matches the contents of the golden file at `path`, after
+    /// both are normalized with `normalizer`.
+    ///
+    /// Panics with the error from [`OutputContext::expect_stdout_with`] if it doesn't, or if the
+    /// file can't be read.
+    ///
+    /// ```no_run
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Normalizer;
+    /// # use command_error::OutputContext;
+    /// Command::new("my-tool")
+    ///     .arg("--help")
+    ///     .output_checked_as(|context: OutputContext<_>| {
+    ///         context.assert_stdout_matches_file(
+    ///             "tests/golden/help.txt",
+    ///             &Normalizer::new().trim_trailing_whitespace(),
+    ///         );
+    ///         Ok::<_, command_error::Error>(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    #[track_caller]
+    pub fn assert_stdout_matches_file(&self, path: impl AsRef<Path>, normalizer: &Normalizer) {
+        let path = path.as_ref();
+        let expected = match std::fs::read_to_string(path) {
+            Ok(expected) => expected,
+            Err(error) => panic!("Failed to read golden file {}: {error}", path.display()),
+        };
+        if let Err(error) = self.expect_stdout_with(&expected, normalizer) {
+            panic!("{error}\nGolden file: {}", path.display());
+        }
+    }
+}
+
+/// Render a unified diff from `old` to `new`, with at most [`MAX_HUNKS`] hunks.
+fn unified_diff(old: &str, new: &str) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let mut unified = diff.unified_diff();
+    unified.context_radius(CONTEXT_LINES);
+
+    let mut rendered = String::from("--- expected\n+++ actual\n");
+    let mut hunks = unified.iter_hunks();
+    for hunk in hunks.by_ref().take(MAX_HUNKS) {
+        // Writing to a `String` can't fail.
+        let _ = write!(rendered, "{hunk}");
+    }
+    let omitted = hunks.count();
+    if omitted > 0 {
+        let _ = writeln!(
+            rendered,
+            "... ({omitted} more hunk{} omitted)",
+            if omitted == 1 { "" } else { "s" }
+        );
+    }
+    // Each hunk line ends with a newline, but the diff is followed by more of the error message.
+    rendered.pop();
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nl\nm\n";
+        assert_eq!(
+            unified_diff(old, new),
+            indoc!(
+                "--- expected
+                +++ actual
+                @@ -1,5 +1,5 @@
+                 a
+                -b
+                +B
+                 c
+                 d
+                 e
+                @@ -8,5 +8,5 @@
+                 h
+                 i
+                 j
+                -k
+                 l
+                +m"
+            )
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_empty() {
+        assert_eq!(
+            unified_diff("", "puppy\n"),
+            "--- expected\n+++ actual\n@@ -0,0 +1 @@\n+puppy"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_missing_newline() {
+        assert_eq!(
+            unified_diff("puppy", "puppy\n"),
+            indoc!(
+                "--- expected
+                +++ actual
+                @@ -1 +1 @@
+                -puppy
+                \\ No newline at end of file
+                +puppy"
+            )
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_max_hunks() {
+        let old = (0..100).map(|i| format!("{i}\n")).collect::<String>();
+        let new = (0..100)
+            .map(|i| {
+                if i % 10 == 0 {
+                    "changed\n".to_owned()
+                } else {
+                    format!("{i}\n")
+                }
+            })
+            .collect::<String>();
+        let diff = unified_diff(&old, &new);
+        assert_eq!(diff.matches("\n@@ ").count(), MAX_HUNKS);
+        assert!(diff.ends_with("\n... (2 more hunks omitted)"), "{diff}");
+    }
+}
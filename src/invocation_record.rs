@@ -0,0 +1,355 @@
+use std::fmt::Write;
+use std::process::Output;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::SystemTime;
+
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Utf8ProgramAndArgs;
+
+/// The number of bytes of each output stream kept in an [`InvocationRecord`].
+const RECORDED_OUTPUT_BYTES: usize = 4096;
+
+/// The next [`InvocationRecord::id`].
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A complete record of a command invocation, for audit logging.
+///
+/// Produced by [`CommandExt::output_checked_recorded`] whether the command succeeds or fails.
+/// Records can be written out with [`InvocationRecord::to_json`]:
+///
+/// ```
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// let (result, record) = Command::new("sh")
+///     .args(["-c", "echo puppy; exit 1"])
+///     .output_checked_recorded("deploy-bot");
+/// assert!(result.is_err());
+/// assert_eq!(record.label(), "deploy-bot");
+/// assert_eq!(record.program(), "sh");
+/// assert_eq!(record.exit_code(), Some(1));
+/// assert_eq!(record.stdout(), "puppy\n");
+/// assert!(record.to_json().starts_with(r#"{"id":"#));
+/// ```
+///
+/// Environment variable values are omitted, since they often contain secrets; only the names of
+/// the variables which were set or removed are recorded. At most 4 KiB of each output stream is
+/// kept, decoded as UTF-8 lossily, along with the full byte counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvocationRecord {
+    pub(crate) id: u64,
+    pub(crate) label: String,
+    pub(crate) program: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) current_dir: Option<String>,
+    pub(crate) env_clear: bool,
+    pub(crate) env: Vec<String>,
+    pub(crate) started: SystemTime,
+    pub(crate) duration: Duration,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) signal: Option<i32>,
+    pub(crate) stdout_bytes: usize,
+    pub(crate) stderr_bytes: usize,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+    pub(crate) error: Option<String>,
+}
+
+impl InvocationRecord {
+    /// Start a record of `command` being run now, on behalf of `label`.
+    ///
+    /// The displayed command's program and arguments are moved into the record rather than
+    /// copied.
+    pub(crate) fn start(label: &str, command: Utf8ProgramAndArgs) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            label: label.to_owned(),
+            program: command.program,
+            args: command.args,
+            current_dir: command.current_dir,
+            env_clear: command.env_clear,
+            env: command.envs.into_iter().map(|(key, _value)| key).collect(),
+            started: SystemTime::now(),
+            duration: Duration::ZERO,
+            exit_code: None,
+            signal: None,
+            stdout_bytes: 0,
+            stderr_bytes: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+            error: None,
+        }
+    }
+
+    /// Record the command's exit status and output.
+    pub(crate) fn with_output(mut self, output: &Output) -> Self {
+        self.exit_code = output.status.code();
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            self.signal = output.status.signal();
+        }
+        self.stdout_bytes = output.stdout.len();
+        self.stderr_bytes = output.stderr.len();
+        self.stdout = truncated(&output.stdout);
+        self.stderr = truncated(&output.stderr);
+        self
+    }
+
+    /// Record how long the command took and the error it failed with, if any.
+    pub(crate) fn finish(mut self, duration: Duration, error: Option<String>) -> Self {
+        self.duration = duration;
+        self.error = error;
+        self
+    }
+
+    /// An identifier for this invocation, unique within the current process.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The caller-supplied label for who (or what) asked for the command to be run.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The program which ran.
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// The program's arguments.
+    ///
+    /// Like [`Utf8ProgramAndArgs`], only the first 1024 arguments are recorded.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// The program's working directory, if it was set.
+    pub fn current_dir(&self) -> Option<&str> {
+        self.current_dir.as_deref()
+    }
+
+    /// Whether the program's environment was cleared.
+    pub fn env_clear(&self) -> bool {
+        self.env_clear
+    }
+
+    /// The names of the environment variables which were set or removed for the program.
+    pub fn env(&self) -> &[String] {
+        &self.env
+    }
+
+    /// When the command was started.
+    pub fn started(&self) -> SystemTime {
+        self.started
+    }
+
+    /// How long the command took to run, including reading its output.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The program's exit code, if it exited normally.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// The signal which killed the program, if it was killed by a signal (on Unix).
+    pub fn signal(&self) -> Option<i32> {
+        self.signal
+    }
+
+    /// The total number of bytes the program wrote to stdout.
+    pub fn stdout_bytes(&self) -> usize {
+        self.stdout_bytes
+    }
+
+    /// The total number of bytes the program wrote to stderr.
+    pub fn stderr_bytes(&self) -> usize {
+        self.stderr_bytes
+    }
+
+    /// The start of the program's stdout, up to 4 KiB.
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
+    /// The start of the program's stderr, up to 4 KiB.
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
+
+    /// The error message the command failed with, if it failed.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Render this record as a single line of JSON.
+    ///
+    /// The keys are `id`, `label`, `program`, `args`, `current_dir`, `env_clear`, `env`,
+    /// `started_unix_ms`, `duration_ms`, `exit_code`, `signal`, `stdout_bytes`, `stderr_bytes`,
+    /// `stdout`, `stderr`, and `error`, always in that order. Missing values are `null`. This
+    /// schema is the same for successful and failed commands, and only changes in a
+    /// semver-breaking release.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        // Writing to a `String` can't fail.
+        let _ = self.write_json(&mut json);
+        json
+    }
+
+    fn write_json(&self, json: &mut String) -> std::fmt::Result {
+        write!(json, r#"{{"id":{},"label":"#, self.id)?;
+        write_json_string(json, &self.label)?;
+        json.push_str(r#","program":"#);
+        write_json_string(json, &self.program)?;
+        json.push_str(r#","args":"#);
+        write_json_strings(json, &self.args)?;
+        json.push_str(r#","current_dir":"#);
+        match &self.current_dir {
+            Some(current_dir) => write_json_string(json, current_dir)?,
+            None => json.push_str("null"),
+        }
+        write!(json, r#","env_clear":{},"env":"#, self.env_clear)?;
+        write_json_strings(json, &self.env)?;
+        write!(
+            json,
+            r#","started_unix_ms":{},"duration_ms":{},"exit_code":{},"signal":{}"#,
+            self.started
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            self.duration.as_millis(),
+            JsonOption(self.exit_code),
+            JsonOption(self.signal),
+        )?;
+        write!(
+            json,
+            r#","stdout_bytes":{},"stderr_bytes":{},"stdout":"#,
+            self.stdout_bytes, self.stderr_bytes
+        )?;
+        write_json_string(json, &self.stdout)?;
+        json.push_str(r#","stderr":"#);
+        write_json_string(json, &self.stderr)?;
+        json.push_str(r#","error":"#);
+        match &self.error {
+            Some(error) => write_json_string(json, error)?,
+            None => json.push_str("null"),
+        }
+        json.push('}');
+        Ok(())
+    }
+}
+
+/// Decode at most [`RECORDED_OUTPUT_BYTES`] of `output` as UTF-8, lossily.
+fn truncated(output: &[u8]) -> String {
+    String::from_utf8_lossy(&output[..output.len().min(RECORDED_OUTPUT_BYTES)]).into_owned()
+}
+
+/// A number or `null`.
+struct JsonOption(Option<i32>);
+
+impl std::fmt::Display for JsonOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(value) => write!(f, "{value}"),
+            None => f.write_str("null"),
+        }
+    }
+}
+
+/// Write a JSON array of strings.
+fn write_json_strings(json: &mut String, strings: &[String]) -> std::fmt::Result {
+    json.push('[');
+    for (i, string) in strings.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        write_json_string(json, string)?;
+    }
+    json.push(']');
+    Ok(())
+}
+
+/// Write a quoted and escaped JSON string.
+fn write_json_string(json: &mut String, string: &str) -> std::fmt::Result {
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32)?,
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::process::Command;
+    use std::process::ExitStatus;
+
+    fn record() -> InvocationRecord {
+        let mut command = Command::new("deploy");
+        command
+            .args(["--env", "prod \"east\""])
+            .current_dir("/srv")
+            .env("TOKEN", "secret");
+        InvocationRecord {
+            id: 7,
+            started: SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            ..InvocationRecord::start("ci\tbot", Utf8ProgramAndArgs::from(&command))
+        }
+        .with_output(&Output {
+            status: ExitStatus::default(),
+            stdout: b"ok\n".to_vec(),
+            stderr: vec![b'x'; RECORDED_OUTPUT_BYTES + 1],
+        })
+        .finish(Duration::from_millis(1500), None)
+    }
+
+    #[test]
+    fn test_to_json_schema() {
+        let record = record();
+        assert_eq!(
+            record.to_json(),
+            format!(
+                concat!(
+                    r#"{{"id":7,"label":"ci\tbot","program":"deploy","args":["--env","prod \"east\""],"#,
+                    r#""current_dir":"/srv","env_clear":false,"env":["TOKEN"],"#,
+                    r#""started_unix_ms":1700000000123,"duration_ms":1500,"exit_code":0,"signal":null,"#,
+                    r#""stdout_bytes":3,"stderr_bytes":4097,"stdout":"ok\n","stderr":"{}","error":null}}"#,
+                ),
+                "x".repeat(RECORDED_OUTPUT_BYTES)
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_json_failure_schema() {
+        let record = InvocationRecord::start("", Utf8ProgramAndArgs::from(&Command::new("x")))
+            .finish(Duration::ZERO, Some("Failed to execute `x`".to_owned()));
+        let json = record.to_json();
+        assert!(json.contains(r#""current_dir":null,"env_clear":false,"env":[],"#));
+        assert!(json.ends_with(
+            r#""exit_code":null,"signal":null,"stdout_bytes":0,"stderr_bytes":0,"stdout":"","stderr":"","error":"Failed to execute `x`"}"#
+        ));
+    }
+
+    #[test]
+    fn test_write_json_string() {
+        let mut json = String::new();
+        write_json_string(&mut json, "a\"b\\c\u{1}").unwrap();
+        assert_eq!(json, r#""a\"b\\c\u0001""#);
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::sync::PoisonError;
+
+#[cfg(doc)]
+use crate::CommandExt;
+#[cfg(doc)]
+use crate::OutputContext;
+#[cfg(doc)]
+use crate::OutputError;
+
+/// An error message which is only built when it's displayed.
+///
+/// This is useful for messages which are expensive to build, like a summary of a large data
+/// structure. The message is built at most once, the first time it's displayed (with [`Display`]
+/// or [`Debug`]), and cached after that.
+///
+/// A [`LazyMessage`] can be used anywhere an error message is accepted, like in the `Err` values
+/// returned to [`CommandExt::output_checked_with`], or with [`OutputContext::error_msg_with`]:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::sync::atomic::AtomicUsize;
+/// # use std::sync::atomic::Ordering;
+/// # use command_error::LazyMessage;
+/// static BUILT: AtomicUsize = AtomicUsize::new(0);
+/// let message = LazyMessage::new(|| {
+///     BUILT.fetch_add(1, Ordering::SeqCst);
+///     "expensive summary".to_owned()
+/// });
+/// assert_eq!(BUILT.load(Ordering::SeqCst), 0);
+///
+/// assert_eq!(message.to_string(), "expensive summary");
+/// assert_eq!(format!("{message:?}"), r#"LazyMessage("expensive summary")"#);
+/// assert_eq!(BUILT.load(Ordering::SeqCst), 1);
+/// ```
+pub struct LazyMessage {
+    message: OnceLock<String>,
+    build: Mutex<Option<Box<Build>>>,
+}
+
+/// Builds a [`LazyMessage`].
+type Build = dyn FnOnce() -> String + Send;
+
+impl LazyMessage {
+    /// Construct a new [`LazyMessage`] which calls `build` to build the message when it's first
+    /// displayed.
+    pub fn new(build: impl FnOnce() -> String + Send + 'static) -> Self {
+        Self {
+            message: OnceLock::new(),
+            build: Mutex::new(Some(Box::new(build))),
+        }
+    }
+
+    /// Get the message, building it if it hasn't been built yet.
+    pub fn get(&self) -> &str {
+        self.message.get_or_init(|| {
+            let build = self
+                .build
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            // `OnceLock` runs this at most once, so `build` is always present.
+            build.map(|build| build()).unwrap_or_default()
+        })
+    }
+}
+
+impl Debug for LazyMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LazyMessage").field(&self.get()).finish()
+    }
+}
+
+impl Display for LazyMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_assertions::assert_impl_all;
+
+    assert_impl_all!(LazyMessage: Send, Sync);
+}
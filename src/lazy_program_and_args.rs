@@ -0,0 +1,121 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::process::Command;
+
+use crate::utf8_program_and_args::write_command;
+use crate::utf8_program_and_args::MAX_DISPLAYED_ARGS;
+use crate::CommandDisplay;
+#[cfg(doc)]
+use crate::Utf8ProgramAndArgs;
+
+/// A program name and arguments borrowed from a [`Command`].
+///
+/// This displays the same way as [`Utf8ProgramAndArgs`], but doesn't copy the command's program
+/// and arguments; they're decoded as UTF-8 (lossily) as the command is displayed. This is used
+/// for logging commands before they're run, so that a command with an enormous number of
+/// arguments isn't held in memory twice while it runs.
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::LazyProgramAndArgs;
+/// # use command_error::Utf8ProgramAndArgs;
+/// let mut command = Command::new("echo");
+/// command.arg("puppy doggy").current_dir("/puppy");
+/// assert_eq!(
+///     LazyProgramAndArgs::new(&command).to_string(),
+///     "cd /puppy && echo 'puppy doggy'"
+/// );
+/// assert_eq!(
+///     LazyProgramAndArgs::new(&command).to_string(),
+///     Utf8ProgramAndArgs::from(&command).to_string(),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct LazyProgramAndArgs<'a> {
+    command: &'a Command,
+}
+
+impl<'a> LazyProgramAndArgs<'a> {
+    /// Construct a new [`LazyProgramAndArgs`] borrowing from `command`.
+    pub fn new(command: &'a Command) -> Self {
+        Self { command }
+    }
+}
+
+impl<'a> From<&'a Command> for LazyProgramAndArgs<'a> {
+    fn from(command: &'a Command) -> Self {
+        Self::new(command)
+    }
+}
+
+impl Display for LazyProgramAndArgs<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_command(
+            f,
+            self.command
+                .get_current_dir()
+                .map(|path| path.to_string_lossy())
+                .as_deref(),
+            false,
+            self.command.get_envs().map(|(key, value)| {
+                (
+                    key.to_string_lossy(),
+                    value.map(|value| value.to_string_lossy()),
+                )
+            }),
+            &self.command.get_program().to_string_lossy(),
+            self.command.get_args().map(|arg| arg.to_string_lossy()),
+            0,
+        )
+    }
+}
+
+impl CommandDisplay for LazyProgramAndArgs<'_> {
+    fn program(&self) -> Cow<'_, str> {
+        self.command.get_program().to_string_lossy()
+    }
+
+    fn args(&self) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        Box::new(
+            self.command
+                .get_args()
+                .take(MAX_DISPLAYED_ARGS)
+                .map(|arg| arg.to_string_lossy()),
+        )
+    }
+
+    fn is_lossy(&self) -> bool {
+        let command = self.command;
+        command.get_program().to_str().is_none()
+            || command.get_args().any(|arg| arg.to_str().is_none())
+            || command
+                .get_current_dir()
+                .is_some_and(|path| path.to_str().is_none())
+            || command.get_envs().any(|(key, value)| {
+                key.to_str().is_none() || value.is_some_and(|value| value.to_str().is_none())
+            })
+    }
+
+    fn omitted_args(&self) -> usize {
+        self.command
+            .get_args()
+            .len()
+            .saturating_sub(MAX_DISPLAYED_ARGS)
+    }
+
+    fn to_command(&self) -> Command {
+        let mut command = Command::new(self.command.get_program());
+        command.args(self.command.get_args());
+        if let Some(current_dir) = self.command.get_current_dir() {
+            command.current_dir(current_dir);
+        }
+        for (key, value) in self.command.get_envs() {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+        command
+    }
+}
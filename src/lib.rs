@@ -27,6 +27,27 @@
 //! for transforming the output (for example, to parse command output as JSON while retaining
 //! information about the command that produced the output in the error message).
 //!
+//! ## Async code
+//!
+//! [`CommandExt`] methods are synchronous: they block the calling thread until the command
+//! finishes and its output is read. In async code, either use the async extension traits below,
+//! or run [`CommandExt`] methods on a blocking thread (like with `tokio::task::spawn_blocking`),
+//! so that capturing a large output (hundreds of megabytes can take a while to read) doesn't
+//! stall the other tasks on an async worker thread.
+//!
+//! With the `tokio` feature, `AsyncCommandExt` and `AsyncChildExt` provide async versions of the
+//! most common methods for `tokio::process::Command` and `tokio::process::Child`, with the same
+//! error messages. The `async-process` feature does the same for `async_process::Command` and
+//! `async_process::Child`, for use with `smol` and similar runtimes. The `process-wrap-tokio`
+//! feature implements them for `process_wrap::tokio::TokioCommandWrap`, to run commands in
+//! process groups or job objects.
+//!
+//! The async methods read output with the runtime's own `output` and `wait_with_output` methods,
+//! which yield to the scheduler whenever a read would block (and, with `tokio`, when the task's cooperative budget
+//! runs out). This crate doesn't have its own chunked capture for async code, so there's no way
+//! to configure the chunk size or how often a capture yields, and the streaming methods (like
+//! [`CommandExt::output_checked_with_backpressure`]) have no async versions.
+//!
 //! ## Enforcing use of `command_error`
 //!
 //! If you'd like to make sure that [`CommandExt`] methods are used instead of the plain
@@ -48,7 +69,7 @@
 //!
 //! [[disallowed-methods]]
 //! path = "std::process::Child::try_wait"
-//! reason = "Use command_error::ChildExt::try_wait_checked[_with]"
+//! reason = "Use command_error::TryWaitExt::try_wait_checked[_with]"
 //!
 //! [[disallowed-methods]]
 //! path = "std::process::Child::wait"
@@ -75,38 +96,262 @@ pub use try_wait_context::TryWaitContext;
 mod child_context;
 pub use child_context::ChildContext;
 
+mod supervisor;
+pub use supervisor::Supervisor;
+pub use supervisor::SupervisorAction;
+
+mod progress;
+pub use progress::Progress;
+pub(crate) use progress::ProgressReaders;
+
 mod output_like;
 pub use output_like::OutputLike;
 
+mod arc_output;
+pub use arc_output::ArcOutput;
+
+mod lossy_utf8_output;
+pub use lossy_utf8_output::LossyUtf8Output;
+
+mod normalized_utf8_output;
+pub use normalized_utf8_output::NormalizedUtf8Output;
+
+mod output_stream;
+pub use output_stream::OutputStream;
+
+mod stdio_config;
+pub use stdio_config::StdioConfig;
+pub use stdio_config::StdioKind;
+
+mod tty_output;
+pub use tty_output::TtyOutput;
+
+mod stream_timings;
+pub use stream_timings::StreamTimings;
+
 mod exec_error;
 pub use exec_error::ExecError;
 
+mod exec_failure;
+pub use exec_failure::ExecFailure;
+
+mod signal;
+pub use signal::exit_signal;
+pub use signal::signal_name;
+
+mod spawn_error_class;
+pub use spawn_error_class::classify_spawn_error;
+pub use spawn_error_class::SpawnErrorClass;
+
 mod output_error;
 pub use output_error::OutputError;
 
+mod diagnostic_rerun;
+pub use diagnostic_rerun::DiagnosticRerun;
+
+mod output_error_signature;
+pub use output_error_signature::OutputErrorSignature;
+
 mod output_conversion_error;
 pub use output_conversion_error::OutputConversionError;
 
 mod wait_error;
 pub use wait_error::WaitError;
 
+mod capture_error;
+pub use capture_error::CaptureError;
+
+mod stdin_error;
+pub use stdin_error::StdinError;
+
+mod already_running_error;
+pub use already_running_error::AlreadyRunningError;
+
+mod circuit_open_error;
+pub use circuit_open_error::CircuitOpenError;
+
+mod timeout_error;
+pub use timeout_error::Termination;
+pub use timeout_error::TimeoutError;
+
+#[cfg(feature = "test-helpers")]
+mod unexpected_success;
+#[cfg(feature = "test-helpers")]
+pub use unexpected_success::UnexpectedSuccess;
+
+#[cfg(feature = "diff")]
+mod golden;
+#[cfg(feature = "diff")]
+pub use golden::Normalizer;
+
 mod error;
 pub use error::Error;
 
+mod github;
+
+mod render;
+
+#[cfg(feature = "diagnostic-summary")]
+mod diagnostic_summary;
+#[cfg(feature = "diagnostic-summary")]
+pub use diagnostic_summary::DiagnosticSummary;
+
+mod dedup;
+
+mod exit;
+
+mod clock;
+pub use clock::Clock;
+#[cfg(feature = "test-helpers")]
+pub use clock::ManualClock;
+
 mod command_display;
 pub use command_display::CommandDisplay;
 
 mod utf8_program_and_args;
 pub use utf8_program_and_args::Utf8ProgramAndArgs;
 
+mod lazy_program_and_args;
+pub use lazy_program_and_args::LazyProgramAndArgs;
+
+#[cfg(feature = "which")]
+mod resolved_program_and_args;
+#[cfg(feature = "which")]
+pub use resolved_program_and_args::ResolvedProgramAndArgs;
+
 mod debug_display;
 pub(crate) use debug_display::DebugDisplay;
 
+mod messages;
+pub use messages::set_messages;
+pub use messages::DisplayWithMessages;
+pub use messages::Messages;
+
+pub mod format;
+pub use format::parse_rendered;
+pub use format::ParsedErrorMessage;
+
+mod env_redaction;
+pub use env_redaction::set_env_redaction;
+
+mod spawn_hook;
+pub use spawn_hook::set_spawn_hook;
+
+mod lazy_message;
+pub use lazy_message::LazyMessage;
+
+mod validator;
+pub use validator::Validator;
+pub use validator::Violation;
+
+mod circuit_breaker;
+pub use circuit_breaker::CircuitBreaker;
+
+mod deadline;
+pub use deadline::Deadline;
+
+mod rate_limiter;
+pub use rate_limiter::RateLimiter;
+
+mod single_flight;
+pub use single_flight::SingleFlight;
+
+#[cfg(unix)]
+mod chroot;
+
+#[cfg(unix)]
+mod pre_exec_guard;
+
+#[cfg(unix)]
+mod resource_limits;
+#[cfg(unix)]
+pub use resource_limits::ResourceLimits;
+
+mod spawner;
+pub use spawner::Spawner;
+pub use spawner::StdSpawner;
+
+#[cfg(all(feature = "posix-spawn", any(target_os = "linux", target_os = "macos")))]
+mod posix_spawner;
+#[cfg(all(feature = "posix-spawn", any(target_os = "linux", target_os = "macos")))]
+pub use posix_spawner::PosixSpawner;
+
+#[cfg(all(
+    feature = "seccomp",
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod seccomp_filter;
+#[cfg(all(
+    feature = "seccomp",
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub use seccomp_filter::SeccompAction;
+#[cfg(all(
+    feature = "seccomp",
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub use seccomp_filter::SeccompFilter;
+
+mod invocation_record;
+pub use invocation_record::InvocationRecord;
+
+mod record_replay;
+pub use record_replay::RecordReplay;
+pub use record_replay::RecordReplayMode;
+
 mod command_ext;
 pub use command_ext::CommandExt;
 
+mod dyn_command_ext;
+pub use dyn_command_ext::DynCommandExt;
+
 mod child_ext;
 pub use child_ext::ChildExt;
 
+mod try_wait_ext;
+pub use try_wait_ext::TryWaitExt;
+
+mod capture;
+
+mod line_prefixer;
+
+mod run;
+
+mod fallback;
+
+mod shell;
+pub use shell::shell;
+pub use shell::Shell;
+
+mod base64;
+
+mod completion;
+pub(crate) use completion::Started;
+
+mod failure_log;
+#[cfg(feature = "tracing")]
+pub use failure_log::log_failures;
+
+mod duration;
+
 #[cfg(feature = "process-wrap")]
 mod process_wrap;
+
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+mod async_ext;
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub use async_ext::AsyncChildExt;
+#[cfg(any(feature = "tokio", feature = "async-process"))]
+pub use async_ext::AsyncCommandExt;
+
+#[cfg(feature = "tokio")]
+mod tokio_process;
+
+#[cfg(feature = "process-wrap-tokio")]
+mod process_wrap_tokio;
+
+#[cfg(feature = "async-process")]
+mod async_process;
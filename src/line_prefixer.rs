@@ -0,0 +1,92 @@
+use std::io::Write;
+
+use crate::OutputStream;
+
+/// Splits a command's output streams into lines and writes each line to the current process's
+/// stdout or stderr, prefixed with a label.
+pub(crate) struct LinePrefixer<'a> {
+    label: &'a str,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl<'a> LinePrefixer<'a> {
+    pub(crate) fn new(label: &'a str) -> Self {
+        Self {
+            label,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        }
+    }
+
+    /// Write each complete line in `chunk`, buffering any incomplete line at the end.
+    pub(crate) fn write_chunk(&mut self, stream: OutputStream, chunk: &[u8]) {
+        let buffer = match stream {
+            OutputStream::Stdout => &mut self.stdout,
+            OutputStream::Stderr => &mut self.stderr,
+        };
+        buffer.extend_from_slice(chunk);
+        if let Some(end) = buffer.iter().rposition(|&byte| byte == b'\n') {
+            let lines = buffer.drain(..=end).collect::<Vec<_>>();
+            write_lines(self.label, stream, &lines);
+        }
+    }
+
+    /// Write any incomplete lines left over after the command has finished.
+    pub(crate) fn finish(self) {
+        for (stream, rest) in [
+            (OutputStream::Stdout, self.stdout),
+            (OutputStream::Stderr, self.stderr),
+        ] {
+            if !rest.is_empty() {
+                write_lines(self.label, stream, &rest);
+            }
+        }
+    }
+}
+
+/// Write `lines` to the stream corresponding to `stream`, prefixing each with `label`.
+fn write_lines(label: &str, stream: OutputStream, lines: &[u8]) {
+    let prefixed = prefix_lines(label, lines);
+    // Write the whole batch at once so lines from concurrent commands don't interleave mid-line.
+    // Errors are ignored (rather than panicking, like `print!`), since the output is still
+    // captured.
+    let _ = match stream {
+        OutputStream::Stdout => std::io::stdout().lock().write_all(&prefixed),
+        OutputStream::Stderr => std::io::stderr().lock().write_all(&prefixed),
+    };
+}
+
+/// Prefix each line in `lines` with `label`, ensuring the last line ends with a newline.
+fn prefix_lines(label: &str, lines: &[u8]) -> Vec<u8> {
+    let mut prefixed = Vec::with_capacity(lines.len());
+    for line in lines
+        .strip_suffix(b"\n")
+        .unwrap_or(lines)
+        .split(|&byte| byte == b'\n')
+    {
+        prefixed.extend_from_slice(label.as_bytes());
+        prefixed.extend_from_slice(b" | ");
+        prefixed.extend_from_slice(line);
+        prefixed.push(b'\n');
+    }
+    prefixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_prefix_lines() {
+        assert_eq!(
+            String::from_utf8(prefix_lines("web", b"one\n\nthree\n")).unwrap(),
+            "web | one\nweb | \nweb | three\n"
+        );
+        assert_eq!(
+            String::from_utf8(prefix_lines("db ", b"partial")).unwrap(),
+            "db  | partial\n"
+        );
+    }
+}
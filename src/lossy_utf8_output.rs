@@ -0,0 +1,100 @@
+use std::borrow::Cow;
+use std::process::ExitStatus;
+use std::process::Output;
+
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::OutputLike;
+
+/// A command's [`Output`], decoded as UTF-8 on demand, replacing invalid sequences with �
+/// U+FFFD REPLACEMENT CHARACTER.
+///
+/// Unlike [`utf8_command::Utf8Output`], the output is kept as bytes and decoded when it's
+/// accessed with [`stdout_str`][LossyUtf8Output::stdout_str] or
+/// [`stderr_str`][LossyUtf8Output::stderr_str]. When the output is valid UTF-8 (the common case),
+/// these borrow it instead of copying it into a new [`String`], so large outputs aren't
+/// duplicated. Only output which contains invalid UTF-8 is copied.
+///
+/// Produced by [`CommandExt::output_checked_utf8_lossy`].
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::borrow::Cow;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// let output = Command::new("printf")
+///     .arg(r"puppy\n\377")
+///     .output_checked_utf8_lossy()
+///     .unwrap();
+///
+/// assert_eq!(output.stdout_str(), "puppy\n\u{FFFD}");
+/// assert!(output.is_lossy());
+///
+/// let output = Command::new("echo")
+///     .arg("doggy")
+///     .output_checked_utf8_lossy()
+///     .unwrap();
+///
+/// assert!(matches!(output.stdout_str(), Cow::Borrowed("doggy\n")));
+/// assert!(!output.is_lossy());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LossyUtf8Output {
+    /// The command's exit status.
+    pub status: ExitStatus,
+    /// The command's stdout, as raw bytes.
+    pub stdout: Vec<u8>,
+    /// The command's stderr, as raw bytes.
+    pub stderr: Vec<u8>,
+}
+
+impl LossyUtf8Output {
+    /// The command's stdout, decoded as UTF-8. This is borrowed if stdout is valid UTF-8.
+    pub fn stdout_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stdout)
+    }
+
+    /// The command's stderr, decoded as UTF-8. This is borrowed if stderr is valid UTF-8.
+    pub fn stderr_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stderr)
+    }
+
+    /// Whether stdout or stderr contain invalid UTF-8, so that decoding them replaces some bytes.
+    pub fn is_lossy(&self) -> bool {
+        std::str::from_utf8(&self.stdout).is_err() || std::str::from_utf8(&self.stderr).is_err()
+    }
+}
+
+impl From<Output> for LossyUtf8Output {
+    fn from(output: Output) -> Self {
+        Self {
+            status: output.status,
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+impl From<LossyUtf8Output> for Output {
+    fn from(output: LossyUtf8Output) -> Self {
+        Self {
+            status: output.status,
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+impl OutputLike for LossyUtf8Output {
+    fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    fn stdout(&self) -> Cow<'_, str> {
+        self.stdout_str()
+    }
+
+    fn stderr(&self) -> Cow<'_, str> {
+        self.stderr_str()
+    }
+}
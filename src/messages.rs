@@ -0,0 +1,367 @@
+use std::cell::Cell;
+use std::fmt::Display;
+use std::sync::RwLock;
+
+use crate::format;
+
+#[cfg(doc)]
+use crate::parse_rendered;
+#[cfg(doc)]
+use crate::CircuitBreaker;
+#[cfg(doc)]
+use crate::Error;
+
+/// The messages currently used by error messages; see [`set_messages`].
+static MESSAGES: RwLock<Messages> = RwLock::new(Messages::ENGLISH);
+
+thread_local! {
+    /// Messages overriding [`MESSAGES`] on this thread; see [`Messages::scope`].
+    static OVERRIDE: Cell<Option<Messages>> = const { Cell::new(None) };
+}
+
+/// The fixed words used in error messages, like the `Command failed:` and `Stdout:` labels and the
+/// `failed` in `` `cargo` failed: exit status: 101 ``.
+///
+/// These can be replaced for the whole process with [`set_messages`], or for a single rendering
+/// with [`Messages::display`] or [`Messages::scope`], to translate error messages or use different
+/// terminology. Only the fixed words are replaced; the command, its output, its exit status, and
+/// any underlying error are always shown as-is.
+///
+/// The [`Default`] messages are [`Messages::ENGLISH`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Messages {
+    pub(crate) command_failed: &'static str,
+    pub(crate) command: &'static str,
+    pub(crate) invoked_as: &'static str,
+    pub(crate) stdout: &'static str,
+    pub(crate) stderr: &'static str,
+    pub(crate) failed_to_execute: &'static str,
+    pub(crate) failed: &'static str,
+    pub(crate) failed_to_wait_for: &'static str,
+    pub(crate) failed_to_convert: &'static str,
+    pub(crate) output: &'static str,
+    pub(crate) failed_to_capture_output_of: &'static str,
+    pub(crate) failed_to_write_to_stdin_of: &'static str,
+    pub(crate) timed_out_after: &'static str,
+    pub(crate) was_short_circuited: &'static str,
+    pub(crate) is_already_running: &'static str,
+}
+
+impl Messages {
+    /// The default English messages.
+    pub const ENGLISH: Self = Self {
+        command_failed: format::COMMAND_FAILED_PREFIX,
+        command: format::COMMAND_PREFIX,
+        invoked_as: format::INVOKED_AS_PREFIX,
+        stdout: format::STDOUT_HEADER,
+        stderr: format::STDERR_HEADER,
+        failed_to_execute: format::FAILED_TO_EXECUTE_PREFIX,
+        failed: "failed",
+        failed_to_wait_for: "Failed to wait for",
+        failed_to_convert: "Failed to convert",
+        output: "output",
+        failed_to_capture_output_of: "Failed to capture output of",
+        failed_to_write_to_stdin_of: "Failed to write to stdin of",
+        timed_out_after: "timed out after",
+        was_short_circuited: "was short-circuited",
+        is_already_running: "is already running",
+    };
+
+    /// Set the label for the command line of a failed command; `Command failed` by default.
+    pub fn with_command_failed(mut self, command_failed: &'static str) -> Self {
+        self.command_failed = command_failed;
+        self
+    }
+
+    /// Set the label for the command line of a command which unexpectedly succeeded; `Command` by
+    /// default.
+    pub fn with_command(mut self, command: &'static str) -> Self {
+        self.command = command;
+        self
+    }
+
+    /// Set the label for a command's overridden `argv[0]`; `Invoked as` by default.
+    pub fn with_invoked_as(mut self, invoked_as: &'static str) -> Self {
+        self.invoked_as = invoked_as;
+        self
+    }
+
+    /// Set the label for a command's stdout; `Stdout` by default.
+    pub fn with_stdout(mut self, stdout: &'static str) -> Self {
+        self.stdout = stdout;
+        self
+    }
+
+    /// Set the label for a command's stderr; `Stderr` by default.
+    pub fn with_stderr(mut self, stderr: &'static str) -> Self {
+        self.stderr = stderr;
+        self
+    }
+
+    /// Set the message for a command which couldn't be started; `Failed to execute` by default.
+    pub fn with_failed_to_execute(mut self, failed_to_execute: &'static str) -> Self {
+        self.failed_to_execute = failed_to_execute;
+        self
+    }
+
+    /// Set the word following the program of a command which failed, like in
+    /// `` `cargo` failed: exit status: 101 ``; `failed` by default.
+    pub fn with_failed(mut self, failed: &'static str) -> Self {
+        self.failed = failed;
+        self
+    }
+
+    /// Set the message for a command which couldn't be waited for; `Failed to wait for` by
+    /// default.
+    pub fn with_failed_to_wait_for(mut self, failed_to_wait_for: &'static str) -> Self {
+        self.failed_to_wait_for = failed_to_wait_for;
+        self
+    }
+
+    /// Set the start of the message for a command whose output couldn't be converted, before
+    /// the program; `Failed to convert` by default.
+    pub fn with_failed_to_convert(mut self, failed_to_convert: &'static str) -> Self {
+        self.failed_to_convert = failed_to_convert;
+        self
+    }
+
+    /// Set the end of the message for a command whose output couldn't be converted, after the
+    /// program; `output` by default.
+    pub fn with_output(mut self, output: &'static str) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Set the message for a command whose output couldn't be captured; `Failed to capture
+    /// output of` by default.
+    pub fn with_failed_to_capture_output_of(
+        mut self,
+        failed_to_capture_output_of: &'static str,
+    ) -> Self {
+        self.failed_to_capture_output_of = failed_to_capture_output_of;
+        self
+    }
+
+    /// Set the message for a command whose stdin couldn't be written; `Failed to write to stdin
+    /// of` by default.
+    pub fn with_failed_to_write_to_stdin_of(
+        mut self,
+        failed_to_write_to_stdin_of: &'static str,
+    ) -> Self {
+        self.failed_to_write_to_stdin_of = failed_to_write_to_stdin_of;
+        self
+    }
+
+    /// Set the words following the program of a command which timed out, before the timeout;
+    /// `timed out after` by default.
+    pub fn with_timed_out_after(mut self, timed_out_after: &'static str) -> Self {
+        self.timed_out_after = timed_out_after;
+        self
+    }
+
+    /// Set the words following the program of a command which wasn't run because a
+    /// [`CircuitBreaker`] is open, in GitHub Actions annotation titles; `was short-circuited` by
+    /// default.
+    pub fn with_was_short_circuited(mut self, was_short_circuited: &'static str) -> Self {
+        self.was_short_circuited = was_short_circuited;
+        self
+    }
+
+    /// Set the words following the program of a command which wasn't run because it's already
+    /// running, in GitHub Actions annotation titles; `is already running` by default.
+    pub fn with_is_already_running(mut self, is_already_running: &'static str) -> Self {
+        self.is_already_running = is_already_running;
+        self
+    }
+}
+
+impl Messages {
+    /// Display `value` with these messages instead of the ones set with [`set_messages`].
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Messages;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "exit 1"])
+    ///     .output_checked()
+    ///     .unwrap_err();
+    ///
+    /// let german = Messages::default()
+    ///     .with_failed("fehlgeschlagen")
+    ///     .with_command_failed("Befehl fehlgeschlagen");
+    /// assert_eq!(
+    ///     german.display(&err).to_string(),
+    ///     "`sh` fehlgeschlagen: exit status: 1\n\
+    ///     Befehl fehlgeschlagen: `sh -c 'exit 1'`"
+    /// );
+    ///
+    /// // Other renderings are unaffected.
+    /// assert!(err.to_string().starts_with("`sh` failed: exit status: 1\n"));
+    /// ```
+    pub fn display<T: Display + ?Sized>(self, value: &T) -> DisplayWithMessages<'_, T> {
+        DisplayWithMessages {
+            messages: self,
+            value,
+        }
+    }
+
+    /// Call `f` with these messages used instead of the ones set with [`set_messages`] on this
+    /// thread.
+    ///
+    /// This applies to everything rendered by `f`, including
+    /// [`Error::to_github_annotation`] and [`parse_rendered`].
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Messages;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "exit 1"])
+    ///     .output_checked()
+    ///     .unwrap_err();
+    ///
+    /// let annotation = Messages::default()
+    ///     .with_failed("fehlgeschlagen")
+    ///     .scope(|| err.to_github_annotation());
+    /// assert!(annotation.starts_with("::error title=`sh` fehlgeschlagen%3A exit status%3A 1::"));
+    /// ```
+    pub fn scope<R>(self, f: impl FnOnce() -> R) -> R {
+        /// Restores the previous override when dropped, even if `f` panics.
+        struct Restore(Option<Messages>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                OVERRIDE.with(|messages| messages.set(self.0));
+            }
+        }
+
+        let _restore = Restore(OVERRIDE.with(|messages| messages.replace(Some(self))));
+        f()
+    }
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Self::ENGLISH
+    }
+}
+
+/// Replace the [`Messages`] used by all error messages displayed after this call, for the rest of
+/// the process.
+///
+/// ```
+/// # use indoc::indoc;
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// # use command_error::Messages;
+/// command_error::set_messages(
+///     Messages::default()
+///         .with_command_failed("Befehl fehlgeschlagen")
+///         .with_stdout("Standardausgabe"),
+/// );
+///
+/// let err = Command::new("sh")
+///     .args(["-c", "echo puppy; false"])
+///     .output_checked_utf8()
+///     .unwrap_err();
+///
+/// assert_eq!(
+///     err.to_string(),
+///     indoc!(
+///         "`sh` failed: exit status: 1
+///         Befehl fehlgeschlagen: `sh -c 'echo puppy; false'`
+///         Standardausgabe:
+///           puppy"
+///     )
+/// );
+/// ```
+///
+/// This also affects [`Error::to_github_annotation`] and [`Error::to_github_group`]:
+///
+/// ```
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// # use command_error::Messages;
+/// command_error::set_messages(Messages::default().with_failed("fehlgeschlagen"));
+///
+/// let err = Command::new("sh")
+///     .args(["-c", "exit 1"])
+///     .output_checked()
+///     .unwrap_err();
+///
+/// assert!(err.to_string().starts_with("`sh` fehlgeschlagen: exit status: 1\n"));
+/// assert!(err
+///     .to_github_annotation()
+///     .starts_with("::error title=`sh` fehlgeschlagen%3A exit status%3A 1::"));
+/// ```
+pub fn set_messages(messages: Messages) {
+    *MESSAGES.write().unwrap_or_else(|error| error.into_inner()) = messages;
+}
+
+/// The [`Messages`] to display errors with.
+pub(crate) fn messages() -> Messages {
+    OVERRIDE
+        .with(Cell::get)
+        .unwrap_or_else(|| *MESSAGES.read().unwrap_or_else(|error| error.into_inner()))
+}
+
+/// A value displayed with particular [`Messages`]; see [`Messages::display`].
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayWithMessages<'a, T: ?Sized> {
+    messages: Messages,
+    value: &'a T,
+}
+
+impl<T: Display + ?Sized> Display for DisplayWithMessages<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.messages.scope(|| self.value.fmt(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::CommandExt;
+
+    #[test]
+    fn test_display_with_messages() {
+        let err = Command::new("sh")
+            .args(["-c", "echo puppy; echo doggy >&2; exit 1"])
+            .output_checked()
+            .unwrap_err();
+        let german = Messages::default()
+            .with_failed("ist fehlgeschlagen")
+            .with_command_failed("Befehl fehlgeschlagen")
+            .with_stdout("Standardausgabe")
+            .with_stderr("Standardfehlerausgabe");
+
+        assert_eq!(
+            german.display(&err).to_string(),
+            indoc!(
+                "`sh` ist fehlgeschlagen: exit status: 1
+                Befehl fehlgeschlagen: `sh -c 'echo puppy; echo doggy >&2; exit 1'`
+                Standardausgabe:
+                  puppy
+                Standardfehlerausgabe:
+                  doggy"
+            )
+        );
+        assert_eq!(messages(), Messages::ENGLISH);
+    }
+
+    #[test]
+    fn test_scope_restores_on_panic() {
+        let german = Messages::default().with_failed("fehlgeschlagen");
+        let result = std::panic::catch_unwind(|| german.scope(|| panic!("puppy")));
+        assert!(result.is_err());
+        assert_eq!(messages(), Messages::ENGLISH);
+    }
+}
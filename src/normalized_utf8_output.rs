@@ -0,0 +1,127 @@
+use std::borrow::Cow;
+use std::process::ExitStatus;
+use std::process::Output;
+
+use utf8_command::Utf8Output;
+
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::OutputLike;
+
+/// A command's [`Output`] decoded as UTF-8, with Windows-style `\r\n` line endings converted to
+/// `\n`.
+///
+/// Output from Windows tools often uses `\r\n` line endings, which causes confusing mismatches
+/// when it's compared with expected output written with `\n`. Lone `\r` characters (like those
+/// used to redraw progress bars) are left alone.
+///
+/// Produced by [`CommandExt::output_checked_utf8_normalized`]. When a command fails, its error
+/// contains the normalized output. To get the raw bytes, use [`CommandExt::output_checked`]
+/// instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedUtf8Output {
+    /// The command's exit status.
+    pub status: ExitStatus,
+    /// The command's stdout, with `\r\n` converted to `\n`.
+    pub stdout: String,
+    /// The command's stderr, with `\r\n` converted to `\n`.
+    pub stderr: String,
+}
+
+impl TryFrom<Output> for NormalizedUtf8Output {
+    type Error = utf8_command::Error;
+
+    fn try_from(output: Output) -> Result<Self, Self::Error> {
+        let output = Utf8Output::try_from(output)?;
+        Ok(Self {
+            status: output.status,
+            stdout: normalize_crlf(output.stdout),
+            stderr: normalize_crlf(output.stderr),
+        })
+    }
+}
+
+impl From<NormalizedUtf8Output> for Utf8Output {
+    fn from(output: NormalizedUtf8Output) -> Self {
+        Self {
+            status: output.status,
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+impl OutputLike for NormalizedUtf8Output {
+    fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    fn stdout(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.stdout)
+    }
+
+    fn stderr(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.stderr)
+    }
+}
+
+/// Convert `\r\n` line endings in `text` to `\n`, without copying it if there are none.
+fn normalize_crlf(text: String) -> String {
+    if text.contains("\r\n") {
+        text.replace("\r\n", "\n")
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use indoc::formatdoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::CommandExt;
+
+    /// Mixed `\n` and `\r\n` line endings, and a lone `\r`.
+    const MIXED: &str = r"puppy\r\ndoggy\nkitty\r\nloading\rdone\n";
+
+    #[test]
+    fn test_normalized_output() {
+        let output = Command::new("printf")
+            .arg(MIXED)
+            .output_checked_utf8_normalized()
+            .unwrap();
+        assert_eq!(output.stdout, "puppy\ndoggy\nkitty\nloading\rdone\n");
+
+        let raw = Command::new("printf").arg(MIXED).output_checked().unwrap();
+        assert_eq!(raw.stdout, b"puppy\r\ndoggy\nkitty\r\nloading\rdone\n");
+    }
+
+    #[test]
+    fn test_normalized_error_rendering() {
+        let script = format!("printf '{}' >&2; exit 1", r"puppy\r\ndoggy\nkitty\r\n");
+        let expected = formatdoc!(
+            "`sh` failed: exit status: 1
+            Command failed: `sh -c {}`
+            Stderr:
+              puppy
+              doggy
+              kitty",
+            shell_words::quote(&script)
+        );
+
+        let normalized = Command::new("sh")
+            .args(["-c", &script])
+            .output_checked_utf8_normalized()
+            .unwrap_err();
+        assert_eq!(normalized.to_string(), expected);
+
+        // The rendering is clean even when the output isn't normalized.
+        let raw = Command::new("sh")
+            .args(["-c", &script])
+            .output_checked()
+            .unwrap_err();
+        assert_eq!(raw.to_string(), expected);
+    }
+}
@@ -1,20 +1,25 @@
 use std::borrow::Borrow;
 use std::fmt::Debug;
 use std::fmt::Display;
-use std::process::ExitStatus;
-
-#[cfg(doc)]
 use std::process::Command;
-#[cfg(doc)]
+use std::process::ExitStatus;
 use std::process::Output;
+use std::time::Duration;
 
 #[cfg(doc)]
 use crate::CommandExt;
 
+use crate::exit_signal;
+use crate::failure_log::log_failure;
+use crate::signal_name;
 use crate::CommandDisplay;
 use crate::Error;
+use crate::LazyMessage;
 use crate::OutputError;
 use crate::OutputLike;
+use crate::OutputStream;
+use crate::StdioConfig;
+use crate::StreamTimings;
 
 /// [`Output`] combined with context about the [`Command`] that produced it.
 ///
@@ -28,6 +33,103 @@ use crate::OutputLike;
 pub struct OutputContext<O> {
     pub(crate) output: O,
     pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
+    pub(crate) stream_timings: Option<StreamTimings>,
+    /// How the command's standard streams were connected, if known.
+    pub(crate) stdio: Option<StdioConfig>,
+    /// Whether the command's output was requested, so errors should note streams which weren't
+    /// captured.
+    pub(crate) note_uncaptured: bool,
+}
+
+impl<O> OutputContext<O> {
+    /// Construct a new [`OutputContext`] from a command's output and the command that produced it.
+    pub fn new(output: O, command: impl Into<Box<dyn CommandDisplay + Send + Sync>>) -> Self {
+        Self {
+            output,
+            command: command.into(),
+            stream_timings: None,
+            stdio: None,
+            note_uncaptured: false,
+        }
+    }
+
+    pub(crate) fn with_stream_timings(mut self, stream_timings: StreamTimings) -> Self {
+        self.stream_timings = Some(stream_timings);
+        self
+    }
+
+    /// How the command's standard streams were connected when it ran, if they were recorded.
+    ///
+    /// See [`StdioConfig`].
+    pub fn stdio(&self) -> Option<StdioConfig> {
+        self.stdio
+    }
+}
+
+impl OutputContext<Output> {
+    /// Construct a new [`OutputContext`] from a command's exit status, its separately captured
+    /// stdout and stderr, and the command that produced them.
+    ///
+    /// This is the recommended way to use this crate's validation and error messages with
+    /// commands run by other means, such as a custom executor or a PTY recorder:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use std::process::ExitStatus;
+    /// # use std::os::unix::process::ExitStatusExt;
+    /// # use command_error::OutputContext;
+    /// # use command_error::Utf8ProgramAndArgs;
+    /// let mut command = Command::new("cargo");
+    /// command.arg("build");
+    /// let displayed = Utf8ProgramAndArgs::from(&command);
+    ///
+    /// // Run the command yourself...
+    /// let status = ExitStatus::from_raw(101 << 8);
+    /// let stdout = Vec::new();
+    /// let stderr = b"could not find `Cargo.toml`\n".to_vec();
+    ///
+    /// let context = OutputContext::from_parts(status, stdout, stderr, displayed.clone());
+    /// assert_eq!(
+    ///     context.require_success().unwrap_err().to_string(),
+    ///     indoc!(
+    ///         "`cargo` failed: exit status: 101
+    ///         Command failed: `cargo build`
+    ///         Stderr:
+    ///           could not find `Cargo.toml`"
+    ///     )
+    /// );
+    ///
+    /// let context = OutputContext::from_parts(status, Vec::new(), Vec::new(), displayed.clone());
+    /// assert_eq!(
+    ///     context.error_msg("no manifest").to_string(),
+    ///     indoc!(
+    ///         "`cargo` failed: no manifest
+    ///         exit status: 101
+    ///         Command failed: `cargo build`"
+    ///     )
+    /// );
+    ///
+    /// let status = ExitStatus::from_raw(0);
+    /// let context = OutputContext::from_parts(status, b"ok\n".to_vec(), Vec::new(), displayed);
+    /// assert_eq!(context.require_success().unwrap().stdout, b"ok\n");
+    /// ```
+    pub fn from_parts(
+        status: ExitStatus,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        command: impl CommandDisplay + Send + Sync + 'static,
+    ) -> Self {
+        Self::new(
+            Output {
+                status,
+                stdout,
+                stderr,
+            },
+            Box::new(command),
+        )
+    }
 }
 
 impl<O> OutputContext<O>
@@ -49,6 +151,58 @@ where
         self.output.status()
     }
 
+    /// Get the command's exit code, if it has one.
+    ///
+    /// This is [`None`] if the command was killed by a signal. See [`ExitStatus::code`].
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let exit_code = Command::new("sh")
+    ///     .args(["-c", "exit 2"])
+    ///     .status_checked_as(|context| match context.code() {
+    ///         Some(0 | 2) => Ok(context.code()),
+    ///         _ => Err(context.error()),
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(exit_code, Some(2));
+    /// ```
+    pub fn code(&self) -> Option<i32> {
+        self.status().code()
+    }
+
+    /// Determine if the command exited successfully. See [`ExitStatus::success`].
+    pub fn success(&self) -> bool {
+        self.status().success()
+    }
+
+    /// Get the number of the signal which killed the command, if it was killed by a signal.
+    ///
+    /// This is always [`None`] on non-Unix platforms. See [`exit_signal`].
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let signal = Command::new("sh")
+    ///     .args(["-c", "kill -TERM $$"])
+    ///     .status_checked_as(|context| match context.signal_name() {
+    ///         Some("SIGTERM") => Ok(context.signal()),
+    ///         _ => Err(context.error()),
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(signal, Some(15));
+    /// ```
+    pub fn signal(&self) -> Option<i32> {
+        exit_signal(self.status())
+    }
+
+    /// Get the name of the signal which killed the command, like `SIGKILL`, if it was killed by a
+    /// signal with a recognized name. See [`signal_name`].
+    pub fn signal_name(&self) -> Option<&'static str> {
+        self.signal().and_then(signal_name)
+    }
+
     /// Get a reference to the command contained in this context object, for use in error messages
     /// or diagnostics.
     pub fn command(&self) -> &(dyn CommandDisplay + Send + Sync) {
@@ -61,12 +215,85 @@ where
         self.command
     }
 
+    /// Reconstruct the command with `extra` arguments appended, to re-run it (for example, with
+    /// `--verbose`) to gather more information about a failure.
+    ///
+    /// The command is rebuilt with [`CommandDisplay::to_command`], which is lossy, and panics if
+    /// any of the command's arguments were omitted; see its documentation for details.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use std::process::Output;
+    /// # use command_error::Error;
+    /// # use command_error::OutputContext;
+    /// # use command_error::Utf8ProgramAndArgs;
+    /// let rerun = Command::new("sh")
+    ///     .args(["-c", "echo \"$@\"", "sh", "puppy"])
+    ///     .output_checked_as(|context: OutputContext<Output>| {
+    ///         Ok::<_, Error>(context.rerun_with_args(&["-v"]))
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     Utf8ProgramAndArgs::from(&rerun).to_string(),
+    ///     r#"sh -c 'echo "$@"' sh puppy -v"#
+    /// );
+    /// ```
+    pub fn rerun_with_args(&self, extra: &[&str]) -> Command {
+        let mut command = self.command.to_command();
+        command.args(extra);
+        command
+    }
+
+    /// Get the timings of the command's output streams relative to its exit, if they were
+    /// recorded.
+    ///
+    /// Timings are recorded by methods which read the command's output themselves, like
+    /// [`CommandExt::output_checked_with_backpressure`], but not by [`CommandExt::output_checked`].
+    /// See [`StreamTimings`] for the full list.
+    pub fn stream_timings(&self) -> Option<StreamTimings> {
+        self.stream_timings
+    }
+
+    /// How long after the command started the first byte of its stdout was read, if timings were
+    /// recorded and the command wrote to stdout.
+    ///
+    /// This is always [`None`] for methods which don't record timings, like
+    /// [`CommandExt::output_checked`]; see [`StreamTimings`] for which methods do. See also
+    /// [`StreamTimings::first_byte_latency`].
+    pub fn stdout_first_byte_latency(&self) -> Option<Duration> {
+        self.stream_timings?
+            .first_byte_latency(OutputStream::Stdout)
+    }
+
+    /// How long after the command started the first byte of its stderr was read, if timings were
+    /// recorded and the command wrote to stderr.
+    ///
+    /// This is always [`None`] for methods which don't record timings, like
+    /// [`CommandExt::output_checked`]; see [`StreamTimings`] for which methods do. See also
+    /// [`StreamTimings::first_byte_latency`].
+    pub fn stderr_first_byte_latency(&self) -> Option<Duration> {
+        self.stream_timings?
+            .first_byte_latency(OutputStream::Stderr)
+    }
+
+    pub(crate) fn into_output_error(self) -> OutputError {
+        let mut error = OutputError::new(self.command, Box::new(self.output));
+        error.stdio = self.stdio;
+        error.note_uncaptured = self.note_uncaptured;
+        match self.stream_timings {
+            Some(stream_timings) => error.with_stream_timings(stream_timings),
+            None => error,
+        }
+    }
+
     /// Construct an error that indicates this command failed, containing information about the
     /// command and its output.
     ///
     /// See [`CommandExt`] for examples of the error format.
     pub fn error(self) -> Error {
-        Error::from(OutputError::new(self.command, Box::new(self.output)))
+        failed(self.into_output_error())
     }
 
     /// Construct an error that indicates this command failed, containing information about the
@@ -77,19 +304,43 @@ where
     where
         E: Debug + Display + Send + Sync + 'static,
     {
-        Error::from(
-            OutputError::new(self.command, Box::new(self.output)).with_message(Box::new(message)),
-        )
+        failed(self.into_output_error().with_message(Box::new(message)))
+    }
+
+    /// Construct an error that indicates this command failed, containing information about the
+    /// command, its output, and a message built by `message`.
+    ///
+    /// `message` is only called if the error is displayed, so this is useful for messages which
+    /// are expensive to build. See [`LazyMessage`].
+    pub fn error_msg_with(self, message: impl FnOnce() -> String + Send + 'static) -> Error {
+        self.error_msg(LazyMessage::new(message))
+    }
+
+    /// Get the output if the command succeeded, or construct an error with
+    /// [`OutputContext::error`] if it failed.
+    pub fn require_success(self) -> Result<O, Error> {
+        if self.success() {
+            Ok(self.into_output())
+        } else {
+            Err(self.error())
+        }
     }
 
     pub(crate) fn maybe_error_msg<E>(self, message: Option<E>) -> Error
     where
         E: Debug + Display + Send + Sync + 'static,
     {
-        let ret = OutputError::new(self.command, Box::new(self.output));
-        Error::from(match message {
+        let ret = self.into_output_error();
+        failed(match message {
             Some(message) => ret.with_message(Box::new(message)),
             None => ret,
         })
     }
 }
+
+/// Convert a failed command's [`OutputError`] into an [`Error`], logging it if failures are being
+/// logged.
+fn failed(error: OutputError) -> Error {
+    log_failure(&error);
+    Error::from(error)
+}
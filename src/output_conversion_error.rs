@@ -1,14 +1,18 @@
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::str::Utf8Error;
 
 #[cfg(doc)]
 use std::process::Command;
 #[cfg(doc)]
 use std::process::Output;
 
+use crate::command_display::guarded;
+use crate::messages::messages;
 use crate::CommandDisplay;
 #[cfg(doc)]
 use crate::CommandExt;
+use crate::OutputStream;
 #[cfg(feature = "miette")]
 use miette::Diagnostic;
 #[cfg(doc)]
@@ -49,23 +53,102 @@ use utf8_command::Utf8Output;
 pub struct OutputConversionError {
     pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
     pub(crate) inner: Box<dyn Display + Send + Sync>,
+    /// The UTF-8 decoding error, if the output was converted to [`Utf8Output`].
+    ///
+    /// This is boxed to keep [`OutputConversionError`] small.
+    pub(crate) utf8: Option<Box<utf8_command::Error>>,
 }
 
 impl OutputConversionError {
     /// Construct a new [`OutputConversionError`].
     pub fn new(
-        command: Box<dyn CommandDisplay + Send + Sync>,
+        command: impl Into<Box<dyn CommandDisplay + Send + Sync>>,
         inner: Box<dyn Display + Send + Sync>,
     ) -> Self {
-        Self { command, inner }
+        Self {
+            command: command.into(),
+            inner,
+            utf8: None,
+        }
+    }
+
+    /// Construct a new [`OutputConversionError`] from an error decoding output as [`Utf8Output`].
+    ///
+    /// The details of the error are available from [`OutputConversionError::utf8_error`].
+    pub fn from_utf8(
+        command: Box<dyn CommandDisplay + Send + Sync>,
+        inner: utf8_command::Error,
+    ) -> Self {
+        Self {
+            command,
+            inner: Box::new(inner.to_string()),
+            utf8: Some(Box::new(inner)),
+        }
+    }
+
+    /// The output stream which contained invalid UTF-8, if the output was converted to
+    /// [`Utf8Output`].
+    pub fn stream(&self) -> Option<OutputStream> {
+        match self.utf8.as_deref()? {
+            utf8_command::Error::Stdout(_) => Some(OutputStream::Stdout),
+            utf8_command::Error::Stderr(_) => Some(OutputStream::Stderr),
+        }
+    }
+
+    /// Where the output stream contained invalid UTF-8, if the output was converted to
+    /// [`Utf8Output`].
+    ///
+    /// [`Utf8Error::valid_up_to`] and [`Utf8Error::error_len`] give the length of the valid
+    /// prefix and the length of the invalid sequence after it, so callers can recover what they
+    /// can:
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// # use command_error::OutputStream;
+    /// let err = Command::new("printf")
+    ///     .arg(r"puppy\300doggy")
+    ///     .output_checked_utf8()
+    ///     .unwrap_err();
+    /// let Error::Conversion(err) = err else { unreachable!() };
+    /// assert_eq!(err.stream(), Some(OutputStream::Stdout));
+    ///
+    /// let utf8_error = err.utf8_error().unwrap();
+    /// assert_eq!(utf8_error.valid_up_to(), 5);
+    /// assert_eq!(utf8_error.error_len(), Some(1));
+    /// assert_eq!(err.invalid_bytes().unwrap(), b"puppy\xc0doggy");
+    /// assert_eq!(err.valid_prefix(), Some("puppy"));
+    /// ```
+    pub fn utf8_error(&self) -> Option<Utf8Error> {
+        Some(self.utf8.as_deref()?.inner().utf8_error())
+    }
+
+    /// The complete contents of the output stream which contained invalid UTF-8, if the output
+    /// was converted to [`Utf8Output`].
+    ///
+    /// See [`OutputConversionError::stream`].
+    pub fn invalid_bytes(&self) -> Option<&[u8]> {
+        Some(self.utf8.as_deref()?.inner().as_bytes())
+    }
+
+    /// The valid UTF-8 at the start of the output stream which contained invalid UTF-8, if the
+    /// output was converted to [`Utf8Output`].
+    ///
+    /// See [`OutputConversionError::utf8_error`].
+    pub fn valid_prefix(&self) -> Option<&str> {
+        let valid_up_to = self.utf8_error()?.valid_up_to();
+        std::str::from_utf8(&self.invalid_bytes()?[..valid_up_to]).ok()
     }
 }
 
 impl Debug for OutputConversionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("OutputConversionError")
-            .field("program", &self.command.program())
+            .field("program", &guarded(&*self.command).program())
             .field("inner", &self.inner.to_string())
+            .field("utf8_error", &self.utf8_error())
             .finish()
     }
 }
@@ -74,8 +157,10 @@ impl Display for OutputConversionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Failed to convert `{}` output: {}",
-            self.command.program_quoted(),
+            "{} `{}` {}: {}",
+            messages().failed_to_convert,
+            guarded(&*self.command).program_quoted(),
+            messages().output,
             self.inner
         )
     }
@@ -84,7 +169,23 @@ impl Display for OutputConversionError {
 impl std::error::Error for OutputConversionError {}
 
 #[cfg(feature = "miette")]
-impl Diagnostic for OutputConversionError {}
+impl Diagnostic for OutputConversionError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::conversion"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        if self.inner.to_string().contains("invalid utf-8") {
+            Some(Box::new(format!(
+                "`{}` printed output which isn't valid UTF-8; use `output_checked` to get the \
+                output as bytes, or decode it with `String::from_utf8_lossy`",
+                guarded(&*self.command).program_quoted()
+            )))
+        } else {
+            None
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -92,4 +193,61 @@ mod tests {
     use static_assertions::assert_impl_all;
 
     assert_impl_all!(OutputConversionError: Send, Sync);
+
+    #[test]
+    fn test_utf8_error() {
+        use pretty_assertions::assert_eq;
+        use std::process::Command;
+
+        use crate::CommandExt;
+        use crate::Error;
+        use crate::Utf8ProgramAndArgs;
+
+        let err = Command::new("sh")
+            .args(["-c", r"printf 'ok\342\202' >&2"])
+            .output_checked_utf8()
+            .unwrap_err();
+        let Error::Conversion(err) = err else {
+            panic!("Expected a conversion error, got {err:?}");
+        };
+        assert_eq!(err.stream(), Some(OutputStream::Stderr));
+        // An incomplete sequence at the end of the stream has no error length.
+        assert_eq!(err.utf8_error().unwrap().error_len(), None);
+        assert_eq!(err.valid_prefix(), Some("ok"));
+
+        let err = OutputConversionError::new(
+            Box::new(Utf8ProgramAndArgs::from(&Command::new("cat"))),
+            Box::new("expected a number"),
+        );
+        assert_eq!(err.stream(), None);
+        assert_eq!(err.utf8_error(), None);
+        assert_eq!(err.valid_prefix(), None);
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn test_miette_report() {
+        use std::process::Command;
+
+        use crate::Utf8ProgramAndArgs;
+
+        let error = OutputConversionError::new(
+            Box::new(Utf8ProgramAndArgs::from(&Command::new("cat"))),
+            Box::new(
+                "Stdout contained invalid utf-8 sequence of 1 bytes from index 0: \"\u{fffd}\"",
+            ),
+        );
+        let report = format!("{:?}", miette::Report::new(error));
+        assert!(report.contains("command_error::conversion"), "{report}");
+        assert!(
+            report.contains("use `output_checked` to get the output as bytes"),
+            "{report}"
+        );
+
+        let error = OutputConversionError::new(
+            Box::new(Utf8ProgramAndArgs::from(&Command::new("cat"))),
+            Box::new("expected a number"),
+        );
+        assert!(error.help().is_none());
+    }
 }
@@ -1,14 +1,33 @@
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::num::NonZeroU32;
+use std::panic::Location;
 
+use crate::command_display::guarded;
+use crate::duration::round_duration;
+use crate::format;
+use crate::messages::messages;
+use crate::utf8_program_and_args::LOSSY_NOTE;
+use crate::validator::violations_message;
+use crate::validator::Violations;
 use crate::CommandDisplay;
 use crate::DebugDisplay;
+use crate::DiagnosticRerun;
+#[cfg(feature = "diagnostic-summary")]
+use crate::DiagnosticSummary;
 use crate::OutputLike;
+use crate::OutputStream;
+use crate::StdioConfig;
+use crate::StdioKind;
+use crate::StreamTimings;
+use crate::Violation;
 
 #[cfg(doc)]
 use crate::CommandExt;
 #[cfg(doc)]
 use crate::ExecError;
+#[cfg(doc)]
+use crate::Validator;
 #[cfg(feature = "miette")]
 use miette::Diagnostic;
 
@@ -64,18 +83,49 @@ pub struct OutputError {
     pub(crate) output: Box<dyn OutputLike + Send + Sync>,
     /// A user-defined error message.
     pub(crate) user_error: Option<Box<dyn DebugDisplay + Send + Sync>>,
+    /// When the program's output streams were closed, relative to when it exited.
+    ///
+    /// This is boxed to keep [`OutputError`] small.
+    pub(crate) stream_timings: Option<Box<StreamTimings>>,
+    /// The output of re-running the program to diagnose this failure.
+    pub(crate) diagnostic_rerun: Option<Box<DiagnosticRerun>>,
+    /// The width to wrap the displayed command at, if any.
+    ///
+    /// This is a `u32` to keep [`OutputError`] small.
+    pub(crate) wrap_width: Option<NonZeroU32>,
+    /// The conditions which failed, if the output was checked with a [`Validator`].
+    ///
+    /// This is boxed to keep [`OutputError`] small.
+    pub(crate) violations: Option<Box<Violations>>,
+    /// Whether to collapse consecutive identical output lines when displayed.
+    pub(crate) collapse_repeats: bool,
+    /// Whether to include extra notes about the program's output when displayed.
+    pub(crate) verbose: bool,
+    /// How the program's standard streams were connected, if known.
+    pub(crate) stdio: Option<StdioConfig>,
+    /// Whether to note output streams which weren't captured even when not verbose, because the
+    /// program's output was requested.
+    pub(crate) note_uncaptured: bool,
 }
 
 impl OutputError {
     /// Construct a new [`OutputError`].
     pub fn new(
-        command: Box<dyn CommandDisplay + Send + Sync>,
+        command: impl Into<Box<dyn CommandDisplay + Send + Sync>>,
         output: Box<dyn OutputLike + Send + Sync>,
     ) -> Self {
         Self {
-            command,
+            command: command.into(),
             output,
             user_error: None,
+            stream_timings: None,
+            diagnostic_rerun: None,
+            wrap_width: None,
+            violations: None,
+            collapse_repeats: false,
+            verbose: false,
+            stdio: None,
+            note_uncaptured: false,
         }
     }
 
@@ -90,23 +140,257 @@ impl OutputError {
         self.user_error = None;
         self
     }
+
+    /// Note that one of the program's output streams wasn't captured (for example, because it was
+    /// inherited from the parent process), so that the error message doesn't suggest the program
+    /// printed nothing.
+    pub fn with_uncaptured(mut self, stream: OutputStream) -> Self {
+        self.stdio
+            .get_or_insert_with(StdioConfig::default)
+            .set(stream, StdioKind::Inherited);
+        self.note_uncaptured = true;
+        self
+    }
+
+    /// Attach how the program's standard streams were connected to this error.
+    ///
+    /// [Verbose](OutputError::verbose) error messages include the whole configuration and note
+    /// output streams which weren't piped.
+    pub fn with_stdio(mut self, stdio: StdioConfig) -> Self {
+        self.stdio = Some(stdio);
+        self
+    }
+
+    /// Attach the timings of the program's output streams to this error.
+    ///
+    /// If a stream remained open for longer than [`StreamTimings::LAG_THRESHOLD`] after the
+    /// program exited, a note is added to the error message.
+    pub fn with_stream_timings(mut self, stream_timings: StreamTimings) -> Self {
+        self.stream_timings = Some(Box::new(stream_timings));
+        self
+    }
+
+    /// Get a reference to the command's output and exit status.
+    pub fn output(&self) -> &(dyn OutputLike + Send + Sync) {
+        &*self.output
+    }
+
+    /// Wrap the displayed command line in this error's message at `width` columns, or don't wrap
+    /// it if `width` is `None` (the default) or zero.
+    ///
+    /// The command is wrapped between arguments, with continuation lines indented and the
+    /// previous lines ending in ` \`, so the wrapped command can still be pasted into a shell.
+    /// Arguments longer than `width` aren't broken up. The program's output isn't re-wrapped.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Command;
+    /// # use std::process::Output;
+    /// # use std::process::ExitStatus;
+    /// # use command_error::Utf8ProgramAndArgs;
+    /// # use command_error::OutputError;
+    /// let mut command = Command::new("cargo");
+    /// command.args(["build", "--release", "--features", "serde tracing", "--target-dir", "/tmp/target"]);
+    /// let error = OutputError::new(
+    ///     Box::new(Utf8ProgramAndArgs::from(&command)),
+    ///     Box::new(ExitStatus::default()),
+    /// )
+    /// .with_wrap_width(Some(40));
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     indoc!(
+    ///         "`cargo` failed: exit status: 0
+    ///         Command failed: `cargo build --release \\
+    ///           --features 'serde tracing' \\
+    ///           --target-dir /tmp/target`"
+    ///     )
+    /// );
+    /// ```
+    pub fn with_wrap_width(mut self, width: Option<usize>) -> Self {
+        self.wrap_width = width
+            .map(|width| u32::try_from(width).unwrap_or(u32::MAX))
+            .and_then(NonZeroU32::new);
+        self
+    }
+
+    /// Collapse consecutive identical lines of the program's output into a single line followed
+    /// by `(×N)` when this error is displayed.
+    ///
+    /// This is off by default, so output is shown verbatim. Lines are compared after trailing
+    /// whitespace is removed, and stdout and stderr are collapsed separately.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use indoc::indoc;
+    /// # use std::process::Output;
+    /// # use std::process::ExitStatus;
+    /// # use command_error::Utf8ProgramAndArgs;
+    /// # use command_error::OutputError;
+    /// # use std::process::Command;
+    /// let error = OutputError::new(
+    ///     Box::new(Utf8ProgramAndArgs::from(&Command::new("lint"))),
+    ///     Box::new(Output {
+    ///         status: ExitStatus::default(),
+    ///         stdout: Vec::new(),
+    ///         stderr: "retrying connection\n".repeat(3).into_bytes(),
+    ///     }),
+    /// )
+    /// .collapse_repeats(true);
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     indoc!(
+    ///         "`lint` failed: exit status: 0
+    ///         Command failed: `lint`
+    ///         Stderr:
+    ///           retrying connection (×3)"
+    ///     )
+    /// );
+    /// ```
+    pub fn collapse_repeats(mut self, collapse: bool) -> Self {
+        self.collapse_repeats = collapse;
+        self
+    }
+
+    /// Include extra notes about the program's output when this error is displayed.
+    ///
+    /// This is off by default. A verbose error notes:
+    ///
+    /// - When one of the program's output streams was
+    ///   [closed immediately](StreamTimings::closed_immediately) without any output, which
+    ///   distinguishes a program that crashed on startup from one that ran and printed nothing.
+    /// - How the program's standard streams were connected, if known (see [`StdioConfig`]).
+    /// - Where in the source code the command was run from (see [`OutputError::location`]).
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Attach the output of re-running the program to diagnose this failure.
+    ///
+    /// The re-run's output is included in the error message.
+    pub fn with_diagnostic_rerun(mut self, rerun: DiagnosticRerun) -> Self {
+        self.diagnostic_rerun = Some(Box::new(rerun));
+        self
+    }
+
+    /// Get the output of re-running the program to diagnose this failure, if it was re-run.
+    pub fn diagnostic_rerun(&self) -> Option<&DiagnosticRerun> {
+        self.diagnostic_rerun.as_deref()
+    }
+
+    /// Attach the conditions which failed when the output was checked with a [`Validator`].
+    ///
+    /// The violations are listed in the error message.
+    pub(crate) fn with_violations(mut self, violations: Vec<Violation>) -> Self {
+        self.user_error = Some(Box::new(violations_message(&violations)));
+        self.violations = Some(Box::new(Violations(violations)));
+        self
+    }
+
+    /// Get the conditions which failed, if the output was checked with a [`Validator`] (with
+    /// [`CommandExt::output_checked_validated`]).
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// # use command_error::Validator;
+    /// # use command_error::Violation;
+    /// let validator = Validator::success().and(Validator::stderr_empty());
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo oops >&2; exit 2"])
+    ///     .output_checked_validated(&validator)
+    ///     .unwrap_err();
+    /// let Error::Output(err) = err else { unreachable!() };
+    /// assert_eq!(
+    ///     err.violations(),
+    ///     [
+    ///         Violation::ExitCode { expected: vec![0], actual: Some(2) },
+    ///         Violation::StderrNotEmpty,
+    ///     ]
+    /// );
+    /// ```
+    pub fn violations(&self) -> &[Violation] {
+        self.violations
+            .as_deref()
+            .map_or(&[], |violations| &violations.0)
+    }
+
+    /// Get the timings of the program's output streams, if they were recorded.
+    pub fn stream_timings(&self) -> Option<StreamTimings> {
+        self.stream_timings.as_deref().copied()
+    }
+
+    /// Where in the source code the command was run from, if known.
+    ///
+    /// See [`CommandDisplay::location`]. [Verbose](OutputError::verbose) error messages include
+    /// the location:
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// let Error::Output(err) = Command::new("false").output_checked().unwrap_err() else {
+    ///     unreachable!()
+    /// };
+    /// let location = err.location().unwrap();
+    /// assert_eq!(location.line(), line!() - 4);
+    /// assert!(err
+    ///     .verbose(true)
+    ///     .to_string()
+    ///     .ends_with(&format!("\nCalled at: {location}")));
+    /// ```
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        guarded(&*self.command).location()
+    }
+
+    /// How the program's standard streams were connected when it ran, if known.
+    ///
+    /// See [`StdioConfig`].
+    pub fn stdio(&self) -> Option<StdioConfig> {
+        self.stdio
+    }
+
+    /// Summarize the compiler diagnostics (`error:` and `warning:` lines) in the program's stderr
+    /// and stdout, if there are any.
+    ///
+    /// When a summary is present, it's included in the error message.
+    #[cfg(feature = "diagnostic-summary")]
+    pub fn diagnostic_summary(&self) -> Option<DiagnosticSummary> {
+        DiagnosticSummary::parse(&[&self.output.stderr(), &self.output.stdout()])
+    }
 }
 
 impl Debug for OutputError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("OutputError")
-            .field("program", &self.command.program())
+            .field("program", &guarded(&*self.command).program())
             .field("status", &self.output.status())
             .field("stdout_utf8", &self.output.stdout())
             .field("stderr_utf8", &self.output.stderr())
             .field("user_error", &self.user_error)
+            .field("stream_timings", &self.stream_timings)
+            .field("diagnostic_rerun", &self.diagnostic_rerun)
+            .field("wrap_width", &self.wrap_width)
+            .field("collapse_repeats", &self.collapse_repeats)
+            .field("verbose", &self.verbose)
+            .field("stdio", &self.stdio)
+            .field("note_uncaptured", &self.note_uncaptured)
+            .field("location", &self.location())
             .finish()
     }
 }
 
 impl Display for OutputError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "`{}` failed: ", self.command.program_quoted())?;
+        write!(
+            f,
+            "`{}` {}: ",
+            guarded(&*self.command).program_quoted(),
+            messages().failed
+        )?;
 
         match &self.user_error {
             Some(user_error) => {
@@ -121,15 +405,56 @@ impl Display for OutputError {
         }
 
         // Command failed: `nix build .#default`
-        write!(f, "\nCommand failed: `{}`", self.command,)?;
+        match self.wrap_width {
+            Some(width) => {
+                let prefix = format!("{}: `", messages().command_failed);
+                write!(f, "\n{prefix}")?;
+                write_wrapped_command(
+                    f,
+                    &guarded(&*self.command).to_string(),
+                    prefix.chars().count(),
+                    width.get() as usize,
+                )?;
+                write!(f, "`")?;
+            }
+            None => write!(
+                f,
+                "\n{}: `{}`",
+                messages().command_failed,
+                guarded(&*self.command)
+            )?,
+        }
 
-        const INDENT: &str = "  ";
+        // Invoked as: `gzip`
+        if let Some(arg0) = guarded(&*self.command).arg0() {
+            write!(
+                f,
+                "\n{}: `{}`",
+                messages().invoked_as,
+                shell_words::quote(&arg0)
+            )?;
+        }
+
+        // Note: some arguments contained non-UTF-8 bytes and are shown approximately
+        if guarded(&*self.command).needs_lossy_note() {
+            write!(f, "\n{LOSSY_NOTE}")?;
+        }
+
+        // Summary: 3 errors, 12 warnings (first: error[E0308]: mismatched types)
+        #[cfg(feature = "diagnostic-summary")]
+        if let Some(summary) = self.diagnostic_summary() {
+            write!(f, "\nSummary: {summary}")?;
+        }
+
+        const INDENT: &str = format::OUTPUT_INDENT;
 
         let stdout = self.output.stdout();
         let stdout = stdout.trim();
         if !stdout.is_empty() {
-            writeln!(f, "\nStdout:")?;
-            write_indented(f, stdout, INDENT)?;
+            writeln!(f, "\n{}:", messages().stdout)?;
+            self.write_output(f, stdout, INDENT)?;
+        } else if let Some(note) = self.uncaptured_note(OutputStream::Stdout) {
+            write!(f, "\n{}: {note}", messages().stdout)?;
         }
 
         // Stdout:
@@ -140,19 +465,98 @@ impl Display for OutputError {
         let stderr = self.output.stderr();
         let stderr = stderr.trim();
         if !stderr.is_empty() {
-            writeln!(f, "\nStderr:")?;
-            write_indented(f, stderr, INDENT)?;
+            writeln!(f, "\n{}:", messages().stderr)?;
+            self.write_output(f, stderr, INDENT)?;
+        } else if let Some(note) = self.uncaptured_note(OutputStream::Stderr) {
+            write!(f, "\n{}: {note}", messages().stderr)?;
+        }
+
+        // Note: stdout remained open 42s after the process exited — a background process may have
+        // inherited the pipe
+        if let Some(stream_timings) = &self.stream_timings {
+            for (stream, lag) in stream_timings.lagging() {
+                write!(
+                    f,
+                    "\nNote: {stream} remained open {:?} after the process exited \
+                    — a background process may have inherited the pipe",
+                    round_duration(lag)
+                )?;
+            }
+
+            // (stderr closed immediately without output)
+            if self.verbose {
+                for stream in [OutputStream::Stdout, OutputStream::Stderr] {
+                    if stream_timings.closed_immediately(stream) {
+                        write!(f, "\n({stream} closed immediately without output)")?;
+                    }
+                }
+            }
+        }
+
+        // stdio: stdin=null stdout=piped stderr=inherited
+        if self.verbose {
+            if let Some(stdio) = self.stdio {
+                write!(f, "\nstdio: {stdio}")?;
+            }
+        }
+
+        // Called at: src/main.rs:12:5
+        if self.verbose {
+            if let Some(location) = self.location() {
+                write!(f, "\nCalled at: {location}")?;
+            }
+        }
+
+        // Diagnostic re-run output (`tool -v`, exit status: 1):
+        //   Stderr:
+        //     ...
+        if let Some(rerun) = &self.diagnostic_rerun {
+            write!(f, "\n{rerun}")?;
         }
         Ok(())
     }
 }
 
+impl OutputError {
+    /// The note shown in place of one of the program's output streams, if it wasn't captured.
+    ///
+    /// Errors from commands run for their exit status alone only include the note when verbose.
+    fn uncaptured_note(&self, stream: OutputStream) -> Option<&'static str> {
+        if !self.verbose && !self.note_uncaptured {
+            return None;
+        }
+        self.stdio?.uncaptured_note(stream)
+    }
+
+    /// Write one of the program's output streams, collapsing repeated lines if enabled.
+    fn write_output(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        text: &str,
+        indent: &str,
+    ) -> std::fmt::Result {
+        if self.collapse_repeats {
+            write_collapsed(f, text, indent)
+        } else {
+            write_indented(f, text, indent)
+        }
+    }
+}
+
 impl std::error::Error for OutputError {}
 
 #[cfg(feature = "miette")]
-impl Diagnostic for OutputError {}
+impl Diagnostic for OutputError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::output"))
+    }
+}
 
-fn write_indented(f: &mut std::fmt::Formatter<'_>, text: &str, indent: &str) -> std::fmt::Result {
+pub(crate) fn write_indented(
+    f: &mut std::fmt::Formatter<'_>,
+    text: &str,
+    indent: &str,
+) -> std::fmt::Result {
     let mut lines = text.lines();
     if let Some(line) = lines.next() {
         write!(f, "{indent}{line}")?;
@@ -163,10 +567,345 @@ fn write_indented(f: &mut std::fmt::Formatter<'_>, text: &str, indent: &str) ->
     Ok(())
 }
 
+/// Write `text` like [`write_indented`], collapsing runs of consecutive identical lines into a
+/// single line followed by `(×N)`.
+///
+/// Trailing whitespace is ignored when comparing lines.
+fn write_collapsed(f: &mut impl std::fmt::Write, text: &str, indent: &str) -> std::fmt::Result {
+    let mut lines = text.lines().map(str::trim_end).peekable();
+    let mut first = true;
+    while let Some(line) = lines.next() {
+        let mut count = 1;
+        while lines.next_if_eq(&line).is_some() {
+            count += 1;
+        }
+        if !first {
+            f.write_char('\n')?;
+        }
+        first = false;
+        write!(f, "{indent}{line}")?;
+        if count > 1 {
+            write!(f, " (×{count})")?;
+        }
+    }
+    Ok(())
+}
+
+/// Write a displayed (shell-quoted) `command`, wrapped between arguments to fit in `width`
+/// columns after the first line's `prefix_len` columns.
+///
+/// Each wrapped line ends with ` \\` and continuation lines are indented, so the result is still
+/// a valid shell command. The closing backtick written after the command is accounted for.
+fn write_wrapped_command(
+    f: &mut std::fmt::Formatter<'_>,
+    command: &str,
+    prefix_len: usize,
+    width: usize,
+) -> std::fmt::Result {
+    const INDENT: &str = "  ";
+    let words = shell_words_in(command);
+    let mut column = prefix_len;
+    for (i, word) in words.iter().enumerate() {
+        let word_len = word.chars().count();
+        if i > 0 {
+            // Leave room for ` \` after this word, or for the closing backtick.
+            let suffix_len = if i + 1 == words.len() { 1 } else { 2 };
+            if column + 1 + word_len + suffix_len > width {
+                write!(f, " \\\n{INDENT}")?;
+                column = INDENT.len();
+            } else {
+                write!(f, " ")?;
+                column += 1;
+            }
+        }
+        write!(f, "{word}")?;
+        column += word_len;
+    }
+    Ok(())
+}
+
+/// Split a shell-quoted command into its space-separated words, keeping quoted and escaped spaces
+/// within their words.
+fn shell_words_in(command: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut in_single_quotes = false;
+    let mut escaped = false;
+    for (i, c) in command.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if in_single_quotes {
+            in_single_quotes = c != '\'';
+        } else if c == '\'' {
+            in_single_quotes = true;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == ' ' {
+            words.push(&command[start..i]);
+            start = i + 1;
+        }
+    }
+    words.push(&command[start..]);
+    words
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
     use static_assertions::assert_impl_all;
+    use std::process::Command;
+    use std::process::ExitStatus;
+    use std::process::Output;
+    use std::process::Stdio;
+
+    use crate::ChildExt;
+    use crate::CommandExt;
+    use crate::Error;
+    use crate::OutputContext;
+    use crate::Utf8ProgramAndArgs;
 
     assert_impl_all!(OutputError: Send, Sync);
+
+    fn wrapped(width: usize) -> String {
+        let mut command = Command::new("terraform");
+        command
+            .current_dir("/srv/infra")
+            .env("TF_LOG", "debug")
+            .args([
+                "apply",
+                "-auto-approve",
+                "-var",
+                "region=us east 1",
+                "-var-file=production.tfvars",
+                "-lock-timeout=5m",
+                "it's",
+            ]);
+        OutputError::new(
+            Box::new(Utf8ProgramAndArgs::from(&command)),
+            Box::new(Output {
+                status: Default::default(),
+                stdout: Vec::new(),
+                stderr: b"Error: a very long line of stderr which is much wider than the wrap width and isn't re-wrapped\n".to_vec(),
+            }),
+        )
+        .with_wrap_width(Some(width))
+        .to_string()
+    }
+
+    fn collapsed(text: &str, indent: &str) -> String {
+        let mut collapsed = String::new();
+        write_collapsed(&mut collapsed, text, indent).unwrap();
+        collapsed
+    }
+
+    #[test]
+    fn test_write_collapsed() {
+        assert_eq!(
+            collapsed("a\na \na\nb\n\n\na\nc\nc", ""),
+            "a (×3)\nb\n (×2)\na\nc (×2)"
+        );
+        assert_eq!(collapsed("a\na\nb", "  "), "  a (×2)\n  b");
+        assert_eq!(collapsed("only", ""), "only");
+        assert_eq!(collapsed("", "  "), "");
+    }
+
+    #[test]
+    fn test_shell_words_in() {
+        assert_eq!(
+            shell_words_in(r#"cd '/a b' && echo 'it'\''s here' a\ b"#),
+            vec!["cd", "'/a b'", "&&", "echo", r#"'it'\''s here'"#, r"a\ b"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_40() {
+        assert_eq!(
+            wrapped(40),
+            indoc!(
+                r#"`terraform` failed: exit status: 0
+                Command failed: `cd /srv/infra && \
+                  TF_LOG=debug terraform apply \
+                  -auto-approve -var \
+                  'region=us east 1' \
+                  '-var-file=production.tfvars' \
+                  '-lock-timeout=5m' 'it'\''s'`
+                Stderr:
+                  Error: a very long line of stderr which is much wider than the wrap width and isn't re-wrapped"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_wrap_80() {
+        assert_eq!(
+            wrapped(80),
+            indoc!(
+                r#"`terraform` failed: exit status: 0
+                Command failed: `cd /srv/infra && TF_LOG=debug terraform apply -auto-approve \
+                  -var 'region=us east 1' '-var-file=production.tfvars' '-lock-timeout=5m' \
+                  'it'\''s'`
+                Stderr:
+                  Error: a very long line of stderr which is much wider than the wrap width and isn't re-wrapped"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_wrap_wide() {
+        let wrapped = wrapped(1000);
+        assert!(wrapped.contains(
+            "Command failed: `cd /srv/infra && TF_LOG=debug terraform apply -auto-approve -var 'region=us east 1' '-var-file=production.tfvars' '-lock-timeout=5m' 'it'\\''s'`"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lossy_note() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut command = Command::new("echo");
+        command.arg(OsStr::from_bytes(b"caf\xe9"));
+        let error = OutputError::new(
+            Box::new(Utf8ProgramAndArgs::from(&command)),
+            Box::new(Output {
+                status: Default::default(),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            }),
+        );
+        assert_eq!(
+            error.to_string(),
+            indoc!(
+                "
+                `echo` failed: exit status: 0
+                Command failed: `echo caf\u{fffd}`
+                Note: some arguments contained non-UTF-8 bytes and are shown approximately"
+            )
+        );
+    }
+
+    #[test]
+    fn test_truncated_without_lossy_note() {
+        let mut command = Command::new("rm");
+        command.args((0..1030).map(|i| format!("{i}.tmp")));
+        let error = OutputError::new(
+            Box::new(Utf8ProgramAndArgs::from(&command)),
+            Box::new(Output {
+                status: Default::default(),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            }),
+        );
+        // The omitted arguments are noted in the command itself.
+        let message = error.to_string();
+        assert!(
+            message.ends_with("1022.tmp 1023.tmp ... (6 more arguments omitted)`"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn test_uncaptured_streams() {
+        let fail = |context: OutputContext<Output>| Err::<Output, _>(context.error());
+        let fail_status =
+            |context: OutputContext<ExitStatus>| Err::<ExitStatus, _>(context.error());
+
+        let err = Command::new("echo")
+            .arg("puppy")
+            .spawn_checked_piped()
+            .unwrap()
+            .output_checked_as(fail)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            indoc!(
+                "`echo` failed: exit status: 0
+                Command failed: `echo puppy`
+                Stdout:
+                  puppy"
+            )
+        );
+
+        // The child waits for its stdin to be closed, which happens when it's waited for, so its
+        // streams can be examined.
+        let err = Command::new("sh")
+            .args(["-c", "read _"])
+            .stdin(Stdio::piped())
+            .spawn_checked()
+            .unwrap()
+            .output_checked_as(fail)
+            .unwrap_err();
+        let note = if cfg!(target_os = "linux") {
+            "inherited"
+        } else {
+            "not piped"
+        };
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "`sh` failed: exit status: 0\n\
+                Command failed: `sh -c 'read _'`\n\
+                Stdout: (output not captured: stream was {note})\n\
+                Stderr: (output not captured: stream was {note})"
+            )
+        );
+
+        // Errors from commands run for their exit status only note uncaptured streams when
+        // verbose.
+        let err = Command::new("true")
+            .status_checked_as(fail_status)
+            .unwrap_err();
+        let Error::Output(err) = err else {
+            panic!("Expected an output error, got {err:?}");
+        };
+        assert_eq!(
+            err.to_string(),
+            "`true` failed: exit status: 0\nCommand failed: `true`"
+        );
+        assert!(
+            err.verbose(true)
+                .to_string()
+                .contains("\nStdout: (output not captured: stream was "),
+            "verbose errors note uncaptured streams"
+        );
+    }
+
+    #[test]
+    fn test_closed_immediately() {
+        let started = std::time::Instant::now();
+        let timings = StreamTimings {
+            started,
+            exited: started + std::time::Duration::from_millis(500),
+            stdout_first_byte: Some(started + std::time::Duration::from_millis(200)),
+            stderr_first_byte: None,
+            stdout_closed: Some(started + std::time::Duration::from_millis(500)),
+            stderr_closed: Some(started + std::time::Duration::from_millis(1)),
+        };
+        assert!(!timings.closed_immediately(OutputStream::Stdout));
+        assert!(timings.closed_immediately(OutputStream::Stderr));
+
+        let error = OutputError::new(
+            Box::new(Utf8ProgramAndArgs::from(&Command::new("ld.so"))),
+            Box::new(Output {
+                status: Default::default(),
+                stdout: b"loading\n".to_vec(),
+                stderr: Vec::new(),
+            }),
+        )
+        .with_stream_timings(timings);
+        let quiet = indoc!(
+            "`ld.so` failed: exit status: 0
+            Command failed: `ld.so`
+            Stdout:
+              loading"
+        );
+        assert_eq!(error.to_string(), quiet);
+        assert_eq!(
+            error.verbose(true).to_string(),
+            format!("{quiet}\n(stderr closed immediately without output)")
+        );
+    }
 }
@@ -0,0 +1,107 @@
+use std::process::ExitStatus;
+
+use crate::CommandDisplay;
+use crate::OutputError;
+
+/// The parts of an [`OutputError`] that identify a failure, ignoring the program's output.
+///
+/// Produced by [`OutputError::signature`]. Two errors with equal signatures ran the same program
+/// with the same arguments and exited with the same code (or were killed by the same signal), so
+/// signatures can be used as [`HashMap`][std::collections::HashMap] keys to group failures
+/// without comparing their (potentially very large) output:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::collections::HashMap;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// # use command_error::Error;
+/// let mut failures = HashMap::<_, Vec<_>>::new();
+/// for time in ["12:00", "12:30", "13:00"] {
+///     let Error::Output(err) = Command::new("sh")
+///         .args(["-c", "echo \"[$TIME] disk full\" >&2; exit 1"])
+///         .env("TIME", time)
+///         .output_checked()
+///         .unwrap_err()
+///     else {
+///         unreachable!()
+///     };
+///     failures.entry(err.signature()).or_default().push(err);
+/// }
+///
+/// assert_eq!(failures.len(), 1);
+/// let (signature, errors) = failures.into_iter().next().unwrap();
+/// assert_eq!(signature.program(), "sh");
+/// assert_eq!(signature.code(), Some(1));
+/// assert_eq!(errors.len(), 3);
+/// ```
+///
+/// The environment and working directory aren't part of the signature.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OutputErrorSignature {
+    program: String,
+    args: Vec<String>,
+    code: Option<i32>,
+    signal: Option<i32>,
+}
+
+impl OutputErrorSignature {
+    pub(crate) fn new(command: &dyn CommandDisplay, status: ExitStatus) -> Self {
+        Self {
+            program: command.program().into_owned(),
+            args: command.args().map(|arg| arg.into_owned()).collect(),
+            code: status.code(),
+            signal: signal(status),
+        }
+    }
+
+    /// The program that failed.
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// The program's arguments.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// The program's exit code, if it exited normally.
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+
+    /// The signal that killed the program, if any.
+    ///
+    /// Always [`None`] on non-Unix platforms.
+    pub fn signal(&self) -> Option<i32> {
+        self.signal
+    }
+}
+
+#[cfg(unix)]
+fn signal(status: ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_status: ExitStatus) -> Option<i32> {
+    None
+}
+
+impl OutputError {
+    /// The program, arguments, and exit status of this error, ignoring its output.
+    ///
+    /// See [`OutputErrorSignature`] for an example.
+    pub fn signature(&self) -> OutputErrorSignature {
+        OutputErrorSignature::new(&*self.command, self.output.status())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_assertions::assert_impl_all;
+
+    assert_impl_all!(OutputErrorSignature: Send, Sync);
+}
@@ -0,0 +1,25 @@
+use std::fmt::Display;
+
+#[cfg(doc)]
+use crate::CommandExt;
+
+/// One of a command's output streams.
+///
+/// Used to label chunks of output passed to streaming methods like
+/// [`CommandExt::output_checked_with_backpressure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputStream {
+    /// The command's standard output.
+    Stdout,
+    /// The command's standard error.
+    Stderr,
+}
+
+impl Display for OutputStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputStream::Stdout => write!(f, "stdout"),
+            OutputStream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
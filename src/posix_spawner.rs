@@ -0,0 +1,540 @@
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::process::Output;
+#[cfg(not(target_os = "linux"))]
+use std::sync::Mutex;
+#[cfg(not(target_os = "linux"))]
+use std::sync::PoisonError;
+use std::thread;
+
+use crate::spawner::sealed::Spawn;
+use crate::spawner::sealed::SpawnFailure;
+#[cfg(doc)]
+use crate::ExecError;
+use crate::Spawner;
+
+/// A [`Spawner`] which starts processes with `posix_spawnp(3)` directly, rather than through
+/// [`std::process`].
+///
+/// Errors report the step of starting the command which failed (see [`ExecError::spawn_step`]):
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// # use command_error::PosixSpawner;
+/// let err = Command::new("ooga booga")
+///     .output_checked_with_spawner(&PosixSpawner)
+///     .unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "Failed to execute `'ooga booga'` via posix_spawn (posix_spawnp): \
+///     No such file or directory (os error 2)"
+/// );
+/// ```
+///
+/// Like [`Command::spawn`], the child starts with `SIGPIPE` at its default disposition (rather
+/// than ignored, as it is in Rust programs) and an empty signal mask, and if the command sets its
+/// own `PATH`, the program is looked up in that rather than in this process's `PATH`.
+///
+/// When capturing output, the command's stdin is `/dev/null` and its stdout and stderr are piped,
+/// like [`Command::output`]; otherwise, all three are inherited.
+///
+/// # Unsupported settings
+///
+/// Only the command's program, arguments, environment variables, and working directory are
+/// used. [`Command`] has no way to read back its other settings, so they can't be detected and
+/// rejected, and are ignored instead. Use [`crate::StdSpawner`] for commands which use:
+///
+/// - Stdio configuration, like [`Command::stdout`].
+/// - [`Command::env_clear`].
+/// - `pre_exec` hooks, and other settings from [`std::os::unix::process::CommandExt`].
+/// - A program or arguments containing NUL bytes. [`Command`] replaces these with a placeholder
+///   when they're added (and fails when it's spawned), so they can't be told apart from other
+///   arguments.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PosixSpawner;
+
+impl Spawner for PosixSpawner {
+    fn name(&self) -> &'static str {
+        "posix_spawn"
+    }
+}
+
+impl Spawn for PosixSpawner {
+    fn output(&self, command: &mut Command) -> Result<Output, SpawnFailure> {
+        let (stdout, stdout_writer) = pipe()?;
+        let (stderr, stderr_writer) = pipe()?;
+        let mut actions = FileActions::new()?;
+        actions.open_null(libc::STDIN_FILENO)?;
+        actions.dup2(&stdout_writer, libc::STDOUT_FILENO)?;
+        actions.dup2(&stderr_writer, libc::STDERR_FILENO)?;
+        let pid = spawn(command, &mut actions)?;
+        drop(stdout_writer);
+        drop(stderr_writer);
+
+        let stderr = thread::spawn(move || {
+            let mut buffer = Vec::new();
+            File::from(stderr).read_to_end(&mut buffer).map(|_| buffer)
+        });
+        let mut stdout_buffer = Vec::new();
+        let stdout = File::from(stdout).read_to_end(&mut stdout_buffer);
+        // The reader thread doesn't panic.
+        let stderr = stderr.join().unwrap_or_else(|_| Ok(Vec::new()));
+        // Wait for the child even if reading its output failed, so that it's reaped.
+        let status = wait(pid)?;
+        stdout.map_err(SpawnFailure::Read)?;
+
+        Ok(Output {
+            status,
+            stdout: stdout_buffer,
+            stderr: stderr.map_err(SpawnFailure::Read)?,
+        })
+    }
+
+    fn status(&self, command: &mut Command) -> Result<ExitStatus, SpawnFailure> {
+        let mut actions = FileActions::new()?;
+        let pid = spawn(command, &mut actions)?;
+        wait(pid)
+    }
+}
+
+/// Start `command` with `actions`, returning its process ID.
+fn spawn(command: &Command, actions: &mut FileActions) -> Result<libc::pid_t, SpawnFailure> {
+    let program = c_string(command.get_program(), "argv")?;
+    let resolved = resolve(command, &program)?;
+    let args = command
+        .get_args()
+        .map(|arg| c_string(arg, "argv"))
+        .collect::<Result<Vec<_>, _>>()?;
+    let argv = std::iter::once(&program)
+        .chain(&args)
+        .map(|arg| arg.as_ptr().cast_mut())
+        .chain(std::iter::once(std::ptr::null_mut()))
+        .collect::<Vec<_>>();
+
+    let env = environment(command)?;
+    let envp = env
+        .iter()
+        .map(|var| var.as_ptr().cast_mut())
+        .chain(std::iter::once(std::ptr::null_mut()))
+        .collect::<Vec<_>>();
+
+    if let Some(dir) = command.get_current_dir() {
+        let dir = c_string(dir.as_os_str(), "current_dir")?;
+        actions.chdir(&dir)?;
+    }
+
+    let attrs = SpawnAttrs::new()?;
+    #[cfg(not(target_os = "linux"))]
+    let _guard = PIPE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut pid = 0;
+    // SAFETY: The pointers are valid, NUL-terminated, and outlive the call.
+    match &resolved {
+        Some(path) => check(
+            unsafe {
+                libc::posix_spawn(
+                    &mut pid,
+                    path.as_ptr(),
+                    &*actions.0,
+                    &*attrs.0,
+                    argv.as_ptr(),
+                    envp.as_ptr(),
+                )
+            },
+            "posix_spawn",
+        )?,
+        None => check(
+            unsafe {
+                libc::posix_spawnp(
+                    &mut pid,
+                    program.as_ptr(),
+                    &*actions.0,
+                    &*attrs.0,
+                    argv.as_ptr(),
+                    envp.as_ptr(),
+                )
+            },
+            "posix_spawnp",
+        )?,
+    }
+    Ok(pid)
+}
+
+/// The search path `execvp(3)` uses when `PATH` isn't set.
+const DEFAULT_PATH: &str = "/bin:/usr/bin";
+
+/// Find `program` in the `PATH` set on `command`, if it sets or removes `PATH`.
+///
+/// `posix_spawnp` searches this process's `PATH`, which is only the child's `PATH` if the command
+/// doesn't change it. Returns [`None`] if `posix_spawnp` can search for the program itself.
+fn resolve(command: &Command, program: &CString) -> Result<Option<CString>, SpawnFailure> {
+    if program.as_bytes().contains(&b'/') {
+        return Ok(None);
+    }
+    let Some((_, path)) = command.get_envs().find(|(key, _)| *key == "PATH") else {
+        return Ok(None);
+    };
+    let path = path.unwrap_or(OsStr::new(DEFAULT_PATH));
+    for dir in path.as_bytes().split(|byte| *byte == b':') {
+        // An empty entry is the working directory.
+        let dir = Path::new(OsStr::from_bytes(if dir.is_empty() {
+            b".".as_slice()
+        } else {
+            dir
+        }));
+        let candidate = dir.join(OsStr::from_bytes(program.as_bytes()));
+        // Relative entries are relative to the directory the child starts in.
+        let in_child = match command.get_current_dir() {
+            Some(current_dir) => current_dir.join(&candidate),
+            None => candidate.clone(),
+        };
+        if is_executable(&in_child) {
+            return c_string(candidate.as_os_str(), "PATH").map(Some);
+        }
+    }
+    Err(SpawnFailure::Spawn {
+        step: Some("PATH"),
+        inner: std::io::Error::from_raw_os_error(libc::ENOENT),
+    })
+}
+
+/// Whether `path` is an executable file.
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Get the environment for `command`, as `KEY=VALUE` strings.
+fn environment(command: &Command) -> Result<Vec<CString>, SpawnFailure> {
+    let mut vars = std::env::vars_os().collect::<BTreeMap<OsString, OsString>>();
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => {
+                vars.insert(key.to_owned(), value.to_owned());
+            }
+            None => {
+                vars.remove(key);
+            }
+        }
+    }
+    vars.into_iter()
+        .map(|(key, value)| {
+            let mut var = key;
+            var.push("=");
+            var.push(value);
+            c_string(&var, "envp")
+        })
+        .collect()
+}
+
+/// Wait for the process `pid` to exit.
+fn wait(pid: libc::pid_t) -> Result<ExitStatus, SpawnFailure> {
+    let mut status = 0;
+    loop {
+        // SAFETY: `status` is a valid pointer.
+        if unsafe { libc::waitpid(pid, &mut status, 0) } == pid {
+            return Ok(ExitStatus::from_raw(status));
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(SpawnFailure::Wait(error));
+        }
+    }
+}
+
+/// Held while creating a pipe and marking it close-on-exec, and while starting a child, on
+/// platforms without `pipe2`, so that a pipe can't leak into a child started by another thread in
+/// between.
+///
+/// Children started by other means, like [`Command::spawn`], don't take this lock.
+#[cfg(not(target_os = "linux"))]
+static PIPE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Create a close-on-exec pipe, returning its read and write ends.
+fn pipe() -> Result<(OwnedFd, OwnedFd), SpawnFailure> {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for two file descriptors.
+    #[cfg(target_os = "linux")]
+    let result = unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) };
+    #[cfg(not(target_os = "linux"))]
+    let _guard = PIPE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    #[cfg(not(target_os = "linux"))]
+    let result = unsafe { libc::pipe(fds.as_mut_ptr()) };
+    if result != 0 {
+        return Err(SpawnFailure::Spawn {
+            step: Some("pipe"),
+            inner: std::io::Error::last_os_error(),
+        });
+    }
+    // SAFETY: `pipe` succeeded, so these are open file descriptors which we own.
+    let fds = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    #[cfg(not(target_os = "linux"))]
+    for fd in [&fds.0, &fds.1] {
+        // SAFETY: `fd` is an open file descriptor.
+        if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+            return Err(SpawnFailure::Spawn {
+                step: Some("fcntl"),
+                inner: std::io::Error::last_os_error(),
+            });
+        }
+    }
+    Ok(fds)
+}
+
+/// Convert `value` to a C string, failing at `step` if it contains a NUL byte.
+fn c_string(value: &OsStr, step: &'static str) -> Result<CString, SpawnFailure> {
+    CString::new(value.as_bytes()).map_err(|_| SpawnFailure::Spawn {
+        step: Some(step),
+        inner: std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "nul byte found in provided data",
+        ),
+    })
+}
+
+/// Check the return value of a `posix_spawn` function, which returns an error number on failure.
+fn check(result: libc::c_int, step: &'static str) -> Result<(), SpawnFailure> {
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(SpawnFailure::Spawn {
+            step: Some(step),
+            inner: std::io::Error::from_raw_os_error(result),
+        })
+    }
+}
+
+/// Check the return value of a function which returns -1 and sets `errno` on failure.
+fn check_errno(result: libc::c_int, step: &'static str) -> Result<(), SpawnFailure> {
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(SpawnFailure::Spawn {
+            step: Some(step),
+            inner: std::io::Error::last_os_error(),
+        })
+    }
+}
+
+/// An initialized `posix_spawn_file_actions_t`, destroyed when dropped.
+///
+/// This is boxed so that it doesn't move after it's initialized.
+struct FileActions(Box<libc::posix_spawn_file_actions_t>);
+
+impl FileActions {
+    fn new() -> Result<Self, SpawnFailure> {
+        // SAFETY: `posix_spawn_file_actions_t` is a plain C type, and it's initialized before use.
+        let mut actions = Box::new(unsafe { std::mem::zeroed() });
+        // SAFETY: `actions` is valid for writes.
+        check(
+            unsafe { libc::posix_spawn_file_actions_init(&mut *actions) },
+            "posix_spawn_file_actions_init",
+        )?;
+        Ok(Self(actions))
+    }
+
+    /// Open `/dev/null` as `target` in the child.
+    fn open_null(&mut self, target: libc::c_int) -> Result<(), SpawnFailure> {
+        // SAFETY: The actions are initialized and the path is NUL-terminated.
+        check(
+            unsafe {
+                libc::posix_spawn_file_actions_addopen(
+                    &mut *self.0,
+                    target,
+                    c"/dev/null".as_ptr(),
+                    libc::O_RDONLY,
+                    0,
+                )
+            },
+            "posix_spawn_file_actions_addopen",
+        )
+    }
+
+    /// Duplicate `fd` as `target` in the child.
+    fn dup2(&mut self, fd: &OwnedFd, target: libc::c_int) -> Result<(), SpawnFailure> {
+        // SAFETY: The actions are initialized.
+        check(
+            unsafe { libc::posix_spawn_file_actions_adddup2(&mut *self.0, fd.as_raw_fd(), target) },
+            "posix_spawn_file_actions_adddup2",
+        )
+    }
+
+    /// Change to `dir` in the child.
+    fn chdir(&mut self, dir: &CString) -> Result<(), SpawnFailure> {
+        // SAFETY: The actions are initialized and the path is NUL-terminated.
+        check(
+            unsafe { libc::posix_spawn_file_actions_addchdir_np(&mut *self.0, dir.as_ptr()) },
+            "posix_spawn_file_actions_addchdir_np",
+        )
+    }
+}
+
+impl Drop for FileActions {
+    fn drop(&mut self) {
+        // SAFETY: The actions were initialized in `FileActions::new`.
+        unsafe {
+            libc::posix_spawn_file_actions_destroy(&mut *self.0);
+        }
+    }
+}
+
+/// An initialized `posix_spawnattr_t`, destroyed when dropped.
+///
+/// This is boxed so that it doesn't move after it's initialized.
+struct SpawnAttrs(Box<libc::posix_spawnattr_t>);
+
+impl SpawnAttrs {
+    /// Attributes which reset `SIGPIPE` to its default disposition and clear the signal mask in
+    /// the child, like [`Command::spawn`] does.
+    ///
+    /// The Rust runtime ignores `SIGPIPE`, and ignored signals and the signal mask are inherited
+    /// across `exec`, so otherwise the child would ignore `SIGPIPE` too.
+    fn new() -> Result<Self, SpawnFailure> {
+        // SAFETY: `posix_spawnattr_t` is a plain C type, and it's initialized before use.
+        let mut attrs = Box::new(unsafe { std::mem::zeroed() });
+        // SAFETY: `attrs` is valid for writes.
+        check(
+            unsafe { libc::posix_spawnattr_init(&mut *attrs) },
+            "posix_spawnattr_init",
+        )?;
+        let mut attrs = Self(attrs);
+
+        // SAFETY: `sigset_t` is a plain C type, and it's initialized before use.
+        let mut signals: libc::sigset_t = unsafe { std::mem::zeroed() };
+        // SAFETY: `signals` is valid for writes.
+        check_errno(unsafe { libc::sigemptyset(&mut signals) }, "sigemptyset")?;
+        // SAFETY: The attributes are initialized and `signals` is a valid signal set.
+        check(
+            unsafe { libc::posix_spawnattr_setsigmask(&mut *attrs.0, &signals) },
+            "posix_spawnattr_setsigmask",
+        )?;
+        // SAFETY: `signals` is a valid signal set.
+        check_errno(
+            unsafe { libc::sigaddset(&mut signals, libc::SIGPIPE) },
+            "sigaddset",
+        )?;
+        // SAFETY: The attributes are initialized and `signals` is a valid signal set.
+        check(
+            unsafe { libc::posix_spawnattr_setsigdefault(&mut *attrs.0, &signals) },
+            "posix_spawnattr_setsigdefault",
+        )?;
+        let flags = libc::POSIX_SPAWN_SETSIGDEF | libc::POSIX_SPAWN_SETSIGMASK;
+        // SAFETY: The attributes are initialized.
+        check(
+            unsafe { libc::posix_spawnattr_setflags(&mut *attrs.0, flags as _) },
+            "posix_spawnattr_setflags",
+        )?;
+        Ok(attrs)
+    }
+}
+
+impl Drop for SpawnAttrs {
+    fn drop(&mut self) {
+        // SAFETY: The attributes were initialized in `SpawnAttrs::new`.
+        unsafe {
+            libc::posix_spawnattr_destroy(&mut *self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use static_assertions::assert_impl_all;
+
+    use crate::CommandExt;
+    use crate::Error;
+
+    assert_impl_all!(PosixSpawner: Send, Sync);
+
+    #[test]
+    fn test_output() {
+        let output = Command::new("sh")
+            .args(["-c", "echo \"$GREETING\"; pwd; echo puppy >&2"])
+            .env("GREETING", "howdy")
+            .current_dir("/")
+            .output_checked_with_spawner(&PosixSpawner)
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "howdy\n/\n");
+        assert_eq!(String::from_utf8(output.stderr).unwrap(), "puppy\n");
+    }
+
+    #[test]
+    fn test_failure() {
+        let err = Command::new("sh")
+            .args(["-c", "exit 3"])
+            .status_checked_with_spawner(&PosixSpawner)
+            .unwrap_err();
+        assert_eq!(err.exit_code(), 3);
+        assert!(matches!(err, Error::Output(_)));
+    }
+
+    #[test]
+    fn test_nul_byte() {
+        let err = Command::new("echo")
+            .env("GREETING", "how\0dy")
+            .output_checked_with_spawner(&PosixSpawner)
+            .unwrap_err();
+        match err {
+            Error::Exec(err) => {
+                assert_eq!(err.backend(), Some("posix_spawn"));
+                assert_eq!(err.spawn_step(), Some("envp"));
+            }
+            err => panic!("Unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_sigpipe_default() {
+        // If `yes` ignored `SIGPIPE`, it would report the broken pipe when `head` exits instead
+        // of being killed by it.
+        let output = Command::new("sh")
+            .args(["-c", "yes | head -n 1"])
+            .output_checked_with_spawner(&PosixSpawner)
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "y\n");
+        assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+    }
+
+    #[test]
+    fn test_command_path() {
+        let dir = std::env::temp_dir().join(format!("command-error-path-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("puppy-greeting");
+        std::fs::write(&script, "#!/bin/sh\necho howdy\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let output = Command::new("puppy-greeting")
+            .env("PATH", &dir)
+            .output_checked_with_spawner(&PosixSpawner);
+        let missing = Command::new("sh")
+            .env("PATH", &dir)
+            .output_checked_with_spawner(&PosixSpawner);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.unwrap().stdout).unwrap(),
+            "howdy\n"
+        );
+        // `sh` isn't in the command's `PATH`, even though it's in ours.
+        match missing.unwrap_err() {
+            Error::Exec(err) => assert_eq!(err.spawn_step(), Some("PATH")),
+            err => panic!("Unexpected error: {err}"),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+#[cfg(doc)]
+use std::process::Command;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Error;
+
+/// Records which setting a `pre_exec` hook applied in a child process, so that hooks stacked on
+/// one [`Command`] don't apply conflicting settings.
+///
+/// [`Command`] has no way to inspect or remove `pre_exec` hooks, so calling a method like
+/// [`CommandExt::output_checked_with_limits`] twice on one command installs two hooks, which both
+/// run in the child. The first hook to run claims the guard. Later hooks with the same setting do
+/// nothing, and later hooks with a different setting fail with `EALREADY`, so the command fails
+/// to start instead of running with both settings.
+///
+/// The guard is only claimed in child processes after they've forked, so it's unclaimed in each
+/// new child.
+pub(crate) struct PreExecGuard(AtomicUsize);
+
+impl PreExecGuard {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicUsize::new(0))
+    }
+
+    /// Claim the guard for the setting identified by `fingerprint`, returning `false` if an
+    /// earlier hook already applied the same setting.
+    ///
+    /// This is async-signal-safe and doesn't allocate, so it can be called in a `pre_exec` hook.
+    pub(crate) fn claim(&self, fingerprint: usize) -> std::io::Result<bool> {
+        match self
+            .0
+            .compare_exchange(0, fingerprint, Ordering::Relaxed, Ordering::Relaxed)
+        {
+            Ok(_) => Ok(true),
+            Err(applied) if applied == fingerprint => Ok(false),
+            Err(_) => Err(std::io::Error::from_raw_os_error(libc::EALREADY)),
+        }
+    }
+}
+
+/// Identify a setting for [`PreExecGuard::claim`].
+pub(crate) fn fingerprint(setting: &impl Hash) -> usize {
+    let mut hasher = DefaultHasher::new();
+    setting.hash(&mut hasher);
+    // Zero means the guard is unclaimed.
+    (hasher.finish() as usize) | 1
+}
+
+/// Explain a failure to start a command because a hook stacked on it by an earlier call refused to
+/// apply a different setting.
+///
+/// `step` names the failing step in the error, and `reason` replaces the `EALREADY` message.
+pub(crate) fn explain(error: Error, step: &'static str, reason: &str) -> Error {
+    match error {
+        Error::Exec(mut error) if error.inner.raw_os_error() == Some(libc::EALREADY) => {
+            error.inner = std::io::Error::new(std::io::ErrorKind::AlreadyExists, reason);
+            error.step = Some(step);
+            Error::Exec(error)
+        }
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_claim() {
+        let guard = PreExecGuard::new();
+        let puppy = fingerprint(&"puppy");
+        assert!(guard.claim(puppy).unwrap());
+        assert!(!guard.claim(puppy).unwrap());
+        assert_eq!(
+            guard
+                .claim(fingerprint(&"doggy"))
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::EALREADY)
+        );
+    }
+}
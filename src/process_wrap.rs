@@ -1,18 +1,101 @@
+use std::borrow::Borrow;
 use std::fmt::Debug;
 use std::fmt::Display;
+#[cfg(unix)]
+use std::os::fd::OwnedFd;
+use std::panic::Location;
+#[cfg(unix)]
+use std::path::Path;
+use std::process::ChildStdin;
+use std::process::ChildStdout;
+use std::process::Command;
+use std::process::ExitStatus;
 use std::process::Output;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::Instant;
 
 use process_wrap::std::StdChildWrapper;
 use process_wrap::std::StdCommandWrap;
 
+use crate::capture;
+use crate::capture::CaptureFailure;
+use crate::capture::WaitChild;
+use crate::capture::RETAINED_TAIL;
+#[cfg(unix)]
+use crate::chroot;
+use crate::fallback;
+use crate::run;
+use crate::run::Finished;
+use crate::run::Pipes;
+use crate::run::Ran;
+use crate::spawn_hook::run_spawn_hook;
 use crate::ChildContext;
+use crate::Clock;
 use crate::CommandExt;
 use crate::Error;
 use crate::ExecError;
+#[cfg(feature = "tracing")]
+use crate::LazyProgramAndArgs;
 use crate::OutputContext;
-use crate::OutputConversionError;
 use crate::OutputLike;
+use crate::OutputStream;
+#[cfg(unix)]
+use crate::ResourceLimits;
+#[cfg(all(
+    feature = "seccomp",
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+use crate::SeccompFilter;
+use crate::Spawner;
+use crate::Started;
+use crate::TryWaitContext;
+use crate::TryWaitExt;
 use crate::Utf8ProgramAndArgs;
+use crate::WaitError;
+
+impl WaitChild for Box<dyn StdChildWrapper> {
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        StdChildWrapper::try_wait(&mut **self)
+    }
+
+    fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        StdChildWrapper::wait(&mut **self)
+    }
+
+    #[cfg(unix)]
+    fn terminate(&mut self) -> std::io::Result<bool> {
+        StdChildWrapper::signal(&**self, libc::SIGTERM).map(|()| true)
+    }
+
+    #[cfg(not(unix))]
+    fn terminate(&mut self) -> std::io::Result<bool> {
+        Ok(false)
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        StdChildWrapper::kill(&mut **self)
+    }
+}
+
+impl TryWaitExt for ChildContext<Box<dyn StdChildWrapper>> {
+    type Error = Error;
+
+    fn try_wait_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(TryWaitContext) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        E: From<Self::Error>,
+    {
+        let command = dyn_clone::clone_box(self.command.borrow());
+        match StdChildWrapper::try_wait(&mut *self.child) {
+            Ok(status) => succeeded(TryWaitContext { status, command }),
+            Err(inner) => Err(Error::from(WaitError { inner, command }).into()),
+        }
+    }
+}
 
 impl CommandExt for StdCommandWrap {
     type Error = Error;
@@ -21,12 +104,190 @@ impl CommandExt for StdCommandWrap {
     fn log(&self) -> Result<(), Self::Error> {
         #[cfg(feature = "tracing")]
         {
-            let command: Utf8ProgramAndArgs = self.command().into();
+            let command = LazyProgramAndArgs::new(self.command());
             tracing::debug!(%command, "Executing command");
         }
         Ok(())
     }
 
+    fn command_display(&self) -> Utf8ProgramAndArgs {
+        self.command().into()
+    }
+
+    fn output_checked_trying(&mut self, programs: &[&str]) -> Result<Output, Self::Error> {
+        for program in programs {
+            // Swap in the fallback program so that the wrappers are still applied.
+            let candidate = fallback::with_program(self.command(), program);
+            let original = std::mem::replace(self.command_mut(), candidate);
+            let result = self.output_checked();
+            *self.command_mut() = original;
+            match result {
+                Err(error) if fallback::is_not_found(&error) => {}
+                result => return result,
+            }
+        }
+        Err(fallback::not_found(self.command(), programs))
+    }
+
+    #[cfg(unix)]
+    fn output_checked_with_stdin_fd(&mut self, stdin: OwnedFd) -> Result<Output, Self::Error> {
+        self.command_mut().stdin(Stdio::from(stdin));
+        self.output_checked()
+    }
+
+    #[cfg(unix)]
+    fn output_checked_with_chroot(
+        &mut self,
+        root: impl AsRef<Path>,
+    ) -> Result<Output, Self::Error> {
+        let root = root.as_ref();
+        if let Err(inner) = chroot::apply(self.command_mut(), root) {
+            return Err(Error::from(ExecError::new(display(self), inner)));
+        }
+        output_displayed(self, |command| {
+            Utf8ProgramAndArgs::from(command).with_chroot(root.to_string_lossy())
+        })
+        .map_err(chroot::explain)?
+        .check_output(OutputContext::require_success)
+    }
+
+    #[cfg(unix)]
+    fn output_checked_with_limits(
+        &mut self,
+        limits: ResourceLimits,
+    ) -> Result<Output, Self::Error> {
+        limits.apply(self.command_mut());
+        self.output_checked_as(|context| limits.check(context))
+            .map_err(ResourceLimits::explain_repeated)
+    }
+
+    #[cfg(all(
+        feature = "seccomp",
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    fn output_checked_with_seccomp(
+        &mut self,
+        filter: &SeccompFilter,
+    ) -> Result<Output, Self::Error> {
+        if let Err(inner) = filter.apply(self.command_mut()) {
+            return Err(Error::from(ExecError::new(display(self), inner)));
+        }
+        self.output_checked_as(|context| filter.check(context))
+            .map_err(SeccompFilter::explain_repeated)
+    }
+
+    fn output_checked_with_clean_env(
+        &mut self,
+        vars: &[(&str, &str)],
+    ) -> Result<Output, Self::Error> {
+        self.command_mut().env_clear().envs(vars.iter().copied());
+        output_displayed(self, |command| {
+            Utf8ProgramAndArgs::from(command).with_env_clear()
+        })?
+        .check_output(OutputContext::require_success)
+    }
+
+    fn output_checked_with_stdin_writer(
+        &mut self,
+        interact: impl FnOnce(&mut ChildStdin, &mut ChildStdout) -> std::io::Result<()>,
+    ) -> Result<Output, Self::Error> {
+        output_interactive(self, Box::new(interact))?.check_output(OutputContext::require_success)
+    }
+
+    fn output_checked_with_input_as<O, R, E>(
+        &mut self,
+        input: &[u8],
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        output_with_input(self, input, true)?.check_output(succeeded)
+    }
+
+    fn output_checked_with_stdin_keeping_open(
+        &mut self,
+        input: impl AsRef<[u8]>,
+        close: bool,
+    ) -> Result<Output, Self::Error> {
+        output_with_input(self, input.as_ref(), close)?.check_output(OutputContext::require_success)
+    }
+
+    fn output_checked_into_buf(
+        &mut self,
+        stdout: &mut Vec<u8>,
+        stderr: &mut Vec<u8>,
+    ) -> Result<ExitStatus, Self::Error> {
+        output_into(self, stdout, stderr)?.check_into(stdout, stderr)
+    }
+
+    fn output_checked_with_spawner(
+        &mut self,
+        spawner: &dyn Spawner,
+    ) -> Result<Output, Self::Error> {
+        run::output_spawned(self.command_mut(), spawner)?
+            .check_output(OutputContext::require_success)
+    }
+
+    fn status_checked_with_spawner(
+        &mut self,
+        spawner: &dyn Spawner,
+    ) -> Result<ExitStatus, Self::Error> {
+        run::status_spawned(self.command_mut(), spawner)?
+            .check_status(OutputContext::require_success)
+    }
+
+    fn output_checked_with_timeout_and_clock(
+        &mut self,
+        timeout: Duration,
+        grace: Duration,
+        clock: &dyn Clock,
+    ) -> Result<Output, Self::Error> {
+        output_with_timeout(self, timeout, grace, clock)?
+            .check_output(OutputContext::require_success)
+    }
+
+    fn succeeds(&mut self) -> Result<bool, Self::Error> {
+        self.command_mut()
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        self.status_checked_as(|context| Ok(context.success()))
+    }
+
+    fn output_checked_on_stall(
+        &mut self,
+        interval: Duration,
+        mut on_stall: impl FnMut(Duration),
+    ) -> Result<Output, Self::Error> {
+        output_on_stall(self, interval, &mut on_stall)?.check_output(OutputContext::require_success)
+    }
+
+    fn status_checked_with_timeout_and_clock(
+        &mut self,
+        timeout: Duration,
+        grace: Duration,
+        clock: &dyn Clock,
+    ) -> Result<ExitStatus, Self::Error> {
+        status_with_timeout(self, timeout, grace, clock)?
+            .check_status(OutputContext::require_success)
+    }
+
+    fn output_checked_timeout_as<O, R, E>(
+        &mut self,
+        timeout: Duration,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        output_with_timeout(self, timeout, Duration::ZERO, &Instant::now)?.check_output(succeeded)
+    }
+
     fn output_checked_as<O, R, E>(
         &mut self,
         succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
@@ -36,33 +297,31 @@ impl CommandExt for StdCommandWrap {
         <O as TryFrom<Output>>::Error: Display + Send + Sync,
         E: From<Self::Error> + Send + Sync,
     {
-        self.log()?;
-        let displayed: Utf8ProgramAndArgs = self.command().into();
-        let child = match self.spawn() {
-            Ok(child) => child,
-            Err(inner) => {
-                return Err(Error::from(ExecError::new(Box::new(displayed), inner)).into());
-            }
-        };
-
-        match child.wait_with_output() {
-            Ok(output) => match output.try_into() {
-                Ok(output) => succeeded(OutputContext {
-                    output,
-                    command: Box::new(displayed),
-                }),
-                Err(error) => Err(Error::from(OutputConversionError {
-                    command: Box::new(displayed),
-                    inner: Box::new(error),
-                })
-                .into()),
-            },
-            Err(inner) => Err(Error::from(ExecError {
-                command: Box::new(displayed),
-                inner,
-            })
-            .into()),
-        }
+        output(self)?.check_output(succeeded)
+    }
+
+    fn output_checked_streaming_as<O, R, E>(
+        &mut self,
+        bound: usize,
+        mut on_chunk: impl FnMut(OutputStream, &[u8]),
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        output_streaming(self, bound, usize::MAX, &mut on_chunk)?.check_output(succeeded)
+    }
+
+    fn status_checked_with_backpressure(
+        &mut self,
+        bound: usize,
+        mut on_chunk: impl FnMut(OutputStream, &[u8]),
+    ) -> Result<ExitStatus, Self::Error> {
+        output_streaming(self, bound, RETAINED_TAIL, &mut on_chunk)?
+            .check_output(OutputContext::<Output>::require_success)
+            .map(|output| output.status)
     }
 
     fn status_checked_as<R, E>(
@@ -72,39 +331,239 @@ impl CommandExt for StdCommandWrap {
     where
         E: From<Self::Error>,
     {
-        self.log()?;
-        let displayed: Utf8ProgramAndArgs = self.command().into();
-        let mut child = match self.spawn() {
-            Ok(child) => child,
-            Err(inner) => {
-                return Err(Error::from(ExecError::new(Box::new(displayed), inner)).into());
-            }
-        };
-
-        match child.wait() {
-            Ok(status) => succeeded(OutputContext {
-                output: status,
-                command: Box::new(displayed),
-            }),
-            Err(inner) => Err(Error::from(ExecError {
-                command: Box::new(displayed),
-                inner,
-            })
-            .into()),
-        }
+        status(self)?.check_status(succeeded)
     }
 
     fn spawn_checked(&mut self) -> Result<Self::Child, Self::Error> {
-        let displayed: Utf8ProgramAndArgs = self.command().into();
+        run_spawn_hook(self.command_mut());
+        self.log()?;
+        let displayed = Utf8ProgramAndArgs::from(self.command()).with_location(Location::caller());
         match self.spawn() {
-            Ok(child) => Ok(ChildContext {
-                child,
-                command: Box::new(displayed),
-            }),
-            Err(inner) => Err(Error::from(ExecError {
-                command: Box::new(displayed),
+            Ok(child) => Ok(ChildContext::new(child, Box::new(displayed))),
+            Err(inner) => Err(Error::from(
+                ExecError::new(Box::new(displayed), inner)
+                    .with_current_dir(self.command().get_current_dir()),
+            )),
+        }
+    }
+
+    fn spawn_checked_piped(&mut self) -> Result<Self::Child, Self::Error> {
+        self.command_mut()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        self.spawn_checked()
+    }
+}
+
+/// Display `command` for an error or context object, after it's run.
+#[track_caller]
+fn display(command: &StdCommandWrap) -> Box<Utf8ProgramAndArgs> {
+    Box::new(Utf8ProgramAndArgs::from(command.command()).with_location(Location::caller()))
+}
+
+/// Run `command` and capture its output.
+///
+/// See [`run`] for why this isn't generic.
+#[track_caller]
+fn output(command: &mut StdCommandWrap) -> Result<Ran<Output>, Error> {
+    output_displayed(command, |command| command.into())
+}
+
+/// Run `command` and capture its output, displaying it with `display` in errors.
+#[track_caller]
+fn output_displayed(
+    command: &mut StdCommandWrap,
+    display: impl FnOnce(&Command) -> Utf8ProgramAndArgs,
+) -> Result<Ran<Output>, Error> {
+    let location = Location::caller();
+    run_spawn_hook(command.command_mut());
+    command.log()?;
+    let started = Started::now();
+    let result = command.spawn().map(|child| child.wait_with_output());
+    let displayed = Box::new(display(command.command()).with_location(location));
+    match result {
+        Ok(Ok(output)) => {
+            started.log_completion(&displayed, output.status);
+            Ok(Ran::new(output, displayed))
+        }
+        Ok(Err(inner)) | Err(inner) => Err(run::exec_error(
+            displayed,
+            command.command().get_current_dir(),
+            inner,
+        )),
+    }
+}
+
+/// Run `command`, passing its output to `on_chunk` as it's read.
+#[track_caller]
+fn output_streaming(
+    command: &mut StdCommandWrap,
+    bound: usize,
+    retain: usize,
+    on_chunk: &mut dyn FnMut(OutputStream, &[u8]),
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::Output, |child| {
+        let stdout = child.stdout().take();
+        let stderr = child.stderr().take();
+        capture::capture(child, stdout, stderr, bound, retain, on_chunk)
+    })?;
+    Ok(finished.into_ran())
+}
+
+/// Run `command` and capture its output, calling `on_stall` whenever it produces no output for
+/// longer than `interval`.
+#[track_caller]
+fn output_on_stall(
+    command: &mut StdCommandWrap,
+    interval: Duration,
+    on_stall: &mut dyn FnMut(Duration),
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::Output, |child| {
+        let stdout = child.stdout().take();
+        let stderr = child.stderr().take();
+        capture::capture_on_stall(child, stdout, stderr, interval, on_stall)
+    })?;
+    Ok(finished.into_ran())
+}
+
+/// Run `command` without capturing its output.
+#[track_caller]
+fn status(command: &mut StdCommandWrap) -> Result<Ran<ExitStatus>, Error> {
+    run_spawn_hook(command.command_mut());
+    command.log()?;
+    let started = Started::now();
+    let result = command.spawn().map(|mut child| child.wait());
+    let displayed = display(command);
+    match result {
+        Ok(Ok(status)) => {
+            started.log_completion(&displayed, status);
+            Ok(Ran::new(status, displayed))
+        }
+        Ok(Err(inner)) | Err(inner) => Err(run::exec_error(
+            displayed,
+            command.command().get_current_dir(),
+            inner,
+        )),
+    }
+}
+
+/// Run `command`, reading its output into `stdout` and `stderr`.
+#[track_caller]
+fn output_into(
+    command: &mut StdCommandWrap,
+    stdout: &mut Vec<u8>,
+    stderr: &mut Vec<u8>,
+) -> Result<Ran<ExitStatus>, Error> {
+    let finished = run_piped(command, Pipes::Output, |child| {
+        let child_stdout = child.stdout().take();
+        let child_stderr = child.stderr().take();
+        capture::read_into(child, child_stdout, child_stderr, stdout, stderr)
+    })?;
+    Ok(finished.into_ran())
+}
+
+/// Run `command` with piped stdio, letting `interact` write to its stdin and read from its stdout.
+#[track_caller]
+fn output_interactive(
+    command: &mut StdCommandWrap,
+    interact: capture::Interact<'_>,
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::All, |child| {
+        let stdin = child.stdin().take().expect("stdin is piped");
+        let stdout = child.stdout().take().expect("stdout is piped");
+        let stderr = child.stderr().take();
+        capture::interact(child, stdin, stdout, stderr, interact)
+    })?;
+    run::interactive_result(finished)
+}
+
+/// Run `command` with piped stdio, writing `input` to its stdin and capturing its output.
+#[track_caller]
+fn output_with_input(
+    command: &mut StdCommandWrap,
+    input: &[u8],
+    close_stdin: bool,
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::All, |child| {
+        let stdin = child.stdin().take().expect("stdin is piped");
+        let stdout = child.stdout().take();
+        let stderr = child.stderr().take();
+        capture::capture_with_input(child, stdin, stdout, stderr, input, close_stdin)
+    })?;
+    Ok(finished.into_ran())
+}
+
+/// Run `command` without capturing its output, stopping it if it runs for longer than `timeout`.
+///
+/// See [`run::status_with_timeout`].
+#[track_caller]
+fn status_with_timeout(
+    command: &mut StdCommandWrap,
+    timeout: Duration,
+    grace: Duration,
+    clock: &dyn Clock,
+) -> Result<Ran<ExitStatus>, Error> {
+    let finished = run_piped(command, Pipes::None, |child| {
+        capture::wait_with_timeout(child, timeout, grace, clock)
+    })?;
+    finished.into_timed_out(timeout)
+}
+
+/// Run `command` and capture its output, stopping it if it runs for longer than `timeout`.
+#[track_caller]
+fn output_with_timeout(
+    command: &mut StdCommandWrap,
+    timeout: Duration,
+    grace: Duration,
+    clock: &dyn Clock,
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::Output, |child| {
+        let stdout = child.stdout().take();
+        let stderr = child.stderr().take();
+        capture::capture_with_timeout(child, stdout, stderr, timeout, grace, clock)
+    })?;
+    finished.into_timed_out(timeout)
+}
+
+/// Spawn `command` with `pipes`, and read its output and wait for it to exit with `capture`.
+///
+/// See [`run::run_piped`]. The stdio configuration isn't recorded, because `process_wrap` children
+/// don't expose their file descriptors.
+#[track_caller]
+fn run_piped<T>(
+    command: &mut StdCommandWrap,
+    pipes: Pipes,
+    capture: impl FnOnce(&mut Box<dyn StdChildWrapper>) -> Result<T, CaptureFailure>,
+) -> Result<Finished<T>, Error> {
+    run_spawn_hook(command.command_mut());
+    command.log()?;
+    pipes.apply(command.command_mut());
+    let started = Started::now();
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(inner) => {
+            return Err(run::exec_error(
+                display(command),
+                command.command().get_current_dir(),
                 inner,
-            })),
+            ));
+        }
+    };
+
+    let result = capture(&mut child);
+    let displayed = display(command);
+    match result {
+        Ok(result) => Ok(Finished {
+            result,
+            started,
+            displayed,
+            stdio: None,
+        }),
+        Err(failure) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(failure.into_error(displayed))
         }
     }
 }
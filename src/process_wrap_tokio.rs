@@ -0,0 +1,187 @@
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::future::Future;
+use std::panic::Location;
+use std::process::ExitStatus;
+use std::process::Output;
+
+use process_wrap::tokio::TokioChildWrapper;
+use process_wrap::tokio::TokioCommandWrap;
+
+use crate::run;
+use crate::run::Ran;
+use crate::spawn_hook::run_spawn_hook;
+use crate::AsyncChildExt;
+use crate::AsyncCommandExt;
+use crate::ChildContext;
+use crate::Error;
+use crate::ExecError;
+#[cfg(feature = "tracing")]
+use crate::LazyProgramAndArgs;
+use crate::OutputContext;
+use crate::OutputLike;
+use crate::Started;
+use crate::Utf8ProgramAndArgs;
+use crate::WaitError;
+
+/// With the `process-wrap-tokio` feature, the async extension traits are implemented for
+/// `process_wrap::tokio::TokioCommandWrap`, so commands can be run in process groups or job
+/// objects with the same error messages as [`crate::CommandExt`]:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use indoc::indoc;
+/// # use process_wrap::tokio::TokioCommandWrap;
+/// # use command_error::AsyncCommandExt;
+/// # async fn example() {
+/// let err = TokioCommandWrap::with_new("sh", |command| {
+///     command.args(["-c", "false"]);
+/// })
+/// .status_checked()
+/// .await
+/// .unwrap_err();
+///
+/// assert_eq!(
+///     err.to_string(),
+///     indoc!(
+///         "`sh` failed: exit status: 1
+///         Command failed: `sh -c false`"
+///     )
+/// );
+/// # }
+/// ```
+impl AsyncCommandExt for TokioCommandWrap {
+    type Error = Error;
+    type Child = ChildContext<Box<dyn TokioChildWrapper>>;
+
+    fn output_checked_as<O, R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        let location = Location::caller();
+        async move {
+            run_spawn_hook(self.command_mut().as_std_mut());
+            self.log()?;
+            let started = Started::now();
+            let result = match self.spawn() {
+                Ok(child) => Box::into_pin(child.wait_with_output()).await,
+                Err(inner) => Err(inner),
+            };
+            let displayed = display(self, location);
+            match result {
+                Ok(output) => {
+                    started.log_completion(&displayed, output.status);
+                    Ran::new(output, displayed).check_output(succeeded)
+                }
+                Err(inner) => Err(run::exec_error(
+                    displayed,
+                    self.command().as_std().get_current_dir(),
+                    inner,
+                )
+                .into()),
+            }
+        }
+    }
+
+    fn status_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        E: From<Self::Error>,
+    {
+        let location = Location::caller();
+        async move {
+            run_spawn_hook(self.command_mut().as_std_mut());
+            self.log()?;
+            let started = Started::now();
+            let result = match self.spawn() {
+                Ok(mut child) => Box::into_pin(child.wait()).await,
+                Err(inner) => Err(inner),
+            };
+            let displayed = display(self, location);
+            match result {
+                Ok(status) => {
+                    started.log_completion(&displayed, status);
+                    Ran::new(status, displayed).check_status(succeeded)
+                }
+                Err(inner) => Err(run::exec_error(
+                    displayed,
+                    self.command().as_std().get_current_dir(),
+                    inner,
+                )
+                .into()),
+            }
+        }
+    }
+
+    fn spawn_checked(&mut self) -> Result<Self::Child, Self::Error> {
+        run_spawn_hook(self.command_mut().as_std_mut());
+        self.log()?;
+        let displayed = display(self, Location::caller());
+        match self.spawn() {
+            Ok(child) => Ok(ChildContext::new(child, displayed)),
+            Err(inner) => Err(Error::from(
+                ExecError::new(displayed, inner)
+                    .with_current_dir(self.command().as_std().get_current_dir()),
+            )),
+        }
+    }
+
+    fn log(&self) -> Result<(), Self::Error> {
+        #[cfg(feature = "tracing")]
+        {
+            let command = LazyProgramAndArgs::new(self.command().as_std());
+            tracing::debug!(%command, "Executing command");
+        }
+        Ok(())
+    }
+}
+
+/// Display `command` for an error or context object, recording that it was run from `location`.
+fn display(
+    command: &TokioCommandWrap,
+    location: &'static Location<'static>,
+) -> Box<Utf8ProgramAndArgs> {
+    Box::new(Utf8ProgramAndArgs::from(command.command().as_std()).with_location(location))
+}
+
+impl AsyncChildExt for ChildContext<Box<dyn TokioChildWrapper>> {
+    type Error = Error;
+
+    async fn output_checked_as<O, R, E>(
+        self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E> + Send,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error>,
+    {
+        let command = dyn_clone::clone_box(self.command.borrow());
+        match Box::into_pin(self.child.wait_with_output()).await {
+            Ok(output) => Ran::new(output, command).check_output(succeeded),
+            Err(inner) => Err(Error::from(WaitError { command, inner }).into()),
+        }
+    }
+
+    async fn wait_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E> + Send,
+    ) -> Result<R, E>
+    where
+        E: From<Self::Error>,
+    {
+        let command = dyn_clone::clone_box(self.command.borrow());
+        match Box::into_pin(self.child.wait()).await {
+            Ok(status) => Ran::new(status, command).check_status(succeeded),
+            Err(inner) => Err(Error::from(WaitError { command, inner }).into()),
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use std::io::ErrorKind;
+use std::io::Read;
+use std::process::Child;
+use std::process::ChildStderr;
+use std::process::ChildStdout;
+use std::process::ExitStatus;
+use std::process::Output;
+
+#[cfg(doc)]
+use crate::ChildContext;
+#[cfg(doc)]
+use crate::ChildExt;
+
+use crate::capture::CHUNK_SIZE;
+
+/// A snapshot of a running child process, produced by [`ChildContext::poll_progress`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// True if the child process has not exited yet.
+    pub running: bool,
+    /// Stdout written since the last call to [`ChildContext::poll_progress`].
+    pub new_stdout: Vec<u8>,
+    /// Stderr written since the last call to [`ChildContext::poll_progress`].
+    pub new_stderr: Vec<u8>,
+    /// The child's exit status, if it has exited.
+    pub status: Option<ExitStatus>,
+}
+
+/// Non-blocking readers for a child's stdout and stderr.
+///
+/// On Unix, the pipes are switched to `O_NONBLOCK`; on Windows, `PeekNamedPipe` is used to read
+/// only as much as is available. Everything read is kept, so that [`ChildExt`] methods can produce
+/// the child's complete output after it's been polled.
+pub(crate) struct ProgressReaders {
+    stdout: Option<Stream<ChildStdout>>,
+    stderr: Option<Stream<ChildStderr>>,
+}
+
+impl ProgressReaders {
+    /// Take the child's stdout and stderr, if they're piped, and stop reads from them blocking.
+    pub(crate) fn new(child: &mut Child) -> std::io::Result<Self> {
+        Ok(Self {
+            stdout: child.stdout.take().map(Stream::new).transpose()?,
+            stderr: child.stderr.take().map(Stream::new).transpose()?,
+        })
+    }
+
+    /// Read whatever output is available without blocking, and get the stdout and stderr read
+    /// since the last call.
+    pub(crate) fn take_new(&mut self) -> std::io::Result<(Vec<u8>, Vec<u8>)> {
+        let stdout = match &mut self.stdout {
+            Some(stream) => stream.take_new()?,
+            None => Vec::new(),
+        };
+        let stderr = match &mut self.stderr {
+            Some(stream) => stream.take_new()?,
+            None => Vec::new(),
+        };
+        Ok((stdout, stderr))
+    }
+
+    /// Read the rest of the child's output, wait for it to exit, and collect all of its output,
+    /// including output already returned from [`ProgressReaders::take_new`].
+    ///
+    /// This is the equivalent of [`Child::wait_with_output`].
+    pub(crate) fn wait_with_output(mut self, mut child: Child) -> std::io::Result<Output> {
+        drop(child.stdin.take());
+        loop {
+            if let Some(stream) = &mut self.stdout {
+                stream.read_available()?;
+            }
+            if let Some(stream) = &mut self.stderr {
+                stream.read_available()?;
+            }
+            let stdout = self.stdout.as_ref().filter(|stream| stream.is_open());
+            let stderr = self.stderr.as_ref().filter(|stream| stream.is_open());
+            if stdout.is_none() && stderr.is_none() {
+                break;
+            }
+            wait_readable(stdout, stderr)?;
+        }
+        let status = child.wait()?;
+        Ok(Output {
+            status,
+            stdout: self.stdout.map(Stream::into_output).unwrap_or_default(),
+            stderr: self.stderr.map(Stream::into_output).unwrap_or_default(),
+        })
+    }
+}
+
+/// One of a child's output pipes, and everything read from it so far.
+struct Stream<R> {
+    /// The pipe, or `None` once it's been closed.
+    reader: Option<R>,
+    output: Vec<u8>,
+    /// How much of `output` has been returned from [`Stream::take_new`].
+    reported: usize,
+}
+
+impl<R: Read + Pipe> Stream<R> {
+    fn new(reader: R) -> std::io::Result<Self> {
+        reader.set_nonblocking()?;
+        Ok(Self {
+            reader: Some(reader),
+            output: Vec::new(),
+            reported: 0,
+        })
+    }
+
+    fn is_open(&self) -> bool {
+        self.reader.is_some()
+    }
+
+    /// Read until the pipe is empty or closed.
+    fn read_available(&mut self) -> std::io::Result<()> {
+        let Some(reader) = &mut self.reader else {
+            return Ok(());
+        };
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let available = match reader.available()? {
+                Some(0) => return Ok(()),
+                Some(available) => available.min(CHUNK_SIZE),
+                None => CHUNK_SIZE,
+            };
+            match reader.read(&mut chunk[..available]) {
+                Ok(0) => {
+                    self.reader = None;
+                    return Ok(());
+                }
+                Ok(n) => self.output.extend_from_slice(&chunk[..n]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn take_new(&mut self) -> std::io::Result<Vec<u8>> {
+        self.read_available()?;
+        let new = self.output[self.reported..].to_vec();
+        self.reported = self.output.len();
+        Ok(new)
+    }
+
+    fn into_output(self) -> Vec<u8> {
+        self.output
+    }
+}
+
+/// Platform-specific operations on a child's output pipe.
+trait Pipe {
+    /// Stop reads from the pipe blocking when it's empty.
+    fn set_nonblocking(&self) -> std::io::Result<()>;
+
+    /// The number of bytes which can be read without blocking, if it's known.
+    ///
+    /// `Some(0)` means the pipe is empty; a closed pipe reports at least one byte available, so
+    /// that reading it finds the end of the stream.
+    fn available(&self) -> std::io::Result<Option<usize>>;
+
+    /// The pipe's file descriptor, to wait for it to become readable.
+    #[cfg(unix)]
+    fn raw_fd(&self) -> std::os::fd::RawFd;
+}
+
+#[cfg(unix)]
+impl<T: std::os::fd::AsRawFd> Pipe for T {
+    fn set_nonblocking(&self) -> std::io::Result<()> {
+        let fd = self.as_raw_fd();
+        // SAFETY: `fd` is an open pipe owned by `self`, and `fcntl` with `F_GETFL` and `F_SETFL`
+        // only reads and sets its status flags.
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFL);
+            if flags == -1 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    fn available(&self) -> std::io::Result<Option<usize>> {
+        // Reads return `WouldBlock` when the pipe is empty.
+        Ok(None)
+    }
+
+    fn raw_fd(&self) -> std::os::fd::RawFd {
+        self.as_raw_fd()
+    }
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn PeekNamedPipe(
+        named_pipe: std::os::windows::io::RawHandle,
+        buffer: *mut std::ffi::c_void,
+        buffer_size: u32,
+        bytes_read: *mut u32,
+        total_bytes_available: *mut u32,
+        bytes_left_this_message: *mut u32,
+    ) -> i32;
+}
+
+/// `ERROR_BROKEN_PIPE`, returned when peeking at a pipe whose write end has been closed.
+#[cfg(windows)]
+const ERROR_BROKEN_PIPE: i32 = 109;
+
+#[cfg(windows)]
+impl<T: std::os::windows::io::AsRawHandle> Pipe for T {
+    fn set_nonblocking(&self) -> std::io::Result<()> {
+        // Anonymous pipes can't be made non-blocking; `available` is used instead.
+        Ok(())
+    }
+
+    fn available(&self) -> std::io::Result<Option<usize>> {
+        let mut available = 0;
+        // SAFETY: The handle is an open pipe owned by `self`. No buffer is passed, so nothing is
+        // copied out of the pipe, and `available` outlives the call.
+        let peeked = unsafe {
+            PeekNamedPipe(
+                self.as_raw_handle(),
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                &mut available,
+                std::ptr::null_mut(),
+            )
+        };
+        if peeked != 0 {
+            return Ok(Some(available as usize));
+        }
+        let error = std::io::Error::last_os_error();
+        if error.raw_os_error() == Some(ERROR_BROKEN_PIPE) {
+            // Let the read report the end of the stream.
+            Ok(Some(1))
+        } else {
+            Err(error)
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+impl<T> Pipe for T {
+    fn set_nonblocking(&self) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            ErrorKind::Unsupported,
+            "non-blocking reads from child processes aren't supported on this platform",
+        ))
+    }
+
+    fn available(&self) -> std::io::Result<Option<usize>> {
+        Ok(None)
+    }
+}
+
+/// Block until either of the open streams can be read from.
+#[cfg(unix)]
+fn wait_readable(
+    stdout: Option<&Stream<ChildStdout>>,
+    stderr: Option<&Stream<ChildStderr>>,
+) -> std::io::Result<()> {
+    let mut fds = Vec::with_capacity(2);
+    for fd in [
+        stdout.and_then(|stream| stream.reader.as_ref().map(Pipe::raw_fd)),
+        stderr.and_then(|stream| stream.reader.as_ref().map(Pipe::raw_fd)),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        fds.push(libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        });
+    }
+    loop {
+        // SAFETY: `fds` is a valid array of `fds.len()` `pollfd`s, and the descriptors stay open
+        // for the duration of the call.
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } != -1 {
+            return Ok(());
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+/// Wait a moment for either of the open streams to be written to.
+///
+/// Windows can't wait for an anonymous pipe to become readable, so this sleeps instead.
+#[cfg(not(unix))]
+fn wait_readable(
+    _stdout: Option<&Stream<ChildStdout>>,
+    _stderr: Option<&Stream<ChildStderr>>,
+) -> std::io::Result<()> {
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::process::Command;
+    use std::process::Stdio;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_poll_without_blocking() {
+        let mut child = Command::new("sh")
+            .args(["-c", "echo puppy; read _; echo doggy >&2"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut readers = ProgressReaders::new(&mut child).unwrap();
+
+        let mut stdout = Vec::new();
+        while stdout != b"puppy\n" {
+            // The child is blocked reading its stdin, so this would hang if reads blocked.
+            let (new_stdout, new_stderr) = readers.take_new().unwrap();
+            assert_eq!(new_stderr, b"");
+            stdout.extend(new_stdout);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let output = readers.wait_with_output(child).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"puppy\n");
+        assert_eq!(output.stderr, b"doggy\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_with_full_pipes() {
+        let mut child = Command::new("sh")
+            .args([
+                "-c",
+                "head -c 200000 /dev/zero; head -c 200000 /dev/zero >&2",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut readers = ProgressReaders::new(&mut child).unwrap();
+        let (new_stdout, _) = readers.take_new().unwrap();
+
+        let output = readers.wait_with_output(child).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), 200000);
+        assert_eq!(output.stderr.len(), 200000);
+        assert!(new_stdout.len() <= output.stdout.len());
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt::Debug;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Clock;
+#[cfg(doc)]
+use crate::CommandExt;
+
+/// A token-bucket rate limiter, for capping how often commands are started.
+///
+/// A [`RateLimiter`] allows `permits` commands to start per `per` interval, with permits refilling
+/// evenly over the interval. Up to `permits` commands may start at once (the "burst"); use
+/// [`RateLimiter::with_burst`] to change this.
+///
+/// A single limiter can be shared (for example, in a `static` or an `Arc`) by every command which
+/// talks to the same remote service. Use it with [`CommandExt::output_checked_rate_limited`],
+/// which blocks until a permit is available before starting the command:
+///
+/// ```
+/// # use std::process::Command;
+/// # use std::time::Duration;
+/// # use std::time::Instant;
+/// # use command_error::CommandExt;
+/// # use command_error::RateLimiter;
+/// let limiter = RateLimiter::new(10, Duration::from_secs(1)).with_burst(1);
+/// let start = Instant::now();
+/// for _ in 0..3 {
+///     Command::new("true").output_checked_rate_limited(&limiter).unwrap();
+/// }
+/// // The first command starts immediately, then one more every 100ms.
+/// assert!(start.elapsed() >= Duration::from_millis(200));
+/// ```
+pub struct RateLimiter {
+    /// The time between permits.
+    interval: Duration,
+    burst: u32,
+    clock: Box<dyn Clock>,
+    /// The time when the bucket will next be full, if it isn't full now.
+    full_at: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Construct a new [`RateLimiter`] which allows `permits` commands to start every `per`.
+    ///
+    /// # Panics
+    ///
+    /// If `permits` is zero.
+    pub fn new(permits: u32, per: Duration) -> Self {
+        assert!(permits > 0, "A rate limiter must allow at least one permit");
+        Self {
+            interval: per / permits,
+            burst: permits,
+            clock: Box::new(Instant::now),
+            full_at: Mutex::new(None),
+        }
+    }
+
+    /// Allow up to `burst` commands to start at once, if permits haven't been used recently.
+    ///
+    /// # Panics
+    ///
+    /// If `burst` is zero.
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        assert!(
+            burst > 0,
+            "A rate limiter must allow a burst of at least one permit"
+        );
+        self.burst = burst;
+        self
+    }
+
+    /// Use `clock` to get the current time and wait for permits, instead of [`Instant::now`] and
+    /// [`std::thread::sleep`].
+    ///
+    /// This is useful for testing.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Block until a permit is available, and take it.
+    pub fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            self.clock.sleep(wait);
+        }
+    }
+
+    /// Take a permit, returning how long to wait before it may be used.
+    ///
+    /// Permits are handed out in the order they're reserved, so callers waiting on the limiter
+    /// are served fairly.
+    pub(crate) fn reserve(&self) -> Duration {
+        let now = self.clock.now();
+        let mut full_at = self.full_at.lock().unwrap_or_else(PoisonError::into_inner);
+        let full_at_now = full_at.map_or(now, |full_at| full_at.max(now));
+        let next = full_at_now + self.interval;
+        // The permit may be used once the bucket has room for `burst - 1` more permits.
+        let allowed_at = next
+            .checked_sub(self.interval * self.burst)
+            .unwrap_or(now)
+            .max(now);
+        *full_at = Some(next);
+        allowed_at - now
+    }
+}
+
+impl Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("interval", &self.interval)
+            .field("burst", &self.burst)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use static_assertions::assert_impl_all;
+
+    use crate::clock::ManualClock;
+
+    assert_impl_all!(RateLimiter: Send, Sync);
+
+    fn limiter(clock: &ManualClock, permits: u32) -> RateLimiter {
+        RateLimiter::new(permits, Duration::from_secs(1)).with_clock(clock.clone())
+    }
+
+    #[test]
+    fn test_burst() {
+        let clock = ManualClock::new();
+        let limiter = limiter(&clock, 4);
+        for _ in 0..4 {
+            assert_eq!(limiter.reserve(), Duration::ZERO);
+        }
+        assert_eq!(limiter.reserve(), Duration::from_millis(250));
+        assert_eq!(limiter.reserve(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_refill() {
+        let clock = ManualClock::new();
+        let limiter = limiter(&clock, 4).with_burst(2);
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert_eq!(limiter.reserve(), Duration::from_millis(250));
+
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(limiter.reserve(), Duration::from_millis(250));
+
+        // After a long pause, the bucket is full again, but no fuller.
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert_eq!(limiter.reserve(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_acquire_waits_on_clock() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let limiter = limiter(&clock, 2).with_burst(1);
+        for _ in 0..5 {
+            limiter.acquire();
+        }
+        assert_eq!(clock.now() - start, Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,289 @@
+use std::io::BufRead;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::process::Output;
+
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Utf8ProgramAndArgs;
+
+/// Whether [`CommandExt::output_checked_recording`] runs commands and records their output or
+/// replays previously-recorded output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordReplayMode {
+    /// Run the command and write its output to the fixture file.
+    Record,
+    /// Read the command's output from the fixture file without running it.
+    Replay,
+}
+
+/// A fixture file for [`CommandExt::output_checked_recording`].
+///
+/// In [`RecordReplayMode::Record`] mode, the command is run and its output (whether it succeeds or
+/// fails) is written to the fixture file. In [`RecordReplayMode::Replay`] mode, the command is not
+/// run; instead, the recorded output is read from the fixture file and checked as if the command
+/// had just produced it.
+///
+/// A common pattern is to switch modes with an environment variable, so that fixtures can be
+/// regenerated by re-running the tests:
+///
+/// ```
+/// # use command_error::RecordReplay;
+/// let fixture = if std::env::var_os("RECORD_FIXTURES").is_some() {
+///     RecordReplay::record("tests/data/git-status.fixture")
+/// } else {
+///     RecordReplay::replay("tests/data/git-status.fixture")
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordReplay {
+    mode: RecordReplayMode,
+    path: PathBuf,
+}
+
+impl RecordReplay {
+    /// Construct a [`RecordReplay`] with the given mode.
+    pub fn new(mode: RecordReplayMode, path: impl Into<PathBuf>) -> Self {
+        Self {
+            mode,
+            path: path.into(),
+        }
+    }
+
+    /// Construct a [`RecordReplay`] which runs commands and records their output to `path`.
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self::new(RecordReplayMode::Record, path)
+    }
+
+    /// Construct a [`RecordReplay`] which replays output recorded in `path`.
+    pub fn replay(path: impl Into<PathBuf>) -> Self {
+        Self::new(RecordReplayMode::Replay, path)
+    }
+
+    /// Get the mode.
+    pub fn mode(&self) -> RecordReplayMode {
+        self.mode
+    }
+
+    /// Get the path of the fixture file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add the fixture's path to an `error` from reading or writing it.
+    pub(crate) fn context(&self, error: std::io::Error) -> std::io::Error {
+        let action = match self.mode {
+            RecordReplayMode::Record => "write",
+            RecordReplayMode::Replay => "read",
+        };
+        std::io::Error::new(
+            error.kind(),
+            format!(
+                "Failed to {action} fixture `{}`: {error}",
+                self.path.display()
+            ),
+        )
+    }
+
+    /// Write a fixture containing the given command and output.
+    pub(crate) fn write(
+        &self,
+        command: &Utf8ProgramAndArgs,
+        output: &Output,
+    ) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&self.path)?);
+        writeln!(writer, "{HEADER}")?;
+        if let Some(current_dir) = &command.current_dir {
+            write_field(&mut writer, "cwd", current_dir.as_bytes())?;
+        }
+        for (key, value) in &command.envs {
+            write_field(&mut writer, "env", key.as_bytes())?;
+            if let Some(value) = value {
+                write_field(&mut writer, "env-value", value.as_bytes())?;
+            }
+        }
+        write_field(&mut writer, "program", command.program.as_bytes())?;
+        for arg in &command.args {
+            write_field(&mut writer, "arg", arg.as_bytes())?;
+        }
+        write_field(
+            &mut writer,
+            "status",
+            status_to_raw(output.status).to_string().as_bytes(),
+        )?;
+        write_field(&mut writer, "stdout", &output.stdout)?;
+        write_field(&mut writer, "stderr", &output.stderr)?;
+        writer.flush()
+    }
+
+    /// Read a fixture, returning the recorded command and output.
+    pub(crate) fn read(&self) -> std::io::Result<(Utf8ProgramAndArgs, Output)> {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(&self.path)?);
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        if header.trim_end() != HEADER {
+            return Err(invalid_data("missing fixture header"));
+        }
+
+        let mut command = Utf8ProgramAndArgs::from_parts("", &[]);
+        let mut status = None;
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+
+        while let Some((name, value)) = read_field(&mut reader)? {
+            match name.as_str() {
+                "cwd" => command.current_dir = Some(into_string(value)?),
+                "env" => command.envs.push((into_string(value)?, None)),
+                "env-value" => match command.envs.last_mut() {
+                    Some((_, env_value)) => *env_value = Some(into_string(value)?),
+                    None => return Err(invalid_data("`env-value` without `env`")),
+                },
+                "program" => command.program = into_string(value)?,
+                "arg" => command.args.push(into_string(value)?),
+                "status" => {
+                    status = Some(status_from_raw(
+                        into_string(value)?
+                            .parse()
+                            .map_err(|_| invalid_data("invalid exit status"))?,
+                    )?)
+                }
+                "stdout" => stdout = value,
+                "stderr" => stderr = value,
+                _ => return Err(invalid_data(&format!("unknown field `{name}`"))),
+            }
+        }
+
+        let status = status.ok_or_else(|| invalid_data("missing exit status"))?;
+        Ok((
+            command,
+            Output {
+                status,
+                stdout,
+                stderr,
+            },
+        ))
+    }
+}
+
+const HEADER: &str = "command-error fixture v1";
+
+/// Fields are written as `{name} {length}\n{value}\n`, so that values may contain arbitrary bytes.
+fn write_field(writer: &mut impl Write, name: &str, value: &[u8]) -> std::io::Result<()> {
+    writeln!(writer, "{name} {}", value.len())?;
+    writer.write_all(value)?;
+    writeln!(writer)
+}
+
+fn read_field(reader: &mut impl BufRead) -> std::io::Result<Option<(String, Vec<u8>)>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let (name, length) = line
+        .trim_end()
+        .split_once(' ')
+        .ok_or_else(|| invalid_data("invalid field"))?;
+    let length: usize = length
+        .parse()
+        .map_err(|_| invalid_data("invalid field length"))?;
+    // Read the value and the trailing newline. The length is untrusted, so the value is read
+    // incrementally rather than allocated up front.
+    let mut value = Vec::new();
+    reader
+        .take((length as u64).saturating_add(1))
+        .read_to_end(&mut value)?;
+    if value.len() != length.saturating_add(1) {
+        return Err(invalid_data("truncated field value"));
+    }
+    if value.pop() != Some(b'\n') {
+        return Err(invalid_data("field value too long"));
+    }
+    Ok(Some((name.to_owned(), value)))
+}
+
+fn into_string(value: Vec<u8>) -> std::io::Result<String> {
+    String::from_utf8(value).map_err(|_| invalid_data("invalid UTF-8"))
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_owned())
+}
+
+#[cfg(unix)]
+fn status_to_raw(status: ExitStatus) -> i64 {
+    use std::os::unix::process::ExitStatusExt;
+    status.into_raw().into()
+}
+
+#[cfg(unix)]
+fn status_from_raw(raw: i64) -> std::io::Result<ExitStatus> {
+    use std::os::unix::process::ExitStatusExt;
+    let raw = i32::try_from(raw).map_err(|_| invalid_data("exit status out of range"))?;
+    Ok(ExitStatus::from_raw(raw))
+}
+
+#[cfg(windows)]
+fn status_to_raw(status: ExitStatus) -> i64 {
+    status
+        .code()
+        .map(|code| code as u32)
+        .unwrap_or_default()
+        .into()
+}
+
+#[cfg(windows)]
+fn status_from_raw(raw: i64) -> std::io::Result<ExitStatus> {
+    use std::os::windows::process::ExitStatusExt;
+    let raw = u32::try_from(raw).map_err(|_| invalid_data("exit status out of range"))?;
+    Ok(ExitStatus::from_raw(raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_read_field() {
+        let mut reader = &b"stdout 6\npuppy\n\n"[..];
+        assert_eq!(
+            read_field(&mut reader).unwrap(),
+            Some(("stdout".to_owned(), b"puppy\n".to_vec()))
+        );
+        assert_eq!(read_field(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_field_huge_length() {
+        let mut reader = &b"stdout 18446744073709551615\npuppy\n"[..];
+        let error = read_field(&mut reader).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "truncated field value");
+    }
+
+    #[test]
+    fn test_status_out_of_range() {
+        let error = status_from_raw(i64::MAX).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "exit status out of range");
+        assert_eq!(
+            status_from_raw(status_to_raw(ExitStatus::default())).unwrap(),
+            ExitStatus::default()
+        );
+    }
+
+    #[test]
+    fn test_context() {
+        let fixture = RecordReplay::replay("tests/data/missing.fixture");
+        let error = fixture.context(invalid_data("missing fixture header"));
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "Failed to read fixture `tests/data/missing.fixture`: missing fixture header"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt::Display;
+use std::io;
+
+use crate::Error;
+
+impl Error {
+    /// Write the first line of this error's message, like `` `cargo` failed: exit status: 101 ``,
+    /// to `writer`.
+    ///
+    /// This streams the message without building it as a [`String`] first, so it's suitable for
+    /// logging sinks which can't afford to allocate in proportion to a command's output.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo puppy >&2; exit 1"])
+    ///     .output_checked()
+    ///     .unwrap_err();
+    /// let mut compact = String::new();
+    /// err.write_compact(&mut compact).unwrap();
+    /// assert_eq!(compact, "`sh` failed: exit status: 1");
+    /// ```
+    pub fn write_compact(&self, writer: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        let mut first_line = FirstLine {
+            writer,
+            done: false,
+        };
+        match std::fmt::write(&mut first_line, format_args!("{self}")) {
+            // `FirstLine` stops formatting at the end of the first line by returning an error.
+            Err(_) if first_line.done => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Write this error as a single line of JSON to `writer`.
+    ///
+    /// The keys are `kind` (the [`Error`] variant, like `output`), `program`, `exit_code` (the
+    /// command's exit code, or `null` if it didn't exit or was killed by a signal), and `message`
+    /// (the complete [`Display`]ed message), always in that order.
+    ///
+    /// Like [`Error::write_compact`], the message is escaped as it's written rather than built as
+    /// a [`String`] first.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// let err = Command::new("sh")
+    ///     .args(["-c", "echo \"puppy\" >&2; exit 1"])
+    ///     .output_checked()
+    ///     .unwrap_err();
+    /// let mut json = Vec::new();
+    /// err.write_json(&mut json).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(json).unwrap(),
+    ///     r#"{"kind":"output","program":"sh","exit_code":1,"message":"`sh` failed: exit status: 1\nCommand failed: `sh -c 'echo \"puppy\" >&2; exit 1'`\nStderr:\n  puppy"}"#
+    /// );
+    /// ```
+    pub fn write_json(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        write!(writer, r#"{{"kind":"{}","program":"#, self.kind())?;
+        write_json_string(writer, self.program())?;
+        match self.status_code() {
+            Some(code) => write!(writer, r#","exit_code":{code}"#)?,
+            None => writer.write_all(br#","exit_code":null"#)?,
+        }
+        writer.write_all(br#","message":"#)?;
+        write_json_string(writer, self)?;
+        writer.write_all(b"}")
+    }
+
+    /// The name of this error's variant, matching its `miette` diagnostic code.
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::Exec(_) => "exec",
+            Error::Wait(_) => "wait",
+            Error::Capture(_) => "capture",
+            Error::Stdin(_) => "stdin",
+            Error::Output(_) => "output",
+            Error::Conversion(_) => "conversion",
+            Error::CircuitOpen(_) => "circuit_open",
+            Error::Timeout(_) => "timeout",
+            Error::AlreadyRunning(_) => "already_running",
+        }
+    }
+
+    /// The failed command's exit code, if it exited.
+    fn status_code(&self) -> Option<i32> {
+        match self {
+            Error::Output(inner) => inner.output.status().code(),
+            _ => None,
+        }
+    }
+}
+
+/// Forwards writes to `writer` until the end of the first line, then fails to stop formatting.
+struct FirstLine<'a> {
+    writer: &'a mut dyn std::fmt::Write,
+    done: bool,
+}
+
+impl std::fmt::Write for FirstLine<'_> {
+    fn write_str(&mut self, text: &str) -> std::fmt::Result {
+        match text.split_once('\n') {
+            Some((line, _)) => {
+                self.writer.write_str(line)?;
+                self.done = true;
+                Err(std::fmt::Error)
+            }
+            None => self.writer.write_str(text),
+        }
+    }
+}
+
+/// Write `value` as a quoted and escaped JSON string.
+fn write_json_string(writer: &mut dyn io::Write, value: impl Display) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+    let mut escaper = JsonEscaper {
+        writer: &mut *writer,
+        error: None,
+    };
+    if std::fmt::write(&mut escaper, format_args!("{value}")).is_err() {
+        return Err(escaper
+            .error
+            .unwrap_or_else(|| io::Error::other("formatter error")));
+    }
+    writer.write_all(b"\"")
+}
+
+/// Escapes text for a JSON string as it's written to `writer`.
+struct JsonEscaper<'a> {
+    writer: &'a mut dyn io::Write,
+    /// The error from `writer`, if writing failed.
+    error: Option<io::Error>,
+}
+
+impl JsonEscaper<'_> {
+    fn write_escaped(&mut self, text: &str) -> io::Result<()> {
+        // Write runs of characters which don't need escaping all at once.
+        let bytes = text.as_bytes();
+        let mut start = 0;
+        for (i, c) in text.char_indices() {
+            let escaped = match c {
+                '"' => "\\\"",
+                '\\' => "\\\\",
+                '\n' => "\\n",
+                '\r' => "\\r",
+                '\t' => "\\t",
+                c if c.is_control() => "",
+                _ => continue,
+            };
+            self.writer.write_all(&bytes[start..i])?;
+            if escaped.is_empty() {
+                write!(self.writer, "\\u{:04x}", c as u32)?;
+            } else {
+                self.writer.write_all(escaped.as_bytes())?;
+            }
+            start = i + c.len_utf8();
+        }
+        self.writer.write_all(&bytes[start..])
+    }
+}
+
+impl std::fmt::Write for JsonEscaper<'_> {
+    fn write_str(&mut self, text: &str) -> std::fmt::Result {
+        self.write_escaped(text).map_err(|error| {
+            self.error = Some(error);
+            std::fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::CommandExt;
+
+    #[test]
+    fn test_write_compact_exec() {
+        let err = Command::new("ooga booga").output_checked().unwrap_err();
+        let mut compact = String::new();
+        err.write_compact(&mut compact).unwrap();
+        assert_eq!(compact, err.to_string().lines().next().unwrap());
+    }
+
+    #[test]
+    fn test_write_json_escapes() {
+        let mut json = Vec::new();
+        write_json_string(&mut json, "tab\there \u{1b}[1m\"quoted\" \\ é\r\n").unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            r#""tab\there \u001b[1m\"quoted\" \\ é\r\n""#
+        );
+    }
+
+    #[test]
+    fn test_write_json_exec() {
+        let err = Command::new("ooga booga").output_checked().unwrap_err();
+        let mut json = Vec::new();
+        err.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(
+            json.starts_with(r#"{"kind":"exec","program":"ooga booga","exit_code":null,"#),
+            "{json}"
+        );
+    }
+}
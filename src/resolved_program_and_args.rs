@@ -0,0 +1,146 @@
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::CommandDisplay;
+use crate::Utf8ProgramAndArgs;
+
+/// A program name and arguments which display the program's resolved path.
+///
+/// When the program is found in `$PATH` (with [`which`](https://docs.rs/which)), the
+/// [`Display`]ed command (like the `Command failed:` line in error messages) shows its absolute
+/// path, so the failure can be reproduced unambiguously. The [`program`][CommandDisplay::program]
+/// (used in error summaries) is the program's basename, so the summaries stay short.
+///
+/// The program is resolved once, when this is constructed. If it can't be found, it's displayed
+/// as given.
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::CommandDisplay;
+/// # use command_error::OutputError;
+/// # use command_error::ResolvedProgramAndArgs;
+/// let mut command = Command::new("sh");
+/// command.args(["-c", "exit 1"]);
+/// let displayed = ResolvedProgramAndArgs::from(&command);
+/// let sh = displayed.resolved_path().unwrap().to_owned();
+/// assert!(sh.is_absolute());
+///
+/// let error = OutputError::new(Box::new(displayed), Box::new(command.output().unwrap()));
+/// assert_eq!(
+///     error.to_string(),
+///     format!(
+///         "`sh` failed: exit status: 1\nCommand failed: `{} -c 'exit 1'`",
+///         sh.display()
+///     )
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResolvedProgramAndArgs {
+    /// The program's basename.
+    program: String,
+    /// The resolved program path, if it was found.
+    resolved: Option<PathBuf>,
+    /// The command, with its program replaced by the resolved path.
+    displayed: Utf8ProgramAndArgs,
+}
+
+impl ResolvedProgramAndArgs {
+    /// Resolve the program in `command`.
+    ///
+    /// The program is looked up like [`Command`] does on Unix: in the `PATH` set on `command`, if
+    /// any, or the current process's `PATH` otherwise, with relative paths resolved against
+    /// `command`'s working directory.
+    pub fn new(command: &Command) -> Self {
+        let mut displayed = Utf8ProgramAndArgs::from(command);
+        let program = Path::new(&displayed.program)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| displayed.program.clone());
+
+        let resolved = resolve(command);
+        if let Some(resolved) = &resolved {
+            displayed.program = resolved.to_string_lossy().into_owned();
+        }
+
+        Self {
+            program,
+            resolved,
+            displayed,
+        }
+    }
+
+    /// The program's resolved path, if it was found.
+    pub fn resolved_path(&self) -> Option<&Path> {
+        self.resolved.as_deref()
+    }
+}
+
+/// Find the absolute path of `command`'s program.
+fn resolve(command: &Command) -> Option<PathBuf> {
+    let path = command
+        .get_envs()
+        .find(|(key, _)| *key == "PATH")
+        .map(|(_, value)| value.map(OsString::from))
+        .unwrap_or_else(|| std::env::var_os("PATH"));
+    let cwd = std::env::current_dir().ok()?;
+    let cwd = match command.get_current_dir() {
+        Some(dir) => cwd.join(dir),
+        None => cwd,
+    };
+    which::which_in(command.get_program(), path, cwd).ok()
+}
+
+impl From<&Command> for ResolvedProgramAndArgs {
+    fn from(command: &Command) -> Self {
+        Self::new(command)
+    }
+}
+
+impl Display for ResolvedProgramAndArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.displayed)
+    }
+}
+
+impl From<ResolvedProgramAndArgs> for Box<dyn CommandDisplay + Send + Sync> {
+    fn from(command: ResolvedProgramAndArgs) -> Self {
+        Box::new(command)
+    }
+}
+
+impl From<&ResolvedProgramAndArgs> for Box<dyn CommandDisplay + Send + Sync> {
+    fn from(command: &ResolvedProgramAndArgs) -> Self {
+        Box::new(command.clone())
+    }
+}
+
+impl CommandDisplay for ResolvedProgramAndArgs {
+    fn program(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.program)
+    }
+
+    fn args(&self) -> Box<dyn Iterator<Item = Cow<'_, str>> + '_> {
+        self.displayed.args()
+    }
+
+    fn arg0(&self) -> Option<Cow<'_, str>> {
+        self.displayed.arg0()
+    }
+
+    fn is_lossy(&self) -> bool {
+        self.displayed.is_lossy()
+    }
+
+    fn omitted_args(&self) -> usize {
+        self.displayed.omitted_args()
+    }
+
+    fn to_command(&self) -> Command {
+        self.displayed.to_command()
+    }
+}
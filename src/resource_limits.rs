@@ -0,0 +1,234 @@
+use std::os::unix::process::CommandExt as _;
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::process::Output;
+use std::time::Duration;
+
+use crate::pre_exec_guard;
+use crate::pre_exec_guard::PreExecGuard;
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Error;
+use crate::OutputContext;
+
+/// Whether limits were set in this (child) process; see [`PreExecGuard`].
+static LIMITS_SET: PreExecGuard = PreExecGuard::new();
+
+/// Resource limits (see [`setrlimit(2)`][setrlimit]) to apply to a child process before it
+/// executes.
+///
+/// Used by [`CommandExt::output_checked_with_limits`]. Each limit sets both the soft and hard
+/// limit, so the child can't raise it again. (The hard CPU time limit is one second above the soft
+/// limit, so that the child is sent `SIGXCPU` before it's killed outright.)
+///
+/// ```
+/// # use std::time::Duration;
+/// # use command_error::ResourceLimits;
+/// let limits = ResourceLimits::new()
+///     .cpu_time(Duration::from_secs(10))
+///     .address_space(512 * 1024 * 1024)
+///     .file_size(1024 * 1024);
+/// ```
+///
+/// [setrlimit]: https://man7.org/linux/man-pages/man2/setrlimit.2.html
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ResourceLimits {
+    cpu_time: Option<Duration>,
+    address_space: Option<u64>,
+    file_size: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Construct a new [`ResourceLimits`] with no limits set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the CPU time the child may use (`RLIMIT_CPU`), rounded up to the nearest second.
+    ///
+    /// When the limit is exceeded, the child is sent `SIGXCPU`.
+    pub fn cpu_time(mut self, cpu_time: Duration) -> Self {
+        self.cpu_time = Some(cpu_time);
+        self
+    }
+
+    /// Limit the size of the child's virtual memory in bytes (`RLIMIT_AS`).
+    ///
+    /// When the limit is exceeded, allocations in the child fail.
+    pub fn address_space(mut self, bytes: u64) -> Self {
+        self.address_space = Some(bytes);
+        self
+    }
+
+    /// Limit the size of files the child may create in bytes (`RLIMIT_FSIZE`).
+    ///
+    /// When the limit is exceeded, the child is sent `SIGXFSZ`.
+    pub fn file_size(mut self, bytes: u64) -> Self {
+        self.file_size = Some(bytes);
+        self
+    }
+
+    /// Set these limits on `command` before it executes.
+    ///
+    /// The limits remain set on `command` for subsequent runs. If different limits were already
+    /// set on `command`, it fails to start; see [`ResourceLimits::explain_repeated`].
+    pub(crate) fn apply(self, command: &mut Command) {
+        let fingerprint = pre_exec_guard::fingerprint(&self);
+        let cpu_time = self.cpu_time.map(|cpu_time| {
+            let seconds = cpu_time.as_secs();
+            if cpu_time.subsec_nanos() > 0 {
+                seconds + 1
+            } else {
+                seconds
+            }
+        });
+
+        // SAFETY: The closure only claims the guard and calls `setrlimit`, which are
+        // async-signal-safe, and doesn't allocate.
+        unsafe {
+            command.pre_exec(move || {
+                if !LIMITS_SET.claim(fingerprint)? {
+                    return Ok(());
+                }
+                if let Some(cpu_time) = cpu_time {
+                    setrlimit(libc::RLIMIT_CPU, cpu_time, cpu_time + 1)?;
+                }
+                if let Some(address_space) = self.address_space {
+                    setrlimit(libc::RLIMIT_AS, address_space, address_space)?;
+                }
+                if let Some(file_size) = self.file_size {
+                    setrlimit(libc::RLIMIT_FSIZE, file_size, file_size)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /// Check a command's output, noting if the command was killed for exceeding a limit.
+    pub(crate) fn check(&self, context: OutputContext<Output>) -> Result<Output, Error> {
+        if context.success() {
+            Ok(context.into_output())
+        } else {
+            match self.explain(context.status()) {
+                Some(explanation) => Err(context.error_msg(explanation)),
+                None => Err(context.error()),
+            }
+        }
+    }
+
+    /// Explain why a command with these limits was killed, if it was likely due to a limit.
+    ///
+    /// Only `SIGXCPU` is attributed to the CPU time limit: a command killed with `SIGKILL` may
+    /// have ignored `SIGXCPU` until the hard limit, but it's just as likely to have been killed
+    /// by something else, like the out-of-memory killer.
+    fn explain(&self, status: ExitStatus) -> Option<String> {
+        match status.signal()? {
+            libc::SIGXCPU => self
+                .cpu_time
+                .map(|cpu_time| format!("CPU time limit of {cpu_time:?} exceeded")),
+            libc::SIGXFSZ => self
+                .file_size
+                .map(|file_size| format!("File size limit of {file_size} bytes exceeded")),
+            libc::SIGSEGV | libc::SIGABRT => self.address_space.map(|address_space| {
+                format!("Address space limit of {address_space} bytes may have been exceeded")
+            }),
+            _ => None,
+        }
+    }
+
+    /// Explain a failure to start a command because different limits were already set on it.
+    pub(crate) fn explain_repeated(error: Error) -> Error {
+        pre_exec_guard::explain(
+            error,
+            "setrlimit",
+            "different resource limits were already set on this command by an earlier call; use \
+            a new `Command` to change them",
+        )
+    }
+}
+
+/// Set the `soft` and `hard` limits for `resource`.
+///
+/// The type of `resource` differs between platforms, so it's generic.
+fn setrlimit<R>(resource: R, soft: u64, hard: u64) -> std::io::Result<()>
+where
+    R: RlimitResource,
+{
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    if resource.setrlimit(&limit) == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// A resource argument to `setrlimit`, whose type differs between platforms.
+trait RlimitResource {
+    fn setrlimit(self, limit: &libc::rlimit) -> libc::c_int;
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+impl RlimitResource for libc::__rlimit_resource_t {
+    fn setrlimit(self, limit: &libc::rlimit) -> libc::c_int {
+        // SAFETY: `limit` is a valid pointer.
+        unsafe { libc::setrlimit(self, limit) }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+impl RlimitResource for libc::c_int {
+    fn setrlimit(self, limit: &libc::rlimit) -> libc::c_int {
+        // SAFETY: `limit` is a valid pointer.
+        unsafe { libc::setrlimit(self, limit) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    use crate::CommandExt;
+
+    #[test]
+    fn test_explain() {
+        let limits = ResourceLimits::new()
+            .cpu_time(Duration::from_secs(1))
+            .address_space(1024);
+        assert_eq!(
+            limits.explain(ExitStatus::from_raw(libc::SIGXCPU)),
+            Some("CPU time limit of 1s exceeded".to_owned())
+        );
+        assert_eq!(limits.explain(ExitStatus::from_raw(libc::SIGKILL)), None);
+        assert_eq!(
+            limits.explain(ExitStatus::from_raw(libc::SIGSEGV)),
+            Some("Address space limit of 1024 bytes may have been exceeded".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_repeated() {
+        let mut command = Command::new("true");
+        let limits = ResourceLimits::new().file_size(1024 * 1024);
+        command.output_checked_with_limits(limits).unwrap();
+        // The same limits again are fine.
+        command.output_checked_with_limits(limits).unwrap();
+
+        let err = command
+            .output_checked_with_limits(limits.file_size(1024))
+            .unwrap_err();
+        let Error::Exec(err) = err else {
+            panic!("Expected an exec error, got {err:?}");
+        };
+        assert_eq!(err.spawn_step(), Some("setrlimit"));
+        assert_eq!(
+            err.to_string(),
+            "Failed to execute `true` (setrlimit): different resource limits were already set on \
+            this command by an earlier call; use a new `Command` to change them"
+        );
+    }
+}
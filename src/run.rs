@@ -0,0 +1,575 @@
+use std::any::Any;
+use std::fmt::Display;
+use std::panic::Location;
+use std::path::Path;
+use std::process::Child;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::process::Output;
+use std::process::Stdio;
+use std::time::Duration;
+
+use crate::capture;
+use crate::capture::CaptureFailure;
+use crate::capture::Captured;
+use crate::spawn_hook::run_spawn_hook;
+use crate::Clock;
+use crate::CommandDisplay;
+use crate::CommandExt;
+use crate::Error;
+use crate::ExecError;
+use crate::OutputContext;
+use crate::OutputConversionError;
+use crate::OutputStream;
+use crate::Spawner;
+use crate::Started;
+use crate::StdioConfig;
+use crate::StreamTimings;
+use crate::Termination;
+use crate::TimeoutError;
+use crate::Utf8ProgramAndArgs;
+use crate::WaitError;
+
+/// A command which ran to completion, before its output is converted and checked.
+///
+/// Running commands and constructing errors happens in non-generic functions (like [`output`])
+/// which produce a [`Ran`], so that only the thin generic shims on top of them
+/// ([`Ran::check_output`] and [`Ran::check_status`]) are instantiated for each output type and
+/// closure.
+pub(crate) struct Ran<T> {
+    output: T,
+    command: Box<dyn CommandDisplay + Send + Sync>,
+    timings: Option<StreamTimings>,
+    stdio: Option<StdioConfig>,
+}
+
+impl<T> Ran<T> {
+    pub(crate) fn new(output: T, command: Box<dyn CommandDisplay + Send + Sync>) -> Self {
+        Self {
+            output,
+            command,
+            timings: None,
+            stdio: None,
+        }
+    }
+
+    pub(crate) fn with_stream_timings(mut self, timings: StreamTimings) -> Self {
+        self.timings = Some(timings);
+        self
+    }
+
+    /// Note how the command's standard streams were connected.
+    pub(crate) fn with_stdio(mut self, stdio: Option<StdioConfig>) -> Self {
+        self.stdio = stdio;
+        self
+    }
+}
+
+fn context<O>(
+    output: O,
+    command: Box<dyn CommandDisplay + Send + Sync>,
+    timings: Option<StreamTimings>,
+    stdio: Option<StdioConfig>,
+) -> OutputContext<O> {
+    let mut context = OutputContext::new(output, command);
+    context.stdio = stdio;
+    match timings {
+        Some(timings) => context.with_stream_timings(timings),
+        None => context,
+    }
+}
+
+impl Ran<Output> {
+    /// Convert the output and check it with `succeeded`.
+    pub(crate) fn check_output<O, R, E>(
+        self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        O: TryFrom<Output> + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Error>,
+    {
+        match O::try_from(self.output) {
+            Ok(output) => {
+                let mut context = context(output, self.command, self.timings, self.stdio);
+                context.note_uncaptured = true;
+                succeeded(context)
+            }
+            Err(error) => Err(conversion_failed(self.command, error).into()),
+        }
+    }
+}
+
+impl Ran<ExitStatus> {
+    /// Check that the command succeeded, copying `stdout` and `stderr` into the error if it
+    /// didn't.
+    pub(crate) fn check_into(self, stdout: &[u8], stderr: &[u8]) -> Result<ExitStatus, Error> {
+        if self.output.success() {
+            return Ok(self.output);
+        }
+        let output = Output {
+            status: self.output,
+            stdout: stdout.to_vec(),
+            stderr: stderr.to_vec(),
+        };
+        Err(context(output, self.command, self.timings, self.stdio).error())
+    }
+
+    /// Check the exit status with `succeeded`.
+    pub(crate) fn check_status<R, E>(
+        self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E>,
+    ) -> Result<R, E> {
+        succeeded(context(self.output, self.command, self.timings, self.stdio))
+    }
+}
+
+/// Construct an [`OutputConversionError`] from the error produced by converting a command's output,
+/// keeping the details of UTF-8 decoding errors.
+fn conversion_failed<T>(command: Box<dyn CommandDisplay + Send + Sync>, error: T) -> Error
+where
+    T: Display + Send + Sync + 'static,
+{
+    let mut error = Some(error);
+    if let Some(utf8) = (&mut error as &mut dyn Any).downcast_mut::<Option<utf8_command::Error>>() {
+        let utf8 = utf8.take().expect("error is only taken once");
+        return Error::from(OutputConversionError::from_utf8(command, utf8));
+    }
+    conversion_error(command, Box::new(error.expect("error is only taken once")))
+}
+
+/// Construct an [`OutputConversionError`].
+pub(crate) fn conversion_error(
+    command: Box<dyn CommandDisplay + Send + Sync>,
+    inner: Box<dyn Display + Send + Sync>,
+) -> Error {
+    Error::from(OutputConversionError::new(command, inner))
+}
+
+/// Construct an [`ExecError`] for a command with working directory `current_dir`.
+pub(crate) fn exec_error(
+    command: Box<dyn CommandDisplay + Send + Sync>,
+    current_dir: Option<&Path>,
+    inner: std::io::Error,
+) -> Error {
+    Error::from(ExecError::new(command, inner).with_current_dir(current_dir))
+}
+
+/// Display `command` for an error or context object.
+///
+/// This is called after the command has run, so that its arguments aren't held in memory twice
+/// while it runs.
+///
+/// The display records the location of the caller, which is `#[track_caller]` up to the public
+/// method the user called.
+#[track_caller]
+fn display(command: &Command) -> Box<Utf8ProgramAndArgs> {
+    Box::new(Utf8ProgramAndArgs::from(command).with_location(Location::caller()))
+}
+
+/// Run `command` and capture its output.
+#[track_caller]
+pub(crate) fn output(command: &mut Command) -> Result<Ran<Output>, Error> {
+    output_displayed(command, |command| command.into())
+}
+
+/// Run `command` and capture its output, displaying it with `display` in errors.
+#[track_caller]
+pub(crate) fn output_displayed(
+    command: &mut Command,
+    display: impl FnOnce(&Command) -> Utf8ProgramAndArgs,
+) -> Result<Ran<Output>, Error> {
+    let location = Location::caller();
+    run_spawn_hook(command);
+    command.log()?;
+    let started = Started::now();
+    let result = command.output();
+    let displayed = Box::new(display(command).with_location(location));
+    match result {
+        Ok(output) => {
+            started.log_completion(&displayed, output.status);
+            Ok(Ran::new(output, displayed))
+        }
+        Err(inner) => Err(exec_error(displayed, command.get_current_dir(), inner)),
+    }
+}
+
+/// Run `command`, passing its output to `on_chunk` as it's read.
+///
+/// See [`capture::capture`].
+#[track_caller]
+pub(crate) fn output_streaming(
+    command: &mut Command,
+    bound: usize,
+    retain: usize,
+    on_chunk: &mut dyn FnMut(OutputStream, &[u8]),
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::Output, |child| {
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        capture::capture(child, stdout, stderr, bound, retain, on_chunk)
+    })?;
+    Ok(finished.into_ran())
+}
+
+/// Run `command` and capture its output, calling `on_stall` whenever it produces no output for
+/// longer than `interval`.
+///
+/// See [`capture::capture_on_stall`].
+#[track_caller]
+pub(crate) fn output_on_stall(
+    command: &mut Command,
+    interval: Duration,
+    on_stall: &mut dyn FnMut(Duration),
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::Output, |child| {
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        capture::capture_on_stall(child, stdout, stderr, interval, on_stall)
+    })?;
+    Ok(finished.into_ran())
+}
+
+/// Run `command` without capturing its output.
+#[track_caller]
+pub(crate) fn status(command: &mut Command) -> Result<Ran<ExitStatus>, Error> {
+    run_spawn_hook(command);
+    command.log()?;
+    let started = Started::now();
+    // This is what `Command::status` does, but the child's streams are examined in between.
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(inner) => {
+            return Err(exec_error(
+                display(command),
+                command.get_current_dir(),
+                inner,
+            ));
+        }
+    };
+    let stdio = StdioConfig::of(&child);
+    let result = child.wait();
+    let displayed = display(command);
+    match result {
+        Ok(status) => {
+            started.log_completion(&displayed, status);
+            Ok(Ran::new(status, displayed).with_stdio(Some(stdio)))
+        }
+        Err(inner) => Err(wait_error(displayed, inner)),
+    }
+}
+
+/// Run `command` with `spawner`, capturing its output.
+#[track_caller]
+pub(crate) fn output_spawned(
+    command: &mut Command,
+    spawner: &dyn Spawner,
+) -> Result<Ran<Output>, Error> {
+    run_spawn_hook(command);
+    command.log()?;
+    let started = Started::now();
+    let result = spawner.output(command);
+    let displayed = display(command);
+    match result {
+        Ok(output) => {
+            started.log_completion(&displayed, output.status);
+            Ok(Ran::new(output, displayed))
+        }
+        Err(failure) => Err(failure.into_error(spawner.name(), displayed)),
+    }
+}
+
+/// Run `command` with `spawner` without capturing its output.
+#[track_caller]
+pub(crate) fn status_spawned(
+    command: &mut Command,
+    spawner: &dyn Spawner,
+) -> Result<Ran<ExitStatus>, Error> {
+    run_spawn_hook(command);
+    command.log()?;
+    let started = Started::now();
+    let result = spawner.status(command);
+    let displayed = display(command);
+    match result {
+        Ok(status) => {
+            started.log_completion(&displayed, status);
+            Ok(Ran::new(status, displayed))
+        }
+        Err(failure) => Err(failure.into_error(spawner.name(), displayed)),
+    }
+}
+
+/// Run `command`, reading its output into `stdout` and `stderr`.
+///
+/// See [`capture::read_into`].
+#[track_caller]
+pub(crate) fn output_into(
+    command: &mut Command,
+    stdout: &mut Vec<u8>,
+    stderr: &mut Vec<u8>,
+) -> Result<Ran<ExitStatus>, Error> {
+    let finished = run_piped(command, Pipes::Output, |child| {
+        let child_stdout = child.stdout.take();
+        let child_stderr = child.stderr.take();
+        capture::read_into(child, child_stdout, child_stderr, stdout, stderr)
+    })?;
+    Ok(finished.into_ran())
+}
+
+/// Run `command` with piped stdio, letting `interact` write to its stdin and read from its stdout.
+///
+/// See [`capture::interact`].
+#[track_caller]
+pub(crate) fn output_interactive(
+    command: &mut Command,
+    interact: capture::Interact<'_>,
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::All, |child| {
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take();
+        capture::interact(child, stdin, stdout, stderr, interact)
+    })?;
+    interactive_result(finished)
+}
+
+/// Run `command` with piped stdio, writing `input` to its stdin and capturing its output.
+///
+/// See [`capture::capture_with_input`].
+#[track_caller]
+pub(crate) fn output_with_input(
+    command: &mut Command,
+    input: &[u8],
+    close_stdin: bool,
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::All, |child| {
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        capture::capture_with_input(child, stdin, stdout, stderr, input, close_stdin)
+    })?;
+    Ok(finished.into_ran())
+}
+
+/// Construct the result of [`output_interactive`] from the result of [`capture::interact`].
+pub(crate) fn interactive_result(
+    finished: Finished<(Output, Option<std::io::Error>)>,
+) -> Result<Ran<Output>, Error> {
+    let Finished {
+        result: (output, interact_error),
+        started,
+        displayed,
+        stdio: _,
+    } = finished;
+    started.log_completion(&displayed, output.status);
+    match interact_error {
+        Some(error) => {
+            Err(OutputContext::new(output, displayed)
+                .error_msg(format!("interaction failed: {error}")))
+        }
+        None => Ok(Ran::new(output, displayed)),
+    }
+}
+
+/// Run `command` and capture its output, stopping it if it runs for longer than `timeout`.
+///
+/// See [`capture::capture_with_timeout`].
+#[track_caller]
+pub(crate) fn output_with_timeout(
+    command: &mut Command,
+    timeout: Duration,
+    grace: Duration,
+    clock: &dyn Clock,
+) -> Result<Ran<Output>, Error> {
+    let finished = run_piped(command, Pipes::Output, |child| {
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        capture::capture_with_timeout(child, stdout, stderr, timeout, grace, clock)
+    })?;
+    finished.into_timed_out(timeout)
+}
+
+/// Run `command` without capturing its output, stopping it if it runs for longer than `timeout`.
+///
+/// See [`capture::wait_with_timeout`].
+#[track_caller]
+pub(crate) fn status_with_timeout(
+    command: &mut Command,
+    timeout: Duration,
+    grace: Duration,
+    clock: &dyn Clock,
+) -> Result<Ran<ExitStatus>, Error> {
+    let finished = run_piped(command, Pipes::None, |child| {
+        capture::wait_with_timeout(child, timeout, grace, clock)
+    })?;
+    finished.into_timed_out(timeout)
+}
+
+/// Which of a command's standard streams [`run_piped`] pipes before spawning it.
+#[derive(Clone, Copy)]
+pub(crate) enum Pipes {
+    /// Leave the command's stdio as it's configured.
+    None,
+    /// Pipe stdout and stderr.
+    Output,
+    /// Pipe stdin, stdout, and stderr.
+    All,
+}
+
+impl Pipes {
+    /// Configure `command` to pipe these streams.
+    ///
+    /// [`Command`] has no way to read or unset its stdio configuration, so this can't be undone:
+    /// the pipes remain configured for later runs of the command.
+    pub(crate) fn apply(self, command: &mut Command) {
+        if let Pipes::All = self {
+            command.stdin(Stdio::piped());
+        }
+        if let Pipes::Output | Pipes::All = self {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+    }
+}
+
+/// A command which [`run_piped`] ran, with the result of capturing its output.
+pub(crate) struct Finished<T> {
+    pub(crate) result: T,
+    pub(crate) started: Started,
+    pub(crate) displayed: Box<Utf8ProgramAndArgs>,
+    pub(crate) stdio: Option<StdioConfig>,
+}
+
+impl Finished<Captured> {
+    /// Log the command's completion and prepare its output to be checked.
+    pub(crate) fn into_ran(self) -> Ran<Output> {
+        let Captured { output, timings } = self.result;
+        self.started.log_completion(&self.displayed, output.status);
+        Ran::new(output, self.displayed)
+            .with_stream_timings(timings)
+            .with_stdio(self.stdio)
+    }
+}
+
+impl Finished<ExitStatus> {
+    /// Log the command's completion and prepare its exit status to be checked.
+    pub(crate) fn into_ran(self) -> Ran<ExitStatus> {
+        self.started.log_completion(&self.displayed, self.result);
+        Ran::new(self.result, self.displayed).with_stdio(self.stdio)
+    }
+}
+
+impl Finished<(Captured, Option<Termination>)> {
+    /// Log the command's completion, returning a [`TimeoutError`] if it was stopped.
+    pub(crate) fn into_timed_out(self, timeout: Duration) -> Result<Ran<Output>, Error> {
+        let (captured, termination) = self.result;
+        match termination {
+            Some(termination) => {
+                self.started
+                    .log_completion(&self.displayed, captured.output.status);
+                Err(timeout_error(
+                    self.displayed,
+                    captured.output,
+                    timeout,
+                    termination,
+                ))
+            }
+            None => Ok(Finished {
+                result: captured,
+                started: self.started,
+                displayed: self.displayed,
+                stdio: self.stdio,
+            }
+            .into_ran()),
+        }
+    }
+}
+
+impl Finished<(ExitStatus, Option<Termination>)> {
+    /// Log the command's completion, returning a [`TimeoutError`] if it was stopped.
+    pub(crate) fn into_timed_out(self, timeout: Duration) -> Result<Ran<ExitStatus>, Error> {
+        let (status, termination) = self.result;
+        match termination {
+            Some(termination) => {
+                self.started.log_completion(&self.displayed, status);
+                Err(timeout_error(
+                    self.displayed,
+                    Output {
+                        status,
+                        stdout: Vec::new(),
+                        stderr: Vec::new(),
+                    },
+                    timeout,
+                    termination,
+                ))
+            }
+            None => Ok(Finished {
+                result: status,
+                started: self.started,
+                displayed: self.displayed,
+                stdio: self.stdio,
+            }
+            .into_ran()),
+        }
+    }
+}
+
+/// Spawn `command` with `pipes`, and read its output and wait for it to exit with `capture`.
+///
+/// If `capture` fails, the child is killed and reaped, and the failure is returned as an error.
+#[track_caller]
+fn run_piped<T>(
+    command: &mut Command,
+    pipes: Pipes,
+    capture: impl FnOnce(&mut Child) -> Result<T, CaptureFailure>,
+) -> Result<Finished<T>, Error> {
+    run_spawn_hook(command);
+    command.log()?;
+    pipes.apply(command);
+    let started = Started::now();
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(inner) => {
+            return Err(exec_error(
+                display(command),
+                command.get_current_dir(),
+                inner,
+            ));
+        }
+    };
+
+    let stdio = StdioConfig::of(&child);
+    let result = capture(&mut child);
+    let displayed = display(command);
+    match result {
+        Ok(result) => Ok(Finished {
+            result,
+            started,
+            displayed,
+            stdio: Some(stdio),
+        }),
+        Err(failure) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(failure.into_error(displayed))
+        }
+    }
+}
+
+/// Construct a [`TimeoutError`].
+pub(crate) fn timeout_error(
+    command: Box<dyn CommandDisplay + Send + Sync>,
+    output: Output,
+    timeout: Duration,
+    termination: Termination,
+) -> Error {
+    Error::from(TimeoutError::new(command, output, timeout, termination))
+}
+
+/// Construct a [`WaitError`].
+pub(crate) fn wait_error(
+    command: Box<dyn CommandDisplay + Send + Sync>,
+    inner: std::io::Error,
+) -> Error {
+    Error::from(WaitError { command, inner })
+}
@@ -0,0 +1,290 @@
+use std::os::unix::process::CommandExt as _;
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+use std::process::Output;
+
+use crate::pre_exec_guard;
+use crate::pre_exec_guard::PreExecGuard;
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Error;
+use crate::OutputContext;
+
+/// Whether a filter was installed in this (child) process; see [`PreExecGuard`].
+static FILTER_INSTALLED: PreExecGuard = PreExecGuard::new();
+
+/// The most instructions the kernel accepts in a BPF program (`BPF_MAXINSNS`).
+const MAX_INSTRUCTIONS: usize = 4096;
+
+/// The `AUDIT_ARCH_*` value for the current architecture, which the kernel reports to seccomp
+/// filters. Syscall numbers differ between architectures, so filters check this first.
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xC000_003E;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xC000_00B7;
+
+/// On x86-64, syscalls with this bit set use the x32 ABI, which has its own syscall numbers.
+#[cfg(target_arch = "x86_64")]
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+/// The offset of the syscall number in `struct seccomp_data`.
+const NR_OFFSET: u32 = 0;
+/// The offset of the architecture in `struct seccomp_data`.
+const ARCH_OFFSET: u32 = 4;
+
+/// What a [`SeccompFilter`] does when a child process makes a system call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SeccompAction {
+    /// Allow the system call.
+    Allow,
+    /// Make the system call fail with the given `errno`, like `libc::EPERM`, without running it.
+    Errno(u16),
+    /// Kill the process with `SIGSYS`.
+    KillProcess,
+}
+
+impl SeccompAction {
+    /// The `SECCOMP_RET_*` value for this action.
+    fn ret(self) -> u32 {
+        match self {
+            SeccompAction::Allow => libc::SECCOMP_RET_ALLOW,
+            SeccompAction::Errno(errno) => libc::SECCOMP_RET_ERRNO | u32::from(errno),
+            SeccompAction::KillProcess => libc::SECCOMP_RET_KILL_PROCESS,
+        }
+    }
+}
+
+/// A seccomp-bpf filter (see [`seccomp(2)`][seccomp]) restricting the system calls a child
+/// process may make.
+///
+/// Used by [`CommandExt::output_checked_with_seccomp`]. A filter has a default action and a list
+/// of rules for specific system calls, which are identified by their numbers (like
+/// `libc::SYS_mkdir`) for the current architecture. The first matching rule is used.
+///
+/// ```
+/// # use command_error::SeccompAction;
+/// # use command_error::SeccompFilter;
+/// // Deny creating directories, but allow everything else.
+/// let filter = SeccompFilter::new(SeccompAction::Allow)
+///     .rule(libc::SYS_mkdir, SeccompAction::Errno(libc::EPERM as u16))
+///     .rule(libc::SYS_mkdirat, SeccompAction::Errno(libc::EPERM as u16));
+/// ```
+///
+/// System calls from other architectures (and, on x86-64, the x32 ABI) kill the process, so that
+/// they can't be used to bypass the filter.
+///
+/// The kernel limits filters to 4096 BPF instructions, which is about 2000 rules; a command with
+/// a larger filter fails to start.
+///
+/// Only available on Linux on x86-64 and AArch64.
+///
+/// [seccomp]: https://man7.org/linux/man-pages/man2/seccomp.2.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SeccompFilter {
+    default: SeccompAction,
+    rules: Vec<(libc::c_long, SeccompAction)>,
+}
+
+impl SeccompFilter {
+    /// Construct a new [`SeccompFilter`] which takes the `default` action for every system call.
+    pub fn new(default: SeccompAction) -> Self {
+        Self {
+            default,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Take `action` when the process makes the system call numbered `syscall`.
+    pub fn rule(mut self, syscall: libc::c_long, action: SeccompAction) -> Self {
+        self.rules.push((syscall, action));
+        self
+    }
+
+    /// Compile this filter to a BPF program.
+    fn compile(&self) -> Vec<libc::sock_filter> {
+        let kill = SeccompAction::KillProcess.ret();
+        let mut program = vec![
+            statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, ARCH_OFFSET),
+            jump(
+                libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                AUDIT_ARCH,
+                1,
+                0,
+            ),
+            statement(libc::BPF_RET | libc::BPF_K, kill),
+            statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, NR_OFFSET),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        program.extend([
+            jump(
+                libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K,
+                X32_SYSCALL_BIT,
+                0,
+                1,
+            ),
+            statement(libc::BPF_RET | libc::BPF_K, kill),
+        ]);
+        for &(syscall, action) in &self.rules {
+            program.extend([
+                // Syscall numbers are compared as 32-bit values, like in `struct seccomp_data`.
+                jump(
+                    libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K,
+                    syscall as u32,
+                    0,
+                    1,
+                ),
+                statement(libc::BPF_RET | libc::BPF_K, action.ret()),
+            ]);
+        }
+        program.push(statement(libc::BPF_RET | libc::BPF_K, self.default.ret()));
+        program
+    }
+
+    /// Install this filter in `command`'s process before it executes.
+    ///
+    /// The filter remains installed on `command` for subsequent runs. If a different filter was
+    /// already installed on `command`, it fails to start; see [`SeccompFilter::explain_repeated`].
+    /// Fails if the compiled filter is too long for the kernel to accept.
+    pub(crate) fn apply(&self, command: &mut Command) -> std::io::Result<()> {
+        let program = self.compile();
+        if program.len() > MAX_INSTRUCTIONS {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "seccomp filter compiles to {} BPF instructions, but at most \
+                    {MAX_INSTRUCTIONS} are allowed",
+                    program.len()
+                ),
+            ));
+        }
+        let fingerprint = pre_exec_guard::fingerprint(self);
+        // SAFETY: The closure only claims the guard and calls `prctl`, which are
+        // async-signal-safe, and doesn't allocate; the program is compiled beforehand.
+        unsafe {
+            command.pre_exec(move || {
+                if !FILTER_INSTALLED.claim(fingerprint)? {
+                    return Ok(());
+                }
+                // Required to install a filter without `CAP_SYS_ADMIN`, and so that the filter
+                // can't be escaped by executing a setuid program.
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                let fprog = libc::sock_fprog {
+                    // The length was checked above.
+                    len: program.len() as libc::c_ushort,
+                    filter: program.as_ptr().cast_mut(),
+                };
+                if libc::prctl(
+                    libc::PR_SET_SECCOMP,
+                    libc::SECCOMP_MODE_FILTER,
+                    &fprog as *const libc::sock_fprog,
+                ) != 0
+                {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// Explain a failure to start a command because a different filter was already installed on
+    /// it.
+    pub(crate) fn explain_repeated(error: Error) -> Error {
+        pre_exec_guard::explain(
+            error,
+            "seccomp",
+            "a different seccomp filter was already installed on this command by an earlier \
+            call; use a new `Command` to change it",
+        )
+    }
+
+    /// Check a command's output, noting if the command was likely killed by this filter.
+    pub(crate) fn check(&self, context: OutputContext<Output>) -> Result<Output, Error> {
+        if context.success() {
+            Ok(context.into_output())
+        } else if context.status().signal() == Some(libc::SIGSYS) {
+            Err(context.error_msg(
+                "Killed by the seccomp filter, likely for making a disallowed system call",
+            ))
+        } else {
+            Err(context.error())
+        }
+    }
+}
+
+/// A BPF statement.
+fn statement(code: u32, k: u32) -> libc::sock_filter {
+    libc::sock_filter {
+        code: code as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+/// A BPF conditional jump, skipping `jt` instructions if the condition is true and `jf` otherwise.
+fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter {
+        code: code as u16,
+        jt,
+        jf,
+        k,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_errno() {
+        let filter = SeccompFilter::new(SeccompAction::Allow)
+            .rule(libc::SYS_mkdir, SeccompAction::Errno(libc::EPERM as u16))
+            .rule(libc::SYS_mkdirat, SeccompAction::Errno(libc::EPERM as u16));
+        let dir =
+            std::env::temp_dir().join(format!("command-error-seccomp-{}", std::process::id()));
+        let mut command = Command::new("mkdir");
+        command.arg(&dir);
+        filter.apply(&mut command).unwrap();
+        let output = command.output().unwrap();
+        assert!(!dir.exists());
+        assert_eq!(output.status.code(), Some(1));
+    }
+
+    #[test]
+    fn test_too_long() {
+        let filter = (0..3000).fold(
+            SeccompFilter::new(SeccompAction::Allow),
+            |filter, syscall| filter.rule(syscall, SeccompAction::KillProcess),
+        );
+        let err = filter.apply(&mut Command::new("true")).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(
+            err.to_string().contains("at most 4096 are allowed"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_repeated() {
+        use crate::CommandExt;
+
+        let allow = SeccompFilter::new(SeccompAction::Allow);
+        let mut command = Command::new("true");
+        command.output_checked_with_seccomp(&allow).unwrap();
+        // The same filter again is fine.
+        command.output_checked_with_seccomp(&allow).unwrap();
+
+        let err = command
+            .output_checked_with_seccomp(&allow.rule(libc::SYS_uname, SeccompAction::KillProcess))
+            .unwrap_err();
+        let Error::Exec(err) = err else {
+            panic!("Expected an exec error, got {err:?}");
+        };
+        assert_eq!(err.spawn_step(), Some("seccomp"));
+    }
+}
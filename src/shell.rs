@@ -0,0 +1,129 @@
+use std::process::Command;
+
+use crate::base64;
+#[cfg(doc)]
+use crate::Utf8ProgramAndArgs;
+
+/// A shell to run a script with.
+///
+/// Used by [`shell`] and [`Shell::command`] to construct a [`Command`] that runs a shell one-liner
+/// without the caller needing to branch on the platform or quote the script themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// `sh -c <script>`.
+    Sh,
+    /// `cmd /S /C "<script>"`.
+    ///
+    /// With `/S`, `cmd.exe` strips the outer quotes and runs the rest of the command line
+    /// verbatim, so the script doesn't need to be escaped. On Windows, the script is passed
+    /// without the standard library's argument quoting, which `cmd.exe` doesn't understand.
+    Cmd,
+    /// `powershell -NoProfile -NonInteractive -EncodedCommand <base64>`.
+    ///
+    /// The script is encoded as base64 UTF-16, which sidesteps quoting entirely. When displayed
+    /// (for example, in error messages), the decoded script is shown instead of the encoded
+    /// blob; see [`Utf8ProgramAndArgs`].
+    PowerShell,
+}
+
+impl Default for Shell {
+    /// [`Shell::Sh`] on Unix and [`Shell::PowerShell`] on Windows.
+    fn default() -> Self {
+        if cfg!(windows) {
+            Self::PowerShell
+        } else {
+            Self::Sh
+        }
+    }
+}
+
+impl Shell {
+    /// Construct a [`Command`] which runs `script` with this shell.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use command_error::Shell;
+    /// # use command_error::Utf8ProgramAndArgs;
+    /// let command = Shell::PowerShell.command("Write-Output 'puppy'");
+    /// assert_eq!(
+    ///     Utf8ProgramAndArgs::from(&command).to_string(),
+    ///     "powershell -NoProfile -NonInteractive -EncodedCommand \
+    ///     '[decoded] Write-Output '\\''puppy'\\'''"
+    /// );
+    /// ```
+    pub fn command(self, script: &str) -> Command {
+        match self {
+            Shell::Sh => {
+                let mut command = Command::new("sh");
+                command.args(["-c", script]);
+                command
+            }
+            Shell::Cmd => {
+                let mut command = Command::new("cmd");
+                command.args(["/S", "/C"]);
+                #[cfg(windows)]
+                {
+                    use std::os::windows::process::CommandExt;
+                    command.raw_arg(format!("\"{script}\""));
+                }
+                #[cfg(not(windows))]
+                {
+                    command.arg(format!("\"{script}\""));
+                }
+                command
+            }
+            Shell::PowerShell => {
+                let mut command = Command::new("powershell");
+                command.args([
+                    "-NoProfile",
+                    "-NonInteractive",
+                    ENCODED_COMMAND,
+                    &encode_powershell(script),
+                ]);
+                command
+            }
+        }
+    }
+}
+
+/// Construct a [`Command`] which runs `script` with the platform's default [`Shell`].
+///
+/// ```
+/// # use command_error::CommandExt;
+/// # use command_error::shell;
+/// let err = shell("exit 3").output_checked().unwrap_err();
+/// let expected = if cfg!(windows) {
+///     "`powershell` failed: exit code: 3"
+/// } else {
+///     "`sh` failed: exit status: 3"
+/// };
+/// assert_eq!(err.to_string().lines().next().unwrap(), expected);
+/// ```
+pub fn shell(script: &str) -> Command {
+    Shell::default().command(script)
+}
+
+/// PowerShell's flag for a base64-encoded script.
+pub(crate) const ENCODED_COMMAND: &str = "-EncodedCommand";
+
+/// Encode a script for PowerShell's `-EncodedCommand`: UTF-16LE, then base64.
+fn encode_powershell(script: &str) -> String {
+    let bytes = script
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    base64::encode(&bytes)
+}
+
+/// Decode a script passed to PowerShell's `-EncodedCommand`.
+pub(crate) fn decode_powershell(encoded: &str) -> Option<String> {
+    let bytes = base64::decode(encoded)?;
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+    let units = bytes
+        .chunks(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<_>>();
+    String::from_utf16(&units).ok()
+}
@@ -0,0 +1,105 @@
+use std::process::ExitStatus;
+
+#[cfg(doc)]
+use crate::OutputContext;
+
+/// The number of the signal which killed a command, if it was killed by a signal.
+///
+/// This is always [`None`] on non-Unix platforms. See also [`OutputContext::signal`].
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// let status = Command::new("sh")
+///     .args(["-c", "kill -TERM $$"])
+///     .status()
+///     .unwrap();
+/// let signal = command_error::exit_signal(status);
+/// assert_eq!(signal, Some(15));
+/// assert_eq!(command_error::signal_name(signal.unwrap()), Some("SIGTERM"));
+/// ```
+#[cfg(unix)]
+pub fn exit_signal(status: ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+/// The number of the signal which killed a command, if it was killed by a signal.
+///
+/// This is always [`None`] on non-Unix platforms. See also [`OutputContext::signal`].
+#[cfg(not(unix))]
+pub fn exit_signal(_status: ExitStatus) -> Option<i32> {
+    None
+}
+
+/// The name of a common signal, like `SIGKILL` for 9, or [`None`] if it isn't recognized.
+///
+/// Signal numbers vary between platforms, so this uses the current platform's numbering. This is
+/// always [`None`] on non-Unix platforms.
+#[cfg(unix)]
+pub fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGCHLD => "SIGCHLD",
+        libc::SIGCONT => "SIGCONT",
+        libc::SIGSTOP => "SIGSTOP",
+        libc::SIGTSTP => "SIGTSTP",
+        libc::SIGTTIN => "SIGTTIN",
+        libc::SIGTTOU => "SIGTTOU",
+        libc::SIGURG => "SIGURG",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGVTALRM => "SIGVTALRM",
+        libc::SIGPROF => "SIGPROF",
+        libc::SIGWINCH => "SIGWINCH",
+        libc::SIGSYS => "SIGSYS",
+        _ => return None,
+    })
+}
+
+/// The name of a common signal, like `SIGKILL` for 9, or [`None`] if it isn't recognized.
+///
+/// Signal numbers vary between platforms, so this uses the current platform's numbering. This is
+/// always [`None`] on non-Unix platforms.
+#[cfg(not(unix))]
+pub fn signal_name(_signal: i32) -> Option<&'static str> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_exit_signal() {
+        assert_eq!(exit_signal(ExitStatus::from_raw(libc::SIGKILL)), Some(9));
+        // `exit 3`; the wait status holds the exit code in the second byte.
+        assert_eq!(exit_signal(ExitStatus::from_raw(3 << 8)), None);
+    }
+
+    #[test]
+    fn test_signal_name() {
+        assert_eq!(signal_name(libc::SIGKILL), Some("SIGKILL"));
+        assert_eq!(signal_name(libc::SIGSEGV), Some("SIGSEGV"));
+        assert_eq!(signal_name(0), None);
+        assert_eq!(signal_name(1000), None);
+    }
+}
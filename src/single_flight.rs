@@ -0,0 +1,437 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::time::SystemTime;
+
+use crate::dedup::command_fingerprint;
+use crate::AlreadyRunningError;
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Error;
+#[cfg(unix)]
+use crate::ExecError;
+use crate::Utf8ProgramAndArgs;
+
+/// The commands currently running under a [`SingleFlight`] in this process, keyed by fingerprint.
+static RUNNING: Mutex<BTreeMap<u64, Invocation>> = Mutex::new(BTreeMap::new());
+
+/// Notified when a command in [`RUNNING`] finishes.
+static FINISHED: Condvar = Condvar::new();
+
+/// A running command, as recorded by a [`SingleFlight`].
+struct Invocation {
+    displayed: String,
+    started: SystemTime,
+}
+
+/// A guard against running the same command more than once at a time.
+///
+/// While a command runs with [`CommandExt::output_checked_with_single_flight`], running the same
+/// command (the same program and arguments) again fails immediately with an
+/// [`AlreadyRunningError`] naming the running invocation. With [`SingleFlight::blocking`], the
+/// duplicate invocation waits for the running one to finish instead.
+///
+/// Running commands are tracked in a process-wide registry, so separate [`SingleFlight`]s in the
+/// same process guard against each other. On Unix, [`SingleFlight::lock_dir`] also guards against
+/// other processes using the same directory.
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use std::time::Duration;
+/// # use command_error::CommandExt;
+/// # use command_error::Error;
+/// # use command_error::SingleFlight;
+/// let single_flight = SingleFlight::new();
+/// std::thread::scope(|scope| {
+///     let running = scope.spawn(|| {
+///         Command::new("sleep")
+///             .arg("1")
+///             .output_checked_with_single_flight(&single_flight)
+///     });
+///     std::thread::sleep(Duration::from_millis(250));
+///
+///     let err = Command::new("sleep")
+///         .arg("1")
+///         .output_checked_with_single_flight(&single_flight)
+///         .unwrap_err();
+///     let Error::AlreadyRunning(err) = err else { unreachable!() };
+///     assert_eq!(err.running(), "sleep 1");
+///     assert_eq!(err.pid(), Some(std::process::id()));
+///
+///     // A blocking guard waits for the running command to finish.
+///     Command::new("sleep")
+///         .arg("1")
+///         .output_checked_with_single_flight(&SingleFlight::new().blocking())
+///         .unwrap();
+///     running.join().unwrap().unwrap();
+/// });
+/// ```
+pub struct SingleFlight {
+    blocking: bool,
+    #[cfg(unix)]
+    lock_dir: Option<PathBuf>,
+}
+
+impl SingleFlight {
+    /// Construct a new [`SingleFlight`] which fails immediately when a command is already running
+    /// in this process.
+    pub fn new() -> Self {
+        Self {
+            blocking: false,
+            #[cfg(unix)]
+            lock_dir: None,
+        }
+    }
+
+    /// Wait for an already-running command to finish, instead of failing.
+    pub fn blocking(mut self) -> Self {
+        self.blocking = true;
+        self
+    }
+
+    /// Also guard against commands running in other processes, with advisory lock files in `dir`.
+    ///
+    /// Each running command holds an exclusive [`flock(2)`][flock] on a file in `dir` named after
+    /// its fingerprint, containing its process ID, start time, and display. A lock file held by a
+    /// process which no longer exists is considered stale and removed. The directory is created
+    /// if it doesn't exist.
+    ///
+    /// [flock]: https://man7.org/linux/man-pages/man2/flock.2.html
+    #[cfg(unix)]
+    pub fn lock_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.lock_dir = Some(dir.into());
+        self
+    }
+
+    /// Wait for or fail on an already-running `command`, returning a guard which releases it once
+    /// it's done running.
+    pub(crate) fn acquire(&self, command: &Utf8ProgramAndArgs) -> Result<SingleFlightGuard, Error> {
+        let key = command_fingerprint(command);
+        let displayed = command.to_string();
+        {
+            let mut running = lock_running();
+            while let Some(invocation) = running.get(&key) {
+                if !self.blocking {
+                    return Err(Error::from(AlreadyRunningError::new(
+                        Box::new(command.clone()),
+                        invocation.displayed.clone(),
+                        Some(std::process::id()),
+                        invocation.started,
+                    )));
+                }
+                running = FINISHED
+                    .wait(running)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+            running.insert(
+                key,
+                Invocation {
+                    displayed: displayed.clone(),
+                    started: SystemTime::now(),
+                },
+            );
+        }
+
+        #[allow(unused_mut)]
+        let mut guard = SingleFlightGuard {
+            key,
+            #[cfg(unix)]
+            lock_file: None,
+        };
+
+        #[cfg(unix)]
+        if let Some(dir) = &self.lock_dir {
+            match lock_file::LockFile::acquire(dir, key, &displayed, self.blocking) {
+                Ok(Ok(lock_file)) => guard.lock_file = Some(lock_file),
+                Ok(Err(holder)) => {
+                    return Err(Error::from(AlreadyRunningError::new(
+                        Box::new(command.clone()),
+                        holder.displayed,
+                        Some(holder.pid),
+                        holder.started,
+                    )));
+                }
+                Err(inner) => {
+                    return Err(Error::from(ExecError::new(
+                        Box::new(command.clone()),
+                        inner,
+                    )));
+                }
+            }
+        }
+
+        Ok(guard)
+    }
+}
+
+impl Default for SingleFlight {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for SingleFlight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("SingleFlight");
+        debug.field("blocking", &self.blocking);
+        #[cfg(unix)]
+        debug.field("lock_dir", &self.lock_dir);
+        debug.finish()
+    }
+}
+
+fn lock_running() -> MutexGuard<'static, BTreeMap<u64, Invocation>> {
+    // The registry is always consistent, even if a panic occurred while it was locked.
+    RUNNING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Marks a command as running until it's dropped.
+pub(crate) struct SingleFlightGuard {
+    key: u64,
+    #[cfg(unix)]
+    lock_file: Option<lock_file::LockFile>,
+}
+
+impl Drop for SingleFlightGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        drop(self.lock_file.take());
+        lock_running().remove(&self.key);
+        FINISHED.notify_all();
+    }
+}
+
+#[cfg(unix)]
+mod lock_file {
+    use std::fs::File;
+    use std::fs::OpenOptions;
+    use std::io::Read;
+    use std::io::Seek;
+    use std::io::Write;
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    /// How many times to retry taking a lock file which was removed or found to be stale.
+    const RETRIES: usize = 16;
+
+    /// How long to wait for a lock file's holder to finish writing its contents.
+    const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// An exclusive lock on a command's lock file, released and removed when dropped.
+    pub(crate) struct LockFile {
+        path: PathBuf,
+        // Unlocked when closed.
+        _file: File,
+    }
+
+    /// The process holding a lock file, according to the file's contents.
+    pub(crate) struct Holder {
+        pub(crate) pid: u32,
+        pub(crate) started: SystemTime,
+        pub(crate) displayed: String,
+    }
+
+    impl LockFile {
+        /// Take the lock file for the command with fingerprint `key` in `dir`, or get the process
+        /// holding it if it's held and `blocking` is false.
+        pub(crate) fn acquire(
+            dir: &Path,
+            key: u64,
+            displayed: &str,
+            blocking: bool,
+        ) -> std::io::Result<Result<Self, Holder>> {
+            std::fs::create_dir_all(dir)?;
+            let path = dir.join(format!("{key:016x}.lock"));
+            let operation = if blocking {
+                libc::LOCK_EX
+            } else {
+                libc::LOCK_EX | libc::LOCK_NB
+            };
+
+            for _ in 0..RETRIES {
+                let mut file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(false)
+                    .open(&path)?;
+
+                if flock(&file, operation)? {
+                    // The previous holder removes the file when it's done, so we may have locked
+                    // a file which no longer exists.
+                    if !is_same_file(&file, &path)? {
+                        continue;
+                    }
+                    file.set_len(0)?;
+                    write!(
+                        file,
+                        "{}\n{}\n{displayed}",
+                        std::process::id(),
+                        unix_millis(SystemTime::now()),
+                    )?;
+                    return Ok(Ok(Self { path, _file: file }));
+                }
+
+                match Holder::read(&mut file)? {
+                    Some(holder) if is_alive(holder.pid) => return Ok(Err(holder)),
+                    Some(_) => {
+                        // Stale; the holder is gone, but something else (like a child which
+                        // inherited the file) is keeping it locked.
+                        remove(&path)?;
+                    }
+                    // The holder hasn't finished writing the file yet.
+                    None => std::thread::sleep(RETRY_INTERVAL),
+                }
+            }
+
+            Err(std::io::Error::other(format!(
+                "Failed to lock {}: it's locked by an unknown process",
+                path.display()
+            )))
+        }
+    }
+
+    impl Drop for LockFile {
+        fn drop(&mut self) {
+            // Remove the file before unlocking it, so that a waiting process which locks it
+            // afterwards knows to retry.
+            let _ = remove(&self.path);
+        }
+    }
+
+    impl Holder {
+        /// Parse a lock file's contents, or `None` if they're incomplete.
+        fn read(file: &mut File) -> std::io::Result<Option<Self>> {
+            let mut contents = String::new();
+            file.rewind()?;
+            file.read_to_string(&mut contents)?;
+            let mut lines = contents.splitn(3, '\n');
+            let (Some(pid), Some(started), Some(displayed)) =
+                (lines.next(), lines.next(), lines.next())
+            else {
+                return Ok(None);
+            };
+            let (Ok(pid), Ok(started)) = (pid.parse(), started.parse()) else {
+                return Ok(None);
+            };
+            Ok(Some(Self {
+                pid,
+                started: SystemTime::UNIX_EPOCH + Duration::from_millis(started),
+                displayed: displayed.to_owned(),
+            }))
+        }
+    }
+
+    /// Apply an advisory lock operation, returning `false` if it would block.
+    fn flock(file: &File, operation: libc::c_int) -> std::io::Result<bool> {
+        loop {
+            // SAFETY: `flock` has no memory-safety preconditions, and `file` is open.
+            if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+                return Ok(true);
+            }
+            let error = std::io::Error::last_os_error();
+            match error.raw_os_error() {
+                Some(libc::EWOULDBLOCK) => return Ok(false),
+                Some(libc::EINTR) => {}
+                _ => return Err(error),
+            }
+        }
+    }
+
+    fn is_same_file(file: &File, path: &Path) -> std::io::Result<bool> {
+        let metadata = file.metadata()?;
+        match std::fs::metadata(path) {
+            Ok(path_metadata) => {
+                Ok(metadata.dev() == path_metadata.dev() && metadata.ino() == path_metadata.ino())
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn is_alive(pid: u32) -> bool {
+        // SAFETY: `kill` has no memory-safety preconditions, and signal 0 only checks that the
+        // process exists.
+        let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+        result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+
+    fn remove(path: &Path) -> std::io::Result<()> {
+        match std::fs::remove_file(path) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    fn unix_millis(time: SystemTime) -> u128 {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use pretty_assertions::assert_eq;
+        use std::process::Command;
+
+        fn temp_dir(name: &str) -> PathBuf {
+            let dir = std::env::temp_dir().join(format!(
+                "command-error-single-flight-{name}-{}",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            dir
+        }
+
+        /// Hold the lock file for `key` in `dir`, claiming to be `pid`.
+        fn hold(dir: &Path, key: u64, pid: u32) -> File {
+            std::fs::create_dir_all(dir).unwrap();
+            let mut file = File::create(dir.join(format!("{key:016x}.lock"))).unwrap();
+            assert!(flock(&file, libc::LOCK_EX | libc::LOCK_NB).unwrap());
+            write!(file, "{pid}\n0\nsleep 1").unwrap();
+            file
+        }
+
+        #[test]
+        fn test_held_by_live_process() {
+            let dir = temp_dir("live");
+            let _held = hold(&dir, 1, std::process::id());
+            let Err(holder) = LockFile::acquire(&dir, 1, "sleep 2", false).unwrap() else {
+                panic!("Lock file should be held");
+            };
+            assert_eq!(holder.pid, std::process::id());
+            assert_eq!(holder.started, SystemTime::UNIX_EPOCH);
+            assert_eq!(holder.displayed, "sleep 1");
+        }
+
+        #[test]
+        fn test_stale_lock_removed() {
+            let dir = temp_dir("stale");
+            let mut child = Command::new("true").spawn().unwrap();
+            let dead = child.id();
+            child.wait().unwrap();
+
+            let _held = hold(&dir, 2, dead);
+            let Ok(lock) = LockFile::acquire(&dir, 2, "sleep 2", false).unwrap() else {
+                panic!("Stale lock file should be removed");
+            };
+            let contents = std::fs::read_to_string(&lock.path).unwrap();
+            assert!(contents.starts_with(&format!("{}\n", std::process::id())));
+            assert!(contents.ends_with("\nsleep 2"));
+
+            drop(lock);
+            assert!(!dir.join(format!("{:016x}.lock", 2)).exists());
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::ErrorKind;
+
+#[cfg(doc)]
+use crate::ExecError;
+
+/// Whether a failure to start a command is worth retrying; see [`classify_spawn_error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpawnErrorClass {
+    /// The failure is likely temporary, like the system being at its process limit, and
+    /// starting the command again later may succeed.
+    Transient,
+    /// The failure won't go away by itself, like the program not existing, so retrying won't
+    /// help.
+    Permanent,
+}
+
+/// Classify an error from starting a command (like from [`std::process::Command::spawn`]) as
+/// [`SpawnErrorClass::Transient`] or [`SpawnErrorClass::Permanent`].
+///
+/// These [`ErrorKind`]s are transient:
+///
+/// - [`ErrorKind::WouldBlock`] (`EAGAIN`), like when the system or user is at its process limit.
+/// - [`ErrorKind::OutOfMemory`] (`ENOMEM`), like when `fork` can't allocate memory.
+/// - [`ErrorKind::Interrupted`] (`EINTR`), when a signal interrupted a system call.
+/// - [`ErrorKind::TimedOut`].
+/// - [`ErrorKind::ResourceBusy`] (`EBUSY`).
+/// - [`ErrorKind::ExecutableFileBusy`] (`ETXTBSY`), when the program is still open for writing,
+///   like right after it's written.
+///
+/// On Unix, `EIO` (which has no dedicated [`ErrorKind`]) is also transient, since it's usually
+/// an intermittent failure of a network filesystem.
+///
+/// Every other error is permanent, including [`ErrorKind::NotFound`] and
+/// [`ErrorKind::PermissionDenied`]. Unrecognized errors are classified as permanent, so that
+/// callers don't retry failures they don't understand.
+///
+/// ```
+/// # use command_error::classify_spawn_error;
+/// # use command_error::SpawnErrorClass;
+/// assert_eq!(
+///     classify_spawn_error(&std::io::Error::from(std::io::ErrorKind::WouldBlock)),
+///     SpawnErrorClass::Transient
+/// );
+/// assert_eq!(
+///     classify_spawn_error(&std::io::Error::from(std::io::ErrorKind::NotFound)),
+///     SpawnErrorClass::Permanent
+/// );
+/// ```
+///
+/// See also [`ExecError::is_transient`].
+pub fn classify_spawn_error(error: &std::io::Error) -> SpawnErrorClass {
+    #[cfg(unix)]
+    if error.raw_os_error() == Some(libc::EIO) {
+        return SpawnErrorClass::Transient;
+    }
+    match error.kind() {
+        ErrorKind::WouldBlock
+        | ErrorKind::OutOfMemory
+        | ErrorKind::Interrupted
+        | ErrorKind::TimedOut
+        | ErrorKind::ResourceBusy
+        | ErrorKind::ExecutableFileBusy => SpawnErrorClass::Transient,
+        _ => SpawnErrorClass::Permanent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Pins the classification of each [`ErrorKind`], so that changes to it are deliberate.
+    #[test]
+    fn test_classify_spawn_error() {
+        use SpawnErrorClass::*;
+        let kinds = [
+            (ErrorKind::NotFound, Permanent),
+            (ErrorKind::PermissionDenied, Permanent),
+            (ErrorKind::ConnectionRefused, Permanent),
+            (ErrorKind::ConnectionReset, Permanent),
+            (ErrorKind::HostUnreachable, Permanent),
+            (ErrorKind::NetworkUnreachable, Permanent),
+            (ErrorKind::ConnectionAborted, Permanent),
+            (ErrorKind::NotConnected, Permanent),
+            (ErrorKind::AddrInUse, Permanent),
+            (ErrorKind::AddrNotAvailable, Permanent),
+            (ErrorKind::NetworkDown, Permanent),
+            (ErrorKind::BrokenPipe, Permanent),
+            (ErrorKind::AlreadyExists, Permanent),
+            (ErrorKind::WouldBlock, Transient),
+            (ErrorKind::NotADirectory, Permanent),
+            (ErrorKind::IsADirectory, Permanent),
+            (ErrorKind::DirectoryNotEmpty, Permanent),
+            (ErrorKind::ReadOnlyFilesystem, Permanent),
+            (ErrorKind::StaleNetworkFileHandle, Permanent),
+            (ErrorKind::InvalidInput, Permanent),
+            (ErrorKind::InvalidData, Permanent),
+            (ErrorKind::TimedOut, Transient),
+            (ErrorKind::WriteZero, Permanent),
+            (ErrorKind::StorageFull, Permanent),
+            (ErrorKind::NotSeekable, Permanent),
+            (ErrorKind::QuotaExceeded, Permanent),
+            (ErrorKind::FileTooLarge, Permanent),
+            (ErrorKind::ResourceBusy, Transient),
+            (ErrorKind::ExecutableFileBusy, Transient),
+            (ErrorKind::Deadlock, Permanent),
+            (ErrorKind::CrossesDevices, Permanent),
+            (ErrorKind::TooManyLinks, Permanent),
+            (ErrorKind::InvalidFilename, Permanent),
+            (ErrorKind::ArgumentListTooLong, Permanent),
+            (ErrorKind::Interrupted, Transient),
+            (ErrorKind::Unsupported, Permanent),
+            (ErrorKind::UnexpectedEof, Permanent),
+            (ErrorKind::OutOfMemory, Transient),
+            (ErrorKind::Other, Permanent),
+        ];
+        for (kind, class) in kinds {
+            assert_eq!(
+                (kind, classify_spawn_error(&std::io::Error::from(kind))),
+                (kind, class)
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_classify_spawn_error_raw() {
+        for (errno, class) in [
+            (libc::EAGAIN, SpawnErrorClass::Transient),
+            (libc::ENOMEM, SpawnErrorClass::Transient),
+            (libc::ETXTBSY, SpawnErrorClass::Transient),
+            (libc::EIO, SpawnErrorClass::Transient),
+            (libc::ENOENT, SpawnErrorClass::Permanent),
+            (libc::EACCES, SpawnErrorClass::Permanent),
+            (libc::ENOEXEC, SpawnErrorClass::Permanent),
+        ] {
+            assert_eq!(
+                (
+                    errno,
+                    classify_spawn_error(&std::io::Error::from_raw_os_error(errno))
+                ),
+                (errno, class)
+            );
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::process::Command;
+use std::sync::RwLock;
+
+#[cfg(doc)]
+use crate::CommandExt;
+
+/// Called with each command just before it's started; see [`set_spawn_hook`].
+type SpawnHook = Box<dyn Fn(&mut Command) + Send + Sync>;
+
+/// The hook currently called before commands are started; see [`set_spawn_hook`].
+static SPAWN_HOOK: RwLock<Option<SpawnHook>> = RwLock::new(None);
+
+/// Call `hook` with each command just before it's started by a checked method (like
+/// [`CommandExt::output_checked`]), for the rest of the process.
+///
+/// This is useful for propagating context from the current process to its children. For
+/// example, to link a child's trace to the current OpenTelemetry span, the hook can inject the
+/// span's context into the command's environment as `TRACEPARENT`, using the
+/// `opentelemetry` crate's `TextMapPropagator::inject_context`:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// # fn current_traceparent() -> String {
+/// #     "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_owned()
+/// # }
+/// command_error::set_spawn_hook(|command| {
+///     command.env("TRACEPARENT", current_traceparent());
+/// });
+///
+/// let output = Command::new("sh")
+///     .args(["-c", "echo $TRACEPARENT"])
+///     .output_checked_utf8()
+///     .unwrap();
+/// assert_eq!(
+///     output.stdout,
+///     "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01\n"
+/// );
+/// ```
+///
+/// With the `tracing` feature, each command is also logged (in the current span) before it's
+/// started; see [`CommandExt::log`].
+///
+/// The hook is called for [`std::process::Command`]s, and for the commands wrapped by
+/// `process_wrap::std::StdCommandWrap` and `tokio::process::Command`. It isn't called for
+/// `async_process::Command`s, which don't expose the [`Command`] they wrap.
+pub fn set_spawn_hook(hook: impl Fn(&mut Command) + Send + Sync + 'static) {
+    *SPAWN_HOOK
+        .write()
+        .unwrap_or_else(|error| error.into_inner()) = Some(Box::new(hook));
+}
+
+/// Call the hook set with [`set_spawn_hook`] with `command`, if there is one.
+pub(crate) fn run_spawn_hook(command: &mut Command) {
+    if let Some(hook) = SPAWN_HOOK
+        .read()
+        .unwrap_or_else(|error| error.into_inner())
+        .as_ref()
+    {
+        hook(command);
+    }
+}
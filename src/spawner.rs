@@ -0,0 +1,108 @@
+use std::fmt::Debug;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::process::Output;
+
+use crate::capture::CaptureFailure;
+use crate::CommandDisplay;
+#[cfg(doc)]
+use crate::CommandExt;
+use crate::Error;
+use crate::ExecError;
+
+/// A backend for starting processes, for [`CommandExt::output_checked_with_spawner`] and
+/// [`CommandExt::status_checked_with_spawner`].
+///
+/// Different backends can surface the same failure differently (for example, sandbox denials on
+/// macOS), so errors from commands started with a [`Spawner`] report which backend was used:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// # use command_error::StdSpawner;
+/// let err = Command::new("ooga booga")
+///     .output_checked_with_spawner(&StdSpawner)
+///     .unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "Failed to execute `'ooga booga'` via std::process: No such file or directory (os error 2)"
+/// );
+/// ```
+///
+/// This trait is sealed; the available backends are [`StdSpawner`] and, with the `posix-spawn`
+/// feature on Linux and macOS, `PosixSpawner`.
+pub trait Spawner: sealed::Spawn + Debug + Send + Sync {
+    /// The name of this backend, shown in error messages.
+    fn name(&self) -> &'static str;
+}
+
+pub(crate) mod sealed {
+    use super::*;
+
+    /// The implementation of a [`Spawner`].
+    pub trait Spawn {
+        /// Run `command`, capturing its output.
+        fn output(&self, command: &mut Command) -> Result<Output, SpawnFailure>;
+
+        /// Run `command` without capturing its output.
+        fn status(&self, command: &mut Command) -> Result<ExitStatus, SpawnFailure>;
+    }
+
+    /// An error from running a command with a [`Spawner`].
+    pub enum SpawnFailure {
+        /// The command couldn't be started.
+        Spawn {
+            /// The step of starting the command which failed, if known.
+            step: Option<&'static str>,
+            inner: std::io::Error,
+        },
+        /// The command started, but reading its output failed.
+        Read(std::io::Error),
+        /// The command started, but waiting for it failed.
+        Wait(std::io::Error),
+    }
+
+    impl SpawnFailure {
+        /// Convert this failure into an [`Error`] for `command`, which was started with
+        /// `backend`.
+        pub(crate) fn into_error(
+            self,
+            backend: &'static str,
+            command: Box<dyn CommandDisplay + Send + Sync>,
+        ) -> Error {
+            match self {
+                SpawnFailure::Spawn { step, inner } => {
+                    Error::from(ExecError::new(command, inner).with_backend(backend, step))
+                }
+                SpawnFailure::Read(inner) => CaptureFailure::Read(inner).into_error(command),
+                SpawnFailure::Wait(inner) => CaptureFailure::Wait(inner).into_error(command),
+            }
+        }
+    }
+}
+
+/// The default [`Spawner`], which starts processes with [`Command::output`] and
+/// [`Command::status`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdSpawner;
+
+impl Spawner for StdSpawner {
+    fn name(&self) -> &'static str {
+        "std::process"
+    }
+}
+
+impl sealed::Spawn for StdSpawner {
+    fn output(&self, command: &mut Command) -> Result<Output, sealed::SpawnFailure> {
+        command
+            .output()
+            .map_err(|inner| sealed::SpawnFailure::Spawn { step: None, inner })
+    }
+
+    fn status(&self, command: &mut Command) -> Result<ExitStatus, sealed::SpawnFailure> {
+        command
+            .status()
+            .map_err(|inner| sealed::SpawnFailure::Spawn { step: None, inner })
+    }
+}
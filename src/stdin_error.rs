@@ -0,0 +1,145 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+
+use crate::command_display::guarded;
+use crate::messages::messages;
+use crate::CommandDisplay;
+#[cfg(doc)]
+use crate::CommandExt;
+#[cfg(doc)]
+use crate::OutputError;
+#[cfg(feature = "miette")]
+use miette::Diagnostic;
+
+/// An error from failing to write a command's input to its stdin.
+///
+/// This is a failure to feed the command, rather than a failure of the command itself (an
+/// [`OutputError`]). Produced by methods which write the command's input themselves, like
+/// [`CommandExt::output_checked_with_input`].
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use command_error::Utf8ProgramAndArgs;
+/// # use command_error::StdinError;
+/// let command = Command::new("jq");
+/// let error = StdinError::new(
+///     Box::new(Utf8ProgramAndArgs::from(&command)),
+///     std::io::Error::other("Input/output error"),
+/// );
+/// assert_eq!(
+///     error.to_string(),
+///     "Failed to write to stdin of `jq`: Input/output error"
+/// );
+/// ```
+pub struct StdinError {
+    pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
+    pub(crate) inner: std::io::Error,
+}
+
+impl StdinError {
+    /// Construct a new [`StdinError`].
+    pub fn new(
+        command: impl Into<Box<dyn CommandDisplay + Send + Sync>>,
+        inner: std::io::Error,
+    ) -> Self {
+        Self {
+            command: command.into(),
+            inner,
+        }
+    }
+
+    /// Get the [`std::io::ErrorKind`] of the underlying error.
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.inner.kind()
+    }
+
+    /// Get a reference to the underlying error.
+    pub fn inner(&self) -> &std::io::Error {
+        &self.inner
+    }
+}
+
+impl Debug for StdinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdinError")
+            .field("program", &guarded(&*self.command).program())
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl Display for StdinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} `{}`: {}",
+            messages().failed_to_write_to_stdin_of,
+            guarded(&*self.command).program_quoted(),
+            self.inner
+        )
+    }
+}
+
+impl std::error::Error for StdinError {}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for StdinError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::stdin"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_assertions::assert_impl_all;
+
+    assert_impl_all!(StdinError: Send, Sync);
+
+    #[test]
+    fn test_capture_stdin_failure() {
+        use std::process::Command;
+        use std::process::Stdio;
+
+        use crate::capture;
+        use crate::Error;
+        use crate::Utf8ProgramAndArgs;
+
+        struct BadPipe;
+
+        impl std::io::Write for BadPipe {
+            fn write(&mut self, _buffer: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("Input/output error"))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut command = Command::new("true");
+        let mut child = command.stdout(Stdio::piped()).spawn().unwrap();
+        let stdout = child.stdout.take();
+        let failure = match capture::capture_with_input(
+            &mut child,
+            BadPipe,
+            stdout,
+            None::<std::io::Empty>,
+            b"puppy",
+            true,
+        ) {
+            Ok(_) => panic!("Writing to a failing stdin succeeded"),
+            Err(failure) => failure,
+        };
+        match failure.into_error(Box::new(Utf8ProgramAndArgs::from(&command))) {
+            Error::Stdin(error) => {
+                assert_eq!(
+                    error.to_string(),
+                    "Failed to write to stdin of `true`: Input/output error"
+                );
+            }
+            error => panic!("Unexpected error: {error}"),
+        }
+    }
+}
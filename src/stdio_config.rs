@@ -0,0 +1,258 @@
+use std::fmt::Display;
+use std::process::Child;
+
+#[cfg(doc)]
+use crate::ChildExt;
+#[cfg(doc)]
+use crate::CommandExt;
+#[cfg(doc)]
+use crate::OutputContext;
+#[cfg(doc)]
+use crate::OutputError;
+use crate::OutputStream;
+
+/// How one of a command's standard streams was connected when it ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StdioKind {
+    /// The stream was inherited from the parent process.
+    Inherited,
+    /// The stream was piped to the parent process.
+    Piped,
+    /// The stream was connected to `/dev/null`.
+    Null,
+    /// The stream was connected to something else, like a file.
+    Other,
+    /// The stream wasn't piped, but how it was connected couldn't be determined.
+    Unknown,
+}
+
+impl Display for StdioKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StdioKind::Inherited => write!(f, "inherited"),
+            StdioKind::Piped => write!(f, "piped"),
+            StdioKind::Null => write!(f, "null"),
+            StdioKind::Other => write!(f, "other"),
+            StdioKind::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// How a command's stdin, stdout, and stderr were connected when it ran.
+///
+/// Recorded by methods which spawn the child process themselves, like
+/// [`CommandExt::status_checked`] and [`CommandExt::spawn_checked`], and available from
+/// [`OutputContext::stdio`] and [`OutputError::stdio`]. [`CommandExt::output_checked`] and
+/// friends can't observe the configuration, so they don't record it.
+///
+/// When a child's output is requested (as with [`ChildExt::output_checked`]), streams which
+/// weren't piped are noted in error messages, so that an empty stream isn't mistaken for a program
+/// that printed nothing. [Verbose](OutputError::verbose) error messages always note them, and
+/// include the whole configuration:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use indoc::indoc;
+/// # use std::process::Command;
+/// # use std::process::Stdio;
+/// # use command_error::ChildExt;
+/// # use command_error::CommandExt;
+/// # use command_error::Error;
+/// # use command_error::StdioKind;
+/// // The child waits for its stdin to be closed, which happens when it's waited for.
+/// let err = Command::new("sh")
+///     .args(["-c", "read _; exit 1"])
+///     .stdin(Stdio::piped())
+///     .stdout(Stdio::piped())
+///     .stderr(Stdio::null())
+///     .spawn_checked()
+///     .unwrap()
+///     .output_checked()
+///     .unwrap_err();
+/// let Error::Output(err) = err else { unreachable!() };
+/// let stdio = err.stdio().unwrap();
+/// assert_eq!(stdio.stdout, StdioKind::Piped);
+/// # #[cfg(target_os = "linux")]
+/// assert_eq!(
+///     err.verbose(true).to_string().lines().take(4).collect::<Vec<_>>(),
+///     [
+///         "`sh` failed: exit status: 1",
+///         "Command failed: `sh -c 'read _; exit 1'`",
+///         "Stderr: (output not captured: stream was redirected to /dev/null)",
+///         "stdio: stdin=piped stdout=piped stderr=null",
+///     ]
+/// );
+/// ```
+///
+/// Whether a stream was piped is always known. Distinguishing inherited streams from `/dev/null`
+/// and other files is only possible on Linux, by examining the child's file descriptors after
+/// it's spawned; if the child has already exited by then, or on other platforms, streams which
+/// weren't piped are reported as [`StdioKind::Unknown`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StdioConfig {
+    /// How the command's stdin was connected.
+    pub stdin: StdioKind,
+    /// How the command's stdout was connected.
+    pub stdout: StdioKind,
+    /// How the command's stderr was connected.
+    pub stderr: StdioKind,
+}
+
+impl StdioConfig {
+    /// Construct a new [`StdioConfig`].
+    pub fn new(stdin: StdioKind, stdout: StdioKind, stderr: StdioKind) -> Self {
+        Self {
+            stdin,
+            stdout,
+            stderr,
+        }
+    }
+
+    /// Determine how `child`'s streams are connected.
+    ///
+    /// This must be called before the child's piped streams are taken.
+    pub(crate) fn of(child: &Child) -> Self {
+        let pid = child.id();
+        Self {
+            stdin: kind(pid, 0, child.stdin.is_some()),
+            stdout: kind(pid, 1, child.stdout.is_some()),
+            stderr: kind(pid, 2, child.stderr.is_some()),
+        }
+    }
+
+    /// How one of the command's output streams was connected.
+    pub fn stream(&self, stream: OutputStream) -> StdioKind {
+        match stream {
+            OutputStream::Stdout => self.stdout,
+            OutputStream::Stderr => self.stderr,
+        }
+    }
+
+    /// Whether one of the command's output streams was piped, so that its output could be
+    /// captured.
+    pub fn is_captured(&self, stream: OutputStream) -> bool {
+        self.stream(stream) == StdioKind::Piped
+    }
+
+    /// Set how one of the command's output streams was connected.
+    pub(crate) fn set(&mut self, stream: OutputStream, kind: StdioKind) {
+        match stream {
+            OutputStream::Stdout => self.stdout = kind,
+            OutputStream::Stderr => self.stderr = kind,
+        }
+    }
+
+    /// The note shown in place of an output stream which wasn't captured, if any.
+    pub(crate) fn uncaptured_note(&self, stream: OutputStream) -> Option<&'static str> {
+        match self.stream(stream) {
+            StdioKind::Piped => None,
+            StdioKind::Inherited => Some("(output not captured: stream was inherited)"),
+            StdioKind::Null => Some("(output not captured: stream was redirected to /dev/null)"),
+            StdioKind::Other => Some("(output not captured: stream was redirected)"),
+            StdioKind::Unknown => Some("(output not captured: stream was not piped)"),
+        }
+    }
+}
+
+impl Default for StdioConfig {
+    /// The configuration of a child spawned with its output captured: stdin is inherited and
+    /// stdout and stderr are piped.
+    fn default() -> Self {
+        Self::new(StdioKind::Inherited, StdioKind::Piped, StdioKind::Piped)
+    }
+}
+
+impl Display for StdioConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "stdin={} stdout={} stderr={}",
+            self.stdin, self.stdout, self.stderr
+        )
+    }
+}
+
+/// Determine how file descriptor `fd` of the process `pid` is connected.
+fn kind(pid: u32, fd: u32, piped: bool) -> StdioKind {
+    if piped {
+        StdioKind::Piped
+    } else {
+        unpiped_kind(pid, fd)
+    }
+}
+
+/// Determine how file descriptor `fd` of the process `pid`, which isn't piped, is connected.
+///
+/// This compares the targets of the descriptors in `/proc`: a descriptor which refers to the same
+/// file as the current process's (other than `/dev/null`) is considered inherited. Once the
+/// process has exited, its descriptors can't be examined, so the stream is reported as unknown.
+#[cfg(target_os = "linux")]
+fn unpiped_kind(pid: u32, fd: u32) -> StdioKind {
+    let Ok(target) = std::fs::read_link(format!("/proc/{pid}/fd/{fd}")) else {
+        return StdioKind::Unknown;
+    };
+    if target == std::path::Path::new("/dev/null") {
+        return StdioKind::Null;
+    }
+    match std::fs::read_link(format!("/proc/self/fd/{fd}")) {
+        Ok(ours) if ours == target => StdioKind::Inherited,
+        _ => StdioKind::Other,
+    }
+}
+
+/// Determine how file descriptor `fd` of the process `pid`, which isn't piped, is connected.
+///
+/// Only Linux can distinguish inherited streams from redirected ones, so the stream is reported as
+/// unknown.
+#[cfg(not(target_os = "linux"))]
+fn unpiped_kind(_pid: u32, _fd: u32) -> StdioKind {
+    StdioKind::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::process::Command;
+    use std::process::Stdio;
+
+    use crate::CommandExt;
+    use crate::Error;
+
+    #[test]
+    fn test_status_checked_stdio() {
+        // The child waits for its stdin to be closed, which happens when it's waited for, so its
+        // streams can be examined.
+        let err = Command::new("sh")
+            .args(["-c", "read _; exit 1"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .status_checked()
+            .unwrap_err();
+        let Error::Output(err) = err else {
+            panic!("Expected an output error, got {err:?}");
+        };
+        let stdio = err.stdio().unwrap();
+        assert_eq!(stdio.stdout, StdioKind::Piped);
+        assert!(!stdio.is_captured(OutputStream::Stderr));
+        #[cfg(target_os = "linux")]
+        assert_eq!(stdio.stderr, StdioKind::Null);
+
+        // Errors from `status_checked` only note uncaptured streams when verbose.
+        assert_eq!(
+            err.to_string(),
+            "`sh` failed: exit status: 1\nCommand failed: `sh -c 'read _; exit 1'`"
+        );
+    }
+
+    #[test]
+    fn test_exited_before_examined() {
+        let mut child = Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        // The child has been reaped, so its descriptors can't be examined.
+        assert_eq!(kind(pid, 1, false), StdioKind::Unknown);
+        assert_eq!(kind(pid, 1, true), StdioKind::Piped);
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+use std::time::Instant;
+
+#[cfg(doc)]
+use crate::ChildExt;
+#[cfg(doc)]
+use crate::CommandExt;
+#[cfg(doc)]
+use crate::OutputContext;
+use crate::OutputStream;
+
+/// When a command's output streams reached end-of-file, relative to when the command exited.
+///
+/// If a command starts a background process which inherits its stdout or stderr, the stream stays
+/// open until the background process closes it, even after the command itself has exited. When
+/// that happens, reading the command's output blocks long after the command has finished.
+///
+/// Available from [`OutputContext::stream_timings`]. When a stream lags behind the command's exit,
+/// error messages include a note:
+///
+/// ```
+/// # use indoc::indoc;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// let err = Command::new("sh")
+///     .args(["-c", "(sleep 2; echo late) 2>/dev/null & exit 3"])
+///     .output_checked_with_backpressure(16, |_stream, _chunk| {})
+///     .unwrap_err();
+///
+/// // The exact lag depends on how busy the machine is.
+/// let message = err.to_string();
+/// assert!(message.starts_with(indoc!(
+///     "`sh` failed: exit status: 3
+///     Command failed: `sh -c '(sleep 2; echo late) 2>/dev/null & exit 3'`
+///     Stdout:
+///       late
+///     Note: stdout remained open "
+/// )));
+/// assert!(message.ends_with(
+///     " after the process exited — a background process may have inherited the pipe"
+/// ));
+/// ```
+///
+/// # Which methods record timings
+///
+/// Timings are only recorded when this crate reads the command's output itself, which is done by:
+///
+/// - The streaming methods: [`CommandExt::output_checked_streaming_as`],
+///   [`CommandExt::output_checked_with_backpressure`], [`CommandExt::output_checked_prefixed`],
+///   [`CommandExt::output_checked_counting_lines`], and `CommandExt::output_checked_hashing`.
+/// - [`CommandExt::output_checked_on_stall`].
+/// - The timeout methods, like [`CommandExt::output_checked_timeout`] and
+///   [`CommandExt::output_checked_with_timeout_and_clock`].
+/// - The methods which write to the command's stdin, like
+///   [`CommandExt::output_checked_with_input_as`] and
+///   [`CommandExt::output_checked_with_stdin_keeping_open`].
+///
+/// Other methods, including [`CommandExt::output_checked`] (which uses [`Command::output`]) and
+/// [`ChildExt::output_checked`] (which uses [`Child::wait_with_output`]), leave the reading to the
+/// standard library, so they don't record timings.
+///
+/// [`Command::output`]: std::process::Command::output
+/// [`Child::wait_with_output`]: std::process::Child::wait_with_output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTimings {
+    pub(crate) started: Instant,
+    pub(crate) exited: Instant,
+    pub(crate) stdout_first_byte: Option<Instant>,
+    pub(crate) stderr_first_byte: Option<Instant>,
+    pub(crate) stdout_closed: Option<Instant>,
+    pub(crate) stderr_closed: Option<Instant>,
+}
+
+impl StreamTimings {
+    /// Streams which remain open for longer than this after the command exits are noted in error
+    /// messages.
+    pub const LAG_THRESHOLD: Duration = Duration::from_secs(1);
+
+    /// Streams which are closed without output within this long of the command starting are
+    /// considered to have been [closed immediately][StreamTimings::closed_immediately].
+    pub const IMMEDIATE_CLOSE_THRESHOLD: Duration = Duration::from_millis(10);
+
+    /// When reading the command's output started, just after it was spawned.
+    pub fn started(&self) -> Instant {
+        self.started
+    }
+
+    /// When the command's exit was observed.
+    pub fn exited(&self) -> Instant {
+        self.exited
+    }
+
+    /// When the first byte of the given stream was read, if the stream was captured and the
+    /// command wrote to it.
+    pub fn first_byte(&self, stream: OutputStream) -> Option<Instant> {
+        match stream {
+            OutputStream::Stdout => self.stdout_first_byte,
+            OutputStream::Stderr => self.stderr_first_byte,
+        }
+    }
+
+    /// How long after the command started the first byte of the given stream was read, if the
+    /// stream was captured and the command wrote to it.
+    ///
+    /// ```
+    /// # use std::process::Command;
+    /// # use command_error::CommandExt;
+    /// # use command_error::OutputContext;
+    /// # use command_error::OutputStream;
+    /// # use std::process::Output;
+    /// Command::new("echo")
+    ///     .arg("puppy")
+    ///     .output_checked_streaming_as(16, |_stream, _chunk| {}, |context: OutputContext<Output>| {
+    ///         let timings = context.stream_timings().unwrap();
+    ///         assert!(timings.first_byte_latency(OutputStream::Stdout).is_some());
+    ///         assert_eq!(timings.first_byte_latency(OutputStream::Stderr), None);
+    ///         Ok::<_, command_error::Error>(context.into_output())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn first_byte_latency(&self, stream: OutputStream) -> Option<Duration> {
+        self.first_byte(stream)
+            .map(|first_byte| first_byte.saturating_duration_since(self.started))
+    }
+
+    /// How long after the command started the given stream reached end-of-file, if it was
+    /// captured.
+    pub fn closed_latency(&self, stream: OutputStream) -> Option<Duration> {
+        self.closed(stream)
+            .map(|closed| closed.saturating_duration_since(self.started))
+    }
+
+    /// Whether the given stream was closed without any output within
+    /// [`StreamTimings::IMMEDIATE_CLOSE_THRESHOLD`] of the command starting.
+    ///
+    /// A program which never writes to a stream usually leaves it open until it exits, so this
+    /// often means the program crashed before it really started, like in the dynamic linker.
+    pub fn closed_immediately(&self, stream: OutputStream) -> bool {
+        self.first_byte(stream).is_none()
+            && self
+                .closed_latency(stream)
+                .is_some_and(|latency| latency <= Self::IMMEDIATE_CLOSE_THRESHOLD)
+    }
+
+    /// When the given stream reached end-of-file, if it was captured.
+    pub fn closed(&self, stream: OutputStream) -> Option<Instant> {
+        match stream {
+            OutputStream::Stdout => self.stdout_closed,
+            OutputStream::Stderr => self.stderr_closed,
+        }
+    }
+
+    /// How long the given stream remained open after the command exited, if it was captured.
+    ///
+    /// This is zero if the stream was closed before the command exited.
+    pub fn lag(&self, stream: OutputStream) -> Option<Duration> {
+        self.closed(stream)
+            .map(|closed| closed.saturating_duration_since(self.exited))
+    }
+
+    /// Streams which remained open for longer than [`StreamTimings::LAG_THRESHOLD`] after the
+    /// command exited, and how long they remained open.
+    pub(crate) fn lagging(&self) -> impl Iterator<Item = (OutputStream, Duration)> + '_ {
+        [OutputStream::Stdout, OutputStream::Stderr]
+            .into_iter()
+            .filter_map(|stream| {
+                self.lag(stream)
+                    .filter(|lag| *lag > Self::LAG_THRESHOLD)
+                    .map(|lag| (stream, lag))
+            })
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt::Debug;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+#[cfg(doc)]
+use crate::ChildExt;
+use crate::Clock;
+use crate::Error;
+use crate::OutputContext;
+
+/// Settings for supervising a child process which is expected to run forever, like a daemon.
+///
+/// Used by [`ChildExt::supervise_checked`]. A [`Supervisor`] stores a function to spawn a
+/// replacement child when the supervised child exits, and limits how often that happens.
+///
+/// After each restart, the supervisor waits before respawning the child; the wait starts at
+/// [`Supervisor::backoff`] and doubles after each restart, up to [`Supervisor::max_backoff`]. If
+/// the initial backoff is longer than the maximum, the maximum is used from the first restart.
+pub struct Supervisor<C> {
+    pub(crate) rebuild: Box<dyn FnMut() -> Result<C, Error>>,
+    pub(crate) max_restarts: usize,
+    pub(crate) backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    pub(crate) clock: Box<dyn Clock>,
+}
+
+impl<C> Supervisor<C> {
+    /// Construct a new [`Supervisor`] which uses `rebuild` to spawn replacement children.
+    ///
+    /// By default, children are restarted at most 3 times, with a backoff starting at 1 second
+    /// and increasing to at most 1 minute.
+    pub fn new(rebuild: impl FnMut() -> Result<C, Error> + 'static) -> Self {
+        Self {
+            rebuild: Box::new(rebuild),
+            max_restarts: 3,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            clock: Box::new(std::time::Instant::now),
+        }
+    }
+
+    /// Spawn a child with this supervisor's `rebuild` function.
+    pub fn spawn(&mut self) -> Result<C, Error> {
+        (self.rebuild)()
+    }
+
+    /// Set the maximum number of times a child is restarted before giving up.
+    pub fn max_restarts(mut self, max_restarts: usize) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    /// Set how long to wait before the first restart.
+    ///
+    /// This is capped at [`Supervisor::max_backoff`].
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the maximum time to wait before a restart.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Use `clock` to wait before restarts instead of [`std::thread::sleep`].
+    ///
+    /// This is useful for testing.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+}
+
+impl<C> Debug for Supervisor<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Supervisor")
+            .field("max_restarts", &self.max_restarts)
+            .field("backoff", &self.backoff)
+            .field("max_backoff", &self.max_backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What to do when a supervised child exits.
+///
+/// Returned from the `on_exit` callback of [`ChildExt::supervise_checked`].
+#[derive(Debug)]
+pub enum SupervisorAction {
+    /// Spawn a replacement child and keep supervising.
+    Restart,
+    /// Stop supervising and return this error.
+    Escalate(Error),
+    /// Stop supervising and return the child's exit status.
+    Ignore,
+}
+
+impl SupervisorAction {
+    /// Stop supervising and return an error noting that the child exited unexpectedly.
+    pub fn escalate(context: OutputContext<ExitStatus>) -> Self {
+        Self::Escalate(context.error_msg("exited unexpectedly"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::cell::Cell;
+    use std::process::Command;
+    use std::rc::Rc;
+
+    use crate::clock::ManualClock;
+    use crate::ChildExt;
+    use crate::CommandExt;
+
+    #[test]
+    fn test_backoff() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let mut supervisor =
+            Supervisor::new(|| Command::new("true").spawn_checked()).with_clock(clock.clone());
+        supervisor
+            .spawn()
+            .unwrap()
+            .supervise_checked(&mut supervisor, |_context| SupervisorAction::Restart)
+            .unwrap_err();
+        // 1s, then 2s, then 4s.
+        assert_eq!(clock.now() - start, Duration::from_secs(7));
+    }
+
+    #[test]
+    fn test_backoff_clamped() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let mut supervisor = Supervisor::new(|| Command::new("true").spawn_checked())
+            .backoff(Duration::from_secs(10))
+            .max_backoff(Duration::from_secs(3))
+            .with_clock(clock.clone());
+        supervisor
+            .spawn()
+            .unwrap()
+            .supervise_checked(&mut supervisor, |_context| SupervisorAction::Restart)
+            .unwrap_err();
+        // 3s, 3 times.
+        assert_eq!(clock.now() - start, Duration::from_secs(9));
+    }
+
+    #[test]
+    fn test_max_restarts_zero() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let spawned = Rc::new(Cell::new(0));
+        let mut supervisor = Supervisor::new({
+            let spawned = spawned.clone();
+            move || {
+                spawned.set(spawned.get() + 1);
+                Command::new("true").spawn_checked()
+            }
+        })
+        .max_restarts(0)
+        .with_clock(clock.clone());
+        let err = supervisor
+            .spawn()
+            .unwrap()
+            .supervise_checked(&mut supervisor, |_context| SupervisorAction::Restart)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("`true` failed: exited unexpectedly (gave up after 0 restarts)\n"),
+            "{err}"
+        );
+        // Only the first child was spawned, and the supervisor never waited.
+        assert_eq!(spawned.get(), 1);
+        assert_eq!(clock.now(), start);
+    }
+
+    #[test]
+    fn test_escalate() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let mut supervisor =
+            Supervisor::new(|| Command::new("false").spawn_checked()).with_clock(clock.clone());
+        let err = supervisor
+            .spawn()
+            .unwrap()
+            .supervise_checked(&mut supervisor, SupervisorAction::escalate)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("`false` failed: exited unexpectedly\nexit status: 1\n"),
+            "{err}"
+        );
+        assert_eq!(clock.now(), start);
+    }
+
+    #[test]
+    fn test_ignore() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let mut supervisor =
+            Supervisor::new(|| Command::new("false").spawn_checked()).with_clock(clock.clone());
+        let status = supervisor
+            .spawn()
+            .unwrap()
+            .supervise_checked(&mut supervisor, |_context| SupervisorAction::Ignore)
+            .unwrap();
+        assert_eq!(status.code(), Some(1));
+        assert_eq!(clock.now(), start);
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::panic::Location;
+use std::process::Output;
+use std::time::Duration;
+
+use crate::command_display::guarded;
+use crate::duration::round_duration;
+use crate::format;
+use crate::messages::messages;
+use crate::output_error::write_indented;
+use crate::utf8_program_and_args::LOSSY_NOTE;
+use crate::CommandDisplay;
+use crate::OutputLike;
+
+#[cfg(doc)]
+use crate::CommandExt;
+#[cfg(feature = "miette")]
+use miette::Diagnostic;
+
+/// How a command was stopped after it timed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// The command exited after it was asked to (with `SIGTERM` on Unix).
+    Graceful,
+    /// The command didn't exit within the grace period after it was asked to, so it was killed
+    /// (with `SIGKILL` on Unix).
+    KilledAfterGrace(Duration),
+    /// The command was killed without a grace period, because none was requested or because
+    /// graceful termination isn't supported on this platform (on Windows, processes are stopped
+    /// with `TerminateProcess`).
+    Killed,
+    /// The command wasn't started, because its deadline had already passed (see
+    /// [`CommandExt::output_checked_with_deadline_shared`]).
+    NotStarted,
+}
+
+impl Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Termination::Graceful => write!(f, "was terminated gracefully"),
+            Termination::KilledAfterGrace(grace) => write!(
+                f,
+                "was killed after it didn't exit within the {:?} grace period",
+                round_duration(*grace)
+            ),
+            Termination::Killed => write!(f, "was killed"),
+            Termination::NotStarted => {
+                write!(f, "wasn't started because its deadline had already passed")
+            }
+        }
+    }
+}
+
+/// An error from a command that didn't exit before its timeout and was stopped.
+///
+/// Produced by methods like [`CommandExt::output_checked_with_timeout_and_grace`]. The error
+/// includes any output the command wrote before it was stopped, which is usually the most useful
+/// information for figuring out why it hung.
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use indoc::indoc;
+/// # use std::time::Duration;
+/// # use std::process::Command;
+/// # use std::process::Output;
+/// # use std::process::ExitStatus;
+/// # use command_error::Utf8ProgramAndArgs;
+/// # use command_error::TimeoutError;
+/// # use command_error::Termination;
+/// let mut command = Command::new("curl");
+/// command.arg("https://example.com");
+/// let error = TimeoutError::new(
+///     Box::new(Utf8ProgramAndArgs::from(&command)),
+///     Output {
+///         status: ExitStatus::default(),
+///         stdout: Vec::new(),
+///         stderr: b"Connecting...\n".to_vec(),
+///     },
+///     Duration::from_secs(30),
+///     Termination::Graceful,
+/// );
+/// assert_eq!(
+///     error.to_string(),
+///     indoc!(
+///         "`curl` timed out after 30s and was terminated gracefully
+///         Command failed: `curl https://example.com`
+///         Stderr:
+///           Connecting..."
+///     )
+/// );
+/// ```
+pub struct TimeoutError {
+    pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
+    /// The output captured before the command was stopped.
+    pub(crate) output: Output,
+    pub(crate) timeout: Duration,
+    pub(crate) termination: Termination,
+}
+
+impl TimeoutError {
+    /// Construct a new [`TimeoutError`].
+    pub fn new(
+        command: impl Into<Box<dyn CommandDisplay + Send + Sync>>,
+        output: Output,
+        timeout: Duration,
+        termination: Termination,
+    ) -> Self {
+        Self {
+            command: command.into(),
+            output,
+            timeout,
+            termination,
+        }
+    }
+
+    /// The output the command wrote before it was stopped, and its exit status after it was
+    /// stopped.
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// The timeout the command exceeded.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// How the command was stopped.
+    pub fn termination(&self) -> Termination {
+        self.termination
+    }
+
+    /// Where in the source code the command was run from, if known.
+    ///
+    /// See [`CommandDisplay::location`].
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        guarded(&*self.command).location()
+    }
+}
+
+impl Debug for TimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimeoutError")
+            .field("program", &guarded(&*self.command).program())
+            .field("status", &self.output.status)
+            .field("stdout_utf8", &OutputLike::stdout(&self.output))
+            .field("stderr_utf8", &OutputLike::stderr(&self.output))
+            .field("timeout", &self.timeout)
+            .field("termination", &self.termination)
+            .field("location", &self.location())
+            .finish()
+    }
+}
+
+impl Display for TimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // `curl` timed out after 30s and was killed
+        // Command failed: `curl https://example.com`
+        write!(
+            f,
+            "`{}` {} {:?} and {}\n{}: `{}`",
+            guarded(&*self.command).program_quoted(),
+            messages().timed_out_after,
+            round_duration(self.timeout),
+            self.termination,
+            messages().command_failed,
+            guarded(&*self.command),
+        )?;
+
+        if guarded(&*self.command).needs_lossy_note() {
+            write!(f, "\n{LOSSY_NOTE}")?;
+        }
+
+        const INDENT: &str = format::OUTPUT_INDENT;
+
+        let stdout = OutputLike::stdout(&self.output);
+        let stdout = stdout.trim();
+        if !stdout.is_empty() {
+            writeln!(f, "\n{}:", messages().stdout)?;
+            write_indented(f, stdout, INDENT)?;
+        }
+
+        let stderr = OutputLike::stderr(&self.output);
+        let stderr = stderr.trim();
+        if !stderr.is_empty() {
+            writeln!(f, "\n{}:", messages().stderr)?;
+            write_indented(f, stderr, INDENT)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for TimeoutError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::timeout"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_assertions::assert_impl_all;
+
+    assert_impl_all!(TimeoutError: Send, Sync);
+}
@@ -0,0 +1,145 @@
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::future::Future;
+use std::panic::Location;
+use std::process::ExitStatus;
+use std::process::Output;
+
+use tokio::process::Child;
+use tokio::process::Command;
+
+use crate::run;
+use crate::run::Ran;
+use crate::spawn_hook::run_spawn_hook;
+use crate::AsyncChildExt;
+use crate::AsyncCommandExt;
+use crate::ChildContext;
+use crate::Error;
+use crate::ExecError;
+#[cfg(feature = "tracing")]
+use crate::LazyProgramAndArgs;
+use crate::OutputContext;
+use crate::OutputLike;
+use crate::Started;
+use crate::Utf8ProgramAndArgs;
+use crate::WaitError;
+
+impl AsyncCommandExt for Command {
+    type Error = Error;
+    type Child = ChildContext<Child>;
+
+    fn output_checked_as<O, R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error> + Send + Sync,
+    {
+        let location = Location::caller();
+        async move {
+            run_spawn_hook(self.as_std_mut());
+            self.log()?;
+            let started = Started::now();
+            let result = self.output().await;
+            let displayed = display(self, location);
+            match result {
+                Ok(output) => {
+                    started.log_completion(&displayed, output.status);
+                    Ran::new(output, displayed).check_output(succeeded)
+                }
+                Err(inner) => {
+                    Err(run::exec_error(displayed, self.as_std().get_current_dir(), inner).into())
+                }
+            }
+        }
+    }
+
+    fn status_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E> + Send,
+    ) -> impl Future<Output = Result<R, E>> + Send
+    where
+        E: From<Self::Error>,
+    {
+        let location = Location::caller();
+        async move {
+            run_spawn_hook(self.as_std_mut());
+            self.log()?;
+            let started = Started::now();
+            let result = self.status().await;
+            let displayed = display(self, location);
+            match result {
+                Ok(status) => {
+                    started.log_completion(&displayed, status);
+                    Ran::new(status, displayed).check_status(succeeded)
+                }
+                Err(inner) => {
+                    Err(run::exec_error(displayed, self.as_std().get_current_dir(), inner).into())
+                }
+            }
+        }
+    }
+
+    fn spawn_checked(&mut self) -> Result<Self::Child, Self::Error> {
+        run_spawn_hook(self.as_std_mut());
+        self.log()?;
+        let displayed = display(self, Location::caller());
+        match self.spawn() {
+            Ok(child) => Ok(ChildContext::new(child, displayed)),
+            Err(inner) => Err(Error::from(
+                ExecError::new(displayed, inner).with_current_dir(self.as_std().get_current_dir()),
+            )),
+        }
+    }
+
+    fn log(&self) -> Result<(), Self::Error> {
+        #[cfg(feature = "tracing")]
+        {
+            let command = LazyProgramAndArgs::new(self.as_std());
+            tracing::debug!(%command, "Executing command");
+        }
+        Ok(())
+    }
+}
+
+/// Display `command` for an error or context object, recording that it was run from `location`.
+fn display(command: &Command, location: &'static Location<'static>) -> Box<Utf8ProgramAndArgs> {
+    Box::new(Utf8ProgramAndArgs::from(command.as_std()).with_location(location))
+}
+
+impl AsyncChildExt for ChildContext<Child> {
+    type Error = Error;
+
+    async fn output_checked_as<O, R, E>(
+        self,
+        succeeded: impl Fn(OutputContext<O>) -> Result<R, E> + Send,
+    ) -> Result<R, E>
+    where
+        O: Debug + OutputLike + TryFrom<Output> + Send + Sync + 'static,
+        <O as TryFrom<Output>>::Error: Display + Send + Sync,
+        E: From<Self::Error>,
+    {
+        let command = dyn_clone::clone_box(self.command.borrow());
+        match self.child.wait_with_output().await {
+            Ok(output) => Ran::new(output, command).check_output(succeeded),
+            Err(inner) => Err(Error::from(WaitError { command, inner }).into()),
+        }
+    }
+
+    async fn wait_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(OutputContext<ExitStatus>) -> Result<R, E> + Send,
+    ) -> Result<R, E>
+    where
+        E: From<Self::Error>,
+    {
+        let command = dyn_clone::clone_box(self.command.borrow());
+        match self.child.wait().await {
+            Ok(status) => Ran::new(status, command).check_status(succeeded),
+            Err(inner) => Err(Error::from(WaitError { command, inner }).into()),
+        }
+    }
+}
@@ -7,6 +7,7 @@ use std::process::Child;
 #[cfg(doc)]
 use std::process::Command;
 
+use crate::command_display::guarded;
 use crate::CommandDisplay;
 use crate::OutputContext;
 
@@ -31,16 +32,14 @@ impl TryWaitContext {
     }
 
     /// Get the command contained in this context object, for use in error messages or diagnostics.
-    pub fn into_command(self) -> Box<(dyn CommandDisplay + Send + Sync)> {
+    pub fn into_command(self) -> Box<dyn CommandDisplay + Send + Sync> {
         self.command
     }
 
     /// If the [`ExitStatus`] is present, get an [`OutputContext`] for constructing error messages.
     pub fn into_output_context(self) -> Option<OutputContext<ExitStatus>> {
-        self.status.map(|status| OutputContext {
-            output: status,
-            command: self.command,
-        })
+        self.status
+            .map(|status| OutputContext::new(status, self.command))
     }
 }
 
@@ -48,7 +47,7 @@ impl Debug for TryWaitContext {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("TryWaitContext")
             .field("status", &self.status)
-            .field("command", &self.command.to_string())
+            .field("command", &guarded(&*self.command).to_string())
             .finish()
     }
 }
@@ -0,0 +1,123 @@
+use std::borrow::Borrow;
+use std::process::Child;
+use std::process::ExitStatus;
+
+use crate::ChildContext;
+#[cfg(doc)]
+use crate::ChildExt;
+use crate::Error;
+use crate::TryWaitContext;
+use crate::WaitError;
+
+/// Checked, non-blocking polling for child processes.
+///
+/// This is implemented for every child process backend (like [`ChildContext<Child>`] and, with
+/// the `process-wrap` feature, `ChildContext<Box<dyn StdChildWrapper>>`), so code which polls
+/// children can be generic over the backend. It's also a supertrait of [`ChildExt`].
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use std::process::Command;
+/// # use std::process::ExitStatus;
+/// # use std::time::Duration;
+/// # use command_error::CommandExt;
+/// # use command_error::TryWaitExt;
+/// /// Poll `child` until it exits.
+/// fn poll<C: TryWaitExt>(child: &mut C) -> Result<ExitStatus, C::Error> {
+///     loop {
+///         if let Some(status) = child.try_wait_checked()? {
+///             return Ok(status);
+///         }
+///         std::thread::sleep(Duration::from_millis(10));
+///     }
+/// }
+///
+/// let mut child = Command::new("sleep").arg("0.1").spawn_checked().unwrap();
+/// assert_eq!(child.try_wait_checked().unwrap(), None);
+/// assert!(poll(&mut child).unwrap().success());
+///
+/// let mut child = Command::new("sh")
+///     .args(["-c", "sleep 0.1; exit 1"])
+///     .spawn_checked()
+///     .unwrap();
+/// assert_eq!(
+///     poll(&mut child).unwrap_err().to_string(),
+///     "`sh` failed: exit status: 1\nCommand failed: `sh -c 'sleep 0.1; exit 1'`"
+/// );
+///
+/// # #[cfg(feature = "process-wrap")]
+/// # {
+/// use process_wrap::std::StdCommandWrap;
+///
+/// let mut child = StdCommandWrap::with_new("sh", |command| {
+///     command.args(["-c", "sleep 0.1; exit 1"]);
+/// })
+/// .spawn_checked()
+/// .unwrap();
+/// assert_eq!(child.try_wait_checked().unwrap(), None);
+/// assert_eq!(
+///     poll(&mut child).unwrap_err().to_string(),
+///     "`sh` failed: exit status: 1\nCommand failed: `sh -c 'sleep 0.1; exit 1'`"
+/// );
+/// # }
+/// ```
+pub trait TryWaitExt {
+    /// The error type returned from methods on this trait.
+    type Error: From<Error>;
+
+    /// Check if the process has exited.
+    ///
+    /// The `succeeded` closure is called and returned to determine the result.
+    ///
+    /// Errors while attempting to retrieve the process's exit status are returned as
+    /// [`WaitError`]s.
+    ///
+    /// See [`Child::try_wait`] for more information.
+    #[track_caller]
+    fn try_wait_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(TryWaitContext) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        E: From<Self::Error>;
+
+    /// Check if the process has exited and, if it failed, return an error.
+    ///
+    /// Returns `Ok(None)` if the process is still running.
+    ///
+    /// Errors while attempting to retrieve the process's exit status are transformed into
+    /// [`WaitError`]s.
+    ///
+    /// See [`Child::try_wait`] for more information.
+    #[track_caller]
+    fn try_wait_checked(&mut self) -> Result<Option<ExitStatus>, Self::Error> {
+        self.try_wait_checked_as(|context| match context.into_output_context() {
+            Some(context) => {
+                if context.success() {
+                    Ok(Some(context.status()))
+                } else {
+                    Err(context.error().into())
+                }
+            }
+            None => Ok(None),
+        })
+    }
+}
+
+impl TryWaitExt for ChildContext<Child> {
+    type Error = Error;
+
+    fn try_wait_checked_as<R, E>(
+        &mut self,
+        succeeded: impl Fn(TryWaitContext) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        E: From<Self::Error>,
+    {
+        let command = dyn_clone::clone_box(self.command.borrow());
+        match self.child.try_wait() {
+            Ok(status) => succeeded(TryWaitContext { status, command }),
+            Err(inner) => Err(Error::from(WaitError { inner, command }).into()),
+        }
+    }
+}
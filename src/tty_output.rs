@@ -0,0 +1,56 @@
+use std::process::ExitStatus;
+use std::process::Output;
+
+#[cfg(doc)]
+use crate::CommandExt;
+
+/// The result of [`CommandExt::output_checked_with_tty_detection`].
+///
+/// If stdout is a terminal, the command inherits the parent's stdio so the user sees its
+/// (possibly colored or interactive) output directly, and only its [`ExitStatus`] is available.
+/// Otherwise, the command's [`Output`] is captured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TtyOutput {
+    /// Stdout was a terminal, so the command's output was not captured.
+    Inherited(ExitStatus),
+    /// Stdout was not a terminal, so the command's output was captured.
+    Captured(Output),
+}
+
+impl TtyOutput {
+    /// The command's exit status.
+    pub fn status(&self) -> ExitStatus {
+        match self {
+            TtyOutput::Inherited(status) => *status,
+            TtyOutput::Captured(output) => output.status,
+        }
+    }
+
+    /// The command's captured output, if it was captured.
+    pub fn output(&self) -> Option<&Output> {
+        match self {
+            TtyOutput::Inherited(_) => None,
+            TtyOutput::Captured(output) => Some(output),
+        }
+    }
+
+    /// Consume this value and return the command's captured output, if it was captured.
+    pub fn into_output(self) -> Option<Output> {
+        match self {
+            TtyOutput::Inherited(_) => None,
+            TtyOutput::Captured(output) => Some(output),
+        }
+    }
+}
+
+impl From<ExitStatus> for TtyOutput {
+    fn from(status: ExitStatus) -> Self {
+        Self::Inherited(status)
+    }
+}
+
+impl From<Output> for TtyOutput {
+    fn from(output: Output) -> Self {
+        Self::Captured(output)
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::process::Output;
+
+use crate::command_display::guarded;
+use crate::format;
+use crate::messages::messages;
+use crate::output_error::write_indented;
+use crate::CommandDisplay;
+use crate::OutputLike;
+
+#[cfg(doc)]
+use crate::CommandExt;
+#[cfg(feature = "miette")]
+use miette::Diagnostic;
+
+/// An error from a command that was expected to fail, but succeeded.
+///
+/// Produced by [`CommandExt::expect_failure`]. The error includes the command's full output, to
+/// help figure out why it didn't fail.
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use indoc::indoc;
+/// # use std::process::Command;
+/// # use std::process::Output;
+/// # use std::process::ExitStatus;
+/// # use command_error::Utf8ProgramAndArgs;
+/// # use command_error::UnexpectedSuccess;
+/// let mut command = Command::new("cargo");
+/// command.args(["build", "--bogus"]);
+/// let error = UnexpectedSuccess::new(
+///     Box::new(Utf8ProgramAndArgs::from(&command)),
+///     Output {
+///         status: ExitStatus::default(),
+///         stdout: Vec::new(),
+///         stderr: b"Finished\n".to_vec(),
+///     },
+/// );
+/// assert_eq!(
+///     error.to_string(),
+///     indoc!(
+///         "`cargo` succeeded unexpectedly: exit status: 0
+///         Command: `cargo build --bogus`
+///         Stderr:
+///           Finished"
+///     )
+/// );
+/// ```
+pub struct UnexpectedSuccess {
+    pub(crate) command: Box<dyn CommandDisplay + Send + Sync>,
+    pub(crate) output: Output,
+}
+
+impl UnexpectedSuccess {
+    /// Construct a new [`UnexpectedSuccess`].
+    pub fn new(command: impl Into<Box<dyn CommandDisplay + Send + Sync>>, output: Output) -> Self {
+        Self {
+            command: command.into(),
+            output,
+        }
+    }
+
+    /// The command's output.
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Get a reference to the command that succeeded.
+    pub fn command(&self) -> &(dyn CommandDisplay + Send + Sync) {
+        &*self.command
+    }
+}
+
+impl Debug for UnexpectedSuccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnexpectedSuccess")
+            .field("program", &guarded(&*self.command).program())
+            .field("status", &self.output.status)
+            .field("stdout_utf8", &OutputLike::stdout(&self.output))
+            .field("stderr_utf8", &OutputLike::stderr(&self.output))
+            .finish()
+    }
+}
+
+impl Display for UnexpectedSuccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // `cargo` succeeded unexpectedly: exit status: 0
+        // Command: `cargo build --bogus`
+        write!(
+            f,
+            "`{}` succeeded unexpectedly: {}\n{}: `{}`",
+            guarded(&*self.command).program_quoted(),
+            self.output.status,
+            messages().command,
+            guarded(&*self.command),
+        )?;
+
+        const INDENT: &str = format::OUTPUT_INDENT;
+
+        let stdout = OutputLike::stdout(&self.output);
+        let stdout = stdout.trim();
+        if !stdout.is_empty() {
+            writeln!(f, "\n{}:", messages().stdout)?;
+            write_indented(f, stdout, INDENT)?;
+        }
+
+        let stderr = OutputLike::stderr(&self.output);
+        let stderr = stderr.trim();
+        if !stderr.is_empty() {
+            writeln!(f, "\n{}:", messages().stderr)?;
+            write_indented(f, stderr, INDENT)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnexpectedSuccess {}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for UnexpectedSuccess {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::unexpected_success"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_assertions::assert_impl_all;
+
+    assert_impl_all!(UnexpectedSuccess: Send, Sync);
+}
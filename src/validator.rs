@@ -0,0 +1,380 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::process::Output;
+
+#[cfg(doc)]
+use crate::CommandExt;
+#[cfg(doc)]
+use crate::OutputError;
+
+/// A composable success condition for a command's [`Output`].
+///
+/// Validators are built from small conditions (like [`Validator::exit_in`] and
+/// [`Validator::stdout_contains`]) and combined with [`Validator::and`] and [`Validator::or`].
+/// Every condition is checked, and each one which fails produces a [`Violation`]. The violations
+/// are listed in the error returned by [`CommandExt::output_checked_validated`], and are available
+/// from [`OutputError::violations`]:
+///
+/// ```
+/// # use pretty_assertions::assert_eq;
+/// # use indoc::indoc;
+/// # use std::process::Command;
+/// # use command_error::CommandExt;
+/// # use command_error::Validator;
+/// let validator = Validator::exit_in(&[0, 2])
+///     .and(Validator::stdout_contains("ok"))
+///     .and(Validator::stderr_empty());
+///
+/// Command::new("sh")
+///     .args(["-c", "echo ok; exit 2"])
+///     .output_checked_validated(&validator)
+///     .unwrap();
+///
+/// let err = Command::new("sh")
+///     .args(["-c", "echo ok; echo warning >&2"])
+///     .output_checked_validated(&validator)
+///     .unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     indoc!(
+///         "`sh` failed: stderr isn't empty
+///         exit status: 0
+///         Command failed: `sh -c 'echo ok; echo warning >&2'`
+///         Stdout:
+///           ok
+///         Stderr:
+///           warning"
+///     )
+/// );
+///
+/// let err = Command::new("sh")
+///     .args(["-c", "echo warning >&2; exit 1"])
+///     .output_checked_validated(&validator)
+///     .unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     indoc!(
+///         "`sh` failed: 3 expectations failed:
+///         - exit code 1 isn't one of 0, 2
+///         - stdout doesn't contain \"ok\"
+///         - stderr isn't empty
+///         exit status: 1
+///         Command failed: `sh -c 'echo warning >&2; exit 1'`
+///         Stderr:
+///           warning"
+///     )
+/// );
+/// ```
+pub struct Validator {
+    description: String,
+    check: Box<Check>,
+}
+
+/// Checks a command's output, returning the conditions which failed.
+type Check = dyn Fn(&Output) -> Vec<Violation> + Send + Sync;
+
+/// A condition checked by a [`Validator`] which didn't hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Violation {
+    /// The command's exit code wasn't one of the expected codes.
+    ExitCode {
+        /// The expected exit codes.
+        expected: Vec<i32>,
+        /// The command's exit code, or `None` if it was killed by a signal.
+        actual: Option<i32>,
+    },
+    /// The command's stdout didn't contain the given pattern.
+    StdoutMissing(String),
+    /// The command's stderr didn't contain the given pattern.
+    StderrMissing(String),
+    /// The command wrote to stdout, but it was expected not to.
+    StdoutNotEmpty,
+    /// The command wrote to stderr, but it was expected not to.
+    StderrNotEmpty,
+    /// None of the alternatives given to [`Validator::or`] held.
+    NoneOf(Vec<Violation>),
+    /// A condition given to [`Validator::new`] failed, with the given message.
+    Custom(String),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::ExitCode { expected, actual } => {
+                let expected = expected
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match actual {
+                    Some(code) => write!(f, "exit code {code} isn't one of {expected}"),
+                    None => write!(f, "exited without an exit code, expected one of {expected}"),
+                }
+            }
+            Violation::StdoutMissing(pattern) => write!(f, "stdout doesn't contain {pattern:?}"),
+            Violation::StderrMissing(pattern) => write!(f, "stderr doesn't contain {pattern:?}"),
+            Violation::StdoutNotEmpty => write!(f, "stdout isn't empty"),
+            Violation::StderrNotEmpty => write!(f, "stderr isn't empty"),
+            Violation::NoneOf(violations) => {
+                for (i, violation) in violations.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", and ")?;
+                    }
+                    write!(f, "{violation}")?;
+                }
+                Ok(())
+            }
+            Violation::Custom(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+/// The conditions which failed, stored in an [`OutputError`].
+#[derive(Debug, Clone)]
+pub(crate) struct Violations(pub(crate) Vec<Violation>);
+
+/// Describe `violations` for an error message, with each violation on its own line if there's
+/// more than one.
+pub(crate) fn violations_message(violations: &[Violation]) -> String {
+    match violations {
+        [violation] => violation.to_string(),
+        violations => {
+            let mut message = format!("{} expectations failed:", violations.len());
+            for violation in violations {
+                message.push_str("\n- ");
+                message.push_str(&violation.to_string());
+            }
+            message
+        }
+    }
+}
+
+impl Validator {
+    /// Construct a new [`Validator`] from a `description` of the condition (used in its [`Debug`]
+    /// output) and a function which checks it, returning a message if it fails.
+    pub fn new(
+        description: impl Into<String>,
+        check: impl Fn(&Output) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self::checking(description, move |output| match check(output) {
+            Ok(()) => Vec::new(),
+            Err(message) => vec![Violation::Custom(message)],
+        })
+    }
+
+    /// Construct a new [`Validator`] from a function which returns the conditions which failed.
+    fn checking(
+        description: impl Into<String>,
+        check: impl Fn(&Output) -> Vec<Violation> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            description: description.into(),
+            check: Box::new(check),
+        }
+    }
+
+    /// Construct a new [`Validator`] which fails with `violation` if `holds` returns false.
+    fn condition(
+        description: impl Into<String>,
+        holds: impl Fn(&Output) -> bool + Send + Sync + 'static,
+        violation: Violation,
+    ) -> Self {
+        Self::checking(description, move |output| {
+            if holds(output) {
+                Vec::new()
+            } else {
+                vec![violation.clone()]
+            }
+        })
+    }
+
+    /// The command exits with exit code 0.
+    pub fn success() -> Self {
+        Self::exit_in(&[0])
+    }
+
+    /// The command exits with one of the given exit codes.
+    pub fn exit_in(codes: &[i32]) -> Self {
+        let codes = codes.to_vec();
+        let expected = codes
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        Self::checking(
+            format!("exit code is one of {expected}"),
+            move |output| match output.status.code() {
+                Some(code) if codes.contains(&code) => Vec::new(),
+                actual => vec![Violation::ExitCode {
+                    expected: codes.clone(),
+                    actual,
+                }],
+            },
+        )
+    }
+
+    /// The command's stdout contains `pattern`.
+    pub fn stdout_contains(pattern: &str) -> Self {
+        let violation = Violation::StdoutMissing(pattern.to_owned());
+        let pattern = pattern.to_owned();
+        Self::condition(
+            format!("stdout contains {pattern:?}"),
+            move |output| String::from_utf8_lossy(&output.stdout).contains(&pattern),
+            violation,
+        )
+    }
+
+    /// The command's stderr contains `pattern`.
+    pub fn stderr_contains(pattern: &str) -> Self {
+        let violation = Violation::StderrMissing(pattern.to_owned());
+        let pattern = pattern.to_owned();
+        Self::condition(
+            format!("stderr contains {pattern:?}"),
+            move |output| String::from_utf8_lossy(&output.stderr).contains(&pattern),
+            violation,
+        )
+    }
+
+    /// The command writes nothing to stdout.
+    pub fn stdout_empty() -> Self {
+        Self::condition(
+            "stdout is empty",
+            |output| output.stdout.is_empty(),
+            Violation::StdoutNotEmpty,
+        )
+    }
+
+    /// The command writes nothing to stderr.
+    pub fn stderr_empty() -> Self {
+        Self::condition(
+            "stderr is empty",
+            |output| output.stderr.is_empty(),
+            Violation::StderrNotEmpty,
+        )
+    }
+
+    /// Both this condition and `other` must hold. Both are always checked, so that every failure
+    /// is reported.
+    pub fn and(self, other: Validator) -> Self {
+        Self::checking(
+            format!("{} and {}", self.description, other.description),
+            move |output| {
+                let mut violations = self.violations(output);
+                violations.extend(other.violations(output));
+                violations
+            },
+        )
+    }
+
+    /// Either this condition or `other` must hold. If both fail, both sets of failures are
+    /// reported in a single [`Violation::NoneOf`].
+    pub fn or(self, other: Validator) -> Self {
+        Self::checking(
+            format!("({} or {})", self.description, other.description),
+            move |output| {
+                let mut violations = self.violations(output);
+                if violations.is_empty() {
+                    return violations;
+                }
+                let other_violations = other.violations(output);
+                if other_violations.is_empty() {
+                    return other_violations;
+                }
+                violations.extend(other_violations);
+                vec![Violation::NoneOf(violations)]
+            },
+        )
+    }
+
+    /// Check `output`, returning every condition which failed.
+    pub fn violations(&self, output: &Output) -> Vec<Violation> {
+        (self.check)(output)
+    }
+
+    /// Check `output`, returning a message describing the failures if it fails.
+    ///
+    /// If more than one condition failed, the message lists each one on its own line.
+    pub fn validate(&self, output: &Output) -> Result<(), String> {
+        let violations = self.violations(output);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations_message(&violations))
+        }
+    }
+}
+
+impl Debug for Validator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Validator").field(&self.description).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use static_assertions::assert_impl_all;
+    use std::process::ExitStatus;
+
+    #[cfg(unix)]
+    use std::os::unix::process::ExitStatusExt;
+
+    assert_impl_all!(Validator: Send, Sync);
+
+    #[cfg(unix)]
+    fn output(code: i32, stdout: &str, stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_or() {
+        let validator = Validator::stdout_contains("ok").or(Validator::stderr_contains("ok"));
+        assert_eq!(validator.validate(&output(0, "", "ok")), Ok(()));
+        assert_eq!(
+            validator.validate(&output(0, "", "")),
+            Err(r#"stdout doesn't contain "ok", and stderr doesn't contain "ok""#.to_owned())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_and_reports_every_violation() {
+        let validator = Validator::success()
+            .and(Validator::stdout_contains("ok"))
+            .and(Validator::stderr_empty());
+        let output = output(0, "", "warning");
+        assert_eq!(
+            validator.violations(&output),
+            [
+                Violation::StdoutMissing("ok".to_owned()),
+                Violation::StderrNotEmpty,
+            ]
+        );
+        assert_eq!(
+            validator.validate(&output),
+            Err(indoc::indoc!(
+                r#"2 expectations failed:
+                - stdout doesn't contain "ok"
+                - stderr isn't empty"#
+            )
+            .to_owned())
+        );
+    }
+
+    #[test]
+    fn test_debug() {
+        let validator =
+            Validator::success().and(Validator::stdout_empty().or(Validator::stderr_empty()));
+        assert_eq!(
+            format!("{validator:?}"),
+            r#"Validator("exit code is one of 0 and (stdout is empty or stderr is empty)")"#
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use std::fmt::Debug;
 use std::fmt::Display;
 
+use crate::command_display::guarded;
+use crate::messages::messages;
 #[cfg(doc)]
 use crate::ChildExt;
 use crate::CommandDisplay;
@@ -37,15 +39,68 @@ pub struct WaitError {
 
 impl WaitError {
     /// Construct a new [`WaitError`].
-    pub fn new(command: Box<dyn CommandDisplay + Send + Sync>, inner: std::io::Error) -> Self {
-        Self { command, inner }
+    pub fn new(
+        command: impl Into<Box<dyn CommandDisplay + Send + Sync>>,
+        inner: std::io::Error,
+    ) -> Self {
+        Self {
+            command: command.into(),
+            inner,
+        }
+    }
+
+    /// Get the [`std::io::ErrorKind`] of the underlying error.
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.inner.kind()
+    }
+
+    /// Get a reference to the underlying error.
+    pub fn inner(&self) -> &std::io::Error {
+        &self.inner
+    }
+
+    /// True if the child process no longer exists because it was already waited for (`ECHILD`).
+    ///
+    /// This usually indicates that the child was reaped somewhere else, such as by a `SIGCHLD`
+    /// handler or a second call to `waitpid`.
+    ///
+    /// ```
+    /// # use pretty_assertions::assert_eq;
+    /// # use std::process::Command;
+    /// # use command_error::ChildExt;
+    /// # use command_error::CommandExt;
+    /// # use command_error::Error;
+    /// let mut child = Command::new("true").spawn_checked().unwrap();
+    /// // Reap the child behind the standard library's back.
+    /// let pid = child.child().id() as libc::pid_t;
+    /// assert_eq!(unsafe { libc::waitpid(pid, std::ptr::null_mut(), 0) }, pid);
+    ///
+    /// let err = match child.wait_checked().unwrap_err() {
+    ///     Error::Wait(err) => err,
+    ///     err => panic!("Unexpected error: {err}"),
+    /// };
+    /// assert!(err.is_already_reaped());
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "`true` was already waited for (child no longer exists)"
+    /// );
+    /// ```
+    pub fn is_already_reaped(&self) -> bool {
+        #[cfg(unix)]
+        {
+            self.inner.raw_os_error() == Some(libc::ECHILD)
+        }
+        #[cfg(not(unix))]
+        {
+            false
+        }
     }
 }
 
 impl Debug for WaitError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("WaitError")
-            .field("program", &self.command.program())
+            .field("program", &guarded(&*self.command).program())
             .field("inner", &self.inner)
             .finish()
     }
@@ -53,19 +108,41 @@ impl Debug for WaitError {
 
 impl Display for WaitError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Failed to wait for `{}`: {}",
-            self.command.program_quoted(),
-            self.inner
-        )
+        if self.is_already_reaped() {
+            write!(
+                f,
+                "`{}` was already waited for (child no longer exists)",
+                guarded(&*self.command).program_quoted()
+            )
+        } else {
+            write!(
+                f,
+                "{} `{}`: {}",
+                messages().failed_to_wait_for,
+                guarded(&*self.command).program_quoted(),
+                self.inner
+            )
+        }
     }
 }
 
 impl std::error::Error for WaitError {}
 
 #[cfg(feature = "miette")]
-impl Diagnostic for WaitError {}
+impl Diagnostic for WaitError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("command_error::wait"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new(if self.is_already_reaped() {
+            "The child was reaped somewhere else, such as by a `SIGCHLD` handler or another call \
+            to `wait`"
+        } else {
+            "Check that the child was started and hasn't already been waited for"
+        }))
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -73,4 +150,33 @@ mod tests {
     use static_assertions::assert_impl_all;
 
     assert_impl_all!(WaitError: Send, Sync);
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn test_miette_report() {
+        use std::process::Command;
+
+        use crate::Utf8ProgramAndArgs;
+
+        let error = WaitError::new(
+            Box::new(Utf8ProgramAndArgs::from(&Command::new("cat"))),
+            std::io::Error::other("interrupted"),
+        );
+        let report = format!("{:?}", miette::Report::new(error));
+        assert!(report.contains("command_error::wait"), "{report}");
+        assert!(
+            report.contains("hasn't already been waited for"),
+            "{report}"
+        );
+
+        #[cfg(unix)]
+        {
+            let error = WaitError::new(
+                Box::new(Utf8ProgramAndArgs::from(&Command::new("cat"))),
+                std::io::Error::from_raw_os_error(libc::ECHILD),
+            );
+            let report = format!("{:?}", miette::Report::new(error));
+            assert!(report.contains("`SIGCHLD` handler"), "{report}");
+        }
+    }
 }
@@ -0,0 +1,158 @@
+//! Check that displaying commands with enormous argument lists doesn't copy them, that errors
+//! with enormous output are rendered without copying it, and that reusable output buffers are
+//! reused.
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+use std::io::Write;
+use std::process::Command;
+
+use command_error::CommandExt;
+use command_error::Error;
+use command_error::LazyProgramAndArgs;
+use command_error::Utf8ProgramAndArgs;
+
+/// Counts the bytes allocated on the current thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.with(|allocated| allocated.set(allocated.get() + layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The number of bytes allocated on this thread while running `f`.
+fn allocated_by(f: impl FnOnce()) -> usize {
+    let before = ALLOCATED.with(Cell::get);
+    f();
+    ALLOCATED.with(Cell::get) - before
+}
+
+fn gigantic_command() -> Command {
+    let mut command = Command::new("rm");
+    command.args((0..100_000).map(|i| format!("file-{i:06}.tmp")));
+    command
+}
+
+#[test]
+fn test_lazy_display_does_not_allocate() {
+    let command = gigantic_command();
+    let mut sink = std::io::sink();
+    let allocated = allocated_by(|| {
+        write!(sink, "{}", LazyProgramAndArgs::new(&command)).unwrap();
+    });
+    assert_eq!(allocated, 0);
+}
+
+#[test]
+fn test_owned_display_is_truncated() {
+    let command = gigantic_command();
+    // 100,000 arguments of 15 bytes each.
+    let args_size = 100_000 * "file-000000.tmp".len();
+    let mut displayed = None;
+    let allocated = allocated_by(|| {
+        displayed = Some(Utf8ProgramAndArgs::from(&command));
+    });
+    assert!(
+        allocated < args_size / 10,
+        "Copied {allocated} bytes of a {args_size}-byte command"
+    );
+
+    let displayed = displayed.unwrap();
+    assert_eq!(displayed.omitted_args(), 98976);
+    // The omitted arguments can't be recovered, so the command can't be reconstructed.
+    assert!(displayed.is_lossy());
+
+    let displayed = displayed.to_string();
+    assert!(displayed.starts_with("rm file-000000.tmp file-000001.tmp "));
+    assert!(displayed.ends_with(" file-001023.tmp ... (98976 more arguments omitted)"));
+}
+
+#[test]
+fn test_output_buffers_are_reused() {
+    let output_size = 1_000_000;
+    let mut command = Command::new("sh");
+    command.args(["-c", &format!("yes puppy | head -c {output_size}")]);
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    command
+        .output_checked_into_buf(&mut stdout, &mut stderr)
+        .unwrap();
+    assert_eq!(stdout.len(), output_size);
+
+    let allocated = allocated_by(|| {
+        command
+            .output_checked_into_buf(&mut stdout, &mut stderr)
+            .unwrap();
+    });
+    assert_eq!(stdout.len(), output_size);
+    assert!(
+        allocated < output_size / 10,
+        "Allocated {allocated} bytes to read {output_size} bytes into a reused buffer"
+    );
+}
+
+/// An error from a command which wrote `stderr_size` bytes of stderr.
+fn noisy_error(stderr_size: usize) -> Error {
+    Command::new("sh")
+        .args([
+            "-c",
+            &format!("yes 'error: puppy' | head -c {stderr_size} >&2; exit 1"),
+        ])
+        .output_checked()
+        .unwrap_err()
+}
+
+#[test]
+fn test_error_rendering_streams_output() {
+    let stderr_size = 10_000_000;
+    let err = noisy_error(stderr_size);
+    let Error::Output(collapsed) = noisy_error(stderr_size) else {
+        unreachable!()
+    };
+    let collapsed = Error::from(collapsed.collapse_repeats(true));
+
+    let mut sink = std::io::sink();
+    for err in [&err, &collapsed] {
+        let allocated = allocated_by(|| {
+            write!(sink, "{err}").unwrap();
+        });
+        assert!(
+            allocated < stderr_size / 100,
+            "Allocated {allocated} bytes to display an error with {stderr_size} bytes of stderr"
+        );
+    }
+
+    let mut compact = String::with_capacity(1024);
+    let allocated = allocated_by(|| {
+        err.write_compact(&mut compact).unwrap();
+    });
+    assert_eq!(compact, "`sh` failed: exit status: 1");
+    assert!(
+        allocated < stderr_size / 100,
+        "Allocated {allocated} bytes to write a compact error"
+    );
+
+    let allocated = allocated_by(|| {
+        err.write_json(&mut sink).unwrap();
+    });
+    assert!(
+        allocated < stderr_size / 100,
+        "Allocated {allocated} bytes to write a JSON error with {stderr_size} bytes of stderr"
+    );
+}
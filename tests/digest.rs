@@ -0,0 +1,100 @@
+//! Check that `CommandExt::output_checked_hashing` hashes stdout as it's read.
+//!
+//! This uses a small FNV-1a hasher rather than a real hash function, so that it doesn't need any
+//! more dependencies.
+
+#![cfg(feature = "digest")]
+
+use std::process::Command;
+
+use command_error::CommandExt;
+use digest::consts::U8;
+use digest::Digest;
+use digest::FixedOutput;
+use digest::FixedOutputReset;
+use digest::HashMarker;
+use digest::Output;
+use digest::OutputSizeUser;
+use digest::Reset;
+use digest::Update;
+use pretty_assertions::assert_eq;
+
+/// The 64-bit FNV-1a hash.
+#[derive(Debug, Clone)]
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl HashMarker for Fnv1a {}
+
+impl OutputSizeUser for Fnv1a {
+    type OutputSize = U8;
+}
+
+impl Update for Fnv1a {
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+impl FixedOutput for Fnv1a {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.0.to_be_bytes());
+    }
+}
+
+impl Reset for Fnv1a {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl FixedOutputReset for Fnv1a {
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.0.to_be_bytes());
+        Reset::reset(self);
+    }
+}
+
+#[test]
+fn test_output_checked_hashing() {
+    let mut hasher = Fnv1a::default();
+    let (output, hash) = Command::new("sh")
+        .args(["-c", "echo puppy; echo doggy >&2"])
+        .output_checked_hashing(&mut hasher)
+        .unwrap();
+
+    assert_eq!(output.stdout, b"puppy\n");
+    assert_eq!(output.stderr, b"doggy\n");
+    // Only stdout is hashed.
+    assert_eq!(hash, Fnv1a::digest(b"puppy\n"));
+
+    // The hasher is reset, so it can be reused.
+    let (_output, hash) = Command::new("echo")
+        .arg("kitty")
+        .output_checked_hashing(&mut hasher)
+        .unwrap();
+    assert_eq!(hash, Fnv1a::digest(b"kitty\n"));
+}
+
+#[test]
+fn test_output_checked_hashing_failure() {
+    let mut hasher = Fnv1a::default();
+    let err = Command::new("sh")
+        .args(["-c", "echo puppy; exit 1"])
+        .output_checked_hashing(&mut hasher)
+        .unwrap_err();
+    assert!(err.to_string().starts_with("`sh` failed: exit status: 1"));
+
+    // The hasher is left holding the output which was read before the command failed.
+    assert_eq!(
+        Digest::finalize_reset(&mut hasher),
+        Fnv1a::digest(b"puppy\n")
+    );
+}
@@ -0,0 +1,70 @@
+//! Check that errors record the location of the call which produced them.
+//!
+//! This is an integration test so that the recorded locations are in a file outside the crate,
+//! like they would be for users.
+
+use std::panic::Location;
+use std::process::Command;
+
+use command_error::ChildExt;
+use command_error::CommandExt;
+use command_error::Error;
+use pretty_assertions::assert_eq;
+
+/// Assert that `result` failed with an error recorded at `line` of this file.
+#[track_caller]
+fn assert_location<T>(result: Result<T, Error>, line: u32) {
+    let err = match result {
+        Ok(_) => panic!("Expected an error"),
+        Err(err) => err,
+    };
+    let location = err.location().unwrap();
+    assert_eq!(
+        (location.file(), location.line()),
+        (Location::caller().file(), line),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_location() {
+    let mut failing = Command::new("sh");
+    failing.args(["-c", "exit 1"]);
+
+    assert_location(failing.output_checked(), line!());
+    assert_location(failing.output_checked_utf8(), line!());
+    assert_location(failing.status_checked(), line!());
+    assert_location(
+        failing.output_checked_with(|output: &std::process::Output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(None::<String>)
+            }
+        }),
+        line!() - 7,
+    );
+    assert_location(
+        failing.output_checked_with_backpressure(1, |_stream, _chunk| {}),
+        line!() - 1,
+    );
+    assert_location(Command::new("ooga booga").output_checked(), line!());
+
+    // Children record where they were spawned.
+    let mut child = failing.spawn_checked().unwrap();
+    let spawned = line!() - 1;
+    assert_location(child.wait_checked(), spawned);
+}
+
+#[test]
+fn test_location_debug() {
+    let err = Command::new("false").output_checked().unwrap_err();
+    let line = line!() - 1;
+    assert!(
+        format!("{err:?}").contains(&format!(
+            "location: Some(Location {{ file: {:?}, line: {line},",
+            file!()
+        )),
+        "{err:?}"
+    );
+}
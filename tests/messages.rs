@@ -0,0 +1,62 @@
+//! Check that custom [`Messages`] replace the labels in error messages.
+//!
+//! This is its own test binary, since [`set_messages`] affects the whole process.
+
+use std::process::Command;
+
+use command_error::set_messages;
+use command_error::CommandExt;
+use command_error::Messages;
+use indoc::indoc;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_custom_messages() {
+    set_messages(
+        Messages::default()
+            .with_command_failed("Process exited abnormally")
+            .with_stdout("Output")
+            .with_stderr("Errors")
+            .with_failed_to_execute("Could not start")
+            .with_failed("went wrong"),
+    );
+
+    let err = Command::new("sh")
+        .args(["-c", "echo puppy; echo doggy >&2; exit 1"])
+        .output_checked_utf8()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        indoc!(
+            "`sh` went wrong: exit status: 1
+            Process exited abnormally: `sh -c 'echo puppy; echo doggy >&2; exit 1'`
+            Output:
+              puppy
+            Errors:
+              doggy"
+        )
+    );
+    assert!(
+        err.to_github_annotation().starts_with(
+            "::error title=`sh` went wrong%3A exit status%3A 1::Process exited abnormally: "
+        ),
+        "{}",
+        err.to_github_annotation()
+    );
+
+    let err = Command::new("command-error-nonexistent-program")
+        .output_checked()
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("Could not start `command-error-nonexistent-program`: "),
+        "{err}"
+    );
+
+    set_messages(Messages::default());
+    let err = Command::new("false").output_checked().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`false` failed: exit status: 1\nCommand failed: `false`"
+    );
+}
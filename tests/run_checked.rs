@@ -0,0 +1,109 @@
+//! Integration tests for `examples/run_checked.rs`.
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+
+use indoc::indoc;
+use pretty_assertions::assert_eq;
+
+/// The path to the `run_checked` example, which `cargo test` builds alongside the tests.
+fn run_checked_path() -> PathBuf {
+    // `target/debug/deps/run_checked-abc123` -> `target/debug/examples/run_checked`
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push("examples");
+    path.push(format!("run_checked{}", std::env::consts::EXE_SUFFIX));
+    path
+}
+
+fn run_checked(args: &[&str]) -> Output {
+    Command::new(run_checked_path())
+        .args(args)
+        .output()
+        .expect("`run_checked` example should be built by `cargo test`")
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn test_success() {
+    let output = run_checked(&["--", "echo", "puppy"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"puppy\n");
+}
+
+#[test]
+fn test_failure() {
+    let output = run_checked(&["--", "sh", "-c", "echo doggy >&2; exit 3"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        stderr(&output),
+        indoc!(
+            "`sh` failed: exit status: 3
+            Command failed: `sh -c 'echo doggy >&2; exit 3'`
+            Stderr:
+              doggy
+            "
+        )
+    );
+}
+
+#[test]
+fn test_not_found() {
+    let output = run_checked(&["--", "ooga-booga"]);
+    assert_eq!(output.status.code(), Some(127));
+    assert!(stderr(&output).starts_with("Failed to execute `ooga-booga`"));
+}
+
+#[test]
+fn test_utf8() {
+    let output = run_checked(&["--utf8", "--", "printf", r"\377"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("Failed to convert `printf` output"));
+}
+
+#[test]
+fn test_json() {
+    let output = run_checked(&["--json", "--", "cat", "tests/data/incomplete.json"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with(indoc!(
+        "`cat` failed: unexpected end of JSON input
+        exit status: 0
+        Command failed: `cat tests/data/incomplete.json`"
+    )));
+}
+
+#[test]
+fn test_timeout() {
+    let output = run_checked(&["--timeout", "0.1", "--", "sleep", "10"]);
+    assert_eq!(output.status.code(), Some(124));
+    assert_eq!(
+        stderr(&output),
+        indoc!(
+            "`sleep` timed out after 100ms and was terminated gracefully
+            Command failed: `sleep 10`
+            "
+        )
+    );
+}
+
+#[test]
+fn test_retry() {
+    let output = run_checked(&["--retry", "2", "--", "sh", "-c", "exit 1"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = stderr(&output);
+    assert!(stderr.contains("Attempt 1 of 3 failed: `sh` failed: exit status: 1"));
+    assert!(stderr.contains("Attempt 2 of 3 failed: `sh` failed: exit status: 1"));
+    assert!(!stderr.contains("Attempt 3 of 3 failed"));
+}
+
+#[test]
+fn test_usage() {
+    let output = run_checked(&["echo"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Usage: run_checked"));
+}